@@ -0,0 +1,40 @@
+// CRT filter, see src/crt.rs
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+
+struct CrtFilter {
+    scanlines: f32,
+    aberration: f32,
+    curvature: f32,
+    vignette: f32,
+}
+@group(0) @binding(2) var<uniform> crt: CrtFilter;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(screen_texture));
+
+    // Bulge: push points out the further they are from the middle
+    let centered = in.uv * 2.0 - 1.0;
+    let bent = centered + centered * centered.yx * centered.yx * crt.curvature;
+    let uv = bent * 0.5 + 0.5;
+
+    // Red and blue pulled apart, more towards the edges
+    let offset = vec2<f32>(crt.aberration / size.x, 0.0) * length(centered);
+    let r = textureSample(screen_texture, texture_sampler, uv + offset).r;
+    let g = textureSample(screen_texture, texture_sampler, uv).g;
+    let b = textureSample(screen_texture, texture_sampler, uv - offset).b;
+    var color = vec3<f32>(r, g, b);
+
+    // Every other row of screen pixels darker
+    let row = floor(uv.y * size.y);
+    color *= 1.0 - crt.scanlines * (row % 2.0);
+
+    color *= 1.0 - crt.vignette * dot(bent, bent) * 0.5;
+
+    // Past the bent edge there's no picture
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return vec4<f32>(select(vec3<f32>(0.0), color, inside), 1.0);
+}
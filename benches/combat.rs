@@ -0,0 +1,92 @@
+//! Combat simulation and HUD benchmarks, run headlessly through `testing::SteppedPull`.
+//!
+//! `cargo bench --bench combat`; pass a filter like `-- hud/` to run one group. Compare
+//! against a saved baseline with `--save-baseline before` / `--baseline before`.
+
+use bevy_game::testing::{AbilityId, SteppedPull, STEP_SECS};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::time::Duration;
+
+const SEED: u64 = 7;
+
+/// GCD, weave, weave: keeps the input buffer and the oGCD path busy
+const ROTATION: [AbilityId; 3] = [AbilityId::Strike, AbilityId::WeaveDash, AbilityId::WeaveSong];
+
+fn presses(c: &mut Criterion) {
+    let mut group = c.benchmark_group("presses");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(20));
+    // One press every frame, far more than a player manages, so most get buffered or dropped
+    group.bench_function("10k", |b| {
+        b.iter_batched(
+            || SteppedPull::new(SEED),
+            |mut pull| {
+                for i in 0..10_000 {
+                    pull.press(ROTATION[i % ROTATION.len()]);
+                    pull.step();
+                }
+                pull
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.finish();
+}
+
+fn dot_hour(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dot");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(60));
+    group.bench_function("1h_ticking", |b| {
+        b.iter_batched(
+            || {
+                let mut pull = SteppedPull::new(SEED);
+                pull.apply_dot(AbilityId::Burn, 40, 3600.0, 3.0);
+                pull
+            },
+            |mut pull| {
+                pull.run_for(3600.0);
+                pull
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.finish();
+}
+
+/// Per-frame cost with 100 statuses up, each ticking once a second
+fn statuses(c: &mut Criterion) {
+    let mut group = c.benchmark_group("statuses");
+    group.warm_up_time(Duration::from_millis(500));
+    group.measurement_time(Duration::from_secs(4));
+    for count in [0, 100] {
+        let mut pull = SteppedPull::new(SEED);
+        pull.apply_statuses(count, 1.0e6);
+        pull.set_hud(false);
+        pull.step();
+        group.bench_function(format!("{count}_concurrent"), |b| b.iter(|| pull.step()));
+    }
+    group.finish();
+}
+
+/// The same frame with and without `GameSet::Ui`; the difference is what the HUD update
+/// systems cost
+fn hud(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hud");
+    group.warm_up_time(Duration::from_millis(500));
+    group.measurement_time(Duration::from_secs(4));
+    for (name, statuses) in [("idle", 0), ("100_statuses", 100)] {
+        for enabled in [false, true] {
+            let mut pull = SteppedPull::new(SEED);
+            pull.apply_statuses(statuses, 1.0e6);
+            pull.set_hud(enabled);
+            pull.run_for(STEP_SECS * 10.0);
+            let label = if enabled { "with_hud" } else { "without_hud" };
+            group.bench_function(format!("{name}/{label}"), |b| b.iter(|| pull.step()));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, presses, dot_hour, statuses, hud);
+criterion_main!(benches);
@@ -0,0 +1,263 @@
+use bevy::prelude::*;
+
+use crate::combat::{is_live, AbilityBook, AbilityUsedEvent, CombatState, PullResult};
+use crate::{persist, GameSet, GameState};
+
+// Achievements, unlocked by watching combat events and saved in the user profile.
+// Whole-pull achievements are checked on the results screen, once the pull is over. Only
+// live pulls count.
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AchievementProfile>()
+            .init_resource::<AchievementTracker>()
+            .add_event::<AchievementUnlockedEvent>()
+            .add_systems(Startup, load_profile)
+            .add_systems(OnEnter(GameState::Playing), reset_tracker)
+            .add_systems(OnEnter(GameState::Results), finish_pull.run_if(is_live))
+            .add_systems(
+                FixedUpdate,
+                track_achievements.in_set(GameSet::Sim).run_if(in_state(GameState::Playing).and(is_live)),
+            )
+            // Toasts outlive the pull: the end-of-pull ones pop up over the results screen
+            .add_systems(Update, (spawn_toasts, fade_toasts).chain().in_set(GameSet::Ui));
+    }
+}
+
+const PROFILE_FILE: &str = "achievements.txt";
+/// Perfect weave windows needed in a row
+const WEAVE_STREAK: u32 = 10;
+/// Pulls that have to end in a kill before the enrage
+pub const CLEARS_BEFORE_ENRAGE: u32 = 5;
+/// GCDs a pull needs before it counts for the zero-clip achievement
+const MIN_PULL_GCDS: u32 = 5;
+const TOAST_SECS: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    ZeroClipPull,
+    WeaveStreak,
+    EnragePractice,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 3] = [Achievement::ZeroClipPull, Achievement::WeaveStreak, Achievement::EnragePractice];
+
+    /// Name in the profile file
+    fn key(self) -> &'static str {
+        match self {
+            Achievement::ZeroClipPull => "zero_clip_pull",
+            Achievement::WeaveStreak => "weave_streak",
+            Achievement::EnragePractice => "enrage_practice",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Achievement::ZeroClipPull => "Clean Pull",
+            Achievement::WeaveStreak => "Weaver",
+            Achievement::EnragePractice => "Beat the Clock",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::ZeroClipPull => "Finish a pull without clipping a single GCD",
+            Achievement::WeaveStreak => "Weave without clipping in 10 GCD windows in a row",
+            Achievement::EnragePractice => "Kill the boss before the enrage 5 times",
+        }
+    }
+}
+
+/// Unlocked achievements and progress counters that carry over between sessions
+#[derive(Resource, Debug, Default)]
+pub struct AchievementProfile {
+    pub unlocked: Vec<Achievement>,
+    pub clears_before_enrage: u32,
+}
+
+impl AchievementProfile {
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    // File format: "<achievement> = 1" per unlocked achievement plus "clears_before_enrage = <n>"
+    fn parse(contents: &str) -> Self {
+        let mut profile = Self::default();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue; };
+            let (key, value) = (key.trim(), value.trim());
+            // Profiles from before the rename count the same pulls as `enrages_survived`
+            if key == "clears_before_enrage" || key == "enrages_survived" {
+                profile.clears_before_enrage = profile.clears_before_enrage.max(value.parse().unwrap_or(0));
+            } else if let Some(achievement) = Achievement::ALL.into_iter().find(|a| a.key() == key) {
+                if value == "1" {
+                    profile.unlocked.push(achievement);
+                }
+            } else {
+                warn!("Unknown achievement {key:?}");
+            }
+        }
+        profile
+    }
+
+    fn save(&self) {
+        let mut out = format!("clears_before_enrage = {}\n", self.clears_before_enrage);
+        for achievement in &self.unlocked {
+            out.push_str(&format!("{} = 1\n", achievement.key()));
+        }
+        persist::save(PROFILE_FILE, &out);
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AchievementUnlockedEvent(pub Achievement);
+
+/// Progress within the current pull
+#[derive(Resource, Debug, Default)]
+struct AchievementTracker {
+    gcds: u32,
+    clipped: bool,
+    /// Weaves and clips since the last GCD, and whether there was a GCD to start the window
+    window_open: bool,
+    window_weaves: u32,
+    window_clipped: bool,
+    weave_streak: u32,
+}
+
+impl AchievementTracker {
+    fn end_pull(&mut self) {
+        *self = Self { weave_streak: self.weave_streak, ..default() };
+    }
+}
+
+#[derive(Component)]
+struct AchievementToast {
+    remaining: f32,
+}
+
+fn load_profile(mut profile: ResMut<AchievementProfile>) {
+    if let Some(contents) = persist::load(PROFILE_FILE) {
+        *profile = AchievementProfile::parse(&contents);
+    }
+}
+
+fn reset_tracker(mut tracker: ResMut<AchievementTracker>) {
+    *tracker = AchievementTracker::default();
+}
+
+fn unlock(
+    achievement: Achievement,
+    profile: &mut AchievementProfile,
+    writer: &mut EventWriter<AchievementUnlockedEvent>,
+) {
+    if profile.is_unlocked(achievement) {
+        return;
+    }
+    info!("Achievement unlocked: {}", achievement.title());
+    profile.unlocked.push(achievement);
+    profile.save();
+    writer.write(AchievementUnlockedEvent(achievement));
+}
+
+fn track_achievements(
+    book: Res<AbilityBook>,
+    combat: Res<CombatState>,
+    mut tracker: ResMut<AchievementTracker>,
+    mut profile: ResMut<AchievementProfile>,
+    mut used: EventReader<AbilityUsedEvent>,
+    mut writer: EventWriter<AchievementUnlockedEvent>,
+) {
+    if combat.clipped {
+        tracker.clipped = true;
+        tracker.window_clipped = true;
+    }
+    for ev in used.read() {
+        let Some(ability) = book.by_id.get(&ev.id) else { continue; };
+        if !ability.triggers_gcd {
+            tracker.window_weaves += 1;
+            continue;
+        }
+        // A GCD closes the previous window
+        if tracker.window_open {
+            let perfect = tracker.window_weaves > 0 && !tracker.window_clipped;
+            tracker.weave_streak = if perfect { tracker.weave_streak + 1 } else { 0 };
+            if tracker.weave_streak >= WEAVE_STREAK {
+                unlock(Achievement::WeaveStreak, &mut profile, &mut writer);
+            }
+        }
+        tracker.window_open = true;
+        tracker.window_weaves = 0;
+        tracker.window_clipped = false;
+        tracker.gcds += 1;
+    }
+}
+
+fn finish_pull(
+    result: Res<PullResult>,
+    mut tracker: ResMut<AchievementTracker>,
+    mut profile: ResMut<AchievementProfile>,
+    mut writer: EventWriter<AchievementUnlockedEvent>,
+) {
+    if result.cleared_before_enrage {
+        profile.clears_before_enrage += 1;
+        profile.save();
+        if profile.clears_before_enrage >= CLEARS_BEFORE_ENRAGE {
+            unlock(Achievement::EnragePractice, &mut profile, &mut writer);
+        }
+    }
+    if !tracker.clipped && tracker.gcds >= MIN_PULL_GCDS {
+        unlock(Achievement::ZeroClipPull, &mut profile, &mut writer);
+    }
+    tracker.end_pull();
+}
+
+fn spawn_toasts(mut commands: Commands, mut evr: EventReader<AchievementUnlockedEvent>) {
+    for AchievementUnlockedEvent(achievement) in evr.read() {
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(40.0),
+                    left: Val::Percent(40.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    flex_direction: FlexDirection::Column,
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                BorderColor(Color::linear_rgb(1.0, 0.8, 0.2)),
+                BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.85)),
+                AchievementToast { remaining: TOAST_SECS },
+            ))
+            .with_children(|toast| {
+                toast.spawn((
+                    Text::new(format!("Achievement unlocked: {}", achievement.title())),
+                    TextFont { font_size: 18.0, ..default() },
+                    TextColor(Color::linear_rgb(1.0, 0.85, 0.3)),
+                ));
+                toast.spawn((
+                    Text::new(achievement.description()),
+                    TextFont { font_size: 13.0, ..default() },
+                    TextColor(Color::linear_rgb(0.85, 0.85, 0.85)),
+                ));
+            });
+    }
+}
+
+/// Toasts are HUD feedback, so they run on real time
+fn fade_toasts(
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+    mut q: Query<(Entity, &mut AchievementToast, &mut BackgroundColor)>,
+) {
+    for (e, mut toast, mut bg) in &mut q {
+        toast.remaining -= time.delta_secs();
+        if toast.remaining <= 0.0 {
+            commands.entity(e).despawn();
+            continue;
+        }
+        bg.0 = bg.0.with_alpha(0.85 * (toast.remaining / 0.5).min(1.0));
+    }
+}
@@ -1,41 +1,185 @@
-use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 
 use crate::actions::game_control::{get_movement, GameControl};
+use crate::combat::AbilityId;
+use crate::waymarks::Waymark;
 use crate::GameSet;
-use crate::player::Player;
 use crate::GameState;
 
 mod game_control;
 
-pub const FOLLOW_EPSILON: f32 = 5.;
+/// Stick deflection below this counts as centered
+const STICK_DEADZONE: f32 = 0.2;
+/// Logical pixels the touch joystick's knob can travel from where the finger went down
+const JOYSTICK_RADIUS: f32 = 60.0;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions.
+// This plugin listens for keyboard, gamepad and touch input and converts the input into Actions.
+// The d-pad and face buttons belong to the crossbar (see `combat::crossbar`), so on a
+// gamepad the left stick moves and clicking it toggles sprint.
+// On a touchscreen every finger is tracked on its own: one that lands on a `Tappable` node
+// (the hotbar buttons) is a tap on it, and the first one that lands anywhere else becomes a
+// virtual joystick centered where it went down, so one thumb can move while the other weaves.
 // Actions can then be used as a resource in other systems to act on the player input.
+//
+// Input is read every frame, but the sim only takes it in on its fixed ticks: whatever the
+// player does to the fight is queued as a `SimAction`, and the next tick collects the queue
+// into `SimInput` for the sim systems to act on. Replays record and play back that stream.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Actions>().add_systems(
-            PreUpdate,
-            set_movement_actions
-                .in_set(GameSet::InputRead)
-                .run_if(in_state(GameState::Playing)),
-        );
+        app.init_resource::<Actions>()
+            .init_resource::<SimInput>()
+            .init_resource::<InputFocus>()
+            .init_resource::<TouchJoystick>()
+            .add_systems(OnEnter(GameState::Playing), (spawn_joystick, reset_sim_input))
+            // The menu's fields don't run outside it, so they can't give the keyboard back
+            .add_systems(OnExit(GameState::Menu), release_focus)
+            .add_systems(
+                PreUpdate,
+                set_movement_actions
+                    .in_set(GameSet::InputRead)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(FixedUpdate, collect_actions.in_set(ActionSet::Collect).run_if(in_state(GameState::Playing)))
+            .add_systems(FixedUpdate, steer.in_set(ActionSet::Apply).run_if(in_state(GameState::Playing)))
+            .add_systems(Update, update_joystick.in_set(GameSet::Ui).run_if(in_state(GameState::Playing)));
     }
 }
 
+/// UI node that a touch landing on counts as a tap on, instead of starting the joystick
+#[derive(Component)]
+pub struct Tappable;
+
 #[derive(Default, Resource)]
 pub struct Actions {
     pub player_movement: Option<Vec2>,
+    /// `Tappable` nodes a finger came down on this frame
+    pub taps: Vec<Entity>,
+    /// Read from the devices but not taken in by a tick yet
+    queued: Vec<SimAction>,
+}
+
+impl Actions {
+    /// Hands `action` to the sim on its next tick
+    pub fn queue(&mut self, action: SimAction) {
+        self.queued.push(action);
+    }
+}
+
+/// One thing the player does to the fight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimAction {
+    /// An ability press as it reaches the sim, after any simulated latency
+    Press(AbilityId),
+    /// Where the player steers from now on, `None` once they let go
+    Move(Option<Vec2>),
+    ToggleSprint,
+    /// Tab to the next enemy
+    CycleTarget,
+    /// Heal target picked on the party frames, by frame slot; slot 0 is the player
+    HealTarget(usize),
+    /// Debuff the next Cleanse takes off, picked on the status row as it was pressed, by its
+    /// place among the cleansable ones; `None` for the newest
+    CleanseTarget(Option<usize>),
+    /// F6 to the next speed tier
+    CycleSpeedTier,
+    /// A waymark put down where the player clicked, or taken away
+    Waymark(Waymark, Option<Vec2>),
+}
+
+/// What the sim takes in on the current fixed tick
+#[derive(Resource, Debug, Default)]
+pub struct SimInput {
+    /// This tick's actions, oldest first
+    pub actions: Vec<SimAction>,
+    /// Where the player is steering, held from tick to tick until a `Move` changes it
+    pub movement: Option<Vec2>,
+}
+
+impl SimInput {
+    pub fn has(&self, action: SimAction) -> bool {
+        self.actions.contains(&action)
+    }
+}
+
+/// A place the player types into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    Console,
+    EncounterSearch,
+    CharacterName,
+    /// Waiting for the key to bind to a hotbar slot
+    Keybind,
+}
+
+/// Which text field has the keyboard, if any. While one does, keys are typing: they go to that
+/// field only, and everything else reading the keyboard runs if [`keyboard_free`]
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputFocus(Option<TextField>);
+
+impl InputFocus {
+    /// Takes the keyboard for `field` while it `wants` it and nothing else has it, and gives it
+    /// back once it doesn't. Returns whether `field` has it
+    pub fn hold(&mut self, field: TextField, wants: bool) -> bool {
+        match self.0 {
+            None if wants => self.0 = Some(field),
+            Some(held) if held == field && !wants => self.0 = None,
+            _ => {}
+        }
+        self.0 == Some(field)
+    }
+}
+
+/// Run condition: no text field has the keyboard. Apps without the actions plugin have none
+pub fn keyboard_free(focus: Option<Res<InputFocus>>) -> bool {
+    focus.is_none_or(|focus| focus.0.is_none())
+}
+
+fn release_focus(mut focus: ResMut<InputFocus>) {
+    *focus = InputFocus::default();
+}
+
+/// Steps of `GameSet::InputApply`, in order
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum ActionSet {
+    /// Queued input, macros and presses arriving through the simulated latency make up the tick's actions
+    Collect,
+    /// Replays write the tick's actions down, or swap in the recorded ones
+    Record,
+    /// Systems act on them
+    Apply,
+}
+
+/// The finger steering the player, if any, and where it went down
+#[derive(Resource, Debug, Default)]
+struct TouchJoystick {
+    touch: Option<u64>,
+    origin: Vec2,
+    /// Knob offset from the origin, clamped to the radius
+    knob: Vec2,
+}
+
+#[derive(Component)]
+struct JoystickBase;
+
+#[derive(Component)]
+struct JoystickKnob;
+
+/// Whether a point in logical window pixels is inside a node, which is laid out in physical ones
+fn node_contains(node: &ComputedNode, transform: &GlobalTransform, point: Vec2, scale_factor: f32) -> bool {
+    Rect::from_center_size(transform.translation().truncate(), node.size()).contains(point * scale_factor)
 }
 
 pub fn set_movement_actions(
     mut actions: ResMut<Actions>,
+    mut joystick: ResMut<TouchJoystick>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     touch_input: Res<Touches>,
-    player: Query<&Transform, With<Player>>,
-    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    gamepads: Query<&Gamepad>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_tappable: Query<(Entity, &ComputedNode, &GlobalTransform, &InheritedVisibility), With<Tappable>>,
 ) -> Result {
     let mut player_movement = Vec2::new(
         get_movement(GameControl::Right, &keyboard_input)
@@ -44,23 +188,40 @@ pub fn set_movement_actions(
             - get_movement(GameControl::Down, &keyboard_input),
     );
 
-    if let Some(touch_position) = touch_input.first_pressed_position() {
-        if let Ok((camera, camera_transform)) = camera.single() {
-            if let Ok(touch_position) =
-                camera.viewport_to_world_2d(camera_transform, touch_position)
-            {
-                let diff = touch_position
-                    - player
-                        .single()
-                        .map(|transform| transform.translation.xy())
-                        .unwrap_or(touch_position);
-                if diff.length() > FOLLOW_EPSILON {
-                    player_movement = diff.normalize();
+    if let Some(stick) = gamepads.iter().map(|g| g.left_stick()).find(|s| s.length() > STICK_DEADZONE) {
+        player_movement = stick;
+    }
+
+    actions.taps.clear();
+    let scale_factor = q_window.single().map_or(1.0, |w| w.scale_factor());
+    for touch in touch_input.iter_just_pressed() {
+        let tapped = q_tappable
+            .iter()
+            .find(|(_, node, transform, vis)| vis.get() && node_contains(node, transform, touch.position(), scale_factor));
+        if let Some((entity, ..)) = tapped {
+            actions.taps.push(entity);
+        } else if joystick.touch.is_none() {
+            *joystick = TouchJoystick { touch: Some(touch.id()), origin: touch.position(), knob: Vec2::ZERO };
+        }
+    }
+    if let Some(id) = joystick.touch {
+        match touch_input.get_pressed(id) {
+            Some(touch) => {
+                joystick.knob = (touch.position() - joystick.origin).clamp_length_max(JOYSTICK_RADIUS);
+                // Screen y grows downwards
+                let stick = Vec2::new(joystick.knob.x, -joystick.knob.y) / JOYSTICK_RADIUS;
+                if stick.length() > STICK_DEADZONE {
+                    player_movement = stick;
                 }
             }
+            None => joystick.touch = None,
         }
     }
 
+    if GameControl::Sprint.just_pressed(&keyboard_input) || gamepads.iter().any(|g| g.just_pressed(GamepadButton::LeftThumb)) {
+        actions.queue(SimAction::ToggleSprint);
+    }
+
     if player_movement != Vec2::ZERO {
         actions.player_movement = Some(player_movement.normalize());
     } else {
@@ -69,3 +230,76 @@ pub fn set_movement_actions(
 
     Ok(())
 }
+
+fn reset_sim_input(mut actions: ResMut<Actions>, mut input: ResMut<SimInput>) {
+    actions.queued.clear();
+    *input = SimInput::default();
+}
+
+/// Starts the tick's actions with what was queued since the last one, and a `Move` when the
+/// player changed direction
+pub(crate) fn collect_actions(mut actions: ResMut<Actions>, mut input: ResMut<SimInput>) {
+    input.actions.clear();
+    if actions.player_movement != input.movement {
+        input.actions.push(SimAction::Move(actions.player_movement));
+    }
+    input.actions.append(&mut actions.queued);
+}
+
+fn steer(mut input: ResMut<SimInput>) {
+    if let Some(movement) = input.actions.iter().rev().find_map(|a| match a {
+        SimAction::Move(movement) => Some(*movement),
+        _ => None,
+    }) {
+        input.movement = movement;
+    }
+}
+
+fn spawn_joystick(mut commands: Commands, mut joystick: ResMut<TouchJoystick>) {
+    *joystick = TouchJoystick::default();
+    commands
+        .spawn((
+            Node { position_type: PositionType::Absolute, border: UiRect::all(Val::Px(2.0)), ..default() },
+            BorderColor(Color::linear_rgb(0.9, 0.9, 0.9).with_alpha(0.5)),
+            BorderRadius::MAX,
+            BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.25)),
+            Visibility::Hidden,
+            JoystickBase,
+            StateScoped(GameState::Playing),
+        ))
+        .with_child((
+            Node { position_type: PositionType::Absolute, ..default() },
+            BorderRadius::MAX,
+            BackgroundColor(Color::linear_rgb(0.9, 0.9, 0.9).with_alpha(0.6)),
+            JoystickKnob,
+        ));
+}
+
+/// Shows the joystick under the finger steering, knob following it. The joystick works in
+/// window pixels, so it's laid out unscaled by the HUD scale
+fn update_joystick(
+    joystick: Res<TouchJoystick>,
+    ui_scale: Res<UiScale>,
+    mut q_base: Query<(&mut Node, &mut Visibility), (With<JoystickBase>, Without<JoystickKnob>)>,
+    mut q_knob: Query<&mut Node, With<JoystickKnob>>,
+) {
+    let Ok((mut base, mut vis)) = q_base.single_mut() else { return; };
+    if joystick.touch.is_none() {
+        vis.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    *vis = Visibility::Inherited;
+    let px = |window_px: f32| Val::Px(window_px / ui_scale.0.max(0.01));
+    base.left = px(joystick.origin.x - JOYSTICK_RADIUS);
+    base.top = px(joystick.origin.y - JOYSTICK_RADIUS);
+    base.width = px(JOYSTICK_RADIUS * 2.0);
+    base.height = base.width;
+    if let Ok(mut knob) = q_knob.single_mut() {
+        // Placed from inside the base's 2px border
+        let border = 2.0 * ui_scale.0;
+        knob.left = px(JOYSTICK_RADIUS / 2.0 - border + joystick.knob.x);
+        knob.top = px(JOYSTICK_RADIUS / 2.0 - border + joystick.knob.y);
+        knob.width = px(JOYSTICK_RADIUS);
+        knob.height = knob.width;
+    }
+}
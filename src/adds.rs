@@ -0,0 +1,298 @@
+use bevy::prelude::*;
+
+use crate::actions::{ActionSet, Actions, SimAction, SimInput};
+use crate::combat::EncounterProgress;
+use crate::console::{CommandResult, ConsoleAppExt};
+use crate::loading::TextureAssets;
+use crate::sim_time::SimTime;
+use crate::waymarks::{CalloutEvent, WaymarkPlacement};
+use crate::world::{Armor, CurrentTarget, DotEffects, Enemy, Health};
+use crate::{vfx, GameSet, GameState};
+
+// Adds: smaller enemies the timeline brings in next to the boss. They have to be
+// targeted (Tab cycles through everything alive) and killed; one left up for too long
+// empowers the boss with extra armor. Every enemy is listed with its HP on the left.
+
+pub struct AddsPlugin;
+
+impl Plugin for AddsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnAddsEvent>()
+            .add_console_cheat("spawn", "spawn add [count]: brings in adds", spawn_command)
+            .add_systems(OnEnter(GameState::Playing), spawn_enemy_list)
+            .add_systems(
+                FixedUpdate,
+                (spawn_adds, empower_boss, despawn_dead_adds)
+                    .chain()
+                    .in_set(GameSet::Sim)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(PreUpdate, read_tab.in_set(GameSet::InputRead).run_if(in_state(GameState::Playing)))
+            .add_systems(FixedUpdate, cycle_target.in_set(ActionSet::Apply).run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                ((rebuild_enemy_list, update_enemy_list).chain(), draw_target_ring)
+                    .in_set(GameSet::Ui)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SpawnAddsEvent {
+    pub count: u32,
+}
+
+#[derive(Component, Debug)]
+pub struct Add {
+    pub name: String,
+    /// Seconds left before it empowers the boss
+    pub empower_in: f32,
+    pub empowered: bool,
+}
+
+const ADD_HP: i32 = 400;
+const ADD_EMPOWER_SECS: f32 = 20.0;
+/// Armor the boss gains from each add left alive too long
+const ADD_EMPOWER_ARMOR: f32 = 15.0;
+/// Adds appear in a ring this far from the boss
+const ADD_SPAWN_RADIUS: f32 = 110.0;
+
+fn spawn_adds(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    mut evr: EventReader<SpawnAddsEvent>,
+    mut progress: ResMut<EncounterProgress>,
+    q_boss: Query<&Transform, With<Enemy>>,
+    q_adds: Query<(), With<Add>>,
+) {
+    let boss = q_boss.single().map(|t| t.translation.truncate()).unwrap_or(Vec2::ZERO);
+    let mut n = q_adds.iter().count();
+    for ev in evr.read() {
+        for _ in 0..ev.count {
+            n += 1;
+            // Spread them around the boss so they don't stack on one spot
+            let pos = boss + Vec2::from_angle(n as f32 * 2.4) * ADD_SPAWN_RADIUS;
+            commands.spawn((
+                Sprite {
+                    image: textures.github.clone(),
+                    color: Color::linear_rgb(0.9, 0.6, 0.4),
+                    custom_size: Some(Vec2::splat(56.0)),
+                    ..default()
+                },
+                Transform::from_translation(pos.extend(0.4)),
+                Add { name: format!("Add {n}"), empower_in: ADD_EMPOWER_SECS, empowered: false },
+                Health { current: ADD_HP, max: ADD_HP },
+                Armor::new(20.0),
+                DotEffects::default(),
+                vfx::VfxAuras::default(),
+                StateScoped(GameState::Playing),
+            ));
+            progress.adds_alive += 1;
+        }
+    }
+}
+
+/// Console: `spawn add 2`, during a pull
+fn spawn_command(
+    In(args): In<Vec<String>>,
+    state: Res<State<GameState>>,
+    mut writer: EventWriter<SpawnAddsEvent>,
+) -> CommandResult {
+    if args.first().map(String::as_str) != Some("add") {
+        return Err("usage: spawn add [count]".to_string());
+    }
+    if *state.get() != GameState::Playing {
+        return Err("adds only come in during a pull".to_string());
+    }
+    let count = match args.get(1) {
+        Some(count) => count.parse().map_err(|_| format!("can't read {count:?} as a count"))?,
+        None => 1,
+    };
+    writer.write(SpawnAddsEvent { count });
+    Ok(format!("{count} add(s) coming in"))
+}
+
+fn empower_boss(
+    time: SimTime,
+    mut q_adds: Query<&mut Add>,
+    mut q_boss: Query<&mut Armor, With<Enemy>>,
+    mut callout_writer: EventWriter<CalloutEvent>,
+) {
+    let dt = time.scaled_delta();
+    for mut add in &mut q_adds {
+        if add.empowered {
+            continue;
+        }
+        add.empower_in -= dt;
+        if add.empower_in > 0.0 {
+            continue;
+        }
+        add.empowered = true;
+        if let Ok(mut armor) = q_boss.single_mut() {
+            armor.base += ADD_EMPOWER_ARMOR;
+        }
+        callout_writer.write(CalloutEvent { text: format!("{} empowers the boss", add.name), waymark: None });
+    }
+}
+
+fn despawn_dead_adds(
+    mut commands: Commands,
+    mut progress: ResMut<EncounterProgress>,
+    mut target: ResMut<CurrentTarget>,
+    q_adds: Query<(Entity, &Transform, &Health), With<Add>>,
+) {
+    for (e, transform, hp) in &q_adds {
+        if hp.current > 0 {
+            continue;
+        }
+        commands.entity(e).despawn();
+        progress.adds_alive = progress.adds_alive.saturating_sub(1);
+        if target.0 == Some(e) {
+            target.0 = None;
+        }
+        vfx::vfx_play(&mut commands, "retro_explosion", transform.translation, None);
+    }
+}
+
+/// Shift+Tab is the focus key instead, see [`crate::unit_frames`]
+fn read_tab(keys: Res<ButtonInput<KeyCode>>, placement: Res<WaymarkPlacement>, mut actions: ResMut<Actions>) {
+    if placement.active || !keys.just_pressed(KeyCode::Tab) || keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        return;
+    }
+    actions.queue(SimAction::CycleTarget);
+}
+
+/// Tab goes to the next enemy: boss first, then adds in spawn order
+fn cycle_target(
+    input: Res<SimInput>,
+    mut target: ResMut<CurrentTarget>,
+    q_boss: Query<Entity, With<Enemy>>,
+    q_adds: Query<(Entity, &Health), With<Add>>,
+) {
+    if !input.has(SimAction::CycleTarget) {
+        return;
+    }
+    let mut adds: Vec<Entity> = q_adds.iter().filter(|(_, hp)| hp.current > 0).map(|(e, _)| e).collect();
+    adds.sort();
+    let enemies: Vec<Entity> = q_boss.iter().chain(adds).collect();
+    let current = target.0.or(q_boss.single().ok());
+    let next = match enemies.iter().position(|e| Some(*e) == current) {
+        Some(i) => enemies[(i + 1) % enemies.len()],
+        None => return,
+    };
+    target.0 = Some(next);
+}
+
+// ==== Enemy list ====
+
+#[derive(Component)]
+struct EnemyList;
+
+fn spawn_enemy_list(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(140.0),
+            left: Val::Px(10.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(3.0),
+            ..default()
+        },
+        EnemyList,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+/// Label of the row for an enemy
+#[derive(Component)]
+struct EnemyRowLabel(Entity);
+
+/// HP fill of the row for an enemy
+#[derive(Component)]
+struct EnemyRowFill(Entity);
+
+/// One row per enemy: target arrow, name and HP bar. Rows are only rebuilt when an enemy
+/// comes or goes; [`update_enemy_list`] keeps them current in between
+fn rebuild_enemy_list(
+    mut commands: Commands,
+    q_new: Query<(), Or<(Added<Enemy>, Added<Add>, Added<EnemyList>)>>,
+    mut removed_adds: RemovedComponents<Add>,
+    mut removed_enemies: RemovedComponents<Enemy>,
+    q_boss: Query<Entity, With<Enemy>>,
+    q_adds: Query<Entity, With<Add>>,
+    list: Query<Entity, With<EnemyList>>,
+    q_children: Query<&Children>,
+) {
+    let removed = removed_adds.read().count() + removed_enemies.read().count() > 0;
+    if q_new.is_empty() && !removed {
+        return;
+    }
+    let Ok(list_entity) = list.single() else { return; };
+    if let Ok(children) = q_children.get(list_entity) {
+        for child in children.iter() {
+            commands.entity(child).despawn();
+        }
+    }
+    commands.entity(list_entity).with_children(|l| {
+        for e in q_boss.iter().chain(&q_adds) {
+            l.spawn(Node { flex_direction: FlexDirection::Row, align_items: AlignItems::Center, column_gap: Val::Px(6.0), ..default() })
+                .with_children(|row| {
+                    row.spawn((
+                        Text::new(""),
+                        TextFont { font_size: 13.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                        Node { width: Val::Px(90.0), ..default() },
+                        EnemyRowLabel(e),
+                    ));
+                    row.spawn((
+                        Node { width: Val::Px(120.0), height: Val::Px(8.0), ..default() },
+                        BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05)),
+                    ))
+                    .with_child((
+                        Node { width: Val::Percent(0.0), height: Val::Percent(100.0), ..default() },
+                        BackgroundColor(Color::linear_rgb(0.8, 0.2, 0.2)),
+                        EnemyRowFill(e),
+                    ));
+                });
+        }
+    });
+}
+
+/// Target arrow, empowered mark and HP on the rows as they are
+fn update_enemy_list(
+    target: Res<CurrentTarget>,
+    q_boss: Query<Entity, With<Enemy>>,
+    q_enemies: Query<(&Health, Option<&Add>)>,
+    mut q_labels: Query<(&EnemyRowLabel, &mut Text)>,
+    mut q_fills: Query<(&EnemyRowFill, &mut Node)>,
+) {
+    let targeted = target.0.or(q_boss.single().ok());
+    for (label, mut text) in &mut q_labels {
+        let Ok((_, add)) = q_enemies.get(label.0) else { continue; };
+        let marker = if Some(label.0) == targeted { ">" } else { " " };
+        let name = add.map_or("Boss", |add| add.name.as_str());
+        let empowered = if add.is_some_and(|add| add.empowered) { " (!)" } else { "" };
+        let wanted = format!("{marker} {name}{empowered}");
+        if text.0 != wanted {
+            text.0 = wanted;
+        }
+    }
+    for (fill, mut node) in &mut q_fills {
+        let Ok((hp, _)) = q_enemies.get(fill.0) else { continue; };
+        let pct = if hp.max > 0 { (hp.current as f32 / hp.max as f32).clamp(0.0, 1.0) * 100.0 } else { 0.0 };
+        if node.width != Val::Percent(pct) {
+            node.width = Val::Percent(pct);
+        }
+    }
+}
+
+fn draw_target_ring(
+    mut gizmos: Gizmos,
+    target: Res<CurrentTarget>,
+    q_adds: Query<&Transform, With<Add>>,
+) {
+    // The boss is the default target, so it only needs a ring when an add is picked
+    let Some(transform) = target.0.and_then(|e| q_adds.get(e).ok()) else { return; };
+    gizmos.circle_2d(transform.translation.truncate(), 40.0, Color::linear_rgb(1.0, 0.9, 0.3));
+}
@@ -1,5 +1,10 @@
+use std::sync::Arc;
+
 use crate::actions::Actions;
+use crate::calibration::ClickSound;
+use crate::combat::{ActionErrorEvent, BossPhaseEvent, MetronomeTickEvent};
 use crate::loading::AudioAssets;
+use crate::settings::Settings;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
@@ -10,24 +15,84 @@ pub struct InternalAudioPlugin;
 impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(AudioPlugin)
+            .init_resource::<AudioUnlocked>()
+            .add_systems(Startup, make_thud_sound)
+            .add_systems(PreUpdate, unlock_audio)
             .add_systems(OnEnter(GameState::Playing), start_audio)
-            .add_systems(Update, control_flying_sound.run_if(in_state(GameState::Playing)));
+            .add_systems(OnExit(GameState::Playing), stop_audio)
+            .add_systems(
+                Update,
+                (control_flying_sound, change_music_on_boss_phase, play_metronome_tick, play_error_thud)
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
 #[derive(Resource)]
 struct FlyingAudio(Handle<AudioInstance>);
 
-fn start_audio(mut commands: Commands, audio_assets: Res<AudioAssets>, audio: Res<Audio>) {
+/// In the browser, Web Audio stays suspended until the page gets its first input (see
+/// `build/web/sound.js`). Until then the backend doesn't take commands off its queue, so short
+/// sounds sent meanwhile would fill it up and then all go off at once; those are skipped
+#[derive(Resource)]
+struct AudioUnlocked(bool);
+
+impl Default for AudioUnlocked {
+    fn default() -> Self {
+        AudioUnlocked(!cfg!(target_arch = "wasm32"))
+    }
+}
+
+fn unlock_audio(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    mut unlocked: ResMut<AudioUnlocked>,
+) {
+    if unlocked.0 {
+        return;
+    }
+    if keys.get_just_pressed().next().is_some() || mouse.get_just_pressed().next().is_some() || touches.any_just_pressed() {
+        unlocked.0 = true;
+    }
+}
+
+/// Played when a press is turned down
+#[derive(Resource)]
+struct ThudSound(Handle<AudioSource>);
+
+/// A dull 90Hz knock that dies away quickly, so it reads as "no" rather than as a beat
+fn make_thud_sound(mut commands: Commands, mut sources: ResMut<Assets<AudioSource>>) {
+    const SAMPLE_RATE: u32 = 44_100;
+    let frames: Vec<Frame> = (0..SAMPLE_RATE / 10)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            Frame::from_mono((t * 90.0 * std::f32::consts::TAU).sin() * (-t * 40.0).exp() * 0.9)
+        })
+        .collect();
+    let sound = StaticSoundData {
+        sample_rate: SAMPLE_RATE,
+        frames: Arc::from(frames),
+        settings: StaticSoundSettings::default(),
+        slice: None,
+    };
+    commands.insert_resource(ThudSound(sources.add(AudioSource { sound })));
+}
+
+fn start_audio(mut commands: Commands, audio_assets: Res<AudioAssets>, audio: Res<Audio>, settings: Res<Settings>) {
     audio.pause();
     let handle = audio
         .play(audio_assets.flying.clone())
         .looped()
-        .with_volume(0.3)
+        .with_volume(0.3 * f64::from(settings.music_volume))
         .handle();
     commands.insert_resource(FlyingAudio(handle));
 }
 
+fn stop_audio(audio: Res<Audio>) {
+    audio.stop();
+}
+
 fn control_flying_sound(
     actions: Res<Actions>,
     audio: Res<FlyingAudio>,
@@ -49,3 +114,40 @@ fn control_flying_sound(
         }
     }
 }
+
+/// Each boss phase plays the track at its own rate, so later phases sound more frantic
+fn change_music_on_boss_phase(
+    mut evr: EventReader<BossPhaseEvent>,
+    audio: Res<FlyingAudio>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    let Some(BossPhaseEvent { music_rate, .. }) = evr.read().last() else { return; };
+    if let Some(instance) = audio_instances.get_mut(&audio.0) {
+        instance.set_playback_rate(*music_rate, AudioTween::default());
+    }
+}
+
+fn play_metronome_tick(
+    mut evr: EventReader<MetronomeTickEvent>,
+    audio: Res<Audio>,
+    click: Res<ClickSound>,
+    settings: Res<Settings>,
+    unlocked: Res<AudioUnlocked>,
+) {
+    if evr.read().count() > 0 && unlocked.0 {
+        audio.play(click.0.clone()).with_volume(0.5 * f64::from(settings.effects_volume));
+    }
+}
+
+/// One thud a frame however many presses were turned down
+fn play_error_thud(
+    mut evr: EventReader<ActionErrorEvent>,
+    audio: Res<Audio>,
+    thud: Res<ThudSound>,
+    settings: Res<Settings>,
+    unlocked: Res<AudioUnlocked>,
+) {
+    if evr.read().count() > 0 && unlocked.0 {
+        audio.play(thud.0.clone()).with_volume(0.5 * f64::from(settings.effects_volume));
+    }
+}
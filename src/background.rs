@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::combat::{CurrentEncounter, EncounterLibrary};
+use crate::player::Player;
+use crate::{vfx, GameSet, GameState};
+
+// Arena backdrops: sprite layers behind the floor that shift against the player's
+// movement by different amounts, so it's easier to judge how far and which way you've
+// moved. Encounters list their own layers; those that don't get a plain starfield.
+//
+// Tiled layers can also drift on their own, like clouds or fog going by, and a layer can be
+// put in front of the arena instead, see-through, for mist over the floor. Encounters can
+// add weather too: effects from the VFX files looped over the arena for the whole pull, like
+// falling snow or rising embers.
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_background).add_systems(
+            Update,
+            (scroll_parallax, animate_tiles).in_set(GameSet::Ui).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// One backdrop layer; the first layer in the list is drawn furthest back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundLayer {
+    /// Image relative to `assets/`; without one the layer is a flat color
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default = "white")]
+    pub color: (f32, f32, f32),
+    /// Area covered, in world units
+    pub size: (f32, f32),
+    /// Repeat the image at this scale of its own size instead of stretching it over the layer
+    #[serde(default)]
+    pub tile: Option<f32>,
+    /// How far the layer shifts against the player's movement: 0 stays put, 1 moves as
+    /// far as the player does
+    #[serde(default)]
+    pub parallax: f32,
+    #[serde(default)]
+    pub animation: Option<TileAnimation>,
+    /// World units a second a tiled layer scrolls on its own, wrapping around a tile
+    #[serde(default)]
+    pub drift: (f32, f32),
+    #[serde(default = "opaque")]
+    pub opacity: f32,
+    /// Drawn over the arena and everyone in it rather than behind
+    #[serde(default)]
+    pub front: bool,
+}
+
+/// Plays the image as a sprite sheet, left to right and top to bottom
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TileAnimation {
+    /// Size of one frame in pixels
+    pub frame: (u32, u32),
+    pub columns: u32,
+    pub rows: u32,
+    pub fps: f32,
+}
+
+fn white() -> (f32, f32, f32) {
+    (1.0, 1.0, 1.0)
+}
+
+fn opaque() -> f32 {
+    1.0
+}
+
+/// Used by encounters without a `background`
+pub fn default_background() -> Vec<BackgroundLayer> {
+    vec![
+        BackgroundLayer {
+            image: None,
+            color: (0.02, 0.02, 0.05),
+            size: (2000.0, 1400.0),
+            tile: None,
+            parallax: 0.0,
+            animation: None,
+            drift: (0.0, 0.0),
+            opacity: 1.0,
+            front: false,
+        },
+        BackgroundLayer {
+            image: Some("textures/smallstar.png".to_string()),
+            color: (0.3, 0.3, 0.45),
+            size: (2000.0, 1400.0),
+            tile: Some(0.5),
+            parallax: 0.05,
+            animation: None,
+            drift: (0.0, 0.0),
+            opacity: 1.0,
+            front: false,
+        },
+    ]
+}
+
+/// Backdrops sit behind the arena floor
+const BACKGROUND_Z: f32 = -10.0;
+/// Front layers sit over everything in the arena, effects included
+const FOREGROUND_Z: f32 = 5.0;
+
+#[derive(Component)]
+struct ParallaxLayer {
+    factor: f32,
+    drift: Vec2,
+    /// How far it has drifted, within one tile
+    drifted: Vec2,
+    /// Scale of a tiled layer's image, and the frame size for a sprite sheet
+    tile: Option<f32>,
+    frame: Option<Vec2>,
+}
+
+#[derive(Component)]
+struct AnimatedTile {
+    frames: usize,
+    fps: f32,
+    elapsed: f32,
+}
+
+fn spawn_background(
+    mut commands: Commands,
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let def = library.get(&encounter.id);
+    let layers = def.map(|def| def.background.clone()).unwrap_or_else(default_background);
+    for (i, layer) in layers.iter().enumerate() {
+        let (r, g, b) = layer.color;
+        let mut sprite = Sprite {
+            color: Color::linear_rgb(r, g, b).with_alpha(layer.opacity),
+            custom_size: Some(Vec2::new(layer.size.0, layer.size.1)),
+            ..default()
+        };
+        if let Some(path) = &layer.image {
+            sprite.image = asset_server.load(path.clone());
+        }
+        if let Some(scale) = layer.tile {
+            sprite.image_mode = SpriteImageMode::Tiled { tile_x: true, tile_y: true, stretch_value: scale };
+        }
+        if let Some(animation) = layer.animation {
+            let layout = TextureAtlasLayout::from_grid(
+                UVec2::new(animation.frame.0, animation.frame.1),
+                animation.columns,
+                animation.rows,
+                None,
+                None,
+            );
+            sprite.texture_atlas = Some(TextureAtlas { layout: layouts.add(layout), index: 0 });
+        }
+        let z = if layer.front { FOREGROUND_Z } else { BACKGROUND_Z } + i as f32 * 0.1;
+        let mut entity = commands.spawn((
+            sprite,
+            Transform::from_translation(Vec3::new(0.0, 0.0, z)),
+            ParallaxLayer {
+                factor: layer.parallax,
+                drift: Vec2::from(layer.drift),
+                drifted: Vec2::ZERO,
+                tile: layer.tile,
+                frame: layer.animation.map(|a| UVec2::new(a.frame.0, a.frame.1).as_vec2()),
+            },
+            StateScoped(GameState::Playing),
+        ));
+        if let Some(animation) = layer.animation {
+            let frames = (animation.columns * animation.rows) as usize;
+            entity.insert(AnimatedTile { frames, fps: animation.fps, elapsed: 0.0 });
+        }
+    }
+    let weather: Vec<&str> = def.map(|def| def.weather.iter().map(String::as_str).collect()).unwrap_or_default();
+    if !weather.is_empty() {
+        let mut auras = vfx::VfxAuras::default();
+        auras.set(&weather);
+        commands.spawn((Transform::default(), auras, StateScoped(GameState::Playing)));
+    }
+}
+
+fn scroll_parallax(
+    time: Res<Time>,
+    images: Res<Assets<Image>>,
+    q_player: Query<&Transform, (With<Player>, Without<ParallaxLayer>)>,
+    mut q_layers: Query<(&mut Transform, &Sprite, &mut ParallaxLayer)>,
+) {
+    let player = q_player.single().map_or(Vec2::ZERO, |t| t.translation.truncate());
+    for (mut transform, sprite, mut layer) in &mut q_layers {
+        // Shifting by less than a tile doesn't show, so a drifting layer never runs out. Until
+        // its image has loaded there's no telling how big a tile is, so it holds still
+        let image = layer.frame.or_else(|| images.get(&sprite.image).map(|image| image.size_f32()));
+        let tile = layer.tile.zip(image).map(|(scale, size)| size * scale);
+        if let Some(tile) = tile.filter(|t| t.min_element() > 0.0) {
+            let drifted = layer.drifted + layer.drift * time.delta_secs();
+            layer.drifted = drifted.rem_euclid(tile);
+        }
+        let offset = -player * layer.factor + layer.drifted;
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+}
+
+// Ambient, so it keeps real time rather than sim time
+fn animate_tiles(time: Res<Time>, mut q: Query<(&mut Sprite, &mut AnimatedTile)>) {
+    for (mut sprite, mut tile) in &mut q {
+        tile.elapsed += time.delta_secs();
+        let index = (tile.elapsed * tile.fps) as usize % tile.frames.max(1);
+        if let Some(atlas) = sprite.texture_atlas.as_mut() {
+            atlas.index = index;
+        }
+    }
+}
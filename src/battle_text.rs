@@ -0,0 +1,309 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::settings::Settings;
+use crate::sim_time::SimTime;
+use crate::world::DamageDealtEvent;
+use crate::{GameSet, GameState};
+
+// Scrolling battle text: the numbers floating off whatever took damage, and the positional
+// verdict under hits that have one. How they behave is picked on the settings panel: DoT ticks
+// summed into one number per target, hits landing close together summed the same way, big
+// gold crits or plain ones, which way they drift, a cap on how many are up at once (the oldest
+// go first) and a minimal mode that leaves out everything but crits and big hits.
+//
+// Numbers that finish are hidden and kept for the next one rather than despawned, so a
+// multi-target DoT phase reuses the same few dozen entities instead of churning through them.
+
+pub struct BattleTextPlugin;
+
+impl Plugin for BattleTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingNumbers>()
+            .init_resource::<BattleTextPool>()
+            .add_systems(OnEnter(GameState::Playing), reset_battle_text)
+            .add_systems(
+                Update,
+                (spawn_battle_text, flush_pending, cap_battle_text, animate_battle_text)
+                    .chain()
+                    .in_set(GameSet::Ui)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrollDirection {
+    #[default]
+    Up,
+    Down,
+    Sideways,
+}
+
+impl ScrollDirection {
+    pub fn cycle(self) -> Self {
+        match self {
+            ScrollDirection::Up => ScrollDirection::Down,
+            ScrollDirection::Down => ScrollDirection::Sideways,
+            ScrollDirection::Sideways => ScrollDirection::Up,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ScrollDirection::Up => "up",
+            ScrollDirection::Down => "down",
+            ScrollDirection::Sideways => "sideways",
+        }
+    }
+
+    fn velocity(self, rng: &mut impl Rng) -> Vec2 {
+        let speed = rng.gen_range(30.0..60.0);
+        match self {
+            ScrollDirection::Up => Vec2::new(0.0, speed),
+            ScrollDirection::Down => Vec2::new(0.0, -speed),
+            ScrollDirection::Sideways => Vec2::new(if rng.gen_bool(0.5) { speed } else { -speed }, 10.0),
+        }
+    }
+}
+
+/// The battle text part of [`Settings`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BattleTextSettings {
+    /// Sum each target's DoT ticks over `COMBINE_SECS` into one number
+    pub combine_ticks: bool,
+    /// Sum each target's hits landing within `MERGE_SECS` into one number. Hits with a
+    /// positional still show on their own
+    pub merge_hits: bool,
+    /// Crits bigger and gold with a "!", or drawn like any other hit
+    pub big_crits: bool,
+    pub direction: ScrollDirection,
+    /// Numbers up at once; the oldest go first
+    pub max_on_screen: usize,
+    /// Only crits and hits of at least `MINIMAL_AMOUNT`
+    pub minimal: bool,
+}
+
+impl Default for BattleTextSettings {
+    fn default() -> Self {
+        BattleTextSettings {
+            combine_ticks: false,
+            merge_hits: false,
+            big_crits: true,
+            direction: ScrollDirection::Up,
+            max_on_screen: 40,
+            minimal: false,
+        }
+    }
+}
+
+impl BattleTextSettings {
+    /// Steps the cap through a few sizes
+    pub fn cycle_max(&mut self) {
+        self.max_on_screen = match self.max_on_screen {
+            ..=10 => 20,
+            11..=20 => 40,
+            21..=40 => 80,
+            _ => 10,
+        };
+    }
+}
+
+/// Seconds of DoT ticks summed into one number when combining
+const COMBINE_SECS: f32 = 1.0;
+/// Seconds of hits summed into one number when merging
+const MERGE_SECS: f32 = 0.3;
+/// Smallest hit that still shows in minimal mode
+const MINIMAL_AMOUNT: i32 = 250;
+/// Seconds a number stays up, fading out as it goes
+const NUMBER_SECS: f32 = 0.8;
+
+#[derive(Component)]
+struct BattleText {
+    ttl: f32,
+    vel: Vec2,
+}
+
+/// Damage waiting to be shown as one number
+#[derive(Debug, Default)]
+struct Pending {
+    sum: i32,
+    count: u32,
+    /// Seconds since the first
+    age: f32,
+    crit: bool,
+}
+
+/// Combined ticks and merged hits, by target and whether they're ticks
+#[derive(Resource, Debug, Default)]
+struct PendingNumbers(HashMap<(Entity, bool), Pending>);
+
+/// Hidden numbers free to be shown again
+#[derive(Resource, Debug, Default)]
+struct BattleTextPool(Vec<Entity>);
+
+/// The pool's entities went with the last pull's state, so it starts empty too
+fn reset_battle_text(mut pending: ResMut<PendingNumbers>, mut pool: ResMut<BattleTextPool>) {
+    pending.0.clear();
+    pool.0.clear();
+}
+
+/// Puts a number up on a free pooled entity, or a new one if none are free
+fn show(commands: &mut Commands, pool: &mut BattleTextPool, number: impl Bundle) {
+    match pool.0.pop() {
+        Some(e) => {
+            commands.entity(e).insert((number, Visibility::Inherited));
+        }
+        None => {
+            commands.spawn((number, Visibility::Inherited, StateScoped(GameState::Playing)));
+        }
+    }
+}
+
+fn spawn_number(
+    commands: &mut Commands,
+    pool: &mut BattleTextPool,
+    settings: &BattleTextSettings,
+    at: Vec3,
+    label: String,
+    crit: bool,
+    positional: Option<bool>,
+) {
+    let rng = &mut rand::thread_rng();
+    let start = at + Vec3::new(rng.gen_range(-10.0..10.0), 40.0, 1.0);
+    let vel = settings.direction.velocity(rng);
+    let (label, size, color) = if crit && settings.big_crits {
+        (format!("{label}!"), 30.0, Color::linear_rgb(1.0, 0.8, 0.2))
+    } else {
+        (label, 22.0, Color::linear_rgb(1.0, 0.9, 0.9))
+    };
+    show(
+        commands,
+        pool,
+        (
+            Text2d::new(label),
+            TextFont { font_size: size, ..default() },
+            TextColor(color),
+            Transform::from_translation(start),
+            BattleText { ttl: NUMBER_SECS, vel },
+        ),
+    );
+    if let Some(hit) = positional {
+        let (label, color) = if hit {
+            ("Positional!", Color::linear_rgb(0.4, 1.0, 0.5))
+        } else {
+            ("Positional missed", Color::linear_rgb(0.6, 0.6, 0.6))
+        };
+        show(
+            commands,
+            pool,
+            (
+                Text2d::new(label),
+                TextFont { font_size: 14.0, ..default() },
+                TextColor(color),
+                Transform::from_translation(start - Vec3::new(0.0, 20.0, 0.0)),
+                BattleText { ttl: NUMBER_SECS, vel },
+            ),
+        );
+    }
+}
+
+fn spawn_battle_text(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut pending: ResMut<PendingNumbers>,
+    mut pool: ResMut<BattleTextPool>,
+    mut evr: EventReader<DamageDealtEvent>,
+    q_targets: Query<&Transform>,
+) {
+    let settings = &settings.battle_text;
+    for ev in evr.read() {
+        let batched = if ev.tick { settings.combine_ticks } else { settings.merge_hits && ev.positional.is_none() };
+        if batched {
+            let entry = pending.0.entry((ev.target, ev.tick)).or_default();
+            entry.sum += ev.amount;
+            entry.count += 1;
+            // Combined ticks have always been drawn plain, crit or not
+            entry.crit |= ev.crit && !ev.tick;
+            continue;
+        }
+        // Positionals are feedback on the player's position, so they show even in minimal mode
+        let shown = !settings.minimal || ev.crit || ev.amount >= MINIMAL_AMOUNT;
+        if !shown && ev.positional.is_none() {
+            continue;
+        }
+        let Ok(transform) = q_targets.get(ev.target) else { continue; };
+        let label = if shown { ev.amount.to_string() } else { String::new() };
+        spawn_number(&mut commands, &mut pool, settings, transform.translation, label, ev.crit && shown, ev.positional);
+    }
+}
+
+/// Shows combined ticks and merged hits once their window is up, or right away if that
+/// option was turned off
+fn flush_pending(
+    time: SimTime,
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut pending: ResMut<PendingNumbers>,
+    mut pool: ResMut<BattleTextPool>,
+    q_targets: Query<&Transform>,
+) {
+    let settings = &settings.battle_text;
+    let dt = time.scaled_delta();
+    pending.0.retain(|(target, tick), batch| {
+        batch.age += dt;
+        let (window, on) =
+            if *tick { (COMBINE_SECS, settings.combine_ticks) } else { (MERGE_SECS, settings.merge_hits) };
+        if batch.age < window && on {
+            return true;
+        }
+        if settings.minimal && !batch.crit && batch.sum < MINIMAL_AMOUNT {
+            return false;
+        }
+        if let Ok(transform) = q_targets.get(*target) {
+            let Pending { sum, count, crit, .. } = *batch;
+            let label = if count > 1 { format!("{sum} ({count})") } else { sum.to_string() };
+            spawn_number(&mut commands, &mut pool, settings, transform.translation, label, crit, None);
+        }
+        false
+    });
+}
+
+/// Ends the oldest numbers past the cap; `animate_battle_text` puts them back in the pool
+fn cap_battle_text(settings: Res<Settings>, mut q: Query<(&mut BattleText, &Visibility)>) {
+    let mut numbers: Vec<Mut<BattleText>> =
+        q.iter_mut().filter(|(_, vis)| **vis != Visibility::Hidden).map(|(text, _)| text).collect();
+    let excess = numbers.len().saturating_sub(settings.battle_text.max_on_screen);
+    if excess == 0 {
+        return;
+    }
+    numbers.sort_by(|a, b| a.ttl.total_cmp(&b.ttl));
+    for mut text in numbers.into_iter().take(excess) {
+        text.ttl = 0.0;
+    }
+}
+
+fn animate_battle_text(
+    time: SimTime,
+    mut pool: ResMut<BattleTextPool>,
+    mut q: Query<(Entity, &mut Transform, &mut TextColor, &mut BattleText, &mut Visibility)>,
+) {
+    let dt = time.scaled_delta();
+    for (e, mut tf, mut color, mut num, mut vis) in &mut q {
+        if *vis == Visibility::Hidden {
+            continue;
+        }
+        num.ttl -= dt;
+        tf.translation.x += num.vel.x * dt;
+        tf.translation.y += num.vel.y * dt;
+        let a = (num.ttl / NUMBER_SECS).clamp(0.0, 1.0);
+        color.0 = color.0.with_alpha(a);
+        if num.ttl <= 0.0 {
+            *vis = Visibility::Hidden;
+            pool.0.push(e);
+        }
+    }
+}
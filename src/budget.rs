@@ -0,0 +1,136 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::ecs::schedule::ScheduleConfigs;
+use bevy::ecs::system::ScheduleSystem;
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::console::{CommandResult, ConsoleAppExt};
+
+// Frame-time budget: HUD systems wrapped in `timed` report how long they took each frame
+// as diagnostics under `hud/`, next to Bevy's own (frame time and, in debug builds, the
+// log every second). The `budget` console command shows them on screen, slowest first,
+// against the frame time.
+//
+// The time is from just before the system starts to just after it ends, so on a busy
+// frame it can include a little waiting on other systems; it's for spotting a system
+// that costs far more than it should, not for exact numbers.
+
+pub struct BudgetPlugin;
+
+impl Plugin for BudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BudgetView>()
+            .init_resource::<Stopwatches>()
+            .add_console_command("budget", "budget: shows or hides HUD system timings", toggle_budget)
+            .add_systems(Startup, spawn_budget_panel)
+            .add_systems(Last, update_budget_panel);
+    }
+}
+
+/// Prefix of every diagnostic `timed` adds
+const PREFIX: &str = "hud/";
+/// A frame at 60 fps, what the shares are of when there's no frame time to go by
+const FRAME_BUDGET_MS: f64 = 1000.0 / 60.0;
+
+/// Runs `system` between two markers that time it into the `hud/<name>` diagnostic
+pub fn timed<M>(name: &'static str, system: impl IntoScheduleConfigs<ScheduleSystem, M>) -> ScheduleConfigs<ScheduleSystem> {
+    (start_stopwatch(name), system, stop_stopwatch(name)).chain()
+}
+
+/// Start times of the systems running right now, by name
+#[derive(Resource, Default)]
+struct Stopwatches(HashMap<&'static str, Instant>);
+
+// Both markers do nothing in an app without the plugin, like the headless test app
+
+fn start_stopwatch(name: &'static str) -> impl FnMut(Option<ResMut<Stopwatches>>) {
+    move |stopwatches| {
+        if let Some(mut stopwatches) = stopwatches {
+            stopwatches.0.insert(name, Instant::now());
+        }
+    }
+}
+
+fn stop_stopwatch(
+    name: &'static str,
+) -> impl FnMut(Option<ResMut<Stopwatches>>, Option<ResMut<DiagnosticsStore>>) {
+    let path = DiagnosticPath::new(format!("{PREFIX}{name}"));
+    move |stopwatches, store| {
+        let Some(mut stopwatches) = stopwatches else { return; };
+        let (Some(started), Some(mut store)) = (stopwatches.0.remove(name), store) else { return; };
+        if store.get(&path).is_none() {
+            store.add(Diagnostic::new(path.clone()).with_suffix("ms"));
+        }
+        if let Some(diagnostic) = store.get_mut(&path) {
+            let now = Instant::now();
+            diagnostic.add_measurement(DiagnosticMeasurement { time: now, value: (now - started).as_secs_f64() * 1000.0 });
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct BudgetView {
+    open: bool,
+}
+
+#[derive(Component)]
+struct BudgetPanel;
+
+fn toggle_budget(_: In<Vec<String>>, mut view: ResMut<BudgetView>) -> CommandResult {
+    view.open = !view.open;
+    Ok(format!("Budget view {}", if view.open { "on" } else { "off" }))
+}
+
+fn spawn_budget_panel(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont { font_size: 12.0, ..default() },
+        TextColor(Color::linear_rgb(1.0, 0.9, 0.6)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            right: Val::Px(8.0),
+            width: Val::Px(260.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.75)),
+        GlobalZIndex(80),
+        Visibility::Hidden,
+        BudgetPanel,
+    ));
+}
+
+fn update_budget_panel(
+    view: Res<BudgetView>,
+    store: Option<Res<DiagnosticsStore>>,
+    mut q_panel: Query<(&mut Text, &mut Visibility), With<BudgetPanel>>,
+) {
+    let Ok((mut text, mut vis)) = q_panel.single_mut() else { return; };
+    vis.set_if_neq(if view.open { Visibility::Inherited } else { Visibility::Hidden });
+    if !view.open {
+        return;
+    }
+    let Some(store) = store else {
+        text.0 = "No diagnostics in this build".to_string();
+        return;
+    };
+    let frame = store.get(&FrameTimeDiagnosticsPlugin::FRAME_TIME).and_then(|d| d.smoothed());
+    let mut rows: Vec<(&str, f64)> = store
+        .iter()
+        .filter_map(|d| Some((d.path().as_str().strip_prefix(PREFIX)?, d.smoothed()?)))
+        .collect();
+    rows.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let total: f64 = rows.iter().map(|(_, ms)| ms).sum();
+    let budget = frame.unwrap_or(FRAME_BUDGET_MS);
+    let mut lines = vec![match frame {
+        Some(ms) => format!("Frame {ms:.2} ms"),
+        None => format!("Frame -  (shares of {FRAME_BUDGET_MS:.1} ms)"),
+    }];
+    lines.push(format!("HUD   {total:.3} ms  {:.1}%", total / budget * 100.0));
+    for (name, ms) in rows {
+        lines.push(format!("  {name:<16} {ms:.3} ms  {:.1}%", ms / budget * 100.0));
+    }
+    text.0 = lines.join("\n");
+}
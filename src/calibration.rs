@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+
+use crate::combat::Calibration;
+use crate::GameState;
+
+// Latency calibration: a steady beat the player taps along to with Space, first by ear
+// (clicks, nothing on screen) and then by eye (flashes, no sound). The median distance
+// between presses and beats is how late that cue reaches the player, input device included.
+
+pub struct CalibrationPlugin;
+
+impl Plugin for CalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CalibrationRun>()
+            .add_systems(Startup, (load_calibration, make_click_sound))
+            .add_systems(OnEnter(GameState::Calibration), setup_calibration)
+            .add_systems(
+                Update,
+                (play_beats, record_presses, update_calibration_text, click_back_button)
+                    .chain()
+                    .run_if(in_state(GameState::Calibration)),
+            );
+    }
+}
+
+/// Seconds between beats
+const BEAT: f32 = 0.75;
+/// Silence before the first beat of each step
+const LEAD_IN: f32 = 1.5;
+/// Presses kept per step
+const PRESSES_PER_STEP: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Audio,
+    Visual,
+    Done,
+}
+
+#[derive(Resource, Debug)]
+struct CalibrationRun {
+    step: Step,
+    /// Real time the current step started
+    started: f32,
+    beats_played: u32,
+    /// Press minus nearest beat, in seconds
+    offsets: Vec<f32>,
+    audio_ms: f32,
+}
+
+impl Default for CalibrationRun {
+    fn default() -> Self {
+        CalibrationRun { step: Step::Audio, started: 0.0, beats_played: 0, offsets: Vec::new(), audio_ms: 0.0 }
+    }
+}
+
+impl CalibrationRun {
+    fn start(&mut self, step: Step, now: f32) {
+        self.step = step;
+        self.started = now;
+        self.beats_played = 0;
+        self.offsets.clear();
+    }
+
+    fn beat_time(&self, n: u32) -> f32 {
+        self.started + LEAD_IN + n as f32 * BEAT
+    }
+}
+
+fn median_ms(offsets: &[f32]) -> f32 {
+    let mut sorted = offsets.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted.get(sorted.len() / 2).copied().unwrap_or(0.0) * 1000.0
+}
+
+/// Also the GCD metronome's tick
+#[derive(Resource)]
+pub struct ClickSound(pub Handle<AudioSource>);
+
+fn load_calibration(mut calibration: ResMut<Calibration>) {
+    *calibration = Calibration::load();
+}
+
+/// A short decaying 1kHz blip, sharp enough to tap along to
+fn make_click_sound(mut commands: Commands, mut sources: ResMut<Assets<AudioSource>>) {
+    const SAMPLE_RATE: u32 = 44_100;
+    let frames: Vec<Frame> = (0..SAMPLE_RATE / 25)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            Frame::from_mono((t * 1000.0 * std::f32::consts::TAU).sin() * (-t * 120.0).exp() * 0.8)
+        })
+        .collect();
+    let sound = StaticSoundData {
+        sample_rate: SAMPLE_RATE,
+        frames: Arc::from(frames),
+        settings: StaticSoundSettings::default(),
+        slice: None,
+    };
+    commands.insert_resource(ClickSound(sources.add(AudioSource { sound })));
+}
+
+#[derive(Component)]
+struct BeatFlash;
+
+#[derive(Component)]
+struct CalibrationText;
+
+#[derive(Component)]
+struct BackButton;
+
+fn setup_calibration(mut commands: Commands, time: Res<Time<Real>>, mut run: ResMut<CalibrationRun>) {
+    run.start(Step::Audio, time.elapsed_secs());
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            StateScoped(GameState::Calibration),
+        ))
+        .with_children(|children| {
+            children.spawn((
+                Node { width: Val::Px(120.0), height: Val::Px(120.0), ..default() },
+                BackgroundColor(Color::linear_rgb(0.1, 0.1, 0.1)),
+                BeatFlash,
+            ));
+            children.spawn((
+                Text::new(""),
+                TextFont { font_size: 20.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                TextLayout::new_with_justify(JustifyText::Center),
+                CalibrationText,
+            ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(40.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::linear_rgb(0.15, 0.15, 0.15)),
+                    BackButton,
+                ))
+                .with_child((
+                    Text::new("Back"),
+                    TextFont { font_size: 24.0, ..default() },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+        });
+}
+
+// Real time throughout: the sim clock can be slowed down, the player's ears can't
+fn play_beats(
+    time: Res<Time<Real>>,
+    mut run: ResMut<CalibrationRun>,
+    audio: Res<Audio>,
+    click: Res<ClickSound>,
+    mut q_flash: Query<&mut BackgroundColor, With<BeatFlash>>,
+) {
+    let now = time.elapsed_secs();
+    let Ok(mut flash) = q_flash.single_mut() else { return; };
+    if run.step == Step::Done {
+        flash.0 = Color::linear_rgb(0.1, 0.1, 0.1);
+        return;
+    }
+    while now >= run.beat_time(run.beats_played) {
+        if run.step == Step::Audio {
+            audio.play(click.0.clone());
+        }
+        run.beats_played += 1;
+    }
+    // The flash fades over the first part of each beat; the audio step keeps the square dark
+    let since_beat = if run.beats_played > 0 { now - run.beat_time(run.beats_played - 1) } else { BEAT };
+    let glow = if run.step == Step::Visual { (1.0 - since_beat / 0.15).max(0.0) } else { 0.0 };
+    flash.0 = Color::linear_rgb(0.1 + 0.9 * glow, 0.1 + 0.8 * glow, 0.1 + 0.3 * glow);
+}
+
+fn record_presses(
+    time: Res<Time<Real>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut run: ResMut<CalibrationRun>,
+    mut calibration: ResMut<Calibration>,
+) {
+    if run.step == Step::Done || !keys.just_pressed(KeyCode::Space) {
+        return;
+    }
+    let now = time.elapsed_secs();
+    let since_first = now - run.beat_time(0);
+    // Presses before the beat starts don't belong to any beat
+    if since_first < -BEAT * 0.5 {
+        return;
+    }
+    let nearest = (since_first / BEAT).round();
+    run.offsets.push(since_first - nearest * BEAT);
+    if run.offsets.len() < PRESSES_PER_STEP {
+        return;
+    }
+    let measured = median_ms(&run.offsets);
+    match run.step {
+        Step::Audio => {
+            run.audio_ms = measured;
+            run.start(Step::Visual, now);
+        }
+        Step::Visual => {
+            *calibration = Calibration { audio_offset_ms: run.audio_ms, visual_offset_ms: measured };
+            calibration.save();
+            info!("Calibrated: audio {:.0}ms, visual {:.0}ms", calibration.audio_offset_ms, calibration.visual_offset_ms);
+            run.step = Step::Done;
+        }
+        Step::Done => {}
+    }
+}
+
+fn update_calibration_text(
+    run: Res<CalibrationRun>,
+    calibration: Res<Calibration>,
+    mut q_text: Query<&mut Text, With<CalibrationText>>,
+) {
+    let Ok(mut text) = q_text.single_mut() else { return; };
+    let left = PRESSES_PER_STEP - run.offsets.len();
+    text.0 = match run.step {
+        Step::Audio => format!("Press Space on each click\n{left} presses left"),
+        Step::Visual => format!("Now press Space on each flash\n{left} presses left"),
+        Step::Done => format!(
+            "Audio {:.0} ms, display {:.0} ms\nSaved; the GCD bar now runs ahead by the display offset",
+            calibration.audio_offset_ms, calibration.visual_offset_ms
+        ),
+    };
+}
+
+fn click_back_button(
+    mut next_state: ResMut<NextState<GameState>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    q_button: Query<&Interaction, (Changed<Interaction>, With<BackButton>)>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || q_button.iter().any(|i| *i == Interaction::Pressed) {
+        next_state.set(GameState::Menu);
+    }
+}
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::combat::{BossPhaseEvent, CombatState, EnrageEvent, PlayerDamageEvent};
+use crate::mechanics::MechanicResolvedEvent;
+use crate::player::Player;
+use crate::settings::Settings;
+use crate::sim_time::SimTime;
+use crate::world::{DamageDealtEvent, Health};
+use crate::{GameSet, GameState, PlayState};
+
+// Camera effects: the arena camera shakes with trauma, and big crits can freeze the pull
+// for a few frames (hit-stop).
+//
+// Trauma is a 0 to 1 amount that hits, damage taken, failed mechanics, phase changes and
+// the enrage add to, and that drains away on its own. The shake goes with its square, so
+// small hits barely move the camera and a pile of them rattles it. The boss's HUD shake
+// mechanic holds it up for as long as it lasts. Only the camera moves; the HUD is drawn in
+// screen space and stays put. The shake is laid over the camera's resting place, `CameraRest`,
+// which is what turning the cursor into a world position (placing waymarks) and pinning HUD to
+// the world (overhead markers) go through, so neither jumps around with it. How hard it
+// shakes is a setting, down to off.
+//
+// Hit-stop pauses the virtual clock like the pause menu does, for a real-time fraction of
+// a second, so no ticks run through it. It's off by default: presses during the stop are
+// held back with the rest of the sim. Seeking through a replay drives the clock itself and
+// never stops.
+
+pub struct CameraFxPlugin;
+
+impl Plugin for CameraFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trauma>()
+            .init_resource::<HitStop>()
+            .add_systems(OnExit(GameState::Playing), reset_camera_fx)
+            .add_systems(
+                Update,
+                (add_trauma, shake_camera, start_hit_stop)
+                    .chain()
+                    .in_set(GameSet::Ui)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Update, end_hit_stop);
+    }
+}
+
+/// Trauma drained per second
+const TRAUMA_DECAY: f32 = 1.2;
+/// Camera offset and roll at full trauma and a shake setting of 1
+const MAX_OFFSET: f32 = 14.0;
+const MAX_ROLL: f32 = 0.03;
+/// Trauma held while the HUD shake mechanic is up
+const MECHANIC_SHAKE: f32 = 0.6;
+/// Smallest crit that stops the pull, the same as a big hit for battle text
+const BIG_CRIT_AMOUNT: i32 = 250;
+/// Real seconds a big crit stops for at a hit-stop setting of 1
+const HIT_STOP_SECS: f32 = 0.08;
+
+/// Where the arena camera sits without the shake
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct CameraRest(pub Transform);
+
+impl CameraRest {
+    /// What the camera's `GlobalTransform` would be if it weren't shaking
+    pub fn global(&self) -> GlobalTransform {
+        GlobalTransform::from(self.0)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct Trauma(f32);
+
+impl Trauma {
+    fn add(&mut self, amount: f32) {
+        self.0 = (self.0 + amount).min(1.0);
+    }
+}
+
+/// Real seconds left of the hit-stop under way
+#[derive(Resource, Debug, Default)]
+struct HitStop {
+    remaining: f32,
+}
+
+fn add_trauma(
+    time: SimTime,
+    combat: Res<CombatState>,
+    mut dealt: EventReader<DamageDealtEvent>,
+    mut taken: EventReader<PlayerDamageEvent>,
+    mut resolved: EventReader<MechanicResolvedEvent>,
+    mut phases: EventReader<BossPhaseEvent>,
+    mut enrage: EventReader<EnrageEvent>,
+    q_player: Query<&Health, With<Player>>,
+    mut trauma: ResMut<Trauma>,
+) {
+    trauma.0 = (trauma.0 - TRAUMA_DECAY * time.scaled_delta()).max(0.0);
+    for ev in dealt.read().filter(|ev| !ev.tick) {
+        trauma.add(if ev.crit { 0.2 } else { 0.08 });
+    }
+    let max_hp = q_player.single().map_or(1, |hp| hp.max.max(1)) as f32;
+    for ev in taken.read() {
+        trauma.add((ev.amount as f32 / max_hp * 2.0).min(0.6));
+    }
+    for ev in resolved.read().filter(|ev| ev.failed && ev.player_hit) {
+        trauma.add(0.3);
+    }
+    for _ in phases.read() {
+        trauma.add(0.6);
+    }
+    for _ in enrage.read() {
+        trauma.add(1.0);
+    }
+    if combat.hud_shake_remaining > 0.0 {
+        trauma.0 = trauma.0.max(MECHANIC_SHAKE);
+    }
+}
+
+fn shake_camera(
+    time: SimTime,
+    trauma: Res<Trauma>,
+    settings: Option<Res<Settings>>,
+    mut q_camera: Query<(&mut Transform, &CameraRest), With<Camera2d>>,
+) {
+    let strength = settings.map_or(1.0, |s| s.screen_shake);
+    let shake = trauma.0 * trauma.0 * strength;
+    let t = time.elapsed_secs();
+    // A few sines at odd frequencies per axis, so it rolls rather than buzzes
+    let wave = |a: f32, b: f32, phase: f32| ((t * a + phase).sin() * 0.6 + (t * b + phase * 1.7).sin() * 0.4) * shake;
+    for (mut transform, rest) in &mut q_camera {
+        let offset = Vec2::new(wave(23.0, 41.0, 0.0), wave(29.0, 37.0, 2.1)) * MAX_OFFSET;
+        let mut shaken = rest.0;
+        shaken.translation += offset.extend(0.0);
+        shaken.rotation *= Quat::from_rotation_z(wave(17.0, 31.0, 4.3) * MAX_ROLL);
+        // A still camera isn't written every frame
+        transform.set_if_neq(shaken);
+    }
+}
+
+fn start_hit_stop(
+    settings: Option<Res<Settings>>,
+    strategy: Res<TimeUpdateStrategy>,
+    play_state: Res<State<PlayState>>,
+    mut dealt: EventReader<DamageDealtEvent>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let strength = settings.map_or(0.0, |s| s.hit_stop);
+    let big_crit = dealt.read().any(|ev| ev.crit && !ev.tick && ev.amount >= BIG_CRIT_AMOUNT);
+    let live = matches!(*strategy, TimeUpdateStrategy::Automatic) && *play_state.get() == PlayState::Running;
+    if !big_crit || strength <= 0.0 || !live || hit_stop.remaining > 0.0 {
+        return;
+    }
+    hit_stop.remaining = HIT_STOP_SECS * strength;
+    time.pause();
+}
+
+/// Starts the clock again once the stop is over, unless the pause menu has it by then
+fn end_hit_stop(
+    real: Res<Time<Real>>,
+    play_state: Option<Res<State<PlayState>>>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if hit_stop.remaining <= 0.0 {
+        return;
+    }
+    hit_stop.remaining -= real.delta_secs();
+    if hit_stop.remaining <= 0.0 && play_state.is_some_and(|s| *s.get() == PlayState::Running) {
+        time.unpause();
+    }
+}
+
+/// Leaving the pull mid-shake or mid-stop puts the camera and the clock back
+fn reset_camera_fx(
+    mut trauma: ResMut<Trauma>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time: ResMut<Time<Virtual>>,
+    mut q_camera: Query<(&mut Transform, &CameraRest), With<Camera2d>>,
+) {
+    trauma.0 = 0.0;
+    if hit_stop.remaining > 0.0 {
+        hit_stop.remaining = 0.0;
+        time.unpause();
+    }
+    for (mut transform, rest) in &mut q_camera {
+        *transform = rest.0;
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageFormat, RgbaImage};
+use std::collections::VecDeque;
+use std::io::Cursor;
+
+use crate::actions::keyboard_free;
+use crate::persist;
+use crate::settings::Settings;
+
+// Captures, anywhere in the game: tap F12 for a screenshot, or hold it to save the last few
+// seconds as a GIF. Both go to `captures/` in the user data dir. Frames are read back from the
+// rendered window, so the UI and effects are in them as seen.
+//
+// Clips come from a rolling buffer of shrunk frames, kept only while the clip buffer is on in
+// the settings since it reads a frame back several times a second; with it off, holding F12
+// saves nothing. Saving empties the buffer, which fills again from there.
+//
+// The GPU readback is already asynchronous; buffered frames are shrunk and the PNG and GIF
+// encoded on the compute task pool, and the files written through `persist`, so a capture
+// never holds up a frame. (F12 is why macros can't go on it.)
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>()
+            .add_systems(Startup, spawn_clip_indicator)
+            .add_systems(
+                Update,
+                (buffer_frames, capture_hotkey.run_if(keyboard_free), update_clip_indicator).chain(),
+            );
+    }
+}
+
+const CAPTURE_KEY: KeyCode = KeyCode::F12;
+const CAPTURE_DIR: &str = "captures";
+/// Holding the key this long saves a clip instead of taking a screenshot
+const HOLD_SECS: f32 = 0.4;
+/// GIF frame rate of the clip buffer
+const GIF_FPS: f32 = 10.0;
+/// Seconds of frames the clip buffer keeps; older ones are dropped as new ones come in
+const GIF_SECS: f32 = 8.0;
+/// Buffered frames are shrunk to this width
+const GIF_WIDTH: u32 = 480;
+/// Seconds "Clip saved" shows for
+const SAVED_NOTICE_SECS: f32 = 1.5;
+
+#[derive(Resource, Default)]
+struct Recording {
+    /// The clip buffer is on
+    enabled: bool,
+    /// Real time the key went down, while it's held and nothing's been taken yet
+    held_since: Option<f32>,
+    /// Real time of the last buffered frame
+    last_frame: Option<f32>,
+    /// Frames being shrunk, oldest first, the last [`GIF_SECS`] at most
+    frames: VecDeque<Task<RgbaImage>>,
+    /// Real time the last clip was saved
+    saved_at: Option<f32>,
+}
+
+#[derive(Component)]
+struct ClipIndicator;
+
+fn spawn_clip_indicator(mut commands: Commands) {
+    commands.spawn((
+        Text::new("Clip saved"),
+        TextFont { font_size: 14.0, ..default() },
+        TextColor(Color::linear_rgb(1.0, 0.2, 0.2)),
+        Node { position_type: PositionType::Absolute, top: Val::Px(8.0), left: Val::Px(8.0), ..default() },
+        GlobalZIndex(100),
+        Visibility::Hidden,
+        ClipIndicator,
+    ));
+}
+
+/// Reads a frame back every [`GIF_FPS`]th of a second while the clip buffer is on
+fn buffer_frames(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Option<Res<Settings>>,
+    mut recording: ResMut<Recording>,
+) {
+    let enabled = settings.is_some_and(|s| s.clip_buffer);
+    if enabled != recording.enabled {
+        recording.enabled = enabled;
+        recording.last_frame = None;
+        recording.frames.clear();
+    }
+    let now = time.elapsed_secs();
+    if enabled && recording.last_frame.is_none_or(|t| now - t >= 1.0 / GIF_FPS) {
+        recording.last_frame = Some(now);
+        commands.spawn(Screenshot::primary_window()).observe(record_frame);
+    }
+}
+
+fn capture_hotkey(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut recording: ResMut<Recording>,
+) {
+    let now = time.elapsed_secs();
+    if keys.just_pressed(CAPTURE_KEY) {
+        recording.held_since = Some(now);
+    }
+    let Some(since) = recording.held_since else { return; };
+    if keys.just_released(CAPTURE_KEY) {
+        recording.held_since = None;
+        commands.spawn(Screenshot::primary_window()).observe(save_screenshot);
+    } else if now - since >= HOLD_SECS {
+        recording.held_since = None;
+        if recording.frames.is_empty() {
+            info!("No clip to save; turn on the clip buffer in the settings to keep one");
+            return;
+        }
+        let frames: Vec<Task<RgbaImage>> = recording.frames.drain(..).collect();
+        save_gif(frames);
+        recording.saved_at = Some(now);
+    }
+}
+
+fn update_clip_indicator(
+    time: Res<Time<Real>>,
+    recording: Res<Recording>,
+    mut q_indicator: Query<&mut Visibility, With<ClipIndicator>>,
+) {
+    let shown = recording.saved_at.is_some_and(|at| time.elapsed_secs() - at < SAVED_NOTICE_SECS);
+    for mut vis in &mut q_indicator {
+        vis.set_if_neq(if shown { Visibility::Inherited } else { Visibility::Hidden });
+    }
+}
+
+/// Next free `<prefix>_<n>.<ext>` in the capture dir, relative to the data dir
+fn next_capture_name(prefix: &str, ext: &str) -> String {
+    format!("{}.{ext}", persist::next_numbered(CAPTURE_DIR, prefix))
+}
+
+fn save_screenshot(trigger: Trigger<ScreenshotCaptured>) {
+    let Ok(image) = trigger.event().0.clone().try_into_dynamic() else {
+        warn!("Screenshot came back in a format that can't be saved");
+        return;
+    };
+    let name = next_capture_name("screenshot", "png");
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            let mut bytes = Vec::new();
+            match image.to_rgb8().write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png) {
+                Ok(()) => {
+                    persist::save_bytes(&name, bytes);
+                    info!("Screenshot saved as {name}");
+                }
+                Err(error) => warn!("Failed to encode {name}: {error}"),
+            }
+        })
+        .detach();
+}
+
+/// Starts shrinking a buffered frame and keeps it, dropping the oldest past the length kept
+fn record_frame(trigger: Trigger<ScreenshotCaptured>, mut recording: ResMut<Recording>) {
+    // Turned off before the readback came in
+    if !recording.enabled {
+        return;
+    }
+    let Ok(image) = trigger.event().0.clone().try_into_dynamic() else { return; };
+    let height = (image.height() * GIF_WIDTH / image.width().max(1)).max(1);
+    let shrink = AsyncComputeTaskPool::get().spawn(async move { image.thumbnail_exact(GIF_WIDTH, height).to_rgba8() });
+    recording.frames.push_back(shrink);
+    // Dropping a task cancels it if it hasn't run yet
+    while recording.frames.len() as f32 > GIF_SECS * GIF_FPS {
+        recording.frames.pop_front();
+    }
+}
+
+fn save_gif(frames: Vec<Task<RgbaImage>>) {
+    if frames.is_empty() {
+        return;
+    }
+    let name = next_capture_name("clip", "gif");
+    let delay = Delay::from_numer_denom_ms(1000, GIF_FPS as u32);
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            let mut shrunk = Vec::with_capacity(frames.len());
+            for frame in frames {
+                shrunk.push(frame.await);
+            }
+            let mut bytes = Vec::new();
+            let encoded = {
+                let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+                encoder.set_repeat(Repeat::Infinite).and_then(|_| {
+                    encoder.encode_frames(shrunk.into_iter().map(|frame| Frame::from_parts(frame, 0, 0, delay)))
+                })
+            };
+            match encoded {
+                Ok(()) => {
+                    persist::save_bytes(&name, bytes);
+                    info!("Clip saved as {name}");
+                }
+                Err(error) => warn!("Failed to encode {name}: {error}"),
+            }
+        })
+        .detach();
+}
@@ -0,0 +1,336 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+use crate::actions::{InputFocus, TextField};
+use crate::combat::{CurrentEncounter, PullResult};
+use crate::loading::TextureAssets;
+use crate::{persist, GameState};
+
+// The player's look: sprite, tint and display name, picked from a small panel on the
+// menu and saved in the user profile. The name goes on the player's nameplate and in
+// the logs, so pulls and players can be told apart.
+
+pub struct CharacterPlugin;
+
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Character>()
+            .init_resource::<NameEditing>()
+            .add_systems(Startup, load_character)
+            .add_systems(OnEnter(GameState::Menu), setup_character_panel)
+            .add_systems(OnEnter(GameState::Playing), log_pull_start)
+            .add_systems(OnEnter(GameState::Results), log_pull_end)
+            .add_systems(
+                Update,
+                (click_character_buttons, type_name, update_character_labels)
+                    .chain()
+                    .run_if(in_state(GameState::Menu)),
+            );
+    }
+}
+
+const CHARACTER_FILE: &str = "character.txt";
+const MAX_NAME_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterSprite {
+    Bevy,
+    Octocat,
+    Star,
+}
+
+impl CharacterSprite {
+    const ALL: [CharacterSprite; 3] = [CharacterSprite::Bevy, CharacterSprite::Octocat, CharacterSprite::Star];
+
+    fn key(self) -> &'static str {
+        match self {
+            CharacterSprite::Bevy => "bevy",
+            CharacterSprite::Octocat => "octocat",
+            CharacterSprite::Star => "star",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            CharacterSprite::Bevy => "Bevy",
+            CharacterSprite::Octocat => "Octocat",
+            CharacterSprite::Star => "Star",
+        }
+    }
+
+    pub fn image(self, textures: &TextureAssets) -> Handle<Image> {
+        match self {
+            CharacterSprite::Bevy => textures.bevy.clone(),
+            CharacterSprite::Octocat => textures.github.clone(),
+            CharacterSprite::Star => textures.y2k_star.clone(),
+        }
+    }
+
+    /// Drawn size in world units, so every choice takes up about the same space
+    pub fn size(self) -> f32 {
+        match self {
+            CharacterSprite::Bevy => 256.0,
+            CharacterSprite::Octocat => 160.0,
+            CharacterSprite::Star => 128.0,
+        }
+    }
+}
+
+/// Tints to pick from, white first so the sprite keeps its own colors
+const TINTS: [(&str, Color); 6] = [
+    ("White", Color::linear_rgb(1.0, 1.0, 1.0)),
+    ("Red", Color::linear_rgb(1.0, 0.45, 0.4)),
+    ("Green", Color::linear_rgb(0.5, 1.0, 0.5)),
+    ("Blue", Color::linear_rgb(0.5, 0.7, 1.0)),
+    ("Gold", Color::linear_rgb(1.0, 0.85, 0.35)),
+    ("Violet", Color::linear_rgb(0.8, 0.55, 1.0)),
+];
+
+/// How the player looks and what they're called
+#[derive(Resource, Debug, Clone)]
+pub struct Character {
+    pub sprite: CharacterSprite,
+    /// Index into the tint palette
+    tint: usize,
+    pub name: String,
+}
+
+impl Default for Character {
+    fn default() -> Self {
+        Character { sprite: CharacterSprite::Bevy, tint: 0, name: "Player".to_string() }
+    }
+}
+
+impl Character {
+    pub fn color(&self) -> Color {
+        TINTS[self.tint % TINTS.len()].1
+    }
+
+    fn tint_label(&self) -> &'static str {
+        TINTS[self.tint % TINTS.len()].0
+    }
+
+    // File format: "sprite = <key>", "tint = <name>" and "name = <display name>"
+    fn parse(contents: &str) -> Self {
+        let mut character = Self::default();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue; };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "sprite" => match CharacterSprite::ALL.into_iter().find(|s| s.key() == value) {
+                    Some(sprite) => character.sprite = sprite,
+                    None => warn!("Unknown character sprite {value:?}"),
+                },
+                "tint" => match TINTS.iter().position(|(name, _)| name.eq_ignore_ascii_case(value)) {
+                    Some(tint) => character.tint = tint,
+                    None => warn!("Unknown character tint {value:?}"),
+                },
+                "name" if !value.is_empty() => character.name = value.chars().take(MAX_NAME_LEN).collect(),
+                _ => warn!("Unknown character setting {key:?}"),
+            }
+        }
+        character
+    }
+
+    fn save(&self) {
+        let out = format!("sprite = {}\ntint = {}\nname = {}\n", self.sprite.key(), self.tint_label(), self.name);
+        persist::save(CHARACTER_FILE, &out);
+    }
+}
+
+fn load_character(mut character: ResMut<Character>) {
+    if let Some(contents) = persist::load(CHARACTER_FILE) {
+        *character = Character::parse(&contents);
+    }
+}
+
+fn log_pull_start(character: Res<Character>, encounter: Res<CurrentEncounter>) {
+    info!("{} pulls {}", character.name, encounter.id);
+}
+
+fn log_pull_end(character: Res<Character>, result: Res<PullResult>) {
+    let outcome = if result.cleared_before_enrage { "cleared" } else { "wiped on" };
+    info!("{} {} {} after {:.1}s", character.name, outcome, result.encounter, result.duration);
+}
+
+/// Whether typed keys go to the name field
+#[derive(Resource, Debug, Default)]
+struct NameEditing(bool);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum CharacterButton {
+    Sprite,
+    Tint,
+    Name,
+}
+
+#[derive(Component)]
+struct CharacterLabel(CharacterButton);
+
+#[derive(Component)]
+struct CharacterPreview;
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+const BUTTON_EDITING: Color = Color::linear_rgb(0.2, 0.3, 0.45);
+
+fn setup_character_panel(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    character: Res<Character>,
+    mut editing: ResMut<NameEditing>,
+) {
+    editing.0 = false;
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                left: Val::Px(20.0),
+                width: Val::Px(220.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                row_gap: Val::Px(6.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.9)),
+            StateScoped(GameState::Menu),
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new("Character"),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            panel.spawn((
+                ImageNode { image: character.sprite.image(&textures), color: character.color(), ..default() },
+                Node { width: Val::Px(64.0), height: Val::Px(64.0), ..default() },
+                CharacterPreview,
+            ));
+            for button in [CharacterButton::Sprite, CharacterButton::Tint, CharacterButton::Name] {
+                panel
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(28.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        button,
+                    ))
+                    .with_child((
+                        Text::new(button_label(button, &character, false)),
+                        TextFont { font_size: 15.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                        CharacterLabel(button),
+                    ));
+            }
+            panel.spawn((
+                Text::new("Click the name to type a new one"),
+                TextFont { font_size: 11.0, ..default() },
+                TextColor(Color::linear_rgb(0.6, 0.6, 0.6)),
+            ));
+        });
+}
+
+fn button_label(button: CharacterButton, character: &Character, editing: bool) -> String {
+    match button {
+        CharacterButton::Sprite => format!("Sprite: {}", character.sprite.label()),
+        CharacterButton::Tint => format!("Color: {}", character.tint_label()),
+        CharacterButton::Name if editing => format!("Name: {}_", character.name),
+        CharacterButton::Name => format!("Name: {}", character.name),
+    }
+}
+
+fn click_character_buttons(
+    mut character: ResMut<Character>,
+    mut editing: ResMut<NameEditing>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &CharacterButton), Changed<Interaction>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                CharacterButton::Sprite => {
+                    let idx = CharacterSprite::ALL.iter().position(|s| *s == character.sprite).unwrap_or(0);
+                    character.sprite = CharacterSprite::ALL[(idx + 1) % CharacterSprite::ALL.len()];
+                    character.save();
+                }
+                CharacterButton::Tint => {
+                    character.tint = (character.tint + 1) % TINTS.len();
+                    character.save();
+                }
+                CharacterButton::Name if editing.0 => finish_editing(&mut character, &mut editing),
+                CharacterButton::Name => editing.0 = true,
+            },
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None if *button == CharacterButton::Name && editing.0 => *color = BUTTON_EDITING.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn finish_editing(character: &mut Character, editing: &mut NameEditing) {
+    character.name = character.name.trim().to_string();
+    // An empty name would leave the nameplate blank
+    if character.name.is_empty() {
+        character.name = Character::default().name;
+    }
+    character.save();
+    editing.0 = false;
+}
+
+fn type_name(
+    mut keys: EventReader<KeyboardInput>,
+    mut focus: ResMut<InputFocus>,
+    mut character: ResMut<Character>,
+    mut editing: ResMut<NameEditing>,
+) {
+    if !focus.hold(TextField::CharacterName, editing.0) {
+        keys.clear();
+        return;
+    }
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+        match &key.logical_key {
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if character.name.chars().count() < MAX_NAME_LEN {
+                        character.name.push(c);
+                    }
+                }
+            }
+            Key::Space if character.name.chars().count() < MAX_NAME_LEN => character.name.push(' '),
+            Key::Backspace => {
+                character.name.pop();
+            }
+            Key::Enter | Key::Escape => finish_editing(&mut character, &mut editing),
+            _ => {}
+        }
+    }
+}
+
+fn update_character_labels(
+    character: Res<Character>,
+    editing: Res<NameEditing>,
+    textures: Res<TextureAssets>,
+    mut labels: Query<(&mut Text, &CharacterLabel)>,
+    mut preview: Query<&mut ImageNode, With<CharacterPreview>>,
+) {
+    if !character.is_changed() && !editing.is_changed() {
+        return;
+    }
+    for (mut text, label) in &mut labels {
+        text.0 = button_label(label.0, &character, editing.0);
+    }
+    if let Ok(mut image) = preview.single_mut() {
+        image.image = character.sprite.image(&textures);
+        image.color = character.color();
+    }
+}
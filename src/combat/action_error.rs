@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use super::{AbilityId, CombatState};
+
+// Action errors: every press that can't go off says why. Whatever turns a press down sends an
+// `ActionErrorEvent`; the HUD shows the reason in red above the hotbars and the audio plugin
+// plays a thud. A cast cancelled by moving is reported the same way. Presses held in the queue
+// or the input buffer aren't errors unless whatever blocks them outlasts the buffer.
+
+/// How long an error stays up
+const ERROR_SECS: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionError {
+    /// The ability's own or shared recast, or the GCD, won't be back in time
+    NotReady,
+    AnimationLocked,
+    Casting,
+    /// Its key was locked by an injected mistake
+    KeyLocked,
+    OutOfRange,
+    NotInView,
+    NoLineOfSight,
+    Silenced,
+    Pacified,
+    /// Moved before the slidecast window, which cancels the cast
+    MovedWhileCasting,
+}
+
+impl ActionError {
+    pub fn message(self) -> &'static str {
+        match self {
+            ActionError::NotReady => "Not ready yet",
+            ActionError::AnimationLocked => "Still in animation lock",
+            ActionError::Casting => "Already casting",
+            ActionError::KeyLocked => "That key is locked",
+            ActionError::OutOfRange => "Target out of range",
+            ActionError::NotInView => "Target not in view",
+            ActionError::NoLineOfSight => "Target not in line of sight",
+            ActionError::Silenced => "Can't cast while silenced",
+            ActionError::Pacified => "Can't weave while pacified",
+            ActionError::MovedWhileCasting => "Cast cancelled by moving",
+        }
+    }
+}
+
+/// A press was turned down
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ActionErrorEvent {
+    pub ability: AbilityId,
+    pub error: ActionError,
+}
+
+/// The newest error replaces whatever was up
+pub(super) fn show_action_errors(mut evr: EventReader<ActionErrorEvent>, mut combat: ResMut<CombatState>) {
+    if let Some(ev) = evr.read().last() {
+        combat.error = Some((ev.error.message(), ERROR_SECS));
+    }
+}
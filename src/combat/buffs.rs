@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use super::timeline::EnemyTimeline;
+use super::{AbilityBook, AbilityUsedEvent, CombatState};
+
+// Buff alignment: how many GCDs went off inside damage buff windows. Any status that raises
+// damage dealt counts as a buff window, so Raging and whatever buffs the status files add
+// later are covered alike. The windows and every GCD are kept with their pull times for the
+// results screen, which lays them out on a strip.
+
+/// A damage buff being up, from when it went on to when it fell off
+#[derive(Debug, Clone)]
+pub struct BuffWindow {
+    pub id: String,
+    pub name: String,
+    pub start: f32,
+    /// None while it's still up
+    pub end: Option<f32>,
+    /// GCD length when it went on, to count how many GCDs it had room for
+    pub gcd_length: f32,
+}
+
+impl BuffWindow {
+    /// Zero while still up; see [`BuffReport::finish`]
+    fn len(&self) -> f32 {
+        self.end.map_or(0.0, |end| end - self.start)
+    }
+}
+
+/// Buff windows and GCDs of the pull so far
+#[derive(Resource, Debug, Clone, Default)]
+pub struct BuffReport {
+    pub windows: Vec<BuffWindow>,
+    /// (pull time, inside a buff window) for every GCD that went off
+    pub gcds: Vec<(f32, bool)>,
+}
+
+impl BuffReport {
+    /// Ends the windows still up at pull time `now`, for the pull's result
+    pub(super) fn finish(&mut self, now: f32) {
+        for window in self.windows.iter_mut().filter(|w| w.end.is_none()) {
+            window.end = Some(now);
+        }
+    }
+
+    pub fn buffed_gcds(&self) -> usize {
+        self.gcds.iter().filter(|(_, buffed)| *buffed).count()
+    }
+
+    /// GCDs that would fit in the windows back to back; what a perfectly aligned pull gets
+    pub fn possible_gcds(&self) -> usize {
+        self.windows
+            .iter()
+            .map(|w| (w.len() / w.gcd_length.max(0.1)).ceil() as usize)
+            .sum()
+    }
+}
+
+pub(super) fn reset_buff_report(mut report: ResMut<BuffReport>) {
+    *report = BuffReport::default();
+}
+
+/// Runs after statuses are updated, so a buff that just went on already covers this frame's GCD
+pub(super) fn track_buff_windows(
+    book: Res<AbilityBook>,
+    combat: Res<CombatState>,
+    timeline: Res<EnemyTimeline>,
+    mut evr: EventReader<AbilityUsedEvent>,
+    mut report: ResMut<BuffReport>,
+) {
+    let now = timeline.pull_time();
+    let up: Vec<_> = combat.statuses.iter().filter(|s| s.def.modifiers.damage_dealt > 1.0).collect();
+    for window in report.windows.iter_mut().filter(|w| w.end.is_none()) {
+        if !up.iter().any(|s| s.def.id == window.id) {
+            window.end = Some(now);
+        }
+    }
+    for status in up {
+        if !report.windows.iter().any(|w| w.end.is_none() && w.id == status.def.id) {
+            report.windows.push(BuffWindow {
+                id: status.def.id.clone(),
+                name: status.def.name.clone(),
+                start: now,
+                end: None,
+                gcd_length: combat.gcd_length,
+            });
+        }
+    }
+    let buffed = report.windows.iter().any(|w| w.end.is_none());
+    for ev in evr.read() {
+        if book.by_id.get(&ev.id).is_some_and(|a| a.triggers_gcd) {
+            report.gcds.push((now, buffed));
+        }
+    }
+}
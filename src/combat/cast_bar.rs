@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use super::{CombatState, PrevTick, SLIDECAST_SECS};
+use crate::overlay::OverlayWidget;
+use crate::settings::Settings;
+use crate::sim_time::SimTime;
+
+// Cast bar: fills as the current cast goes on. Its size and where it sits on screen come from
+// settings. Optional ticks mark the slidecast threshold (moving before it cancels the cast) and
+// where the GCD queue window opens, the two points in a cast the trainer teaches around.
+
+#[derive(Component)]
+pub(super) struct CastBarRoot;
+
+#[derive(Component)]
+pub(super) struct CastBarFill;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub(super) enum CastBarTick {
+    Slidecast,
+    Queue,
+}
+
+impl CastBarTick {
+    /// Seconds left on the cast where the tick sits
+    fn seconds_left(self, combat: &CombatState) -> f32 {
+        match self {
+            CastBarTick::Slidecast => SLIDECAST_SECS,
+            CastBarTick::Queue => combat.gcd_queue_window,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            CastBarTick::Slidecast => Color::linear_rgb(1.0, 0.85, 0.2),
+            CastBarTick::Queue => Color::linear_rgb(0.9, 0.9, 0.9),
+        }
+    }
+}
+
+/// Spawned under the HUD root with the rest of the HUD
+pub(super) fn spawn_cast_bar(root: &mut ChildSpawnerCommands, settings: &Settings) {
+    root.spawn((
+        Node {
+            width: Val::Px(settings.cast_bar_width),
+            height: Val::Px(settings.cast_bar_height),
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(settings.cast_bar_bottom),
+            left: Val::Percent(settings.cast_bar_left),
+            justify_content: JustifyContent::FlexStart,
+            align_items: AlignItems::Stretch,
+            ..default()
+        },
+        BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05)),
+        CastBarRoot,
+        OverlayWidget,
+    ))
+    .with_children(|bar| {
+        bar.spawn((
+            Node { width: Val::Percent(0.0), height: Val::Percent(100.0), ..default() },
+            BackgroundColor(Color::linear_rgb(0.2, 0.6, 1.0)),
+            CastBarFill,
+        ));
+        if !settings.cast_bar_ticks {
+            return;
+        }
+        for tick in [CastBarTick::Queue, CastBarTick::Slidecast] {
+            bar.spawn((
+                Node {
+                    width: Val::Px(2.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                BackgroundColor(tick.color()),
+                Visibility::Hidden,
+                tick,
+            ));
+        }
+    });
+}
+
+/// Whether a cast is up, or was last time so the bar still needs emptying
+pub(super) fn casting(combat: Res<CombatState>, mut was: Local<bool>) -> bool {
+    let now = combat.cast.is_some();
+    std::mem::replace(&mut *was, now) || now
+}
+
+pub(super) fn update_cast_bar(
+    time: SimTime,
+    combat: Res<CombatState>,
+    prev: Res<PrevTick>,
+    mut q_fill: Query<&mut Node, With<CastBarFill>>,
+    mut q_ticks: Query<(&mut Node, &mut Visibility, &CastBarTick), Without<CastBarFill>>,
+) {
+    if let Ok(mut node) = q_fill.single_mut() {
+        if let (Some(cast), Some(remaining)) = (&combat.cast, prev.cast(&combat, time.tick_alpha())) {
+            let p = if cast.total > 0.0 { 1.0 - (remaining / cast.total) } else { 1.0 };
+            node.width = Val::Percent((p * 100.0).clamp(0.0, 100.0));
+        } else {
+            node.width = Val::Percent(0.0);
+        }
+    }
+    for (mut node, mut vis, tick) in &mut q_ticks {
+        // Hidden with no cast, or when the cast is shorter than the tick's lead
+        let at = combat
+            .cast
+            .as_ref()
+            .filter(|cast| cast.total > 0.0)
+            .map(|cast| 1.0 - tick.seconds_left(&combat) / cast.total)
+            .filter(|p| *p > 0.0);
+        match at {
+            Some(p) => {
+                node.left = Val::Percent(p * 100.0);
+                *vis = Visibility::Inherited;
+            }
+            None => *vis = Visibility::Hidden,
+        }
+    }
+}
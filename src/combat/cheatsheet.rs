@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+
+use super::macros::find_ability;
+use super::{AbilityBook, AbilityId, AbilityUsedEvent};
+use crate::{persist, GameState};
+
+// Rotation cheat sheet: a short list of upcoming abilities next to the hotbar that
+// moves on as the player uses them, for learning an opener hands-on.
+
+/// Lines shown around the current step
+const LINES_BEFORE: usize = 1;
+const LINES_AFTER: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct RotationStep {
+    pub id: AbilityId,
+    /// Free-form timing hint shown next to the ability, e.g. "weave late"
+    pub note: Option<String>,
+}
+
+/// The imported rotation and how far into it the player is
+#[derive(Resource, Debug, Default)]
+pub struct CheatSheet {
+    pub steps: Vec<RotationStep>,
+    pub current: usize,
+    /// Abilities used that weren't the next step
+    pub off_script: u32,
+}
+
+/// Parses `rotation.txt`: one ability per line, optionally followed by `| note`.
+/// Blank lines and `#` comments are skipped.
+///
+/// ```text
+/// # Opener
+/// Fireball | hard cast at -2.5s
+/// Weave: Song
+/// Strike | weave Dash after this one
+/// ```
+pub fn parse_rotation(text: &str, book: &AbilityBook) -> Result<Vec<RotationStep>, String> {
+    let mut steps = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (name, note) = match line.split_once('|') {
+            Some((name, note)) => (name.trim(), Some(note.trim().to_string()).filter(|n| !n.is_empty())),
+            None => (line, None),
+        };
+        let id = find_ability(book, name).ok_or_else(|| format!("line {}: unknown ability {name:?}", n + 1))?;
+        steps.push(RotationStep { id, note });
+    }
+    Ok(steps)
+}
+
+#[derive(Component)]
+pub(super) struct CheatSheetPanel;
+
+pub(super) fn load_cheat_sheet(book: Res<AbilityBook>, mut sheet: ResMut<CheatSheet>) {
+    *sheet = CheatSheet::default();
+    let Some(contents) = persist::load("rotation.txt") else { return; };
+    match parse_rotation(&contents, &book) {
+        Ok(steps) => sheet.steps = steps,
+        Err(error) => warn!("rotation.txt {error}"),
+    }
+}
+
+pub(super) fn spawn_cheat_sheet(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont { font_size: 14.0, ..default() },
+        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            right: Val::Px(10.0),
+            width: Val::Px(220.0),
+            ..default()
+        },
+        BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.5)),
+        Visibility::Hidden,
+        CheatSheetPanel,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+pub(super) fn advance_cheat_sheet(mut evr: EventReader<AbilityUsedEvent>, mut sheet: ResMut<CheatSheet>) {
+    for ev in evr.read() {
+        let Some(step) = sheet.steps.get(sheet.current) else { continue; };
+        if step.id == ev.id {
+            sheet.current += 1;
+        } else {
+            sheet.off_script += 1;
+        }
+    }
+}
+
+pub(super) fn update_cheat_sheet(
+    book: Res<AbilityBook>,
+    sheet: Res<CheatSheet>,
+    mut q: Query<(&mut Text, &mut Visibility), With<CheatSheetPanel>>,
+) {
+    let Ok((mut text, mut vis)) = q.single_mut() else { return; };
+    if sheet.steps.is_empty() {
+        *vis = Visibility::Hidden;
+        return;
+    }
+    *vis = Visibility::Inherited;
+    let mut out = format!("Rotation {}/{}\n", sheet.current.min(sheet.steps.len()), sheet.steps.len());
+    let from = sheet.current.saturating_sub(LINES_BEFORE);
+    let to = (sheet.current + LINES_AFTER + 1).min(sheet.steps.len());
+    for (i, step) in sheet.steps.iter().enumerate().take(to).skip(from) {
+        let cursor = if i == sheet.current { ">" } else if i < sheet.current { "." } else { " " };
+        let name = book.by_id.get(&step.id).map(|a| a.name).unwrap_or("?");
+        match &step.note {
+            Some(note) => out.push_str(&format!("{cursor} {name}  ({note})\n")),
+            None => out.push_str(&format!("{cursor} {name}\n")),
+        }
+    }
+    if sheet.current >= sheet.steps.len() {
+        out.push_str("Done!\n");
+    }
+    if sheet.off_script > 0 {
+        out.push_str(&format!("Off script: {}", sheet.off_script));
+    }
+    text.0 = out;
+}
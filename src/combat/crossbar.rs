@@ -0,0 +1,422 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::macros::find_ability;
+use super::{AbilityBook, AbilityId, ButtonFlashEvent, CombatState, HotbarRoot, HotbarSets, InputLatency};
+use crate::settings::Settings;
+use crate::{persist, GameState};
+
+// Gamepad cross hotbar. Holding a trigger shows 8 slots on the d-pad and face buttons;
+// double-tapping and holding a trigger shows that side's expanded (WXHB) set instead,
+// for 4 x 8 = 32 slots per hotbar set. With the crossbar layout (picked in the menu, or
+// automatic while a controller is connected) the keyboard rows make way for an on-screen
+// crossbar; otherwise a small popup lists the slots while a trigger is held.
+
+/// Slot buttons in slot order: d-pad (up, right, down, left) then face (north, east, south, west)
+const SLOT_BUTTONS: [GamepadButton; 8] = [
+    GamepadButton::DPadUp,
+    GamepadButton::DPadRight,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::North,
+    GamepadButton::East,
+    GamepadButton::South,
+    GamepadButton::West,
+];
+
+const SLOT_BUTTON_LABELS: [&str; 8] = ["Up", "Right", "Down", "Left", "Y", "B", "A", "X"];
+
+/// Max time between release and second press of a trigger to count as a double tap
+const DOUBLE_TAP_SECS: f32 = 0.3;
+
+const LAYOUT_FILE: &str = "hotbar_layout.txt";
+const CELL_SIZE: f32 = 40.0;
+/// Cell offsets within a diamond, in slot order: up, right, down, left
+const DIAMOND: [(f32, f32); 4] = [(1.0, 0.0), (2.0, 1.0), (1.0, 2.0), (0.0, 1.0)];
+
+/// Which hotbar the HUD draws
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HotbarLayout {
+    /// The crossbar while a controller is connected, the keyboard rows otherwise
+    #[default]
+    Auto,
+    Keyboard,
+    Crossbar,
+}
+
+impl HotbarLayout {
+    const ALL: [HotbarLayout; 3] = [HotbarLayout::Auto, HotbarLayout::Keyboard, HotbarLayout::Crossbar];
+
+    fn key(self) -> &'static str {
+        match self {
+            HotbarLayout::Auto => "auto",
+            HotbarLayout::Keyboard => "keyboard",
+            HotbarLayout::Crossbar => "crossbar",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HotbarLayout::Auto => "Auto",
+            HotbarLayout::Keyboard => "Keyboard",
+            HotbarLayout::Crossbar => "Crossbar",
+        }
+    }
+
+    /// Switches to the next layout and saves it
+    pub fn cycle(&mut self) {
+        let idx = Self::ALL.iter().position(|l| l == self).unwrap_or(0);
+        *self = Self::ALL[(idx + 1) % Self::ALL.len()];
+        persist::save(LAYOUT_FILE, &format!("{}\n", self.key()));
+    }
+
+    fn uses_crossbar(self, gamepad_connected: bool) -> bool {
+        match self {
+            HotbarLayout::Auto => gamepad_connected,
+            HotbarLayout::Keyboard => false,
+            HotbarLayout::Crossbar => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CrossbarSide {
+    Left,
+    Right,
+    ExpandedLeft,
+    ExpandedRight,
+}
+
+impl CrossbarSide {
+    const ALL: [CrossbarSide; 4] =
+        [CrossbarSide::Left, CrossbarSide::Right, CrossbarSide::ExpandedLeft, CrossbarSide::ExpandedRight];
+
+    fn key(self) -> &'static str {
+        match self {
+            CrossbarSide::Left => "left",
+            CrossbarSide::Right => "right",
+            CrossbarSide::ExpandedLeft => "wxhb_left",
+            CrossbarSide::ExpandedRight => "wxhb_right",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            CrossbarSide::Left => "LT",
+            CrossbarSide::Right => "RT",
+            CrossbarSide::ExpandedLeft => "LT LT",
+            CrossbarSide::ExpandedRight => "RT RT",
+        }
+    }
+}
+
+pub type CrossbarSlots = [Option<AbilityId>; 8];
+
+/// Cross hotbar layouts per hotbar set name. Sets without an entry get one derived
+/// from their keyboard slots. Overrides come from `crossbar.txt`:
+///
+/// ```text
+/// [AoE]
+/// left = Burn, Weave: Song, -, -, Fireball, Strike, Heal, Cleanse
+/// wxhb_right = Jump
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct CrossbarMapping {
+    pub by_set: HashMap<String, HashMap<CrossbarSide, CrossbarSlots>>,
+}
+
+impl CrossbarMapping {
+    /// Slots for a side of the active hotbar set
+    pub fn slots(&self, sets: &HotbarSets, side: CrossbarSide) -> CrossbarSlots {
+        let set = &sets.sets[sets.active];
+        if let Some(slots) = self.by_set.get(&set.name.to_lowercase()).and_then(|sides| sides.get(&side)) {
+            return *slots;
+        }
+        // Default: keyboard slots 1-8 on the left, 9-0 on the right, expanded sides empty
+        let mut slots = [None; 8];
+        match side {
+            CrossbarSide::Left => {
+                for (slot, id) in slots.iter_mut().zip(set.slots.iter()) {
+                    *slot = Some(*id);
+                }
+            }
+            CrossbarSide::Right => {
+                for (slot, id) in slots.iter_mut().zip(set.slots[8..].iter()) {
+                    *slot = Some(*id);
+                }
+            }
+            CrossbarSide::ExpandedLeft | CrossbarSide::ExpandedRight => {}
+        }
+        slots
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct CrossbarState {
+    active: Option<CrossbarSide>,
+    /// Real time each trigger was last released, for double-tap detection
+    left_released: Option<f32>,
+    right_released: Option<f32>,
+}
+
+#[derive(Component)]
+pub(super) struct CrossbarPanel;
+
+#[derive(Component)]
+pub(super) struct CrossbarHud;
+
+/// One cluster of the on-screen crossbar: the left or right trigger's 8 slots
+#[derive(Component)]
+pub(super) struct CrossbarCluster {
+    right: bool,
+}
+
+#[derive(Component)]
+pub(super) struct CrossbarCell {
+    right: bool,
+    index: usize,
+}
+
+pub(super) fn load_hotbar_layout(mut layout: ResMut<HotbarLayout>) {
+    let Some(contents) = persist::load(LAYOUT_FILE) else { return; };
+    match HotbarLayout::ALL.into_iter().find(|l| l.key() == contents.trim()) {
+        Some(loaded) => *layout = loaded,
+        None => warn!("Unknown hotbar layout {:?}", contents.trim()),
+    }
+}
+
+pub(super) fn load_crossbar_mapping(book: Res<AbilityBook>, mut mapping: ResMut<CrossbarMapping>) {
+    mapping.by_set.clear();
+    let Some(contents) = persist::load("crossbar.txt") else { return; };
+    let mut set = None;
+    for (n, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            set = Some(name.trim().to_lowercase());
+            continue;
+        }
+        let Some((side, abilities)) = line.split_once('=') else { continue; };
+        let Some(set) = &set else {
+            warn!("crossbar.txt line {}: slots before any [set] header", n + 1);
+            continue;
+        };
+        let Some(side) = CrossbarSide::ALL.into_iter().find(|s| s.key() == side.trim()) else {
+            warn!("crossbar.txt line {}: unknown side {:?}", n + 1, side.trim());
+            continue;
+        };
+        let mut slots = [None; 8];
+        for (slot, name) in slots.iter_mut().zip(abilities.split(',').map(str::trim)) {
+            if name == "-" || name.is_empty() {
+                continue;
+            }
+            *slot = find_ability(&book, name);
+            if slot.is_none() {
+                warn!("crossbar.txt line {}: unknown ability {name:?}", n + 1);
+            }
+        }
+        mapping.by_set.entry(set.clone()).or_default().insert(side, slots);
+    }
+}
+
+pub(super) fn spawn_crossbar_panel(mut commands: Commands, settings: Option<Res<Settings>>) {
+    // Both sit where the keyboard hotbars would
+    let anchor = settings.map(|s| s.hud_anchor).unwrap_or_default();
+    let anchored = |mut node: Node| {
+        anchor.place(&mut node);
+        node
+    };
+    // On-screen crossbar: for each trigger a d-pad diamond and a face button diamond
+    commands
+        .spawn((
+            anchored(Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                column_gap: Val::Px(40.0),
+                ..default()
+            }),
+            Visibility::Hidden,
+            CrossbarHud,
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|hud| {
+            for right in [false, true] {
+                hud.spawn((
+                    Node { column_gap: Val::Px(8.0), padding: UiRect::all(Val::Px(4.0)), ..default() },
+                    BackgroundColor(Color::NONE),
+                    CrossbarCluster { right },
+                ))
+                .with_children(|cluster| {
+                    for diamond in 0..2 {
+                        cluster
+                            .spawn(Node { width: Val::Px(CELL_SIZE * 3.0), height: Val::Px(CELL_SIZE * 3.0), ..default() })
+                            .with_children(|d| {
+                                for (i, (x, y)) in DIAMOND.iter().enumerate() {
+                                    let index = diamond * 4 + i;
+                                    d.spawn((
+                                        Node {
+                                            position_type: PositionType::Absolute,
+                                            left: Val::Px(x * CELL_SIZE),
+                                            top: Val::Px(y * CELL_SIZE),
+                                            width: Val::Px(CELL_SIZE - 2.0),
+                                            height: Val::Px(CELL_SIZE - 2.0),
+                                            flex_direction: FlexDirection::Column,
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        BackgroundColor(Color::linear_rgb(0.1, 0.1, 0.1)),
+                                    ))
+                                    .with_children(|cell| {
+                                        cell.spawn((
+                                            Text::new(SLOT_BUTTON_LABELS[index]),
+                                            TextFont { font_size: 10.0, ..default() },
+                                            TextColor(Color::linear_rgb(0.6, 0.6, 0.6)),
+                                        ));
+                                        cell.spawn((
+                                            Text::new(""),
+                                            TextFont { font_size: 9.0, ..default() },
+                                            TextColor(Color::WHITE),
+                                            CrossbarCell { right, index },
+                                        ));
+                                    });
+                                }
+                            });
+                    }
+                });
+            }
+        });
+
+    commands
+        .spawn((
+            anchored(Node { position_type: PositionType::Absolute, bottom: Val::Px(180.0), ..default() }),
+            StateScoped(GameState::Playing),
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont { font_size: 14.0, ..default() },
+            TextColor(Color::WHITE),
+            BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.6)),
+            Visibility::Hidden,
+            CrossbarPanel,
+        ));
+}
+
+/// Tracks which side is shown and sends presses for the slot buttons
+pub(super) fn handle_crossbar_input(
+    time: Res<Time<Real>>,
+    gamepads: Query<&Gamepad>,
+    sets: Res<HotbarSets>,
+    mapping: Res<CrossbarMapping>,
+    mut state: ResMut<CrossbarState>,
+    mut latency: ResMut<InputLatency>,
+    mut flash_writer: EventWriter<ButtonFlashEvent>,
+) {
+    let now = time.elapsed_secs();
+    for gamepad in &gamepads {
+        let triggers = [
+            (GamepadButton::LeftTrigger2, CrossbarSide::Left, CrossbarSide::ExpandedLeft),
+            (GamepadButton::RightTrigger2, CrossbarSide::Right, CrossbarSide::ExpandedRight),
+        ];
+        for (trigger, side, expanded) in triggers {
+            let released = if side == CrossbarSide::Left { &mut state.left_released } else { &mut state.right_released };
+            if gamepad.just_released(trigger) {
+                *released = Some(now);
+            }
+            if gamepad.just_pressed(trigger) {
+                let double_tap = released.is_some_and(|t| now - t <= DOUBLE_TAP_SECS);
+                state.active = Some(if double_tap { expanded } else { side });
+            }
+        }
+        let held = |side: CrossbarSide| match side {
+            CrossbarSide::Left | CrossbarSide::ExpandedLeft => gamepad.pressed(GamepadButton::LeftTrigger2),
+            CrossbarSide::Right | CrossbarSide::ExpandedRight => gamepad.pressed(GamepadButton::RightTrigger2),
+        };
+        if state.active.is_some_and(|side| !held(side)) {
+            state.active = None;
+        }
+
+        let Some(side) = state.active else { continue; };
+        let slots = mapping.slots(&sets, side);
+        for (button, slot) in SLOT_BUTTONS.iter().zip(slots) {
+            if let (true, Some(id)) = (gamepad.just_pressed(*button), slot) {
+                flash_writer.write(ButtonFlashEvent { id });
+                latency.send(id);
+            }
+        }
+    }
+}
+
+pub(super) fn update_crossbar_panel(
+    book: Res<AbilityBook>,
+    sets: Res<HotbarSets>,
+    mapping: Res<CrossbarMapping>,
+    state: Res<CrossbarState>,
+    layout: Res<HotbarLayout>,
+    gamepads: Query<(), With<Gamepad>>,
+    mut q: Query<(&mut Text, &mut Visibility), With<CrossbarPanel>>,
+) {
+    let Ok((mut text, mut vis)) = q.single_mut() else { return; };
+    // The on-screen crossbar already shows the slots
+    let popup_side = state.active.filter(|_| !layout.uses_crossbar(!gamepads.is_empty()));
+    let Some(side) = popup_side else {
+        *vis = Visibility::Hidden;
+        return;
+    };
+    *vis = Visibility::Inherited;
+    let mut out = format!("{}\n", side.label());
+    for (label, slot) in SLOT_BUTTON_LABELS.iter().zip(mapping.slots(&sets, side)) {
+        let name = slot.and_then(|id| book.by_id.get(&id)).map(|a| a.name).unwrap_or("-");
+        out.push_str(&format!("{label:>5}  {name}\n"));
+    }
+    text.0 = out;
+}
+
+/// Shows either the keyboard rows or the on-screen crossbar, following the layout setting
+pub(super) fn apply_hotbar_layout(
+    layout: Res<HotbarLayout>,
+    gamepads: Query<(), With<Gamepad>>,
+    mut q_rows: Query<&mut Visibility, (With<HotbarRoot>, Without<CrossbarHud>)>,
+    mut q_hud: Query<&mut Visibility, With<CrossbarHud>>,
+) {
+    let crossbar = layout.uses_crossbar(!gamepads.is_empty());
+    let (rows, hud) = if crossbar { (Visibility::Hidden, Visibility::Inherited) } else { (Visibility::Inherited, Visibility::Hidden) };
+    for mut vis in &mut q_rows {
+        vis.set_if_neq(rows);
+    }
+    if let Ok(mut vis) = q_hud.single_mut() {
+        vis.set_if_neq(hud);
+    }
+}
+
+/// Cells show the held side's slots, or the plain sides when no trigger is held; abilities
+/// on cooldown are dimmed
+pub(super) fn update_crossbar_hud(
+    book: Res<AbilityBook>,
+    sets: Res<HotbarSets>,
+    mapping: Res<CrossbarMapping>,
+    state: Res<CrossbarState>,
+    combat: Res<CombatState>,
+    mut q_clusters: Query<(&CrossbarCluster, &mut BackgroundColor)>,
+    mut q_cells: Query<(&CrossbarCell, &mut Text, &mut TextColor)>,
+) {
+    let shown = |right: bool| match (right, state.active) {
+        (false, Some(side @ (CrossbarSide::Left | CrossbarSide::ExpandedLeft))) => (side, true),
+        (true, Some(side @ (CrossbarSide::Right | CrossbarSide::ExpandedRight))) => (side, true),
+        (false, _) => (CrossbarSide::Left, false),
+        (true, _) => (CrossbarSide::Right, false),
+    };
+    for (cluster, mut bg) in &mut q_clusters {
+        let (_, held) = shown(cluster.right);
+        bg.0 = if held { Color::linear_rgb(0.2, 0.3, 0.45).with_alpha(0.6) } else { Color::NONE };
+    }
+    for (cell, mut text, mut color) in &mut q_cells {
+        let (side, _) = shown(cell.right);
+        let slot = mapping.slots(&sets, side)[cell.index];
+        let name = slot.and_then(|id| book.by_id.get(&id)).map(|a| a.name).unwrap_or("-");
+        if text.0 != name {
+            text.0 = name.to_string();
+        }
+        let on_cd = slot.is_some_and(|id| combat.ability_cds.get(&id).is_some_and(|cd| *cd > 0.0));
+        color.0 = if on_cd { Color::linear_rgb(0.45, 0.45, 0.45) } else { Color::WHITE };
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+use super::timeline::EnemyTimeline;
+use super::{AbilityBook, AbilityId, CombatState, CurrentEncounter, EncounterLibrary};
+use crate::sim_time::SimTime;
+use crate::world::{DamageDealtEvent, DotEffects, Enemy};
+use crate::overlay::OverlayWidget;
+use crate::GameState;
+
+// DPS meter: damage that actually landed this pull, as a rolling DPS over the last few
+// seconds, a running total and a per-ability breakdown. Hits and DoT ticks count for the
+// ability behind them; status damage is lumped under "Other".
+//
+// Against a striking dummy the rolling DPS is over a minute instead, and the meter adds DoT
+// uptime (any DoT on the dummy) and GCD uptime (GCD rolling or a cast going).
+//
+// Every DoT that went on the boss also gets its own uptime, shown here and on the results
+// screen, since keeping Burn up is half the rotation. All uptimes are shares of the pull so
+// far, as kept by the enemy timeline, so they add up with each other and the reports.
+
+/// Seconds the rolling DPS averages over
+const ROLLING_SECS: f32 = 10.0;
+/// The same against a striking dummy, where the pull is long and steady
+const DUMMY_ROLLING_SECS: f32 = 60.0;
+/// Breakdown rows shown, biggest first
+const BREAKDOWN_ROWS: usize = 6;
+
+#[derive(Resource, Debug, Default)]
+pub struct DpsMeter {
+    pub total: i64,
+    pub by_source: HashMap<Option<AbilityId>, i64>,
+    /// (sim time, amount) for hits inside the rolling window
+    recent: VecDeque<(f32, i32)>,
+    started: Option<f32>,
+    /// Hitting a striking dummy
+    pub dummy: bool,
+    /// Seconds of the pull with a DoT up, with the GCD busy, and with each DoT on the boss
+    dot_up_secs: f32,
+    gcd_busy_secs: f32,
+    dot_secs: HashMap<AbilityId, f32>,
+}
+
+impl DpsMeter {
+    /// Average over the rolling window, or since the first hit if that's shorter
+    pub fn rolling_dps(&self, now: f32) -> f32 {
+        let Some(started) = self.started else { return 0.0; };
+        let span = (now - started).clamp(1.0, self.window());
+        self.recent.iter().map(|(_, amount)| *amount as f32).sum::<f32>() / span
+    }
+
+    fn window(&self) -> f32 {
+        if self.dummy { DUMMY_ROLLING_SECS } else { ROLLING_SECS }
+    }
+
+    /// Share of the `pull_secs` so far with a DoT on the target
+    pub fn dot_uptime(&self, pull_secs: f32) -> f32 {
+        self.dot_up_secs / pull_secs.max(f32::EPSILON)
+    }
+
+    /// Share of the `pull_secs` so far with the GCD rolling or a cast going
+    pub fn gcd_uptime(&self, pull_secs: f32) -> f32 {
+        self.gcd_busy_secs / pull_secs.max(f32::EPSILON)
+    }
+
+    /// Each DoT that was applied this pull with its share of the `pull_secs` so far, highest first
+    pub fn dot_uptimes(&self, pull_secs: f32) -> Vec<(AbilityId, f32)> {
+        let mut rows: Vec<_> =
+            self.dot_secs.iter().map(|(id, secs)| (*id, secs / pull_secs.max(f32::EPSILON))).collect();
+        rows.sort_by(|a, b| b.1.total_cmp(&a.1));
+        rows
+    }
+
+    /// Sources by damage done, biggest first, with their share of the total
+    pub fn breakdown(&self) -> Vec<(Option<AbilityId>, i64, f32)> {
+        let mut rows: Vec<_> = self
+            .by_source
+            .iter()
+            .map(|(source, amount)| (*source, *amount, *amount as f32 / self.total.max(1) as f32))
+            .collect();
+        rows.sort_by(|a, b| b.1.cmp(&a.1));
+        rows
+    }
+}
+
+#[derive(Component)]
+pub(super) struct DpsMeterText;
+
+pub(super) fn reset_dps_meter(
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+    mut meter: ResMut<DpsMeter>,
+) {
+    let dummy = library.get(&encounter.id).is_some_and(|def| def.dummy);
+    *meter = DpsMeter { dummy, ..default() };
+}
+
+pub(super) fn spawn_dps_meter(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont { font_size: 13.0, ..default() },
+        TextColor(Color::linear_rgb(0.85, 0.85, 0.85)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(80.0),
+            right: Val::Px(240.0),
+            width: Val::Px(200.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.4)),
+        DpsMeterText,
+        OverlayWidget,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+pub(super) fn record_damage(
+    time: SimTime,
+    combat: Res<CombatState>,
+    q_boss: Query<&DotEffects, With<Enemy>>,
+    mut evr: EventReader<DamageDealtEvent>,
+    mut meter: ResMut<DpsMeter>,
+) {
+    let now = time.elapsed_secs();
+    let dt = time.scaled_delta();
+    if let Ok(dots) = q_boss.single() {
+        for dot in &dots.dots {
+            *meter.dot_secs.entry(dot.source).or_default() += dt;
+        }
+        if !dots.dots.is_empty() {
+            meter.dot_up_secs += dt;
+        }
+    }
+    if combat.gcd_remaining > 0.0 || combat.cast.is_some() {
+        meter.gcd_busy_secs += dt;
+    }
+    for ev in evr.read() {
+        meter.started.get_or_insert(now);
+        meter.total += ev.amount as i64;
+        *meter.by_source.entry(ev.source).or_default() += ev.amount as i64;
+        meter.recent.push_back((now, ev.amount));
+    }
+    let window = meter.window();
+    while meter.recent.front().is_some_and(|(t, _)| now - t > window) {
+        meter.recent.pop_front();
+    }
+}
+
+pub(super) fn update_dps_meter(
+    time: SimTime,
+    book: Res<AbilityBook>,
+    timeline: Res<EnemyTimeline>,
+    meter: Res<DpsMeter>,
+    mut q_text: Query<&mut Text, With<DpsMeterText>>,
+) {
+    let Ok(mut text) = q_text.single_mut() else { return; };
+    let dps_label = if meter.dummy { "DPS (60s)" } else { "DPS" };
+    let mut lines = vec![
+        format!("{dps_label} {:.0}", meter.rolling_dps(time.elapsed_secs())),
+        format!("Total {}", meter.total),
+    ];
+    if meter.dummy {
+        lines.push(format!("DoT uptime {:.0}%", meter.dot_uptime(timeline.pull_time()) * 100.0));
+        lines.push(format!("GCD uptime {:.0}%", meter.gcd_uptime(timeline.pull_time()) * 100.0));
+    }
+    for (id, uptime) in meter.dot_uptimes(timeline.pull_time()) {
+        let name = book.by_id.get(&id).map_or("?", |a| a.name);
+        lines.push(format!("{name} up {:.0}%", uptime * 100.0));
+    }
+    for (source, amount, share) in meter.breakdown().into_iter().take(BREAKDOWN_ROWS) {
+        let name = source.and_then(|id| book.by_id.get(&id)).map_or("Other", |a| a.name);
+        lines.push(format!("{name:<10} {amount:>7} {:>4.0}%", share * 100.0));
+    }
+    text.0 = lines.join("\n");
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::timeline::EnemyTimeline;
+use super::{AbilityId, AbilityUsedEvent, CombatState};
+
+// Cooldown drift: for the oGCDs meant to be used on cooldown, how long each sat ready before
+// it was pressed, added up over the pull. Holding one from the pull counts too, and one still
+// sitting ready when the pull ends counts up to the end.
+
+/// Cooldowns that should go out as soon as they're up
+const TRACKED: [AbilityId; 3] = [AbilityId::Swiftcast, AbilityId::Raging, AbilityId::Jump];
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Drift {
+    /// Pull time it came off cooldown, None while it's recasting
+    ready_since: Option<f32>,
+    secs: f32,
+    uses: u32,
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct CooldownDrift {
+    by_ability: HashMap<AbilityId, Drift>,
+}
+
+impl CooldownDrift {
+    /// (ability, seconds of drift, uses) for each tracked cooldown, including the time it's
+    /// been sitting ready as of pull time `now`
+    pub(super) fn report(&self, now: f32) -> Vec<(AbilityId, f32, u32)> {
+        TRACKED
+            .iter()
+            .map(|id| {
+                let drift = self.by_ability.get(id).copied().unwrap_or_default();
+                let waiting = drift.ready_since.map_or(0.0, |t| now - t);
+                (*id, drift.secs + waiting, drift.uses)
+            })
+            .collect()
+    }
+}
+
+/// Every tracked cooldown starts the pull ready
+pub(super) fn reset_cooldown_drift(mut drift: ResMut<CooldownDrift>) {
+    *drift = CooldownDrift::default();
+    for id in TRACKED {
+        drift.by_ability.insert(id, Drift { ready_since: Some(0.0), ..default() });
+    }
+}
+
+pub(super) fn track_cooldown_drift(
+    combat: Res<CombatState>,
+    timeline: Res<EnemyTimeline>,
+    mut evr: EventReader<AbilityUsedEvent>,
+    mut drift: ResMut<CooldownDrift>,
+) {
+    let now = timeline.pull_time();
+    for ev in evr.read().filter(|ev| TRACKED.contains(&ev.id)) {
+        let entry = drift.by_ability.entry(ev.id).or_default();
+        entry.secs += entry.ready_since.take().map_or(0.0, |t| now - t);
+        entry.uses += 1;
+    }
+    for id in TRACKED {
+        let entry = drift.by_ability.entry(id).or_default();
+        let ready = combat.ability_cds.get(&id).is_none_or(|cd| *cd <= 0.0);
+        if ready && entry.ready_since.is_none() {
+            entry.ready_since = Some(now);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+
+use super::encounter::{EncounterDef, EncounterLibrary, USER_ENCOUNTER_DIR};
+use super::timeline::EnemyEvent;
+use super::{CurrentEncounter, MuddledVariant};
+use crate::{persist, GameState};
+
+// Timeline editor, opened from the menu: the picked encounter's branches laid out on a
+// horizontal track, one marker per event. Events can be picked (click, or Left/Right),
+// moved, deleted and added from a small palette of defaults, then previewed as a normal
+// pull or saved as an encounter file in the user data dir. Saves go under a copy's id,
+// `<id>-custom`, so the original and the records kept against it are left alone; the copy
+// is loaded with the built-in encounters and picked from the menu like them, and editing it
+// again saves over it.
+//
+// The edits stay around between visits as long as the same encounter is picked, so a
+// preview pull can be followed by more editing.
+
+/// Suffix on the id of an encounter saved from the editor
+const CUSTOM_SUFFIX: &str = "-custom";
+
+/// Seconds the track shows at least, so short branches don't stretch across the screen
+const MIN_TRACK_SECS: f32 = 30.0;
+
+const HELP: &str = "Left/Right pick  ,/. move (Shift: finer)  T palette  A add  Del delete  \
+                    Tab branch  P preview  S save  Esc back";
+
+/// The encounter being edited and where the cursor is
+#[derive(Resource, Debug, Default)]
+pub(super) struct TimelineEditor {
+    def: Option<EncounterDef>,
+    branch: usize,
+    selected: Option<usize>,
+    /// Index into [`palette`] for the next added event
+    palette: usize,
+    /// Last thing that happened, shown under the track
+    status: String,
+}
+
+impl TimelineEditor {
+    fn events(&self) -> &[(f32, EnemyEvent)] {
+        self.def.as_ref().and_then(|def| def.branches.get(self.branch)).map_or(&[], |b| b.events.as_slice())
+    }
+
+    fn events_mut(&mut self) -> Option<&mut Vec<(f32, EnemyEvent)>> {
+        Some(&mut self.def.as_mut()?.branches.get_mut(self.branch)?.events)
+    }
+
+    /// Moves the picked event and keeps it picked wherever it ends up in the order
+    fn move_selected(&mut self, by: f32) {
+        let Some(selected) = self.selected else { return; };
+        let Some(events) = self.events_mut() else { return; };
+        let Some(mut event) = (selected < events.len()).then(|| events.remove(selected)) else { return; };
+        event.0 = (event.0 + by).max(0.0);
+        // After every event at the same time, like the loader's stable sort leaves it
+        let at = events.iter().position(|(t, _)| *t > event.0).unwrap_or(events.len());
+        events.insert(at, event);
+        self.selected = Some(at);
+    }
+
+    fn add_event(&mut self) {
+        let mechanic = self.def.as_ref().and_then(|def| def.mechanics.first()).map(|m| m.name.clone());
+        let event = palette(mechanic)[self.palette].clone();
+        let at = self.selected.and_then(|i| self.events().get(i)).map_or(0.0, |(t, _)| t + 1.0);
+        let label = event.label();
+        let Some(events) = self.events_mut() else { return; };
+        let idx = events.iter().position(|(t, _)| *t > at).unwrap_or(events.len());
+        events.insert(idx, (at, event));
+        self.selected = Some(idx);
+        self.status = format!("Added {label} at {at:.1}s");
+    }
+
+    fn delete_selected(&mut self) {
+        let Some(selected) = self.selected else { return; };
+        let Some(events) = self.events_mut() else { return; };
+        if selected >= events.len() {
+            return;
+        }
+        let (at, event) = events.remove(selected);
+        let left = events.len();
+        self.selected = if left == 0 { None } else { Some(selected.min(left - 1)) };
+        self.status = format!("Deleted {} at {at:.1}s", event.label());
+    }
+}
+
+/// What `A` adds, cycled with `T`. Mechanics use the encounter's first one
+fn palette(mechanic: Option<String>) -> [EnemyEvent; 8] {
+    [
+        EnemyEvent::Hit { amount: 150 },
+        EnemyEvent::Mechanic { name: mechanic.unwrap_or_else(|| "Puddle".to_string()) },
+        EnemyEvent::Muddled { duration: 5.0, variant: MuddledVariant::Wobble, intensity: 1.0 },
+        EnemyEvent::HudShake { duration: 1.0 },
+        EnemyEvent::Cast { name: "Attack".to_string() },
+        EnemyEvent::Hazard { radius: 50.0, damage: 80, duration: Some(10.0) },
+        EnemyEvent::Spawn { count: 2 },
+        EnemyEvent::Enrage,
+    ]
+}
+
+#[derive(Component)]
+pub(super) struct EditorTrack;
+
+#[derive(Component)]
+pub(super) struct EditorText;
+
+/// Marker for the event at this index of the shown branch
+#[derive(Component)]
+pub(super) struct EventMarker(usize);
+
+/// Picks up the encounter from the menu, keeping earlier edits if it's the same one
+pub(super) fn setup_editor(
+    mut commands: Commands,
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+    mut editor: ResMut<TimelineEditor>,
+) {
+    let Some(def) = library.get(&encounter.id) else {
+        editor.def = None;
+        editor.status = "No encounters loaded".to_string();
+        return;
+    };
+    if editor.def.as_ref().is_none_or(|d| d.id != def.id) {
+        *editor = TimelineEditor { def: Some(def.clone()), ..default() };
+    }
+    // Draws the track on the first frame even when nothing changed since the last visit
+    editor.set_changed();
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            StateScoped(GameState::Editor),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Node { width: Val::Percent(90.0), height: Val::Px(90.0), ..default() },
+                BackgroundColor(Color::linear_rgb(0.08, 0.08, 0.1)),
+                EditorTrack,
+            ));
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 16.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                TextLayout::new_with_justify(JustifyText::Center),
+                EditorText,
+            ));
+        });
+}
+
+pub(super) fn edit_timeline(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<TimelineEditor>,
+    mut library: ResMut<EncounterLibrary>,
+    mut encounter: ResMut<CurrentEncounter>,
+    mut next_state: ResMut<NextState<GameState>>,
+    q_markers: Query<(&Interaction, &EventMarker), Changed<Interaction>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+        return;
+    }
+    if let Some((_, marker)) = q_markers.iter().find(|(i, _)| **i == Interaction::Pressed) {
+        editor.selected = Some(marker.0);
+    }
+    let count = editor.events().len();
+    let fine = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let step = if fine { 0.1 } else { 0.5 };
+    if keys.just_pressed(KeyCode::ArrowRight) && count > 0 {
+        editor.selected = Some(editor.selected.map_or(0, |i| (i + 1).min(count - 1)));
+    }
+    if keys.just_pressed(KeyCode::ArrowLeft) && count > 0 {
+        editor.selected = Some(editor.selected.map_or(0, |i| i.saturating_sub(1)));
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        editor.move_selected(step);
+    }
+    if keys.just_pressed(KeyCode::Comma) {
+        editor.move_selected(-step);
+    }
+    if keys.just_pressed(KeyCode::KeyT) {
+        editor.palette = (editor.palette + 1) % palette(None).len();
+    }
+    if keys.just_pressed(KeyCode::KeyA) {
+        editor.add_event();
+    }
+    if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        editor.delete_selected();
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        let branches = editor.def.as_ref().map_or(0, |def| def.branches.len());
+        if branches > 0 {
+            editor.branch = (editor.branch + 1) % branches;
+            editor.selected = None;
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyS) {
+        if let Some(def) = editor.def.as_mut().filter(|def| !def.id.ends_with(CUSTOM_SUFFIX)) {
+            def.id.push_str(CUSTOM_SUFFIX);
+            def.name.push_str(" (custom)");
+        }
+    }
+    let Some(def) = editor.def.clone() else { return; };
+    if keys.just_pressed(KeyCode::KeyS) {
+        match ron::ser::to_string_pretty(&def, PrettyConfig::default()) {
+            Ok(text) => {
+                let file = format!("{USER_ENCOUNTER_DIR}/{}.encounter.ron", def.id);
+                persist::save(&file, &text);
+                // Picked in the menu from now on, so coming back edits the copy
+                encounter.id.clone_from(&def.id);
+                library.replace(def.clone());
+                editor.status = format!("Saved to {file}");
+            }
+            Err(error) => editor.status = format!("Couldn't save: {error}"),
+        }
+    }
+    // Previews play the edited copy; it stays in the library until the game closes
+    if keys.just_pressed(KeyCode::KeyP) {
+        encounter.id.clone_from(&def.id);
+        library.replace(EncounterDef { preview: true, ..def });
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// Rebuilds the track and the readout whenever the editor changed
+pub(super) fn update_editor(
+    mut commands: Commands,
+    editor: Res<TimelineEditor>,
+    q_track: Query<Entity, With<EditorTrack>>,
+    q_children: Query<&Children>,
+    mut q_text: Query<&mut Text, With<EditorText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+    let Some(def) = editor.def.as_ref() else {
+        if let Ok(mut text) = q_text.single_mut() {
+            text.0.clone_from(&editor.status);
+        }
+        return;
+    };
+    let events = editor.events();
+    let span = events.last().map_or(0.0, |(t, _)| t + 2.0).max(MIN_TRACK_SECS);
+    if let Ok(track) = q_track.single() {
+        if let Ok(children) = q_children.get(track) {
+            for child in children.iter() {
+                commands.entity(child).despawn();
+            }
+        }
+        commands.entity(track).with_children(|track| {
+            for (i, (at, event)) in events.iter().enumerate() {
+                let picked = editor.selected == Some(i);
+                // Alternate rows so events close together don't cover each other
+                let top = if i % 2 == 0 { 8.0 } else { 48.0 };
+                track
+                    .spawn((
+                        Button,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(at / span * 100.0),
+                            top: Val::Px(top),
+                            padding: UiRect::horizontal(Val::Px(3.0)),
+                            border: UiRect::left(Val::Px(2.0)),
+                            ..default()
+                        },
+                        BorderColor(Color::linear_rgb(1.0, 0.85, 0.3)),
+                        BackgroundColor(if picked {
+                            Color::linear_rgb(0.6, 0.45, 0.1)
+                        } else {
+                            Color::linear_rgb(0.2, 0.2, 0.25)
+                        }),
+                        EventMarker(i),
+                    ))
+                    .with_child((
+                        Text::new(event.label()),
+                        TextFont { font_size: 11.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+            }
+        });
+    }
+    let Ok(mut text) = q_text.single_mut() else { return; };
+    let branch = def.branches.get(editor.branch).map_or("-", |b| b.name.as_str());
+    let picked = match editor.selected.and_then(|i| events.get(i)) {
+        Some((at, event)) => format!("{at:.1}s  {}", event.label()),
+        None => "nothing picked".to_string(),
+    };
+    let next = &palette(def.mechanics.first().map(|m| m.name.clone()))[editor.palette];
+    text.0 = format!(
+        "{}, branch {branch} ({}/{}), {span:.0}s shown\n{picked}\nNext added: {}\n{}\n{HELP}",
+        def.name,
+        editor.branch + 1,
+        def.branches.len(),
+        next.label(),
+        editor.status,
+    );
+}
@@ -0,0 +1,168 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::script::ScriptDef;
+use super::timeline::{EncounterPhase, SyncPoint, TimelineBranch};
+use crate::background::{default_background, BackgroundLayer};
+use crate::mechanics::MechanicDef;
+use crate::world::Arena;
+
+// Encounter files: the boss timeline for one fight, written in RON and loaded as an asset.
+// See `assets/encounters/default.encounter.ron` for the format. Files saved from the
+// timeline editor go in the user data dir and are read at startup next to the assets.
+
+/// Where the timeline editor saves, relative to the user data dir
+pub const USER_ENCOUNTER_DIR: &str = "encounters";
+
+/// One fight: timeline branches (the first one starts the pull), HP phases and sync points
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct EncounterDef {
+    /// Per-encounter data like saved waymarks is keyed by this
+    pub id: String,
+    pub name: String,
+    /// Shown and searched in the encounter picker
+    #[serde(default)]
+    pub meta: EncounterMeta,
+    /// A striking dummy: the boss doesn't fight back and doesn't go down
+    #[serde(default)]
+    pub dummy: bool,
+    /// An unsaved copy the timeline editor is previewing
+    #[serde(skip)]
+    pub preview: bool,
+    pub(super) branches: Vec<TimelineBranch>,
+    #[serde(default)]
+    pub(super) phases: Vec<EncounterPhase>,
+    #[serde(default)]
+    pub(super) syncs: Vec<SyncPoint>,
+    /// Ground AoEs that `Mechanic` events refer to by name
+    #[serde(default)]
+    pub(super) mechanics: Vec<MechanicDef>,
+    /// Rhai scripts that `Script` events run by name
+    #[serde(default)]
+    pub(super) scripts: Vec<ScriptDef>,
+    #[serde(default)]
+    pub arena: Arena,
+    /// Backdrop layers behind the arena, furthest first
+    #[serde(default = "default_background")]
+    pub background: Vec<BackgroundLayer>,
+    /// Effect ids looped over the arena for the whole pull
+    #[serde(default)]
+    pub weather: Vec<String>,
+}
+
+/// Who wrote an encounter and what to expect from it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncounterMeta {
+    pub author: Option<String>,
+    pub difficulty: Difficulty,
+    /// Seconds a pull normally lasts
+    pub expected_duration: Option<f32>,
+    /// Mechanics the fight drills, e.g. "stack", "adds", "enrage"
+    pub tags: Vec<String>,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Extreme,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Extreme];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Extreme => "Extreme",
+        }
+    }
+}
+
+impl EncounterDef {
+    pub fn parse(text: &str) -> Result<Self, ron::de::SpannedError> {
+        let mut def: EncounterDef = ron::from_str(text)?;
+        // The timeline walks each branch's events in order
+        for branch in &mut def.branches {
+            if !branch.events.is_sorted_by(|a, b| a.0 <= b.0) {
+                warn!("Encounter {}: events in branch {} aren't in time order, sorting them", def.id, branch.name);
+                branch.events.sort_by(|a, b| a.0.total_cmp(&b.0));
+            }
+        }
+        Ok(def)
+    }
+}
+
+#[derive(Default)]
+pub struct EncounterLoader;
+
+impl AssetLoader for EncounterLoader {
+    type Asset = EncounterDef;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<EncounterDef, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(EncounterDef::parse(std::str::from_utf8(&bytes)?)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["encounter.ron"]
+    }
+}
+
+/// Every encounter that finished loading, in menu order
+#[derive(Resource, Debug, Default)]
+pub struct EncounterLibrary {
+    pub encounters: Vec<EncounterDef>,
+}
+
+impl EncounterLibrary {
+    /// Adds an encounter, renaming it if its id or name is already taken. Ids get a
+    /// numbered suffix so saved data stays apart; names get the author, or a number
+    pub fn add(&mut self, mut def: EncounterDef) {
+        if self.encounters.iter().any(|e| e.id == def.id) {
+            let id = (2..).map(|n| format!("{}-{n}", def.id)).find(|id| self.encounters.iter().all(|e| e.id != *id)).unwrap();
+            warn!("Duplicate encounter id {}, using {id}", def.id);
+            def.id = id;
+        }
+        if self.encounters.iter().any(|e| e.name == def.name) {
+            let by_author = def.meta.author.as_ref().map(|author| format!("{} ({author})", def.name));
+            let name = by_author
+                .filter(|name| self.encounters.iter().all(|e| e.name != *name))
+                .unwrap_or_else(|| {
+                    (2..).map(|n| format!("{} ({n})", def.name)).find(|name| self.encounters.iter().all(|e| e.name != *name)).unwrap()
+                });
+            warn!("Duplicate encounter name {}, showing it as {name}", def.name);
+            def.name = name;
+        }
+        self.encounters.push(def);
+    }
+
+    /// Puts `def` in place of the encounter with the same id, or adds it if there's none
+    pub fn replace(&mut self, def: EncounterDef) {
+        match self.encounters.iter_mut().find(|e| e.id == def.id) {
+            Some(existing) => *existing = def,
+            None => self.add(def),
+        }
+    }
+
+    /// The encounter with `id`, or the first one if it's gone
+    pub fn get(&self, id: &str) -> Option<&EncounterDef> {
+        self.encounters.iter().find(|e| e.id == id).or_else(|| self.encounters.first())
+    }
+}
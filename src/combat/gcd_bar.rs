@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+
+use super::{AbilityBook, AbilityId, Calibration, CombatState, InputLatency, PrevTick};
+use crate::sim_time::SimTime;
+use crate::GameState;
+
+// GCD / weave visualizer: a strip showing the next few seconds, with the running GCD,
+// the current animation lock and a ghost where the pending press is predicted to resolve.
+
+/// Seconds shown across the full width of the strip
+const WINDOW_SECS: f32 = 3.0;
+
+#[derive(Component)]
+pub(super) struct GcdBarGcd;
+
+#[derive(Component)]
+pub(super) struct GcdBarLock;
+
+#[derive(Component)]
+pub(super) struct GcdBarGhost;
+
+#[derive(Component)]
+pub(super) struct GcdBarGhostLabel;
+
+/// Where a pending press is expected to land
+#[derive(Debug, Clone, Copy)]
+pub struct PressForecast {
+    pub ability: AbilityId,
+    /// Seconds from now until it resolves (or starts casting)
+    pub resolves_in: f32,
+    /// False when the press is expected to be dropped, e.g. a weave that won't fit
+    pub lands: bool,
+}
+
+/// Predicts the most recent pending press: still in flight, queued for the next GCD, or buffered
+pub fn forecast_press(combat: &CombatState, latency: &InputLatency, book: &AbilityBook) -> Option<PressForecast> {
+    let (ability, arrives_in, buffer_left) = if let Some((id, left)) = latency.in_flight().last() {
+        (id, left, combat.buffer_window.min(0.4))
+    } else if let Some(id) = combat.gcd_queue {
+        (id, 0.0, f32::INFINITY)
+    } else if let Some((id, left)) = combat.buffer {
+        (id, 0.0, left)
+    } else {
+        return None;
+    };
+    let spec = book.by_id.get(&ability)?;
+    let cast_left = combat.cast.as_ref().map(|c| c.remaining).unwrap_or(0.0);
+    let own_cd = combat.ability_cds.get(&ability).copied().unwrap_or(0.0).max(combat.group_cd(spec));
+    let blocked_for = combat.ani_lock_remaining.max(cast_left).max(own_cd);
+    let ready_in = if spec.triggers_gcd { blocked_for.max(combat.gcd_remaining) } else { blocked_for };
+    let resolves_in = ready_in.max(arrives_in);
+    // How long the press has to wait once the sim has it
+    let wait = resolves_in - arrives_in;
+    let lands = if spec.triggers_gcd {
+        wait <= buffer_left.max(combat.gcd_queue_window)
+    } else {
+        // Weaves need the GCD still rolling and at most two per GCD
+        resolves_in < combat.gcd_remaining && combat.weaves_in_current_gcd < 2 && wait <= buffer_left
+    };
+    Some(PressForecast { ability, resolves_in, lands })
+}
+
+pub(super) fn spawn_gcd_bar(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Px(400.0),
+                height: Val::Px(14.0),
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(124.0),
+                left: Val::Percent(50.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.8)),
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|bar| {
+            bar.spawn((
+                Node { position_type: PositionType::Absolute, height: Val::Percent(100.0), ..default() },
+                BackgroundColor(Color::linear_rgb(0.3, 0.4, 0.6)),
+                GcdBarGcd,
+            ));
+            bar.spawn((
+                Node { position_type: PositionType::Absolute, height: Val::Percent(50.0), ..default() },
+                BackgroundColor(Color::linear_rgb(1.0, 0.55, 0.2)),
+                GcdBarLock,
+            ));
+            bar.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    height: Val::Percent(100.0),
+                    overflow: Overflow::visible(),
+                    ..default()
+                },
+                BackgroundColor(Color::WHITE.with_alpha(0.3)),
+                Visibility::Hidden,
+                GcdBarGhost,
+            ))
+            .with_children(|ghost| {
+                ghost.spawn((
+                    Text::new(""),
+                    TextFont { font_size: 11.0, ..default() },
+                    TextColor(Color::WHITE.with_alpha(0.7)),
+                    Node { position_type: PositionType::Absolute, bottom: Val::Px(14.0), ..default() },
+                    GcdBarGhostLabel,
+                ));
+            });
+        });
+}
+
+fn pct(secs: f32) -> Val {
+    Val::Percent((secs / WINDOW_SECS * 100.0).clamp(0.0, 100.0))
+}
+
+/// Whether the GCD or an animation lock is running or a press is pending, or was last time so
+/// the strip still needs clearing
+pub(super) fn gcd_bar_moving(
+    combat: Res<CombatState>,
+    prev: Res<PrevTick>,
+    latency: Res<InputLatency>,
+    mut was: Local<bool>,
+) -> bool {
+    let now = combat.gcd_remaining > 0.0
+        || prev.gcd_remaining > 0.0
+        || combat.ani_lock_remaining > 0.0
+        || prev.ani_lock_remaining > 0.0
+        || combat.gcd_queue.is_some()
+        || combat.buffer.is_some()
+        || latency.in_flight().next().is_some();
+    std::mem::replace(&mut *was, now) || now
+}
+
+pub(super) fn update_gcd_bar(
+    time: SimTime,
+    book: Res<AbilityBook>,
+    combat: Res<CombatState>,
+    prev: Res<PrevTick>,
+    latency: Res<InputLatency>,
+    calibration: Res<Calibration>,
+    mut q_gcd: Query<&mut Node, (With<GcdBarGcd>, Without<GcdBarLock>, Without<GcdBarGhost>)>,
+    mut q_lock: Query<&mut Node, (With<GcdBarLock>, Without<GcdBarGcd>, Without<GcdBarGhost>)>,
+    mut q_ghost: Query<(&mut Node, &mut Visibility, &mut BackgroundColor), (With<GcdBarGhost>, Without<GcdBarGcd>, Without<GcdBarLock>)>,
+    mut q_label: Query<&mut Text, With<GcdBarGhostLabel>>,
+) {
+    // Drawn ahead by the calibrated display lag, so the bar empties when the player sees it do so
+    let lead = calibration.visual_lead();
+    let alpha = time.tick_alpha();
+    if let Ok(mut node) = q_gcd.single_mut() {
+        node.width = pct(prev.gcd(&combat, alpha) - lead);
+    }
+    if let Ok(mut node) = q_lock.single_mut() {
+        node.width = pct(prev.ani_lock(&combat, alpha) - lead);
+    }
+    let Ok((mut node, mut vis, mut color)) = q_ghost.single_mut() else { return; };
+    let Some(forecast) = forecast_press(&combat, &latency, &book) else {
+        *vis = Visibility::Hidden;
+        return;
+    };
+    let ability = &book.by_id[&forecast.ability];
+    *vis = Visibility::Inherited;
+    node.left = pct(forecast.resolves_in - lead);
+    // The ghost is as wide as what the press will occupy: its cast, or its animation lock
+    node.width = pct(ability.cast_time.max(ability.ani_lock).max(0.05));
+    color.0 = if forecast.lands { Color::WHITE.with_alpha(0.3) } else { Color::linear_rgb(1.0, 0.2, 0.2).with_alpha(0.35) };
+    if let Ok(mut text) = q_label.single_mut() {
+        text.0 = if forecast.lands { ability.name.to_string() } else { format!("{} (dropped)", ability.name) };
+    }
+}
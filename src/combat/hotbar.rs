@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+
+use super::{AbilityBook, AbilityButton, AbilityId, CooldownBar, PhaseChangeEvent};
+use crate::persist;
+
+const SWAP_ANIM_SECS: f32 = 0.25;
+
+#[derive(Debug, Clone)]
+pub struct HotbarSet {
+    pub name: String,
+    pub slots: [AbilityId; 10],
+}
+
+/// Every hotbar layout the player can switch between; keybinds follow the active one
+#[derive(Resource, Debug)]
+pub struct HotbarSets {
+    pub sets: Vec<HotbarSet>,
+    pub active: usize,
+}
+
+impl Default for HotbarSets {
+    fn default() -> Self {
+        Self {
+            sets: vec![
+                HotbarSet {
+                    name: "Single target".to_string(),
+                    slots: [
+                        AbilityId::Strike,
+                        AbilityId::Fireball,
+                        AbilityId::WeaveDash,
+                        AbilityId::WeaveSong,
+                        AbilityId::Cleanse,
+                        AbilityId::Burn,
+                        AbilityId::Heal,
+                        AbilityId::Swiftcast,
+                        AbilityId::Raging,
+                        AbilityId::Jump,
+                    ],
+                },
+                // DoTs up front for add phases
+                HotbarSet {
+                    name: "AoE".to_string(),
+                    slots: [
+                        AbilityId::Burn,
+                        AbilityId::WeaveSong,
+                        AbilityId::Fireball,
+                        AbilityId::WeaveDash,
+                        AbilityId::Cleanse,
+                        AbilityId::Strike,
+                        AbilityId::Heal,
+                        AbilityId::Swiftcast,
+                        AbilityId::Raging,
+                        AbilityId::Jump,
+                    ],
+                },
+                // Mitigation up front for tankbusters
+                HotbarSet {
+                    name: "Defensive".to_string(),
+                    slots: [
+                        AbilityId::Rampart,
+                        AbilityId::Aegis,
+                        AbilityId::Invuln,
+                        AbilityId::Heal,
+                        AbilityId::Cleanse,
+                        AbilityId::Swiftcast,
+                        AbilityId::Strike,
+                        AbilityId::Fireball,
+                        AbilityId::Burn,
+                        AbilityId::WeaveDash,
+                    ],
+                },
+            ],
+            active: 0,
+        }
+    }
+}
+
+impl HotbarSets {
+    pub fn active_slots(&self) -> &[AbilityId; 10] {
+        &self.sets[self.active].slots
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.sets.iter().position(|s| s.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// User overrides for which set to switch to when a phase starts.
+/// Loaded from `hotbar_rules.txt`: one "<phase> = <set name>" per line.
+#[derive(Resource, Default, Debug)]
+pub struct HotbarRules {
+    pub by_phase: Vec<(String, String)>,
+}
+
+/// Request to switch the active hotbar set by name
+#[derive(Event, Debug, Clone)]
+pub struct SwapHotbarEvent {
+    pub set: String,
+}
+
+/// Drives the brief dip-and-return animation on the hotbar rows after a swap
+#[derive(Resource, Default)]
+pub(super) struct HotbarSwapAnim {
+    pub(super) remaining: f32,
+}
+
+impl HotbarSwapAnim {
+    /// Vertical offset in px for the hotbar rows
+    pub(super) fn offset(&self) -> f32 {
+        let t = (self.remaining / SWAP_ANIM_SECS).clamp(0.0, 1.0);
+        -30.0 * (t * std::f32::consts::PI).sin()
+    }
+}
+
+/// Shows the name of the ability currently in a slot
+#[derive(Component)]
+pub(super) struct SlotAbilityLabel {
+    pub(super) index: usize,
+}
+
+pub(super) fn reset_hotbar(mut sets: ResMut<HotbarSets>, mut rules: ResMut<HotbarRules>) {
+    sets.active = 0;
+    rules.by_phase.clear();
+    if let Some(contents) = persist::load("hotbar_rules.txt") {
+        for line in contents.lines() {
+            if let Some((phase, set)) = line.split_once('=') {
+                rules.by_phase.push((phase.trim().to_string(), set.trim().to_string()));
+            }
+        }
+    }
+}
+
+pub(super) fn page_hotbar(
+    keys: Res<ButtonInput<KeyCode>>,
+    sets: Res<HotbarSets>,
+    mut writer: EventWriter<SwapHotbarEvent>,
+) {
+    if keys.just_pressed(KeyCode::BracketRight) {
+        let next = (sets.active + 1) % sets.sets.len();
+        writer.write(SwapHotbarEvent { set: sets.sets[next].name.clone() });
+    }
+}
+
+/// User rules win over whatever set the encounter suggests for a phase
+pub(super) fn swap_on_phase_change(
+    mut evr: EventReader<PhaseChangeEvent>,
+    rules: Res<HotbarRules>,
+    mut writer: EventWriter<SwapHotbarEvent>,
+) {
+    for PhaseChangeEvent { name, hotbar } in evr.read() {
+        let rule = rules
+            .by_phase
+            .iter()
+            .find(|(phase, _)| phase.eq_ignore_ascii_case(name))
+            .map(|(_, set)| set.clone());
+        if let Some(set) = rule.or_else(|| hotbar.clone()) {
+            writer.write(SwapHotbarEvent { set });
+        }
+    }
+}
+
+pub(super) fn apply_hotbar_swap(
+    mut evr: EventReader<SwapHotbarEvent>,
+    book: Res<AbilityBook>,
+    mut sets: ResMut<HotbarSets>,
+    mut anim: ResMut<HotbarSwapAnim>,
+    mut q_buttons: Query<&mut AbilityButton>,
+    mut q_bars: Query<(&mut CooldownBar, &ChildOf)>,
+    mut q_labels: Query<(&SlotAbilityLabel, &mut Text)>,
+) {
+    let Some(SwapHotbarEvent { set }) = evr.read().last() else { return; };
+    let Some(idx) = sets.index_of(set) else {
+        warn!("Unknown hotbar set {set:?}");
+        return;
+    };
+    if idx == sets.active {
+        return;
+    }
+    sets.active = idx;
+    anim.remaining = SWAP_ANIM_SECS;
+
+    let slots = *sets.active_slots();
+    for (mut bar, parent) in &mut q_bars {
+        if let Ok(button) = q_buttons.get(parent.parent()) {
+            let id = slots[button.index];
+            bar.id = id;
+            bar.triggers_gcd = book.by_id.get(&id).map(|a| a.triggers_gcd).unwrap_or(false);
+        }
+    }
+    for mut button in &mut q_buttons {
+        button.id = slots[button.index];
+    }
+    for (label, mut text) in &mut q_labels {
+        text.0 = book.by_id.get(&slots[label.index]).map(|a| a.name).unwrap_or("").to_string();
+    }
+}
+
+pub(super) fn tick_hotbar_swap_anim(time: Res<Time<Real>>, mut anim: ResMut<HotbarSwapAnim>) {
+    // Left alone once done, so the hotbar layout only moves rows while it plays
+    if anim.remaining > 0.0 {
+        anim.remaining = (anim.remaining - time.delta_secs()).max(0.0);
+    }
+}
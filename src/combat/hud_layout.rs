@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// HUD layout: the hotbar rows (and the crossbar that stands in for them) are placed from an
+// anchor preset rather than fixed pixels, so they stay put on any window size. The preset is
+// picked on the settings panel; the UI scale and its fit to narrow windows live in `settings`.
+
+/// Gap to the screen edge for left-anchored rows
+const EDGE_INSET: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HudAnchor {
+    #[default]
+    BottomCenter,
+    BottomLeft,
+}
+
+impl HudAnchor {
+    pub fn cycle(self) -> Self {
+        match self {
+            HudAnchor::BottomCenter => HudAnchor::BottomLeft,
+            HudAnchor::BottomLeft => HudAnchor::BottomCenter,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HudAnchor::BottomCenter => "bottom center",
+            HudAnchor::BottomLeft => "bottom left",
+        }
+    }
+
+    /// Lines an absolutely positioned row up horizontally. Centered rows span the screen and
+    /// center their children, so they need no width to be known
+    pub fn place(self, node: &mut Node) {
+        match self {
+            HudAnchor::BottomCenter => {
+                node.left = Val::Px(0.0);
+                node.right = Val::Px(0.0);
+                node.justify_content = JustifyContent::Center;
+            }
+            HudAnchor::BottomLeft => {
+                node.left = Val::Px(EDGE_INSET);
+                node.right = Val::Auto;
+                node.justify_content = JustifyContent::FlexStart;
+            }
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use std::collections::HashMap;
+
+use super::encounter::{EncounterDef, EncounterLibrary, EncounterMeta, USER_ENCOUNTER_DIR};
+use super::timeline::{EnemyEvent, TimelineBranch};
+use crate::background::default_background;
+use crate::persist;
+use crate::world::Arena;
+
+// Timeline import: boss casts from a real fight turned into an encounter file, so it can be
+// practiced without writing the timeline by hand. Drop an ACT network log (`Network_*.log`)
+// or a short CSV of casts in the `imports` folder of the user data dir; at startup each one
+// without an encounter yet becomes `encounters/import-<file name>.encounter.ron`, which the
+// timeline editor can then touch up. Delete that file to import the log again.
+//
+// From an ACT log only the boss side is kept: every cast the enemies start becomes a Cast
+// event, and every enemy ability that lands on the player who wrote the log becomes a Hit,
+// scaled from their share of the logging player's HP to this game's HP pool. A log with
+// several pulls in it gives the longest one.
+
+/// Where logs to import go, relative to the user data dir
+pub const IMPORT_DIR: &str = "imports";
+
+/// The player's HP here; logged hits are scaled to it
+const PLAYER_HP: f32 = 1000.0;
+/// A gap this long between boss lines ends one pull and starts the next
+const PULL_GAP_SECS: f32 = 30.0;
+
+/// Imports every log in [`IMPORT_DIR`] that doesn't have an encounter in the library yet,
+/// adding it and saving it next to the editor's files
+pub fn import_logs(library: &mut EncounterLibrary) {
+    for name in persist::list(IMPORT_DIR) {
+        let Some((stem, ext)) = name.rsplit_once('.') else { continue; };
+        let id = format!("import-{stem}");
+        if library.encounters.iter().any(|e| e.id == id) {
+            continue;
+        }
+        let Some(text) = persist::load(&format!("{IMPORT_DIR}/{name}")) else { continue; };
+        let result = match ext.to_ascii_lowercase().as_str() {
+            "log" => parse_act_log(&text),
+            "csv" => parse_cast_csv(&text),
+            _ => continue,
+        };
+        let imported = match result {
+            Ok(imported) => imported,
+            Err(error) => {
+                warn!("Ignoring {IMPORT_DIR}/{name}: {error}");
+                continue;
+            }
+        };
+        let def = imported.into_encounter(id, stem);
+        match ron::ser::to_string_pretty(&def, PrettyConfig::default()) {
+            Ok(text) => persist::save(&format!("{USER_ENCOUNTER_DIR}/{}.encounter.ron", def.id), &text),
+            Err(error) => warn!("Couldn't save the import of {name}: {error}"),
+        }
+        info!("Imported {IMPORT_DIR}/{name} as {} ({} events)", def.id, def.branches[0].events.len());
+        library.add(def);
+    }
+}
+
+/// A pull read from a log, before it's made into an encounter
+#[derive(Debug, Default)]
+struct ImportedPull {
+    /// Zone or boss name, when the log says
+    name: Option<String>,
+    /// (seconds into the pull, event), in order
+    events: Vec<(f32, EnemyEvent)>,
+}
+
+impl ImportedPull {
+    fn into_encounter(self, id: String, file_stem: &str) -> EncounterDef {
+        let duration = self.events.last().map(|(t, _)| *t);
+        EncounterDef {
+            id,
+            name: self.name.unwrap_or_else(|| file_stem.to_string()),
+            meta: EncounterMeta {
+                expected_duration: duration,
+                tags: vec!["imported".to_string()],
+                ..default()
+            },
+            dummy: false,
+            preview: false,
+            branches: vec![TimelineBranch { name: "main".to_string(), events: self.events }],
+            phases: Vec::new(),
+            syncs: Vec::new(),
+            mechanics: Vec::new(),
+            scripts: Vec::new(),
+            arena: Arena::default(),
+            background: default_background(),
+            weather: Vec::new(),
+        }
+    }
+}
+
+/// Reads a simplified list of boss casts, one per line:
+///
+/// ```text
+/// time,cast[,damage]
+/// 0:08,Ruinous Flame
+/// 14.5,Tail Swipe,350
+/// ```
+///
+/// `time` is seconds or `m:ss` into the pull and `damage` is a hit on the player out of
+/// 1000 HP. A header line, blank lines and lines starting with `#` are skipped
+fn parse_cast_csv(text: &str) -> Result<ImportedPull, String> {
+    let mut pull = ImportedPull::default();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(',').map(str::trim);
+        let (time, name) = (fields.next().unwrap_or_default(), fields.next().unwrap_or_default());
+        let Some(t) = parse_clock(time) else {
+            // Only the first line can be a header
+            if n == 0 {
+                continue;
+            }
+            return Err(format!("line {}: can't read {time:?} as a time", n + 1));
+        };
+        if name.is_empty() {
+            return Err(format!("line {}: no cast name", n + 1));
+        }
+        pull.events.push((t, EnemyEvent::Cast { name: name.to_string() }));
+        if let Some(damage) = fields.next().filter(|f| !f.is_empty()) {
+            let amount = damage.parse().map_err(|_| format!("line {}: can't read damage {damage:?}", n + 1))?;
+            pull.events.push((t, EnemyEvent::Hit { amount }));
+        }
+    }
+    if pull.events.is_empty() {
+        return Err("no casts in it".to_string());
+    }
+    pull.events.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(pull)
+}
+
+/// Seconds, or minutes and seconds as `m:ss`
+fn parse_clock(text: &str) -> Option<f32> {
+    match text.split_once(':') {
+        Some((min, sec)) => Some(min.parse::<f32>().ok()? * 60.0 + sec.parse::<f32>().ok()?),
+        None => text.parse().ok(),
+    }
+}
+
+// ACT network log lines are `|`-separated, type first and timestamp second:
+//   01 zone change     01|ts|zone id|zone name|...
+//   02 logging player  02|ts|player id|player name|...
+//   20 starts casting  20|ts|source id|source|ability id|ability|target id|target|cast time|...
+//   21/22 ability      21|ts|source id|source|ability id|ability|target id|target|flags|damage|
+//                      (14 more flag and value fields)|target hp|target max hp|...
+// Actor ids starting with 1 are players, 4 are enemies and other NPCs.
+const FIELD_TARGET_MAX_HP: usize = 25;
+
+fn parse_act_log(text: &str) -> Result<ImportedPull, String> {
+    let mut zone = None;
+    let mut player: Option<String> = None;
+    // Boss names by how often they cast, for a name when the log has no zone
+    let mut casters: HashMap<String, usize> = HashMap::new();
+    // (seconds since the first line, event)
+    let mut events: Vec<(f32, EnemyEvent)> = Vec::new();
+    let mut first = None;
+
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split('|').collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        let Some(secs) = parse_timestamp(fields[1]) else { continue; };
+        let t = (secs - *first.get_or_insert(secs)) as f32;
+        match fields[0] {
+            "01" => zone = Some(fields[3].to_string()),
+            "02" => player = Some(fields[2].to_string()),
+            "20" if fields.len() > 6 && is_enemy(fields[2]) => {
+                *casters.entry(fields[3].to_string()).or_default() += 1;
+                events.push((t, EnemyEvent::Cast { name: fields[5].to_string() }));
+            }
+            "21" | "22" if fields.len() > FIELD_TARGET_MAX_HP && is_enemy(fields[2]) => {
+                // Without a logging player line, the first player hit stands in for them
+                let target = fields[6];
+                if !target.starts_with('1') || *player.get_or_insert_with(|| target.to_string()) != target {
+                    continue;
+                }
+                // Auto-attacks would bury the timeline
+                if fields[5].eq_ignore_ascii_case("attack") {
+                    continue;
+                }
+                let flags = u32::from_str_radix(fields[8], 16).unwrap_or(0);
+                let damage = unscramble_damage(fields[9]);
+                let max_hp: f32 = fields[FIELD_TARGET_MAX_HP].parse().unwrap_or(0.0);
+                if flags & 0xF != 0x3 || damage == 0 || max_hp <= 0.0 {
+                    continue;
+                }
+                let amount = (damage as f32 / max_hp * PLAYER_HP).round().max(1.0) as i32;
+                events.push((t, EnemyEvent::Hit { amount }));
+            }
+            _ => {}
+        }
+    }
+    if events.is_empty() {
+        return Err("no enemy casts or hits on the player in it".to_string());
+    }
+    events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Split into pulls at long quiet gaps and keep the longest
+    let mut pulls: Vec<Vec<(f32, EnemyEvent)>> = Vec::new();
+    let mut last = f32::NEG_INFINITY;
+    for (t, event) in events {
+        if t - last > PULL_GAP_SECS {
+            pulls.push(Vec::new());
+        }
+        last = t;
+        pulls.last_mut().unwrap().push((t, event));
+    }
+    let mut events = pulls
+        .into_iter()
+        .max_by(|a, b| {
+            let span = |p: &Vec<(f32, EnemyEvent)>| p.last().unwrap().0 - p[0].0;
+            span(a).total_cmp(&span(b))
+        })
+        .unwrap();
+    let start = events[0].0;
+    for (t, _) in &mut events {
+        *t -= start;
+    }
+
+    let boss = casters.into_iter().max_by_key(|(_, n)| *n).map(|(name, _)| name);
+    Ok(ImportedPull { name: zone.filter(|z| !z.is_empty()).or(boss), events })
+}
+
+/// Pets have enemy-style ids too, but they don't hit the player or cast with a bar
+fn is_enemy(actor_id: &str) -> bool {
+    actor_id.starts_with('4')
+}
+
+/// Seconds since 1970 from an ACT timestamp like `2024-03-09T21:04:11.2310000-05:00`. The
+/// offset is left off; it's the same for the whole log
+fn parse_timestamp(ts: &str) -> Option<f64> {
+    let (date, time) = ts.split_once('T')?;
+    let mut ymd = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (ymd.next()??, ymd.next()??, ymd.next()??);
+    // Cut the offset: the first + or - after the seconds
+    let time = time.find(['+', '-', 'Z']).map_or(time, |i| &time[..i]);
+    let mut hms = time.splitn(3, ':');
+    let hours: f64 = hms.next()?.parse().ok()?;
+    let minutes: f64 = hms.next()?.parse().ok()?;
+    let seconds: f64 = hms.next()?.parse().ok()?;
+    Some(days_from_civil(year, month, day) as f64 * 86_400.0 + hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Days since 1970-01-01 for a calendar date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The damage field packs big numbers: `AAAABBCC` is normally AAAA, but with 0x40 in BB
+/// CC is a third, high byte on top of it
+fn unscramble_damage(field: &str) -> i64 {
+    let len = field.len();
+    if len <= 4 || !field.is_ascii() {
+        return 0;
+    }
+    let Ok(damage) = i64::from_str_radix(&field[..len - 4], 16) else { return 0; };
+    if field.as_bytes()[len - 4] == b'4' {
+        let high = i64::from_str_radix(&field[len - 2..], 16).unwrap_or(0);
+        return (high << 16) | (damage & 0xFFFF);
+    }
+    damage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A timestamp `secs` after 21:00 on a day in March 2024, at UTC-5
+    fn ts(secs: u32) -> String {
+        format!("2024-03-09T21:{:02}:{:02}.0000000-05:00", secs / 60, secs % 60)
+    }
+
+    fn cast(secs: u32, name: &str) -> String {
+        format!("20|{}|40001234|Lindwurm|1F00|{name}|10005678|Some One|2.700|0|0", ts(secs))
+    }
+
+    /// An ability line hitting `target` with the 14 flag and value fields before the HP zeroed
+    fn hit(secs: u32, name: &str, target: &str, flags: &str, damage: &str) -> String {
+        let mut fields = vec![
+            "21".to_string(),
+            ts(secs),
+            "40001234".to_string(),
+            "Lindwurm".to_string(),
+            "1F01".to_string(),
+            name.to_string(),
+            target.to_string(),
+            "Some One".to_string(),
+            flags.to_string(),
+            damage.to_string(),
+        ];
+        fields.extend(std::iter::repeat_n("0".to_string(), 14));
+        fields.extend(["60000".to_string(), "100000".to_string(), "0".to_string()]);
+        fields.join("|")
+    }
+
+    fn hits(pull: &ImportedPull) -> Vec<(f32, i32)> {
+        let as_hit = |(t, event): &(f32, EnemyEvent)| match event {
+            EnemyEvent::Hit { amount } => Some((*t, *amount)),
+            _ => None,
+        };
+        pull.events.iter().filter_map(as_hit).collect()
+    }
+
+    fn casts(pull: &ImportedPull) -> Vec<(f32, &str)> {
+        let as_cast = |(t, event): &(f32, EnemyEvent)| match event {
+            EnemyEvent::Cast { name } => Some((*t, name.as_str())),
+            _ => None,
+        };
+        pull.events.iter().filter_map(as_cast).collect()
+    }
+
+    #[test]
+    fn timestamps_drop_the_offset() {
+        let day = 19_791.0 * 86_400.0;
+        let at = |text| parse_timestamp(text).unwrap();
+        assert!((at("2024-03-09T21:04:11.2310000-05:00") - (day + 75_851.231)).abs() < 1e-3);
+        assert_eq!(at("2024-03-09T21:04:11.0000000+09:00"), day + 75_851.0);
+        assert_eq!(at("1970-01-01T00:00:01.5000000Z"), 1.5);
+        assert_eq!(at("2024-03-09T21:04:11"), day + 75_851.0);
+        assert!(parse_timestamp("21:04:11").is_none());
+        assert!(parse_timestamp("2024-03-09T21:04").is_none());
+    }
+
+    #[test]
+    fn damage_high_byte_is_unscrambled() {
+        assert_eq!(unscramble_damage("05DC0000"), 1500);
+        assert_eq!(unscramble_damage("2B3C401A"), 0x1A2B3C);
+        assert_eq!(unscramble_damage("0000"), 0);
+        assert_eq!(unscramble_damage("zz000000"), 0);
+    }
+
+    #[test]
+    fn act_log_keeps_the_longest_pull() {
+        let log = [
+            format!("01|{}|3E8|The Arena|", ts(0)),
+            format!("02|{}|10005678|Some One|", ts(0)),
+            // A short first pull
+            cast(1, "Opener"),
+            hit(4, "Opener", "10005678", "750003", "0FA00000"),
+            // More than PULL_GAP_SECS later, a longer one
+            cast(120, "Ruinous Flame"),
+            hit(123, "Ruinous Flame", "10005678", "750003", "0FA00000"),
+            hit(124, "attack", "10005678", "750003", "0FA00000"),
+            hit(125, "Tail Swipe", "10009999", "750003", "0FA00000"),
+            hit(126, "Missed", "10005678", "750001", "0FA00000"),
+            hit(150, "Tail Swipe", "10005678", "3", "2B3C401A"),
+            cast(180, "Enrage"),
+        ]
+        .join("\n");
+        let pull = parse_act_log(&log).unwrap();
+        assert_eq!(pull.name.as_deref(), Some("The Arena"));
+        assert_eq!(casts(&pull), vec![(0.0, "Ruinous Flame"), (60.0, "Enrage")]);
+        // 4000 of 100000 HP is 40 of ours; the unscrambled hit is more than the whole bar
+        assert_eq!(hits(&pull), vec![(3.0, 40), (30.0, 17_150)]);
+    }
+
+    #[test]
+    fn act_log_without_enemies_is_refused() {
+        let log = format!("01|{}|3E8|The Arena|\n02|{}|10005678|Some One|", ts(0), ts(0));
+        assert!(parse_act_log(&log).is_err());
+    }
+
+    #[test]
+    fn cast_csv_reads_times_and_damage() {
+        let csv = "time,cast,damage\n# opener\n\n0:08,Ruinous Flame\n14.5,Tail Swipe,350\n1:02,Enrage,\n";
+        let pull = parse_cast_csv(csv).unwrap();
+        assert_eq!(casts(&pull), vec![(8.0, "Ruinous Flame"), (14.5, "Tail Swipe"), (62.0, "Enrage")]);
+        assert_eq!(hits(&pull), vec![(14.5, 350)]);
+    }
+
+    #[test]
+    fn cast_csv_reports_bad_lines() {
+        assert!(parse_cast_csv("").is_err());
+        assert!(parse_cast_csv("0:08,Ruinous Flame\nsoon,Tail Swipe").is_err());
+        assert!(parse_cast_csv("0:08,").is_err());
+        assert!(parse_cast_csv("0:08,Tail Swipe,lots").is_err());
+    }
+}
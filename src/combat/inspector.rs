@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+
+use super::timeline::EnemyTimeline;
+use super::{AbilityBook, AbilityId, CombatState};
+use crate::console::CommandResult;
+use crate::GameState;
+
+// Debug inspector: two text panels with the live combat state (GCD, animation lock, weaves,
+// buffer, queue, cooldowns, statuses) and where the boss timeline is, for checking timing
+// logic by eye. Off by default; the `inspect` console command turns it on and off, and it
+// stays that way across pulls.
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct Inspector {
+    open: bool,
+}
+
+#[derive(Component)]
+pub(super) struct InspectorPanel;
+
+#[derive(Component)]
+pub(super) struct CombatStateText;
+
+#[derive(Component)]
+pub(super) struct TimelineText;
+
+/// Console: `inspect` shows or hides the panels
+pub(super) fn toggle_inspector(_: In<Vec<String>>, mut inspector: ResMut<Inspector>) -> CommandResult {
+    inspector.open = !inspector.open;
+    Ok(format!("Inspector {}", if inspector.open { "on" } else { "off" }))
+}
+
+pub(super) fn spawn_inspector(mut commands: Commands) {
+    let panel = |left: f32| {
+        (
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.0),
+                left: Val::Px(left),
+                width: Val::Px(300.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.75)),
+            GlobalZIndex(80),
+            Visibility::Hidden,
+            InspectorPanel,
+            StateScoped(GameState::Playing),
+        )
+    };
+    let text = || (Text::new(""), TextFont { font_size: 12.0, ..default() }, TextColor(Color::linear_rgb(0.7, 1.0, 0.8)));
+    commands.spawn(panel(8.0)).with_child((text(), CombatStateText));
+    commands.spawn(panel(316.0)).with_child((text(), TimelineText));
+}
+
+pub(super) fn update_inspector(
+    inspector: Res<Inspector>,
+    book: Res<AbilityBook>,
+    combat: Res<CombatState>,
+    timeline: Res<EnemyTimeline>,
+    mut q_panels: Query<&mut Visibility, With<InspectorPanel>>,
+    mut q_combat: Query<&mut Text, (With<CombatStateText>, Without<TimelineText>)>,
+    mut q_timeline: Query<&mut Text, With<TimelineText>>,
+) {
+    for mut vis in &mut q_panels {
+        vis.set_if_neq(if inspector.open { Visibility::Inherited } else { Visibility::Hidden });
+    }
+    if !inspector.open {
+        return;
+    }
+    if let Ok(mut text) = q_combat.single_mut() {
+        text.0 = format!("COMBAT\n{}", combat_lines(&combat, &book).join("\n"));
+    }
+    if let Ok(mut text) = q_timeline.single_mut() {
+        text.0 = format!("TIMELINE\n{}", timeline.inspect().join("\n"));
+    }
+}
+
+fn combat_lines(combat: &CombatState, book: &AbilityBook) -> Vec<String> {
+    let name = |id: &AbilityId| book.by_id.get(id).map_or("?", |a| a.name);
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let mut lines = vec![
+        format!("GCD {:.2} / {:.2}  queue window {:.2}", combat.gcd_remaining, combat.gcd_length, combat.gcd_queue_window),
+        format!(
+            "Cast {}",
+            or_dash(combat.cast.as_ref().map(|c| format!("{} {:.2} / {:.2}", name(&c.ability), c.remaining, c.total)))
+        ),
+        format!(
+            "Anim lock {:.2}  weaves {}  clipped {}",
+            combat.ani_lock_remaining, combat.weaves_in_current_gcd, combat.clipped
+        ),
+        format!("Buffer {}", or_dash(combat.buffer.map(|(id, left)| format!("{} {left:.2}s", name(&id))))),
+        format!("Queued GCD {}", or_dash(combat.gcd_queue.map(|id| name(&id).to_string()))),
+    ];
+    let mut cds: Vec<String> = combat
+        .ability_cds
+        .iter()
+        .filter(|(_, cd)| **cd > 0.0)
+        .map(|(id, cd)| format!("{} {cd:.1}", name(id)))
+        .collect();
+    cds.extend(combat.group_cds.iter().filter(|(_, (cd, _))| *cd > 0.0).map(|(g, (cd, total))| format!("{g:?} {cd:.1}/{total:.0}")));
+    cds.sort();
+    lines.push(format!("Cooldowns {}", if cds.is_empty() { "-".to_string() } else { cds.join(", ") }));
+    for status in &combat.statuses {
+        lines.push(format!("  {} x{} {:.1}s", status.def.name, status.stacks, status.remaining));
+    }
+    if let Some(left) = combat.muddled {
+        lines.push(format!("Muddled {left:.1}s {:?} x{:.1}", combat.muddled_variant, combat.muddled_intensity));
+    }
+    if let Some(left) = combat.swiftcast_remaining {
+        lines.push(format!("Swiftcast ready {left:.1}s"));
+    }
+    if let Some(left) = combat.mitigation_remaining {
+        lines.push(format!("Rampart {left:.1}s"));
+    }
+    if let Some(shield) = combat.shield {
+        lines.push(format!("Shield {} for {:.1}s", shield.amount, shield.remaining));
+    }
+    if let Some(left) = combat.invuln_remaining {
+        lines.push(format!("Invuln {left:.1}s"));
+    }
+    if let Some((id, left)) = combat.locked_ability {
+        lines.push(format!("Locked {} {left:.1}s", name(&id)));
+    }
+    lines.push(format!("Position {}", or_dash(combat.position.map(|p| format!("{p:?}")))));
+    lines
+}
@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+
+use super::AbilityId;
+use crate::actions::{SimAction, SimInput};
+use crate::persist;
+use crate::settings::Settings;
+use crate::sim_time::SimTime;
+
+/// One RTT measurement from a recorded trace
+#[derive(Debug, Clone, Copy)]
+pub struct LatencySample {
+    pub t: f32,
+    pub rtt_ms: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum LatencyProfile {
+    #[default]
+    Off,
+    Constant { rtt_ms: f32 },
+    /// Replays recorded samples from the start of the pull, looping at the end
+    Trace { samples: Vec<LatencySample> },
+}
+
+impl LatencyProfile {
+    /// RTT at `t` seconds into the pull. Traces hold each sample until the next one,
+    /// so spikes come through exactly as recorded instead of being smoothed out.
+    pub fn rtt_at(&self, t: f32) -> f32 {
+        match self {
+            LatencyProfile::Off => 0.0,
+            LatencyProfile::Constant { rtt_ms } => *rtt_ms,
+            LatencyProfile::Trace { samples } => {
+                let Some(last) = samples.last() else { return 0.0; };
+                let t = if last.t > 0.0 { t % last.t } else { 0.0 };
+                samples
+                    .iter()
+                    .take_while(|s| s.t <= t)
+                    .last()
+                    .unwrap_or(&samples[0])
+                    .rtt_ms
+            }
+        }
+    }
+
+    /// Parses a trace: one "<seconds> <rtt ms>" pair per line, `#` starts a comment.
+    /// Commas work as separators too so CSV exports can be used directly.
+    pub fn parse_trace(contents: &str) -> Result<Self, String> {
+        let mut samples = Vec::new();
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty());
+            let (Some(t), Some(rtt)) = (parts.next(), parts.next()) else {
+                return Err(format!("line {}: expected \"<seconds> <rtt ms>\"", n + 1));
+            };
+            let (Ok(t), Ok(rtt_ms)) = (t.parse::<f32>(), rtt.parse::<f32>()) else {
+                return Err(format!("line {}: not a number", n + 1));
+            };
+            samples.push(LatencySample { t, rtt_ms });
+        }
+        if samples.is_empty() {
+            return Err("trace has no samples".to_string());
+        }
+        samples.sort_by(|a, b| a.t.total_cmp(&b.t));
+        Ok(LatencyProfile::Trace { samples })
+    }
+}
+
+/// Simulated connection between the player's key presses and the combat sim
+#[derive(Resource, Debug, Default)]
+pub struct InputLatency {
+    pub profile: LatencyProfile,
+    pub enabled: bool,
+    pub elapsed: f32,
+    pending: Vec<(AbilityId, f32)>,
+}
+
+impl InputLatency {
+    pub fn current_rtt_ms(&self) -> f32 {
+        if self.enabled { self.profile.rtt_at(self.elapsed) } else { 0.0 }
+    }
+
+    /// Queues a press to land after the one-way delay (half the RTT)
+    pub fn send(&mut self, id: AbilityId) {
+        let delay = self.current_rtt_ms() * 0.5 / 1000.0;
+        self.pending.push((id, delay));
+    }
+
+    /// Presses sent but not yet arrived, with seconds left until they do
+    pub fn in_flight(&self) -> impl Iterator<Item = (AbilityId, f32)> + '_ {
+        self.pending.iter().copied()
+    }
+
+    /// Advances the clock and returns the presses that arrived, oldest first
+    pub fn deliver(&mut self, dt: f32) -> Vec<AbilityId> {
+        self.elapsed += dt;
+        let mut arrived = Vec::new();
+        for (id, left) in self.pending.iter_mut() {
+            *left -= dt;
+            if *left <= 0.0 {
+                arrived.push(*id);
+            }
+        }
+        self.pending.retain(|(_, left)| *left > 0.0);
+        arrived
+    }
+}
+
+const CALIBRATION_FILE: &str = "calibration.txt";
+
+/// How late the player presses after a cue they hear or see, measured on the calibration
+/// screen. Covers the whole chain (output device, reaction, input device), positive when late.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct Calibration {
+    pub audio_offset_ms: f32,
+    pub visual_offset_ms: f32,
+}
+
+impl Calibration {
+    /// Seconds the visualizers run ahead so what's on screen lines up with what the player
+    /// perceives. Negative offsets (pressing early) aren't compensated.
+    pub fn visual_lead(&self) -> f32 {
+        self.visual_offset_ms.max(0.0) / 1000.0
+    }
+
+    /// The same for sounds timed to the sim, like the GCD metronome
+    pub fn audio_lead(&self) -> f32 {
+        self.audio_offset_ms.max(0.0) / 1000.0
+    }
+
+    // File format: "audio_offset_ms = <n>" and "visual_offset_ms = <n>"
+    pub fn load() -> Self {
+        let mut calibration = Self::default();
+        let Some(contents) = persist::load(CALIBRATION_FILE) else { return calibration; };
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue; };
+            let Ok(value) = value.trim().parse::<f32>() else {
+                warn!("Bad calibration value {line:?}");
+                continue;
+            };
+            match key.trim() {
+                "audio_offset_ms" => calibration.audio_offset_ms = value,
+                "visual_offset_ms" => calibration.visual_offset_ms = value,
+                key => warn!("Unknown calibration setting {key:?}"),
+            }
+        }
+        calibration
+    }
+
+    pub fn save(&self) {
+        let out = format!("audio_offset_ms = {:.0}\nvisual_offset_ms = {:.0}\n", self.audio_offset_ms, self.visual_offset_ms);
+        persist::save(CALIBRATION_FILE, &out);
+    }
+}
+
+/// Loads `latency_trace.txt` from the user data dir (or the file in `JRPG_LATENCY_TRACE`).
+/// Without a trace, `JRPG_LATENCY_MS` or else the latency setting sets a constant RTT.
+pub(super) fn load_latency_profile(settings: Option<Res<Settings>>, mut latency: ResMut<InputLatency>) {
+    latency.elapsed = 0.0;
+    latency.pending.clear();
+    if let Some(rtt_ms) = settings.map(|s| s.latency_ms).filter(|ms| *ms > 0.0) {
+        latency.profile = LatencyProfile::Constant { rtt_ms };
+        latency.enabled = true;
+    }
+    if let Some(rtt_ms) = std::env::var("JRPG_LATENCY_MS").ok().and_then(|v| v.parse::<f32>().ok()) {
+        latency.profile = LatencyProfile::Constant { rtt_ms };
+        latency.enabled = true;
+    }
+    let Some(contents) = load_trace_override().unwrap_or_else(|| persist::load("latency_trace.txt")) else { return; };
+    match LatencyProfile::parse_trace(&contents) {
+        Ok(profile) => {
+            latency.profile = profile;
+            latency.enabled = true;
+        }
+        Err(error) => warn!("Ignoring latency trace: {error}"),
+    }
+}
+
+/// The trace named by `JRPG_LATENCY_TRACE`, a path anywhere on disk rather than under the data
+/// dir; `None` when it isn't set
+#[cfg(not(target_arch = "wasm32"))]
+fn load_trace_override() -> Option<Option<String>> {
+    let path = std::env::var("JRPG_LATENCY_TRACE").ok()?;
+    Some(std::fs::read_to_string(&path).map_err(|error| warn!("Failed to read latency trace {path:?}: {error:?}")).ok())
+}
+
+/// No env vars or free-standing files in the browser
+#[cfg(target_arch = "wasm32")]
+fn load_trace_override() -> Option<Option<String>> {
+    None
+}
+
+/// Presses arriving this tick join its actions
+pub(super) fn deliver_presses(time: SimTime, mut latency: ResMut<InputLatency>, mut input: ResMut<SimInput>) {
+    for id in latency.deliver(time.scaled_delta()) {
+        input.actions.push(SimAction::Press(id));
+    }
+}
+
+pub(super) fn toggle_latency(keys: Res<ButtonInput<KeyCode>>, mut latency: ResMut<InputLatency>) {
+    if keys.just_pressed(KeyCode::F7) {
+        latency.enabled = !latency.enabled;
+        info!("Simulated latency {}", if latency.enabled { "on" } else { "off" });
+    }
+}
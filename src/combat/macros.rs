@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use super::{AbilityBook, AbilityId, ButtonFlashEvent, InputLatency};
+use crate::persist;
+use crate::sim_time::SimTime;
+
+// User macros: a key fires a list of ability presses with optional waits.
+// Presses go through the same latency, buffering and GCD rules as manual input,
+// so macro clipping can be compared against playing by hand.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MacroStep {
+    Press(AbilityId),
+    Wait(f32),
+}
+
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub key: KeyCode,
+    pub steps: Vec<MacroStep>,
+}
+
+/// Macros bound to keys, loaded from `macros.txt`: one "<key> = <steps>" per line,
+/// steps separated by commas, e.g. `F1 = Strike, wait 0.5, Weave: Dash`
+#[derive(Resource, Debug, Default)]
+pub struct MacroBook {
+    pub macros: Vec<Macro>,
+}
+
+/// The macro currently executing; starting another one replaces it, like in game
+#[derive(Resource, Debug, Default)]
+pub struct MacroRunner {
+    steps: Vec<MacroStep>,
+    idx: usize,
+    wait: f32,
+}
+
+impl MacroRunner {
+    pub fn start(&mut self, steps: Vec<MacroStep>) {
+        self.steps = steps;
+        self.idx = 0;
+        self.wait = 0.0;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.idx < self.steps.len()
+    }
+}
+
+/// Matches ability names loosely: case, spaces and punctuation are ignored,
+/// so "Weave: Dash", "weave dash" and "WeaveDash" are the same ability
+pub(super) fn find_ability(book: &AbilityBook, name: &str) -> Option<AbilityId> {
+    let normalize = |s: &str| s.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+    let wanted = normalize(name);
+    book.by_id.values().find(|a| normalize(a.name) == wanted).map(|a| a.id)
+}
+
+pub fn parse_steps(text: &str, book: &AbilityBook) -> Result<Vec<MacroStep>, String> {
+    let mut steps = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        if let Some(secs) = part.strip_prefix("wait") {
+            let secs = secs.trim().parse::<f32>().map_err(|_| format!("bad wait {part:?}"))?;
+            steps.push(MacroStep::Wait(secs.max(0.0)));
+        } else {
+            let id = find_ability(book, part).ok_or_else(|| format!("unknown ability {part:?}"))?;
+            steps.push(MacroStep::Press(id));
+        }
+    }
+    if steps.is_empty() {
+        return Err("macro has no steps".to_string());
+    }
+    Ok(steps)
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let keys = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+    ];
+    let names = ["F1", "F2", "F3", "F4", "F5", "F9", "F10", "F11"];
+    names.iter().position(|n| n.eq_ignore_ascii_case(name)).map(|i| keys[i])
+}
+
+pub(super) fn load_macros(book: Res<AbilityBook>, mut macros: ResMut<MacroBook>, mut runner: ResMut<MacroRunner>) {
+    *runner = MacroRunner::default();
+    macros.macros.clear();
+    let Some(contents) = persist::load("macros.txt") else { return; };
+    for (n, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((key, steps)) = line.split_once('=') else { continue; };
+        let Some(key) = parse_key(key.trim()) else {
+            warn!("macros.txt line {}: unsupported key {:?} (F1-F5, F9-F11; F12 is captures)", n + 1, key.trim());
+            continue;
+        };
+        match parse_steps(steps, &book) {
+            Ok(steps) => macros.macros.push(Macro { key, steps }),
+            Err(error) => warn!("macros.txt line {}: {error}", n + 1),
+        }
+    }
+}
+
+pub(super) fn trigger_macros(keys: Res<ButtonInput<KeyCode>>, macros: Res<MacroBook>, mut runner: ResMut<MacroRunner>) {
+    if let Some(m) = macros.macros.iter().find(|m| keys.just_pressed(m.key)) {
+        runner.start(m.steps.clone());
+    }
+}
+
+/// Sends every press up to the next wait; runs on the sim's ticks, so waits are counted in them
+pub(super) fn run_macros(
+    time: SimTime,
+    mut runner: ResMut<MacroRunner>,
+    mut latency: ResMut<InputLatency>,
+    mut flash_writer: EventWriter<ButtonFlashEvent>,
+) {
+    if !runner.is_running() {
+        return;
+    }
+    runner.wait -= time.scaled_delta();
+    while runner.wait <= 0.0 {
+        let Some(step) = runner.steps.get(runner.idx).copied() else { break; };
+        runner.idx += 1;
+        match step {
+            MacroStep::Press(id) => {
+                flash_writer.write(ButtonFlashEvent { id });
+                latency.send(id);
+            }
+            MacroStep::Wait(secs) => runner.wait += secs,
+        }
+    }
+}
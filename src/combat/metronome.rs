@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use super::{Calibration, CombatState};
+use crate::GameState;
+
+// GCD metronome: a tick sound and a pulsing ring the moment the GCD comes back, to get
+// the 2.5s rhythm into the player's hands. Off by default, switched on from the menu.
+// Like the GCD bar, both cues go out early by the calibrated audio and display lag.
+// Whether it's on is kept with the other settings, see `settings`.
+
+/// Seconds the ring takes to fade after a beat
+const PULSE_SECS: f32 = 0.3;
+
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct Metronome {
+    pub enabled: bool,
+}
+
+impl Metronome {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+}
+
+/// The GCD is about to come back; the audio plugin plays the tick
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MetronomeTickEvent;
+
+#[derive(Component)]
+pub(super) struct MetronomeRing;
+
+pub(super) fn spawn_metronome_ring(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Px(22.0),
+            height: Val::Px(22.0),
+            bottom: Val::Px(120.0),
+            left: Val::Percent(50.0),
+            margin: UiRect::left(Val::Px(-30.0)),
+            border: UiRect::all(Val::Px(3.0)),
+            ..default()
+        },
+        BorderRadius::MAX,
+        BorderColor(Color::NONE),
+        Visibility::Hidden,
+        MetronomeRing,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+/// Whether the GCD crossed `lead` seconds left this frame. A GCD that ended and restarted
+/// within the frame (a queued GCD going off) counts too
+fn crossed(prev: f32, now: f32, lead: f32) -> bool {
+    prev > lead && (now <= lead || now > prev)
+}
+
+pub(super) fn beat(
+    metronome: Res<Metronome>,
+    combat: Res<CombatState>,
+    calibration: Res<Calibration>,
+    mut prev_remaining: Local<f32>,
+    mut pulse: Local<f32>,
+    real_time: Res<Time<Real>>,
+    mut tick_writer: EventWriter<MetronomeTickEvent>,
+    mut q_ring: Query<(&mut BorderColor, &mut Visibility), With<MetronomeRing>>,
+) {
+    let prev = std::mem::replace(&mut *prev_remaining, combat.gcd_remaining);
+    let Ok((mut border, mut vis)) = q_ring.single_mut() else { return; };
+    if !metronome.enabled {
+        *vis = Visibility::Hidden;
+        return;
+    }
+    *vis = Visibility::Inherited;
+    if crossed(prev, combat.gcd_remaining, calibration.audio_lead()) {
+        tick_writer.write(MetronomeTickEvent);
+    }
+    if crossed(prev, combat.gcd_remaining, calibration.visual_lead()) {
+        *pulse = PULSE_SECS;
+    }
+    *pulse = (*pulse - real_time.delta_secs()).max(0.0);
+    let glow = *pulse / PULSE_SECS;
+    border.0 = Color::linear_rgb(0.5 + 0.5 * glow, 0.6 + 0.4 * glow, 0.9).with_alpha(0.25 + 0.75 * glow);
+}
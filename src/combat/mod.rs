@@ -1,55 +1,274 @@
 use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
+use crate::actions::{collect_actions, set_movement_actions, ActionSet, Actions, SimAction, SimInput, Tappable};
+use crate::budget::timed;
+use crate::console::{CommandResult, ConsoleAppExt};
 use crate::{GameState, GameSet};
+use crate::keybinds::Keybinds;
 use crate::loading::TextureAssets;
+use crate::player::Player;
+use crate::rng::GameRng;
+use crate::settings::Settings;
+use crate::sim_time::{sim_running, SimTime, SimTimeScale};
+use crate::world::Health;
+
+mod action_error;
+mod buffs;
+mod cast_bar;
+mod cheatsheet;
+mod crossbar;
+mod dps_meter;
+mod drift;
+mod editor;
+mod encounter;
+mod gcd_bar;
+mod hotbar;
+mod hud_layout;
+mod import;
+mod inspector;
+mod latency;
+mod macros;
+mod metronome;
+mod notes;
+mod opener;
+mod positional;
+mod pull;
+mod queue_view;
+mod report;
+mod script;
+mod stats;
+mod status;
+mod timeline;
+mod view;
+
+pub use action_error::{ActionError, ActionErrorEvent};
+pub use crossbar::{CrossbarMapping, HotbarLayout};
+pub use encounter::{Difficulty, EncounterDef, EncounterLibrary, EncounterLoader, USER_ENCOUNTER_DIR};
+pub use hotbar::{HotbarSets, SwapHotbarEvent};
+pub use buffs::{BuffReport, BuffWindow};
+pub use hud_layout::HudAnchor;
+pub use import::import_logs;
+pub use latency::{Calibration, InputLatency};
+pub use macros::{parse_steps as parse_macro_steps, MacroRunner};
+pub use metronome::{Metronome, MetronomeTickEvent};
+pub use notes::{Bookmark, PullNotes};
+pub use opener::{parse_opener, OpenerTrainer};
+use macros::MacroBook;
+pub use positional::{PositionalTally, RelativePosition};
+use positional::PositionalSpec;
+pub use pull::{is_live, set_pull_origin, PullOrigin, PullResult};
+pub use stats::PlayerStats;
+pub use status::{update_statuses, ApplyStatusEvent, CleansePick, StatusBook, StatusDef, StatusFile, StatusLoader};
+use status::ActiveStatus;
+pub use timeline::{
+    BossPhaseEvent, CheckpointResult, CurrentEncounter, EncounterProgress, EnemyTimeline, EnrageEvent, PhaseChangeEvent,
+    TimelineSync,
+};
+pub use view::TargetView;
+use hotbar::{HotbarRules, HotbarSwapAnim, SlotAbilityLabel};
 
 const BUTTON_SIZE: f32 = 64.0;
+const BUTTONS_PER_ROW: usize = 5;
+/// Space between buttons in a hotbar row
+const HOTBAR_GAP: f32 = 8.0;
+/// Seconds before a cast finishes from which moving no longer cancels it
+const SLIDECAST_SECS: f32 = 0.5;
 
 pub struct CombatPlugin;
 
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<AbilityBook>()
+            .init_resource::<PlayerStats>()
+            .init_resource::<HotbarSets>()
+            .init_resource::<HotbarRules>()
+            .init_resource::<HotbarSwapAnim>()
+            .init_resource::<cheatsheet::CheatSheet>()
+            .init_resource::<OpenerTrainer>()
+            .init_resource::<CrossbarMapping>()
+            .init_resource::<crossbar::CrossbarState>()
+            .init_resource::<HotbarLayout>()
+            .init_resource::<Metronome>()
+            .init_resource::<InputLatency>()
+            .init_resource::<Calibration>()
+            .init_resource::<MacroBook>()
+            .init_resource::<MacroRunner>()
             .init_resource::<CombatState>()
+            .init_resource::<PrevTick>()
             .init_resource::<EnemyTimeline>()
+            .init_resource::<TimelineSync>()
+            .init_resource::<CurrentEncounter>()
+            .init_resource::<EncounterLibrary>()
+            .init_resource::<EncounterProgress>()
+            .init_resource::<PullNotes>()
+            .init_resource::<StatusBook>()
+            .init_resource::<PullResult>()
+            .init_resource::<pull::PullOrigin>()
+            .init_resource::<dps_meter::DpsMeter>()
+            .init_resource::<BuffReport>()
+            .init_resource::<drift::CooldownDrift>()
+            .init_resource::<report::PullReport>()
+            .init_resource::<editor::TimelineEditor>()
+            .init_resource::<inspector::Inspector>()
             .add_event::<HudShakeEvent>()
             .add_event::<DamageEvent>()
+            .add_event::<PlayerDamageEvent>()
             .add_event::<ApplyDotEvent>()
             .add_event::<ButtonFlashEvent>()
-            .add_systems(OnEnter(GameState::Playing), (spawn_hud, reset_combat))
+            .add_event::<AbilityUsedEvent>()
+            .add_event::<PhaseChangeEvent>()
+            .add_event::<BossPhaseEvent>()
+            .add_event::<EnrageEvent>()
+            .add_event::<SwapHotbarEvent>()
+            .add_event::<ApplyStatusEvent>()
+            .add_event::<MetronomeTickEvent>()
+            .add_event::<ActionErrorEvent>()
+            .add_console_cheat("grant", "grant <ability>: takes the ability off cooldown", grant_ability)
+            .add_console_cheat(
+                "timeline",
+                "timeline skip <seconds> | timeline branch <name>: moves the boss timeline",
+                timeline::timeline_command,
+            )
+            .add_console_command("inspect", "inspect: shows or hides the combat state inspector", inspector::toggle_inspector)
+            .add_systems(Startup, crossbar::load_hotbar_layout)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    (hotbar::reset_hotbar, spawn_hud).chain(),
+                    reset_combat,
+                    timeline::reset_encounter_progress,
+                    timeline::load_encounter,
+                    notes::reset_notes,
+                    (timeline::spawn_forecast_sidebar, timeline::spawn_enrage_timer),
+                    stats::load_player_stats,
+                    latency::load_latency_profile,
+                    macros::load_macros,
+                    crossbar::load_crossbar_mapping,
+                    crossbar::spawn_crossbar_panel,
+                    cheatsheet::load_cheat_sheet,
+                    cheatsheet::spawn_cheat_sheet,
+                    (opener::load_opener, opener::spawn_opener_panel),
+                    gcd_bar::spawn_gcd_bar,
+                    (queue_view::spawn_queue_view, inspector::spawn_inspector),
+                    metronome::spawn_metronome_ring,
+                    (dps_meter::reset_dps_meter, dps_meter::spawn_dps_meter),
+                    (buffs::reset_buff_report, drift::reset_cooldown_drift, report::reset_pull_report),
+                ),
+            )
             .add_systems(
                 PreUpdate,
                 (
-                    tick_combat_timers,
-                    process_cast_completion,
-                    handle_ability_input,
-                    process_buffered_ability,
-                    process_gcd_queue,
+                    cycle_speed_tier,
+                    latency::toggle_latency,
+                    hotbar::page_hotbar,
+                    hotbar::apply_hotbar_swap,
+                    macros::trigger_macros,
+                    crossbar::handle_crossbar_input,
+                    tap_hotbar_buttons,
+                    read_ability_keys,
+                    notes::drop_bookmark,
                 )
                     .chain()
-                    .in_set(GameSet::InputApply)
-                    .run_if(in_state(GameState::Playing)),
+                    .after(set_movement_actions)
+                    .in_set(GameSet::InputRead)
+                    .run_if(in_state(GameState::Playing).and(sim_running)),
+            )
+            .add_systems(
+                PreUpdate,
+                pull::restart_hotkey.in_set(GameSet::InputRead).run_if(in_state(GameState::Playing)),
             )
+            .add_systems(OnEnter(GameState::Playing), pull::set_pull_origin)
+            .add_systems(OnEnter(GameState::Restarting), pull::finish_restart)
+            .add_systems(OnEnter(GameState::Results), report::write_pull_report.run_if(pull::is_live))
+            .add_systems(OnEnter(GameState::Editor), editor::setup_editor)
             .add_systems(
                 Update,
-                run_enemy_timeline
+                (editor::edit_timeline, editor::update_editor).chain().run_if(in_state(GameState::Editor)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (macros::run_macros, latency::deliver_presses)
+                    .chain()
+                    .after(collect_actions)
+                    .in_set(ActionSet::Collect)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (positional::track_position, view::track_view, apply_speed_tier, status::pick_cleanse_target, apply_presses)
+                    .chain()
+                    .in_set(ActionSet::Apply)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    (
+                        snapshot_timers,
+                        tick_combat_timers,
+                        cancel_cast_on_move,
+                        process_cast_completion,
+                        process_buffered_ability,
+                        process_gcd_queue,
+                    )
+                        .chain(),
+                    timeline::sync_timeline,
+                    timeline::run_enemy_timeline,
+                    apply_player_damage,
+                    hotbar::swap_on_phase_change,
+                    status::update_statuses,
+                    opener::grade_opener,
+                    buffs::track_buff_windows,
+                    drift::track_cooldown_drift,
+                    crate::world::settle_projectiles.run_if(pull::pull_ending),
+                    dps_meter::record_damage,
+                    report::track_pull_report,
+                    pull::end_pull,
+                )
+                    .chain()
                     .in_set(GameSet::Sim)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
                 (
-                    update_cooldown_bars,
-                    update_cast_bar,
-                    update_status_row,
-                    update_muddled_layout,
-                    update_muddled_buttons,
+                    hotbar::tick_hotbar_swap_anim,
+                    timed("cooldown bars", update_cooldown_bars.run_if(cooldowns_running)),
+                    timed("cast bar", cast_bar::update_cast_bar.run_if(cast_bar::casting)),
+                    (action_error::show_action_errors, update_error_text).chain(),
+                    timed("gcd bar", gcd_bar::update_gcd_bar.run_if(gcd_bar::gcd_bar_moving)),
+                    (timed("queue view", queue_view::update_queue_view), inspector::update_inspector),
+                    metronome::beat,
+                    timed("dps meter", dps_meter::update_dps_meter.run_if(resource_changed::<dps_meter::DpsMeter>)),
+                    timed("status row", update_status_row.run_if(status_row_changed)),
+                    (timed("forecast", timeline::update_forecast_sidebar), timeline::update_enrage_timer),
+                    crossbar::update_crossbar_panel,
+                    (crossbar::apply_hotbar_layout, crossbar::update_crossbar_hud),
+                    (
+                        (cheatsheet::advance_cheat_sheet, cheatsheet::update_cheat_sheet).chain(),
+                        opener::update_opener,
+                    ),
+                    timed(
+                        "hotbar layout",
+                        update_muddled_layout.run_if(
+                            resource_changed::<HotbarSwapAnim>
+                                .or(resource_exists_and_changed::<Settings>)
+                                .or(hotbars_added),
+                        ),
+                    ),
+                    timed(
+                        "muddled buttons",
+                        update_muddled_buttons.run_if(buttons_displaced),
+                    ),
                     trigger_button_flash,
                     decay_button_shake,
+                    status::show_status_auras,
                     animate_ui_effects,
                     apply_hud_shake,
-                    shake_hud_node,
                 )
                     .in_set(GameSet::Ui)
                     .run_if(in_state(GameState::Playing)),
@@ -65,12 +284,15 @@ pub enum AbilityId {
     Fireball,   // GCD hard cast
     WeaveDash,  // oGCD instant
     WeaveSong,  // oGCD instant
-    Cleanse,    // oGCD instant - clears muddled
+    Cleanse,    // oGCD instant - clears muddled or another cleansable debuff
     Burn,       // GCD instant DoT (placeholder)
-    Heal,       // GCD hard cast heal (placeholder)
+    Heal,       // GCD hard cast heal, leaves a regen
     Swiftcast,  // oGCD buff: next cast instant within 10s
-    Raging,     // oGCD buff window (placeholder)
+    Raging,     // oGCD buff window, the "raging" status
     Jump,       // oGCD instant
+    Rampart,    // oGCD mitigation: 20% less damage taken
+    Aegis,      // oGCD absorb shield
+    Invuln,     // oGCD: no damage taken for a few seconds
 }
 
 #[derive(Debug, Clone)]
@@ -81,6 +303,56 @@ pub struct Ability {
     pub cast_time: f32,   // seconds; 0.0 means instant
     pub cooldown: f32,    // seconds per ability
     pub ani_lock: f32,    // seconds the animation lock lasts
+    pub potency: u32,     // direct damage potency; 0 means no hit
+    pub dot: Option<DotSpec>,
+    pub cooldown_group: Option<CooldownGroup>, // abilities in a group share one recast timer
+    pub positional: Option<PositionalSpec>,
+    pub penetration: f32, // fraction of enemy armor the hit ignores
+    pub shred: Option<ShredSpec>,
+    pub applies: Option<&'static str>, // status id put on the player when it resolves
+    pub movement: Option<AbilityMovement>,
+    pub projectile: Option<ProjectileSpec>, // flies from the player; the hit lands when it gets there
+    pub impact_vfx: Option<&'static str>, // effect id played where its hits land, stars if None
+}
+
+/// Moves the player when the ability resolves; the arena edge stops the move either way
+#[derive(Debug, Clone, Copy)]
+pub enum AbilityMovement {
+    /// This far along the direction the player is facing
+    Dash { distance: f32 },
+    /// To melee range of the current target
+    LeapToTarget,
+}
+
+/// How a ranged ability's hit travels to its target
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectileSpec {
+    /// Pixels a second along the way
+    pub speed: f32,
+    /// Pixels it rises above the straight line at the middle of the way
+    pub arc: f32,
+    /// Effect id played along its path
+    pub trail: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CooldownGroup {
+    Mobility, // Weave: Dash and Jump lock each other out
+}
+
+/// Temporarily lowers the target's armor when the hit lands
+#[derive(Debug, Clone, Copy)]
+pub struct ShredSpec {
+    pub armor: f32,
+    pub duration: f32,
+}
+
+/// Damage-over-time applied when an ability resolves
+#[derive(Debug, Clone, Copy)]
+pub struct DotSpec {
+    pub potency: u32, // per tick
+    pub duration: f32,
+    pub tick_every: f32,
 }
 
 #[derive(Resource)]
@@ -93,43 +365,55 @@ impl Default for AbilityBook {
         let mut by_id = HashMap::new();
         by_id.insert(
             AbilityId::Strike,
-            Ability { id: AbilityId::Strike, name: "Strike", triggers_gcd: true, cast_time: 0.0, cooldown: 2.5, ani_lock: 0.6 },
+            Ability { id: AbilityId::Strike, name: "Strike", triggers_gcd: true, cast_time: 0.0, cooldown: 2.5, ani_lock: 0.6, potency: 100, dot: None, cooldown_group: None, positional: Some(PositionalSpec { from: RelativePosition::Flank, bonus: 40 }), penetration: 0.0, shred: None, applies: None, movement: None, projectile: None, impact_vfx: Some("slash_arc") },
         );
         by_id.insert(
             AbilityId::Fireball,
-            Ability { id: AbilityId::Fireball, name: "Fireball", triggers_gcd: true, cast_time: 1.5, cooldown: 2.5, ani_lock: 0.6 },
+            Ability { id: AbilityId::Fireball, name: "Fireball", triggers_gcd: true, cast_time: 1.5, cooldown: 2.5, ani_lock: 0.6, potency: 180, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: None, movement: None, projectile: Some(ProjectileSpec { speed: 520.0, arc: 40.0, trail: "fireball_trail" }), impact_vfx: Some("fireball_explosion") },
         );
         by_id.insert(
             AbilityId::WeaveDash,
-            Ability { id: AbilityId::WeaveDash, name: "Weave: Dash", triggers_gcd: false, cast_time: 0.0, cooldown: 20.0, ani_lock: 0.6 },
+            Ability { id: AbilityId::WeaveDash, name: "Weave: Dash", triggers_gcd: false, cast_time: 0.0, cooldown: 20.0, ani_lock: 0.6, potency: 60, dot: None, cooldown_group: Some(CooldownGroup::Mobility), positional: None, penetration: 0.0, shred: None, applies: None, movement: Some(AbilityMovement::Dash { distance: 140.0 }), projectile: None, impact_vfx: None },
         );
         by_id.insert(
             AbilityId::WeaveSong,
-            Ability { id: AbilityId::WeaveSong, name: "Weave: Song", triggers_gcd: false, cast_time: 0.0, cooldown: 30.0, ani_lock: 0.6 },
+            Ability { id: AbilityId::WeaveSong, name: "Weave: Song", triggers_gcd: false, cast_time: 0.0, cooldown: 30.0, ani_lock: 0.6, potency: 50, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: Some(ShredSpec { armor: 20.0, duration: 15.0 }), applies: None, movement: None, projectile: None, impact_vfx: None },
         );
         by_id.insert(
             AbilityId::Cleanse,
-            Ability { id: AbilityId::Cleanse, name: "Cleanse", triggers_gcd: false, cast_time: 0.0, cooldown: 12.0, ani_lock: 0.1 },
+            Ability { id: AbilityId::Cleanse, name: "Cleanse", triggers_gcd: false, cast_time: 0.0, cooldown: 12.0, ani_lock: 0.1, potency: 0, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: None, movement: None, projectile: None, impact_vfx: None },
         );
         by_id.insert(
             AbilityId::Burn,
-            Ability { id: AbilityId::Burn, name: "Burn", triggers_gcd: true, cast_time: 0.0, cooldown: 2.5, ani_lock: 0.6 },
+            Ability { id: AbilityId::Burn, name: "Burn", triggers_gcd: true, cast_time: 0.0, cooldown: 2.5, ani_lock: 0.6, potency: 0, dot: Some(DotSpec { potency: 20, duration: 12.0, tick_every: 1.0 }), cooldown_group: None, positional: Some(PositionalSpec { from: RelativePosition::Rear, bonus: 5 }), penetration: 0.0, shred: None, applies: None, movement: None, projectile: None, impact_vfx: None },
         );
         by_id.insert(
             AbilityId::Heal,
-            Ability { id: AbilityId::Heal, name: "Heal", triggers_gcd: true, cast_time: 2.0, cooldown: 2.5, ani_lock: 0.6 },
+            Ability { id: AbilityId::Heal, name: "Heal", triggers_gcd: true, cast_time: 2.0, cooldown: 2.5, ani_lock: 0.6, potency: 0, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: Some("regen"), movement: None, projectile: None, impact_vfx: None },
         );
         by_id.insert(
             AbilityId::Swiftcast,
-            Ability { id: AbilityId::Swiftcast, name: "Swiftcast", triggers_gcd: false, cast_time: 0.0, cooldown: 60.0, ani_lock: 0.6 },
+            Ability { id: AbilityId::Swiftcast, name: "Swiftcast", triggers_gcd: false, cast_time: 0.0, cooldown: 60.0, ani_lock: 0.6, potency: 0, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: None, movement: None, projectile: None, impact_vfx: None },
         );
         by_id.insert(
             AbilityId::Raging,
-            Ability { id: AbilityId::Raging, name: "Raging", triggers_gcd: false, cast_time: 0.0, cooldown: 90.0, ani_lock: 0.6 },
+            Ability { id: AbilityId::Raging, name: "Raging", triggers_gcd: false, cast_time: 0.0, cooldown: 90.0, ani_lock: 0.6, potency: 0, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: Some("raging"), movement: None, projectile: None, impact_vfx: None },
         );
         by_id.insert(
             AbilityId::Jump,
-            Ability { id: AbilityId::Jump, name: "Jump", triggers_gcd: false, cast_time: 0.0, cooldown: 30.0, ani_lock: 0.6 },
+            Ability { id: AbilityId::Jump, name: "Jump", triggers_gcd: false, cast_time: 0.0, cooldown: 30.0, ani_lock: 0.6, potency: 120, dot: None, cooldown_group: Some(CooldownGroup::Mobility), positional: None, penetration: 0.5, shred: None, applies: None, movement: Some(AbilityMovement::LeapToTarget), projectile: None, impact_vfx: Some("shockwave") },
+        );
+        by_id.insert(
+            AbilityId::Rampart,
+            Ability { id: AbilityId::Rampart, name: "Rampart", triggers_gcd: false, cast_time: 0.0, cooldown: 90.0, ani_lock: 0.6, potency: 0, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: None, movement: None, projectile: None, impact_vfx: None },
+        );
+        by_id.insert(
+            AbilityId::Aegis,
+            Ability { id: AbilityId::Aegis, name: "Aegis", triggers_gcd: false, cast_time: 0.0, cooldown: 30.0, ani_lock: 0.6, potency: 0, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: None, movement: None, projectile: None, impact_vfx: None },
+        );
+        by_id.insert(
+            AbilityId::Invuln,
+            Ability { id: AbilityId::Invuln, name: "Invuln", triggers_gcd: false, cast_time: 0.0, cooldown: 120.0, ani_lock: 0.6, potency: 0, dot: None, cooldown_group: None, positional: None, penetration: 0.0, shred: None, applies: None, movement: None, projectile: None, impact_vfx: None },
         );
         Self { by_id }
     }
@@ -150,25 +434,97 @@ pub struct CombatState {
     pub buffer: Option<(AbilityId, f32)>, // (ability, time_left)
     pub gcd_queue: Option<AbilityId>,     // queued next GCD
     pub ability_cds: HashMap<AbilityId, f32>,
+    pub group_cds: HashMap<CooldownGroup, (f32, f32)>, // (remaining, total)
     pub gcd_length: f32,
     pub buffer_window: f32,
     pub clipped: bool,
     pub muddled: Option<f32>, // time remaining
+    pub muddled_age: f32,     // seconds since muddled was applied, to tell which debuff is newest
+    pub muddled_variant: MuddledVariant,
+    pub muddled_intensity: f32, // drift multiplier for the Wobble variant
+    pub cleanse_pick: Option<CleansePick>, // picked on the status row as Cleanse was pressed
     pub hud_shake_remaining: f32,
     pub ani_lock_remaining: f32,
     pub gcd_queue_window: f32,
     pub swiftcast_remaining: Option<f32>, // seconds left to use; next cast instant
-    pub raging_remaining: Option<f32>,    // placeholder buff
+    pub mitigation_remaining: Option<f32>, // Rampart
+    pub shield: Option<Shield>,
+    pub invuln_remaining: Option<f32>,
+    pub locked_ability: Option<(AbilityId, f32)>, // its key does nothing until the time runs out
+    pub statuses: Vec<ActiveStatus>, // data-defined statuses on the player
+    pub position: Option<RelativePosition>, // None when there is no player or enemy to measure
+    pub view: Option<TargetView>,           // None when there is no player or target to look at
+    pub error: Option<(&'static str, f32)>, // (message, time left on screen)
+    pub positionals: PositionalTally,
+}
+
+/// How Muddled scrambles the hotbar, picked per timeline event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MuddledVariant {
+    /// Buttons drift around their slots, as far as the event's intensity says
+    #[default]
+    Wobble,
+    /// Buttons trade places within their row; the keys stay with their abilities
+    Shuffle,
+    /// Buttons stay put but the cooldown bars go dark
+    Blind,
+}
+
+/// How long Invuln lasts
+pub const INVULN_SECS: f32 = 10.0;
+
+/// Damage taken is reduced by this fraction while Rampart is up
+pub const RAMPART_REDUCTION: f32 = 0.2;
+
+/// Absorbs incoming damage until used up or expired
+#[derive(Debug, Clone, Copy)]
+pub struct Shield {
+    pub amount: i32,
+    pub remaining: f32,
+}
+
+impl Ability {
+    /// Recast after speed scaling; GCD recasts scale, oGCD cooldowns don't
+    pub fn recast(&self, stats: &PlayerStats) -> f32 {
+        if self.triggers_gcd { stats.scaled_time(self.cooldown) } else { self.cooldown }
+    }
+
+    /// GCDs that hit the target need it in front of the player and in sight
+    pub fn needs_target_in_view(&self) -> bool {
+        self.triggers_gcd && (self.potency > 0 || self.dot.is_some())
+    }
 }
 
 impl CombatState {
+    /// Remaining shared recast for the ability's cooldown group, 0 if none
+    fn group_cd(&self, ability: &Ability) -> f32 {
+        ability
+            .cooldown_group
+            .and_then(|g| self.group_cds.get(&g))
+            .map(|(remaining, _)| *remaining)
+            .unwrap_or(0.0)
+    }
+
+    /// Why a press that can't go off now would still be stuck once the input buffer runs out
+    fn blocked_past_buffer(&self, ability: &Ability) -> Option<ActionError> {
+        let own_cd = self.ability_cds.get(&ability.id).copied().unwrap_or(0.0).max(self.group_cd(ability));
+        let recast = if ability.triggers_gcd { own_cd.max(self.gcd_remaining) } else { own_cd };
+        let cast = self.cast.as_ref().map_or(0.0, |c| c.remaining);
+        [(recast, ActionError::NotReady), (cast, ActionError::Casting), (self.ani_lock_remaining, ActionError::AnimationLocked)]
+            .into_iter()
+            .filter(|(left, _)| *left > self.buffer_window.min(0.4))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, error)| error)
+    }
+
     fn can_use_now(&self, ability: &Ability) -> bool {
         let cd_ready = self
             .ability_cds
             .get(&ability.id)
             .copied()
             .unwrap_or(0.0)
-            .le(&0.0);
+            .le(&0.0)
+            && self.group_cd(ability) <= 0.0;
         let not_casting = self.cast.is_none();
         if ability.triggers_gcd {
             // Next GCD can start only when GCD ready and no animation lock
@@ -193,15 +549,28 @@ impl Default for CombatState {
             buffer: None,
             gcd_queue: None,
             ability_cds: HashMap::new(),
+            group_cds: HashMap::new(),
             gcd_length: 2.5,
             buffer_window: 0.6,
             clipped: false,
             muddled: None,
+            muddled_age: 0.0,
+            muddled_variant: MuddledVariant::default(),
+            muddled_intensity: 1.0,
+            cleanse_pick: None,
             hud_shake_remaining: 0.0,
             ani_lock_remaining: 0.0,
             gcd_queue_window: 0.6,
             swiftcast_remaining: None,
-            raging_remaining: None,
+            statuses: Vec::new(),
+            mitigation_remaining: None,
+            shield: None,
+            invuln_remaining: None,
+            locked_ability: None,
+            position: None,
+            view: None,
+            error: None,
+            positionals: PositionalTally::default(),
         }
     }
 }
@@ -235,15 +604,22 @@ struct CooldownBar {
 }
 
 #[derive(Component)]
-struct CastBarRoot;
-
-#[derive(Component)]
-struct CastBarFill;
+struct ErrorText;
 
 #[derive(Component)]
 struct StatusRow;
 
-fn spawn_hud(mut commands: Commands) {
+fn spawn_hud(
+    mut commands: Commands,
+    book: Res<AbilityBook>,
+    sets: Res<HotbarSets>,
+    binds: Res<Keybinds>,
+    settings: Option<Res<Settings>>,
+) {
+    let settings = settings.map(|s| s.clone()).unwrap_or_default();
+    let slots = *sets.active_slots();
+    let slot_name = |i: usize| book.by_id.get(&slots[i]).map(|a| a.name).unwrap_or("");
+    let slot_gcd = |i: usize| book.by_id.get(&slots[i]).map(|a| a.triggers_gcd).unwrap_or(false);
     commands
         .spawn((
             Node {
@@ -253,6 +629,7 @@ fn spawn_hud(mut commands: Commands) {
                 ..default()
             },
             HudRoot,
+            StateScoped(GameState::Playing),
         ))
         .with_children(|root| {
             // Hotbar row 1 (1..5)
@@ -264,24 +641,17 @@ fn spawn_hud(mut commands: Commands) {
                         height: Val::Px(80.0),
                         position_type: PositionType::Absolute,
                         bottom: Val::Px(10.0),
-                        left: Val::Px(400.0), // approximate center baseline
                         flex_direction: FlexDirection::Row,
                         align_items: AlignItems::Center,
                         justify_content: JustifyContent::Center,
-                        column_gap: Val::Px(8.0),
+                        column_gap: Val::Px(HOTBAR_GAP),
                         ..default()
                     },
                     HotbarRoot { row: 0 },
                 ))
                 .with_children(|hotbar| {
-                    let ids = [
-                        AbilityId::Strike,
-                        AbilityId::Fireball,
-                        AbilityId::WeaveDash,
-                        AbilityId::WeaveSong,
-                        AbilityId::Cleanse,
-                    ];
-                    for (i, id) in ids.into_iter().enumerate() {
+                    for i in 0..5 {
+                        let id = slots[i];
                         hotbar
                             .spawn((
                                 Button,
@@ -308,8 +678,11 @@ fn spawn_hud(mut commands: Commands) {
                                             bottom: Val::Px(0.0),
                                             ..default()
                                         },
+                                        // Lit up by the opener trainer on the next expected button
+                                        Outline::new(Val::Px(3.0), Val::Px(1.0), Color::NONE),
                                         ButtonContent,
                                         AbilityButton { id, index: i },
+                                        Tappable,
                                     ))
                                     .with_children(|content| {
                                         content.spawn((
@@ -323,13 +696,20 @@ fn spawn_hud(mut commands: Commands) {
                                                 ..default()
                                             },
                                             BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.75)),
-                                            CooldownBar { id, triggers_gcd: matches!(id, AbilityId::Strike | AbilityId::Fireball) },
+                                            CooldownBar { id, triggers_gcd: slot_gcd(i) },
                                         ));
                                         content.spawn((
-                                            Text::new(format!("{}", i + 1)),
+                                            Text::new(binds.label(i)),
                                             TextFont { font_size: 18.0, ..default() },
                                             TextColor(Color::WHITE),
                                         ));
+                                        content.spawn((
+                                            Text::new(slot_name(i)),
+                                            TextFont { font_size: 10.0, ..default() },
+                                            TextColor(Color::linear_rgb(0.8, 0.8, 0.8)),
+                                            Node { position_type: PositionType::Absolute, bottom: Val::Px(2.0), left: Val::Px(2.0), ..default() },
+                                            SlotAbilityLabel { index: i },
+                                        ));
                                     });
                             });
                     }
@@ -343,25 +723,17 @@ fn spawn_hud(mut commands: Commands) {
                         height: Val::Px(80.0),
                         position_type: PositionType::Absolute,
                         bottom: Val::Px(90.0),
-                        left: Val::Px(400.0),
                         flex_direction: FlexDirection::Row,
                         align_items: AlignItems::Center,
                         justify_content: JustifyContent::Center,
-                        column_gap: Val::Px(8.0),
+                        column_gap: Val::Px(HOTBAR_GAP),
                         ..default()
                     },
                     HotbarRoot { row: 1 },
                 ))
                 .with_children(|hotbar| {
-                    let ids = [
-                        AbilityId::Burn,      // 6
-                        AbilityId::Heal,      // 7
-                        AbilityId::Swiftcast, // 8
-                        AbilityId::Raging,    // 9
-                        AbilityId::Jump,      // 0
-                    ];
-                    let labels = ["6", "7", "8", "9", "0"];
-                    for (i, (id, label)) in ids.into_iter().zip(labels).enumerate() {
+                    for i in 5..10 {
+                        let id = slots[i];
                         hotbar
                             .spawn((
                                 Button,
@@ -388,8 +760,11 @@ fn spawn_hud(mut commands: Commands) {
                                             bottom: Val::Px(0.0),
                                             ..default()
                                         },
+                                        // Lit up by the opener trainer on the next expected button
+                                        Outline::new(Val::Px(3.0), Val::Px(1.0), Color::NONE),
                                         ButtonContent,
-                                        AbilityButton { id, index: i + 5 },
+                                        AbilityButton { id, index: i },
+                                        Tappable,
                                     ))
                                     .with_children(|content| {
                                         content.spawn((
@@ -403,41 +778,36 @@ fn spawn_hud(mut commands: Commands) {
                                                 ..default()
                                             },
                                             BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.75)),
-                                            CooldownBar { id, triggers_gcd: matches!(id, AbilityId::Burn | AbilityId::Heal) },
+                                            CooldownBar { id, triggers_gcd: slot_gcd(i) },
                                         ));
                                         content.spawn((
-                                            Text::new(label.to_string()),
+                                            Text::new(binds.label(i)),
                                             TextFont { font_size: 18.0, ..default() },
                                             TextColor(Color::WHITE),
                                         ));
+                                        content.spawn((
+                                            Text::new(slot_name(i)),
+                                            TextFont { font_size: 10.0, ..default() },
+                                            TextColor(Color::linear_rgb(0.8, 0.8, 0.8)),
+                                            Node { position_type: PositionType::Absolute, bottom: Val::Px(2.0), left: Val::Px(2.0), ..default() },
+                                            SlotAbilityLabel { index: i },
+                                        ));
                                     });
                             });
                     }
                 });
 
-            // Cast bar
-            root
-                .spawn((
-                    Node {
-                        width: Val::Px(400.0),
-                        height: Val::Px(18.0),
-                        position_type: PositionType::Absolute,
-                        bottom: Val::Px(100.0),
-                        left: Val::Percent(50.0),
-                        justify_content: JustifyContent::FlexStart,
-                        align_items: AlignItems::Stretch,
-                        ..default()
-                    },
-                    BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05)),
-                    CastBarRoot,
-                ))
-                .with_children(|bar| {
-                    bar.spawn((
-                        Node { width: Val::Percent(0.0), height: Val::Percent(100.0), ..default() },
-                        BackgroundColor(Color::linear_rgb(0.2, 0.6, 1.0)),
-                        CastBarFill,
-                    ));
-                });
+            cast_bar::spawn_cast_bar(root, &settings);
+
+            // Why the last press was rejected, just above the cast bar
+            let error_bottom = settings.cast_bar_bottom + settings.cast_bar_height + 6.0;
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(1.0, 0.35, 0.3)),
+                Node { position_type: PositionType::Absolute, bottom: Val::Px(error_bottom), left: Val::Percent(settings.cast_bar_left), ..default() },
+                ErrorText,
+            ));
 
             // Status row
             root.spawn((
@@ -456,51 +826,134 @@ fn spawn_hud(mut commands: Commands) {
         });
 }
 
-fn reset_combat(mut combat: ResMut<CombatState>) {
+fn reset_combat(settings: Option<Res<Settings>>, mut combat: ResMut<CombatState>) {
     *combat = CombatState::default();
+    if let Some(settings) = settings {
+        combat.gcd_queue_window = settings.gcd_queue_window;
+        combat.buffer_window = settings.buffer_window;
+    }
 }
 
 // ==== Input and execution ====
 
-fn handle_ability_input(
+/// Keys address slots; the ability comes from whichever hotbar set is active.
+/// The button reacts immediately, the sim only sees the press once it "arrives".
+/// Cleanse goes for the debuff under the cursor, picked right away.
+fn read_ability_keys(
     keys: Res<ButtonInput<KeyCode>>,
+    binds: Res<Keybinds>,
+    sets: Res<HotbarSets>,
+    combat: Res<CombatState>,
+    q_chips: Query<(&Interaction, &StatusChip)>,
+    mut actions: ResMut<Actions>,
+    mut latency: ResMut<InputLatency>,
+    mut flash_writer: EventWriter<ButtonFlashEvent>,
+    mut error_writer: EventWriter<ActionErrorEvent>,
+) {
+    for (kc, id) in binds.slots.into_iter().zip(sets.active_slots().iter().copied()) {
+        if !keys.just_pressed(kc) {
+            continue;
+        }
+        if combat.locked_ability.is_some_and(|(locked, _)| locked == id) {
+            error_writer.write(ActionErrorEvent { ability: id, error: ActionError::KeyLocked });
+            continue;
+        }
+        if id == AbilityId::Cleanse {
+            let hovered = q_chips.iter().find(|(interaction, _)| **interaction == Interaction::Hovered);
+            let pick = hovered.and_then(|(_, chip)| chip.cleanse.as_ref());
+            let slot = pick.and_then(|pick| combat.cleansable().iter().position(|p| p == pick));
+            actions.queue(SimAction::CleanseTarget(slot));
+        }
+        flash_writer.write(ButtonFlashEvent { id });
+        latency.send(id);
+    }
+}
+
+/// Tapped hotbar buttons press their slot, the same as its key
+fn tap_hotbar_buttons(
+    actions: Res<Actions>,
+    combat: Res<CombatState>,
+    q_buttons: Query<&AbilityButton>,
+    mut latency: ResMut<InputLatency>,
+    mut flash_writer: EventWriter<ButtonFlashEvent>,
+    mut error_writer: EventWriter<ActionErrorEvent>,
+) {
+    for button in actions.taps.iter().filter_map(|e| q_buttons.get(*e).ok()) {
+        if combat.locked_ability.is_some_and(|(locked, _)| locked == button.id) {
+            error_writer.write(ActionErrorEvent { ability: button.id, error: ActionError::KeyLocked });
+            continue;
+        }
+        flash_writer.write(ButtonFlashEvent { id: button.id });
+        latency.send(button.id);
+    }
+}
+
+/// Presses that reached the sim this tick go off, queue or buffer
+fn apply_presses(
+    input: Res<SimInput>,
     book: Res<AbilityBook>,
+    stats: Res<PlayerStats>,
     mut combat: ResMut<CombatState>,
     mut dmg_writer: EventWriter<DamageEvent>,
     mut dot_writer: EventWriter<ApplyDotEvent>,
-    mut flash_writer: EventWriter<ButtonFlashEvent>,
+    mut used_writer: EventWriter<AbilityUsedEvent>,
+    mut rng: ResMut<GameRng>,
+    mut error_writer: EventWriter<ActionErrorEvent>,
 ) {
-    let mapping: &[(KeyCode, AbilityId)] = &[
-        (KeyCode::Digit1, AbilityId::Strike),
-        (KeyCode::Digit2, AbilityId::Fireball),
-        (KeyCode::Digit3, AbilityId::WeaveDash),
-        (KeyCode::Digit4, AbilityId::WeaveSong),
-        (KeyCode::Digit5, AbilityId::Cleanse),
-        (KeyCode::Digit6, AbilityId::Burn),
-        (KeyCode::Digit7, AbilityId::Heal),
-        (KeyCode::Digit8, AbilityId::Swiftcast),
-        (KeyCode::Digit9, AbilityId::Raging),
-        (KeyCode::Digit0, AbilityId::Jump),
-    ];
-
-    for (kc, id) in mapping.iter().copied() {
-        if keys.just_pressed(kc) {
-            if let Some(ability) = book.by_id.get(&id) {
-                flash_writer.write(ButtonFlashEvent { id });
-                try_use_or_buffer(ability, &mut combat, &mut dmg_writer, &mut dot_writer);
-            }
+    for action in &input.actions {
+        let SimAction::Press(id) = action else { continue; };
+        if let Some(ability) = book.by_id.get(id) {
+            try_use_or_buffer(
+                ability,
+                &stats,
+                &mut combat,
+                &mut dmg_writer,
+                &mut dot_writer,
+                &mut used_writer,
+                &mut error_writer,
+                &mut rng,
+            );
         }
     }
 }
 
+fn cycle_speed_tier(keys: Res<ButtonInput<KeyCode>>, mut actions: ResMut<Actions>) {
+    if keys.just_pressed(KeyCode::F6) {
+        actions.queue(SimAction::CycleSpeedTier);
+    }
+}
+
+fn apply_speed_tier(input: Res<SimInput>, mut stats: ResMut<PlayerStats>) {
+    if input.has(SimAction::CycleSpeedTier) {
+        stats.cycle_speed_tier();
+        info!("Speed {} -> GCD {:.2}s", stats.speed, stats.scaled_time(2.5));
+    }
+}
+
+/// Console: `grant swiftcast` makes an ability ready now, along with its cooldown group
+fn grant_ability(In(args): In<Vec<String>>, book: Res<AbilityBook>, mut combat: ResMut<CombatState>) -> CommandResult {
+    let name = args.join(" ");
+    let id = macros::find_ability(&book, &name).ok_or_else(|| format!("unknown ability {name:?}"))?;
+    let ability = &book.by_id[&id];
+    combat.ability_cds.remove(&id);
+    if let Some(group) = ability.cooldown_group {
+        combat.group_cds.remove(&group);
+    }
+    Ok(format!("{} is ready", ability.name))
+}
+
 fn try_use_or_buffer(
     ability: &Ability,
+    stats: &PlayerStats,
     combat: &mut CombatState,
     dmg_writer: &mut EventWriter<DamageEvent>,
     dot_writer: &mut EventWriter<ApplyDotEvent>,
+    used_writer: &mut EventWriter<AbilityUsedEvent>,
+    error_writer: &mut EventWriter<ActionErrorEvent>,
+    rng: &mut GameRng,
 ) {
     if combat.can_use_now(ability) {
-        start_cast_or_instant(ability, combat, dmg_writer, dot_writer);
+        start_cast_or_instant(ability, stats, combat, dmg_writer, dot_writer, used_writer, error_writer, rng);
         return;
     }
     if ability.triggers_gcd {
@@ -516,17 +969,39 @@ fn try_use_or_buffer(
             return;
         }
     }
-    // Fallback short buffer
+    // Fallback short buffer, unless it would run out before the press could go off
+    if let Some(error) = combat.blocked_past_buffer(ability) {
+        error_writer.write(ActionErrorEvent { ability: ability.id, error });
+        return;
+    }
     combat.buffer = Some((ability.id, combat.buffer_window.min(0.4)));
 }
 
 fn start_cast_or_instant(
     ability: &Ability,
+    stats: &PlayerStats,
     combat: &mut CombatState,
     dmg_writer: &mut EventWriter<DamageEvent>,
     dot_writer: &mut EventWriter<ApplyDotEvent>,
+    used_writer: &mut EventWriter<AbilityUsedEvent>,
+    error_writer: &mut EventWriter<ActionErrorEvent>,
+    rng: &mut GameRng,
 ) {
-    let mut cast_time = ability.cast_time;
+    if ability.cast_time > 0.0 && combat.silenced() {
+        error_writer.write(ActionErrorEvent { ability: ability.id, error: ActionError::Silenced });
+        return;
+    }
+    if !ability.triggers_gcd && ability.id != AbilityId::Cleanse && combat.pacified() {
+        error_writer.write(ActionErrorEvent { ability: ability.id, error: ActionError::Pacified });
+        return;
+    }
+    if ability.needs_target_in_view() {
+        if let Some(error) = combat.view.and_then(TargetView::error) {
+            error_writer.write(ActionErrorEvent { ability: ability.id, error });
+            return;
+        }
+    }
+    let mut cast_time = stats.scaled_time(ability.cast_time);
     // Swiftcast makes next cast instant
     if cast_time > 0.0 {
         if let Some(rem) = combat.swiftcast_remaining {
@@ -539,30 +1014,34 @@ fn start_cast_or_instant(
     if cast_time > 0.0 {
         combat.cast = Some(CastState { ability: ability.id, remaining: cast_time, total: cast_time });
     } else {
-        resolve_ability(ability, combat, dmg_writer, dot_writer);
+        resolve_ability(ability, stats, combat, dmg_writer, dot_writer, used_writer, rng);
     }
 }
 
 fn resolve_ability(
     ability: &Ability,
+    stats: &PlayerStats,
     combat: &mut CombatState,
     dmg_writer: &mut EventWriter<DamageEvent>,
     dot_writer: &mut EventWriter<ApplyDotEvent>,
+    used_writer: &mut EventWriter<AbilityUsedEvent>,
+    rng: &mut GameRng,
 ) {
+    used_writer.write(AbilityUsedEvent { id: ability.id });
     // Apply cooldown
-    combat.ability_cds.insert(ability.id, ability.cooldown);
+    combat.ability_cds.insert(ability.id, ability.recast(stats));
+    if let Some(group) = ability.cooldown_group {
+        let recast = ability.recast(stats);
+        combat.group_cds.insert(group, (recast, recast));
+    }
 
     if ability.triggers_gcd {
         // Start/refresh GCD
+        combat.gcd_length = ability.recast(stats);
         combat.gcd_remaining = combat.gcd_length;
         combat.weaves_in_current_gcd = 0;
         combat.clipped = false;
         combat.ani_lock_remaining = ability.ani_lock;
-        // Instant damage for GCD if any
-        let mult = if combat.raging_remaining.unwrap_or(0.0) > 0.0 { 1.2 } else { 1.0 };
-        let base = match ability.id { AbilityId::Strike => 100, AbilityId::Fireball => 180, AbilityId::Burn => 0, AbilityId::Heal => 0, _ => 0 };
-        if base > 0 { dmg_writer.write(DamageEvent { amount: ((base as f32) * mult) as i32 }); }
-        if ability.id == AbilityId::Burn { dot_writer.write(ApplyDotEvent { dps: 20, duration: 12.0, tick_every: 1.0 }); }
     } else {
         // oGCD weave window logic
         combat.weaves_in_current_gcd = combat.weaves_in_current_gcd.saturating_add(1);
@@ -571,20 +1050,104 @@ fn resolve_ability(
 
         // Special abilities
         match ability.id {
-            AbilityId::Cleanse => { combat.muddled = None; }
+            AbilityId::Cleanse => { combat.cleanse(); }
             AbilityId::Swiftcast => { combat.swiftcast_remaining = Some(10.0); }
-            AbilityId::Raging => { combat.raging_remaining = Some(15.0); }
-            AbilityId::WeaveDash => { dmg_writer.write(DamageEvent { amount: 60 }); }
-            AbilityId::WeaveSong => { dmg_writer.write(DamageEvent { amount: 50 }); }
-            AbilityId::Jump => { dmg_writer.write(DamageEvent { amount: 120 }); }
+            AbilityId::Rampart => { combat.mitigation_remaining = Some(20.0); }
+            AbilityId::Aegis => { combat.shield = Some(Shield { amount: 300, remaining: 15.0 }); }
+            AbilityId::Invuln => { combat.invuln_remaining = Some(INVULN_SECS); }
             _ => {}
         }
     }
+
+    // Positionals are judged where the player stands when the ability resolves
+    let positional = match (ability.positional, combat.position) {
+        (Some(spec), Some(position)) => Some((spec, position == spec.from)),
+        _ => None,
+    };
+    let bonus = match positional {
+        Some((spec, true)) => spec.bonus,
+        _ => 0,
+    };
+    match positional {
+        Some((_, true)) => combat.positionals.hits += 1,
+        Some((_, false)) => combat.positionals.misses += 1,
+        None => {}
+    }
+    let positional = positional.map(|(_, hit)| hit);
+
+    // Damage from potency, with buffs applied
+    let mult = combat.damage_dealt_mult();
+    if ability.potency > 0 {
+        let (amount, crit) = stats.roll_damage(ability.potency + bonus, mult, rng.rng());
+        dmg_writer.write(DamageEvent {
+            amount,
+            crit,
+            positional,
+            penetration: ability.penetration,
+            shred: ability.shred,
+            target: None,
+            source: Some(ability.id),
+            tick: false,
+        });
+    }
+    // DoTs snapshot the damage multiplier at application time
+    if let Some(dot) = ability.dot {
+        let tick_bonus = if ability.potency > 0 { 0 } else { bonus };
+        let dps = (stats.base_damage(dot.potency + tick_bonus) * mult) as i32;
+        dot_writer.write(ApplyDotEvent { source: ability.id, dps, duration: dot.duration, tick_every: dot.tick_every });
+    }
 }
 
-fn tick_combat_timers(time: Res<Time>, mut combat: ResMut<CombatState>) {
-    let dt = time.delta_secs();
+/// Timers as they were before the latest fixed tick, for HUD bars to move smoothly between ticks
+#[derive(Resource, Debug, Default)]
+struct PrevTick {
+    gcd_remaining: f32,
+    ani_lock_remaining: f32,
+    cast_remaining: Option<f32>,
+    ability_cds: HashMap<AbilityId, f32>,
+}
+
+impl PrevTick {
+    fn gcd(&self, combat: &CombatState, alpha: f32) -> f32 {
+        blend(self.gcd_remaining, combat.gcd_remaining, alpha)
+    }
+
+    fn ani_lock(&self, combat: &CombatState, alpha: f32) -> f32 {
+        blend(self.ani_lock_remaining, combat.ani_lock_remaining, alpha)
+    }
+
+    fn cast(&self, combat: &CombatState, alpha: f32) -> Option<f32> {
+        let now = combat.cast.as_ref()?.remaining;
+        Some(blend(self.cast_remaining.unwrap_or(now), now, alpha))
+    }
+
+    fn cd(&self, id: AbilityId, combat: &CombatState, alpha: f32) -> f32 {
+        let now = combat.ability_cds.get(&id).copied().unwrap_or(0.0);
+        blend(self.ability_cds.get(&id).copied().unwrap_or(now), now, alpha)
+    }
+}
+
+/// A countdown `alpha` of the way from its previous tick value to its current one. One that
+/// restarted since shows its new value straight away
+fn blend(prev: f32, now: f32, alpha: f32) -> f32 {
+    if prev >= now { prev + (now - prev) * alpha } else { now }
+}
+
+fn snapshot_timers(combat: Res<CombatState>, mut prev: ResMut<PrevTick>) {
+    prev.gcd_remaining = combat.gcd_remaining;
+    prev.ani_lock_remaining = combat.ani_lock_remaining;
+    prev.cast_remaining = combat.cast.as_ref().map(|c| c.remaining);
+    prev.ability_cds.clone_from(&combat.ability_cds);
+}
+
+fn tick_combat_timers(time: SimTime, mut combat: ResMut<CombatState>) {
+    let dt = time.scaled_delta();
+    let gcd_was_running = combat.gcd_remaining > 0.0;
     combat.gcd_remaining = (combat.gcd_remaining - dt).max(0.0);
+    // Animation lock outlasting the GCD means a weave delayed the next GCD
+    if gcd_was_running && combat.gcd_remaining <= 0.0 && combat.ani_lock_remaining > dt {
+        combat.clipped = true;
+    }
     if let Some(cast) = &mut combat.cast {
         cast.remaining -= dt;
         if cast.remaining < 0.0 {
@@ -594,27 +1157,80 @@ fn tick_combat_timers(time: Res<Time>, mut combat: ResMut<CombatState>) {
     for v in combat.ability_cds.values_mut() {
         *v = (*v - dt).max(0.0);
     }
+    for (remaining, _) in combat.group_cds.values_mut() {
+        *remaining = (*remaining - dt).max(0.0);
+    }
     if let Some((id, left)) = combat.buffer.take() {
         let new_left = left - dt;
         if new_left > 0.0 { combat.buffer = Some((id, new_left)); }
     }
     if let Some(t) = combat.muddled.as_mut() { *t = (*t - dt).max(0.0); if *t == 0.0 { combat.muddled = None; } }
+    if combat.muddled.is_some() { combat.muddled_age += dt; }
     if combat.hud_shake_remaining > 0.0 { combat.hud_shake_remaining = (combat.hud_shake_remaining - dt).max(0.0); }
     if combat.ani_lock_remaining > 0.0 { combat.ani_lock_remaining = (combat.ani_lock_remaining - dt).max(0.0); }
     if let Some(t) = combat.swiftcast_remaining.as_mut() { *t = (*t - dt).max(0.0); if *t == 0.0 { combat.swiftcast_remaining = None; } }
-    if let Some(t) = combat.raging_remaining.as_mut() { *t = (*t - dt).max(0.0); if *t == 0.0 { combat.raging_remaining = None; } }
+    if let Some(t) = combat.mitigation_remaining.as_mut() { *t = (*t - dt).max(0.0); if *t == 0.0 { combat.mitigation_remaining = None; } }
+    if let Some(s) = combat.shield.as_mut() { s.remaining = (s.remaining - dt).max(0.0); if s.remaining == 0.0 { combat.shield = None; } }
+    if let Some(t) = combat.invuln_remaining.as_mut() { *t = (*t - dt).max(0.0); if *t == 0.0 { combat.invuln_remaining = None; } }
+    if let Some((_, t)) = combat.locked_ability.as_mut() { *t = (*t - dt).max(0.0); if *t == 0.0 { combat.locked_ability = None; } }
+    if let Some((_, t)) = combat.error.as_mut() { *t = (*t - dt).max(0.0); if *t == 0.0 { combat.error = None; } }
+}
+
+/// Incoming damage: invuln negates it, otherwise mitigation applies first, then the shield absorbs what it can
+fn apply_player_damage(
+    mut evr: EventReader<PlayerDamageEvent>,
+    mut combat: ResMut<CombatState>,
+    mut q_player: Query<&mut Health, With<Player>>,
+) {
+    let Ok(mut hp) = q_player.single_mut() else { return; };
+    for PlayerDamageEvent { amount } in evr.read() {
+        if combat.invuln_remaining.is_some() {
+            continue;
+        }
+        let mut amount = (*amount as f32 * combat.damage_taken_mult()) as i32;
+        if combat.mitigation_remaining.is_some() {
+            amount = (amount as f32 * (1.0 - RAMPART_REDUCTION)) as i32;
+        }
+        if let Some(shield) = combat.shield.as_mut() {
+            let absorbed = amount.min(shield.amount);
+            shield.amount -= absorbed;
+            amount -= absorbed;
+            if shield.amount <= 0 {
+                combat.shield = None;
+            }
+        }
+        hp.current = (hp.current - amount).max(0);
+    }
+}
+
+/// Moving cancels a cast, except in its last [`SLIDECAST_SECS`]. Nothing is spent: the GCD and cooldown only start
+/// when a cast completes, so the ability can be pressed again at once
+fn cancel_cast_on_move(
+    input: Res<SimInput>,
+    mut combat: ResMut<CombatState>,
+    mut error_writer: EventWriter<ActionErrorEvent>,
+) {
+    if input.movement.is_none() {
+        return;
+    }
+    let Some(cast) = combat.cast.as_ref().filter(|cast| cast.remaining > SLIDECAST_SECS) else { return; };
+    error_writer.write(ActionErrorEvent { ability: cast.ability, error: ActionError::MovedWhileCasting });
+    combat.cast = None;
 }
 
 fn process_cast_completion(
     book: Res<AbilityBook>,
+    stats: Res<PlayerStats>,
     mut combat: ResMut<CombatState>,
     mut dmg_writer: EventWriter<DamageEvent>,
     mut dot_writer: EventWriter<ApplyDotEvent>,
+    mut used_writer: EventWriter<AbilityUsedEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
     if let Some(cast) = &combat.cast {
         if cast.remaining <= 0.0 {
             if let Some(ability) = book.by_id.get(&cast.ability) {
-                resolve_ability(ability, &mut combat, &mut dmg_writer, &mut dot_writer);
+                resolve_ability(ability, &stats, &mut combat, &mut dmg_writer, &mut dot_writer, &mut used_writer, &mut rng);
             }
             combat.cast = None;
         }
@@ -623,16 +1239,20 @@ fn process_cast_completion(
 
 fn process_buffered_ability(
     book: Res<AbilityBook>,
+    stats: Res<PlayerStats>,
     mut combat: ResMut<CombatState>,
     mut dmg_writer: EventWriter<DamageEvent>,
     mut dot_writer: EventWriter<ApplyDotEvent>,
+    mut used_writer: EventWriter<AbilityUsedEvent>,
+    mut error_writer: EventWriter<ActionErrorEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
     if combat.cast.is_some() { return; }
     if let Some((id, _)) = combat.buffer {
         if let Some(ability) = book.by_id.get(&id) {
             if combat.can_use_now(ability) {
                 combat.buffer = None;
-                start_cast_or_instant(ability, &mut combat, &mut dmg_writer, &mut dot_writer);
+                start_cast_or_instant(ability, &stats, &mut combat, &mut dmg_writer, &mut dot_writer, &mut used_writer, &mut error_writer, &mut rng);
             }
         }
     }
@@ -640,16 +1260,20 @@ fn process_buffered_ability(
 
 fn process_gcd_queue(
     book: Res<AbilityBook>,
+    stats: Res<PlayerStats>,
     mut combat: ResMut<CombatState>,
     mut dmg_writer: EventWriter<DamageEvent>,
     mut dot_writer: EventWriter<ApplyDotEvent>,
+    mut used_writer: EventWriter<AbilityUsedEvent>,
+    mut error_writer: EventWriter<ActionErrorEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
     if combat.cast.is_some() { return; }
     if let Some(id) = combat.gcd_queue {
         if let Some(ability) = book.by_id.get(&id) {
             if ability.triggers_gcd && combat.gcd_remaining <= 0.0 && combat.ani_lock_remaining <= 0.0 {
                 combat.gcd_queue = None;
-                start_cast_or_instant(ability, &mut combat, &mut dmg_writer, &mut dot_writer);
+                start_cast_or_instant(ability, &stats, &mut combat, &mut dmg_writer, &mut dot_writer, &mut used_writer, &mut error_writer, &mut rng);
             }
         }
     }
@@ -657,19 +1281,49 @@ fn process_gcd_queue(
 
 // ==== HUD updates ====
 
+/// Whether a cooldown, the GCD or a muddle gives the cooldown bars something to draw, or did
+/// last time so they still need emptying. The timers are written every tick whether they move
+/// or not, so `CombatState` always reads as changed
+fn cooldowns_running(combat: Res<CombatState>, prev: Res<PrevTick>, mut was: Local<bool>) -> bool {
+    let running = |cds: &HashMap<AbilityId, f32>| cds.values().any(|cd| *cd > 0.0);
+    let now = combat.gcd_remaining > 0.0
+        || prev.gcd_remaining > 0.0
+        || combat.muddled.is_some()
+        || running(&combat.ability_cds)
+        || running(&prev.ability_cds)
+        || combat.group_cds.values().any(|(cd, _)| *cd > 0.0);
+    std::mem::replace(&mut *was, now) || now
+}
+
 fn update_cooldown_bars(
+    time: SimTime,
     book: Res<AbilityBook>,
+    stats: Res<PlayerStats>,
     combat: Res<CombatState>,
+    prev: Res<PrevTick>,
     mut q: Query<(&CooldownBar, &mut Node, &mut BackgroundColor)>,
 ) {
+    let alpha = time.tick_alpha();
+    let blind = combat.muddled.is_some() && combat.muddled_variant == MuddledVariant::Blind;
     for (bar, mut node, mut color) in &mut q {
-        let cd = combat.ability_cds.get(&bar.id).copied().unwrap_or(0.0);
-        let total = book.by_id.get(&bar.id).map(|a| a.cooldown).unwrap_or(1.0);
+        if blind {
+            node.height = Val::Px(0.0);
+            continue;
+        }
+        let cd = prev.cd(bar.id, &combat, alpha);
+        let ability = book.by_id.get(&bar.id);
+        let total = ability.map(|a| a.recast(&stats)).unwrap_or(1.0);
         let mut frac_cd = if total > 0.0 { (cd / total).clamp(0.0, 1.0) } else { 0.0 };
+        // A shared group timer started by another ability shows against that ability's recast
+        if let Some((group_cd, group_total)) = ability.and_then(|a| a.cooldown_group).and_then(|g| combat.group_cds.get(&g)) {
+            if *group_total > 0.0 {
+                frac_cd = frac_cd.max((group_cd / group_total).clamp(0.0, 1.0));
+            }
+        }
         let mut frac_gcd = 0.0;
         if bar.triggers_gcd {
             if combat.gcd_length > 0.0 {
-                frac_gcd = (combat.gcd_remaining / combat.gcd_length).clamp(0.0, 1.0);
+                frac_gcd = (prev.gcd(&combat, alpha) / combat.gcd_length).clamp(0.0, 1.0);
             }
         }
         let frac = frac_cd.max(frac_gcd);
@@ -681,145 +1335,319 @@ fn update_cooldown_bars(
     }
 }
 
-fn update_cast_bar(
-    combat: Res<CombatState>,
-    mut q_fill: Query<&mut Node, With<CastBarFill>>,
-) {
-    if let Ok(mut node) = q_fill.single_mut() {
-        if let Some(cast) = &combat.cast {
-            let p = if cast.total > 0.0 { 1.0 - (cast.remaining / cast.total) } else { 1.0 };
-            node.width = Val::Percent((p * 100.0).clamp(0.0, 100.0));
-        } else {
-            node.width = Val::Percent(0.0);
+/// Fades out over the last half second
+fn update_error_text(combat: Res<CombatState>, mut q: Query<(&mut Text, &mut TextColor), With<ErrorText>>) {
+    let Ok((mut text, mut color)) = q.single_mut() else { return; };
+    match combat.error {
+        Some((message, remaining)) => {
+            if text.0 != message {
+                text.0 = message.to_string();
+            }
+            color.0 = color.0.with_alpha((remaining / 0.5).min(1.0));
+        }
+        None => {
+            if !text.0.is_empty() {
+                text.0.clear();
+            }
         }
     }
 }
 
+/// One entry on the status row, kept from frame to frame and updated in place. `key` says
+/// what it stands for (a status id, "gcd", ...), `text` is its text child, `cleanse` the
+/// debuff it is when Cleanse can take it off
+#[derive(Component)]
+struct StatusChip {
+    key: String,
+    text: Entity,
+    cleanse: Option<CleansePick>,
+}
+
+/// What a chip should show this frame
+struct ChipSpec {
+    key: String,
+    icon: Option<String>,
+    text: String,
+    color: Color,
+    cleanse: Option<CleansePick>,
+}
+
+impl ChipSpec {
+    fn new(key: impl Into<String>, text: impl Into<String>, color: Color) -> Self {
+        Self { key: key.into(), icon: None, text: text.into(), color, cleanse: None }
+    }
+}
+
+/// Seconds left, with a tenth once it's nearly over
+fn countdown(secs: f32) -> String {
+    if secs < 3.0 { format!("{secs:.1}") } else { format!("{:.0}", secs.ceil()) }
+}
+
+fn status_chips(stats: &PlayerStats, latency: &InputLatency, time_scale: &SimTimeScale, combat: &CombatState) -> Vec<ChipSpec> {
+    let grey = Color::linear_rgb(0.7, 0.7, 0.7);
+    let mut chips = vec![ChipSpec::new("gcd", format!("GCD {:.2}", stats.scaled_time(2.5)), grey)];
+    if latency.enabled {
+        chips.push(ChipSpec::new("ping", format!("Ping {:.0}ms", latency.current_rtt_ms()), grey));
+    }
+    if time_scale.effective() != 1.0 {
+        chips.push(ChipSpec::new("time", format!("Time x{}", time_scale.effective()), Color::linear_rgb(0.5, 0.9, 1.0)));
+    }
+    if let Some(left) = combat.muddled {
+        chips.push(ChipSpec {
+            cleanse: Some(CleansePick::Muddled),
+            ..ChipSpec::new("muddled", format!("Muddled {}", countdown(left)), Color::linear_rgb(1.0, 0.3, 0.2))
+        });
+    }
+    if combat.hud_shake_remaining > 0.0 {
+        chips.push(ChipSpec::new("shake", "Screen Shaking", Color::linear_rgb(0.95, 0.9, 0.2)));
+    }
+    if let Some(left) = combat.swiftcast_remaining.filter(|t| *t > 0.0) {
+        chips.push(ChipSpec::new("swiftcast", format!("Swiftcast {}", countdown(left)), Color::linear_rgb(0.5, 0.9, 1.0)));
+    }
+    for status in &combat.statuses {
+        let (red, green, blue) = status.def.color.unwrap_or((1.0, 0.5, 0.2));
+        let stacks = if status.stacks > 1 { format!(" x{}", status.stacks) } else { String::new() };
+        chips.push(ChipSpec {
+            key: format!("status:{}", status.def.id),
+            icon: status.def.icon.clone(),
+            text: format!("{}{stacks} {}", status.def.name, countdown(status.remaining)),
+            color: Color::linear_rgb(red, green, blue),
+            cleanse: status.def.cleansable.then(|| CleansePick::Status(status.def.id.clone())),
+        });
+    }
+    if let Some(left) = combat.mitigation_remaining {
+        chips.push(ChipSpec::new("rampart", format!("Rampart {}", countdown(left)), Color::linear_rgb(0.6, 0.7, 1.0)));
+    }
+    if let Some(left) = combat.invuln_remaining {
+        chips.push(ChipSpec::new("invuln", format!("Invuln {}", countdown(left)), Color::linear_rgb(1.0, 1.0, 0.6)));
+    }
+    if let Some(shield) = combat.shield {
+        let text = format!("Shield {} {}", shield.amount, countdown(shield.remaining));
+        chips.push(ChipSpec::new("shield", text, Color::linear_rgb(1.0, 0.9, 0.4)));
+    }
+    if let Some(position) = combat.position {
+        let tally = combat.positionals;
+        chips.push(ChipSpec::new("position", format!("{} {}/{}", position.label(), tally.hits, tally.hits + tally.misses), grey));
+    }
+    chips
+}
+
+fn spawn_status_chip(commands: &mut Commands, spec: &ChipSpec, asset_server: Option<&AssetServer>) -> Entity {
+    let text = commands
+        .spawn((Text::new(spec.text.clone()), TextFont { font_size: 16.0, ..default() }, TextColor(spec.color)))
+        .id();
+    let mut chip = commands.spawn(Node { align_items: AlignItems::Center, column_gap: Val::Px(2.0), ..default() });
+    if let (Some(icon), Some(assets)) = (&spec.icon, asset_server) {
+        chip.with_child((ImageNode::new(assets.load(icon.clone())), Node { width: Val::Px(16.0), height: Val::Px(16.0), ..default() }));
+    }
+    chip.add_child(text).insert(StatusChip { key: spec.key.clone(), text, cleanse: spec.cleanse.clone() });
+    // Hovered while Cleanse is pressed picks it
+    if spec.cleanse.is_some() {
+        chip.insert(Interaction::default());
+    }
+    chip.id()
+}
+
+/// Everything the status row shows, hashed, with countdowns as shown so a running timer only
+/// counts when its figure moves. Keep in step with [`status_chips`]
+fn status_row_fingerprint(
+    stats: &PlayerStats,
+    latency: &InputLatency,
+    time_scale: &SimTimeScale,
+    combat: &CombatState,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    ((stats.scaled_time(2.5) * 100.0).round() as i64).hash(&mut hasher);
+    (latency.enabled, latency.current_rtt_ms().round() as i64).hash(&mut hasher);
+    time_scale.effective().to_bits().hash(&mut hasher);
+    combat.muddled.map(countdown).hash(&mut hasher);
+    (combat.hud_shake_remaining > 0.0).hash(&mut hasher);
+    combat.swiftcast_remaining.filter(|t| *t > 0.0).map(countdown).hash(&mut hasher);
+    for status in &combat.statuses {
+        (&status.def.id, status.stacks, countdown(status.remaining)).hash(&mut hasher);
+    }
+    combat.mitigation_remaining.map(countdown).hash(&mut hasher);
+    combat.invuln_remaining.map(countdown).hash(&mut hasher);
+    combat.shield.map(|shield| (shield.amount, countdown(shield.remaining))).hash(&mut hasher);
+    combat.position.map(RelativePosition::label).hash(&mut hasher);
+    (combat.positionals.hits, combat.positionals.misses).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether the status row needs redrawing: something on it changed, or it was just spawned
+fn status_row_changed(
+    stats: Res<PlayerStats>,
+    latency: Res<InputLatency>,
+    time_scale: Res<SimTimeScale>,
+    combat: Res<CombatState>,
+    q_added: Query<(), Added<StatusRow>>,
+    mut shown: Local<Option<u64>>,
+) -> bool {
+    let now = status_row_fingerprint(&stats, &latency, &time_scale, &combat);
+    shown.replace(now) != Some(now) || !q_added.is_empty()
+}
+
+/// Keeps one chip per thing shown: text and color are changed in place, chips come and go
+/// as statuses do, and the row's children are only reordered when the set changes
 fn update_status_row(
     mut commands: Commands,
+    stats: Res<PlayerStats>,
+    latency: Res<InputLatency>,
+    time_scale: Res<SimTimeScale>,
     combat: Res<CombatState>,
-    row: Query<Entity, With<StatusRow>>,
-    q_children: Query<&Children>,
+    asset_server: Option<Res<AssetServer>>,
+    row: Query<(Entity, Option<&Children>), With<StatusRow>>,
+    q_chips: Query<&StatusChip>,
+    mut q_text: Query<(&mut Text, &mut TextColor)>,
 ) {
-    let Ok(row_entity) = row.single() else { return; };
-    // Despawn existing direct children (they are leaf Text nodes)
-    if let Ok(children) = q_children.get(row_entity) {
-        for child in children.iter() {
-            commands.entity(child).despawn();
+    let Ok((row_entity, children)) = row.single() else { return; };
+    let existing: Vec<(Entity, &StatusChip)> =
+        children.into_iter().flat_map(|c| c.iter()).filter_map(|e| Some((e, q_chips.get(e).ok()?))).collect();
+    let mut order = Vec::new();
+    for spec in status_chips(&stats, &latency, &time_scale, &combat) {
+        let Some((entity, chip)) = existing.iter().find(|(_, chip)| chip.key == spec.key) else {
+            order.push(spawn_status_chip(&mut commands, &spec, asset_server.as_deref()));
+            continue;
+        };
+        if let Ok((mut text, mut color)) = q_text.get_mut(chip.text) {
+            if text.0 != spec.text {
+                text.0 = spec.text;
+            }
+            if color.0 != spec.color {
+                color.0 = spec.color;
+            }
         }
+        order.push(*entity);
     }
-    commands.entity(row_entity).with_children(|r| {
-        if combat.muddled.is_some() {
-            r.spawn((Text::new("Muddled"), TextFont { font_size: 16.0, ..default() }, TextColor(Color::linear_rgb(1.0, 0.3, 0.2))));
-        }
-        if combat.hud_shake_remaining > 0.0 {
-            r.spawn((Text::new("HUD Shaking"), TextFont { font_size: 16.0, ..default() }, TextColor(Color::linear_rgb(0.95, 0.9, 0.2))));
+    for (entity, _) in &existing {
+        if !order.contains(entity) {
+            commands.entity(*entity).despawn();
         }
-        if let Some(t) = combat.swiftcast_remaining { if t > 0.0 { r.spawn((Text::new("Swiftcast"), TextFont { font_size: 16.0, ..default() }, TextColor(Color::linear_rgb(0.5, 0.9, 1.0)))); } }
-        if let Some(t) = combat.raging_remaining { if t > 0.0 { r.spawn((Text::new("Raging"), TextFont { font_size: 16.0, ..default() }, TextColor(Color::linear_rgb(1.0, 0.5, 0.2)))); } }
-    });
+    }
+    if existing.iter().map(|(e, _)| *e).ne(order.iter().copied()) {
+        commands.entity(row_entity).replace_children(&order);
+    }
+}
+
+fn hotbars_added(q: Query<(), Added<HotbarRoot>>) -> bool {
+    !q.is_empty()
 }
 
 fn update_muddled_layout(
-    _time: Res<Time>,
-    _combat: Res<CombatState>,
+    settings: Option<Res<Settings>>,
+    swap_anim: Res<HotbarSwapAnim>,
     mut q_hotbars: Query<(&mut Node, &HotbarRoot)>,
 ) {
     // Keep rows anchored; per-button drift is handled in update_muddled_buttons
+    let anchor = settings.map(|s| s.hud_anchor).unwrap_or_default();
     for (mut node, root) in &mut q_hotbars {
-        let base_left = 400.0;
         let base_bottom = if root.row == 0 { 10.0 } else { 90.0 };
-        node.left = Val::Px(base_left);
-        node.bottom = Val::Px(base_bottom);
+        let mut placed = node.clone();
+        anchor.place(&mut placed);
+        placed.bottom = Val::Px(base_bottom + swap_anim.offset());
+        // Only a real move marks the row for layout
+        node.set_if_neq(placed);
     }
 }
 
+/// Whether a muddle or a shake has the buttons out of place, or had them last time so they
+/// still need putting back
+fn buttons_displaced(combat: Res<CombatState>, q_shake: Query<&ButtonShake>, mut was: Local<bool>) -> bool {
+    let now = combat.muddled.is_some() || q_shake.iter().any(|shake| shake.remaining > 0.0);
+    std::mem::replace(&mut *was, now) || now
+}
+
 fn update_muddled_buttons(
-    time: Res<Time>,
+    time: SimTime,
     combat: Res<CombatState>,
+    // Row position each slot shows at while a Shuffle muddle is up
+    mut shuffled: Local<Vec<usize>>,
     mut q_buttons: Query<(&AbilityButton, &ChildOf, &mut Node, Option<&ButtonShake>)>,
     q_button_rows: Query<&ButtonRow>,
 ) {
+    let variant = combat.muddled.map(|_| combat.muddled_variant);
+    if variant != Some(MuddledVariant::Shuffle) {
+        shuffled.clear();
+    } else if shuffled.is_empty() {
+        // Cosmetic, so not from the pull's generator
+        let rng = &mut rand::thread_rng();
+        for _ in 0..2 {
+            let mut row: Vec<usize> = (0..BUTTONS_PER_ROW).collect();
+            row.shuffle(rng);
+            shuffled.extend(row);
+        }
+    }
     for (btn, parent, mut node, shake) in &mut q_buttons {
-        if combat.muddled.is_some() {
-            let row = q_button_rows
-                .get(parent.parent())
-                .map(|r| r.0)
-                .unwrap_or(0);
-            let t = time.elapsed_secs();
-            // unique phase per button using index and row
-            let phase = (btn.index as f32) * 0.8 + (row as f32) * 0.5;
-            let dx = (t * 1.9 + phase).sin() * 24.0;
-            let dy = (t * 1.3 + phase).cos() * 12.0;
-            let (sx, sy) = if let Some(sh) = shake {
-                if sh.remaining > 0.0 {
-                    let tt = time.elapsed_secs() + sh.phase;
-                    let amp = sh.amp * (sh.remaining.min(1.0));
-                    let sdx = (tt * sh.freq).sin() * amp;
-                    let sdy = (tt * sh.freq * 0.9).cos() * amp * 0.5;
-                    (sdx, sdy)
-                } else { (0.0, 0.0) }
-            } else { (0.0, 0.0) };
-            node.left = Val::Px(dx + sx);
-            node.bottom = Val::Px(dy + sy);
-        } else {
-            let (sx, sy) = if let Some(sh) = shake {
-                if sh.remaining > 0.0 {
-                    let tt = time.elapsed_secs() + sh.phase;
-                    let amp = sh.amp * (sh.remaining.min(1.0));
-                    let sdx = (tt * sh.freq).sin() * amp;
-                    let sdy = (tt * sh.freq * 0.9).cos() * amp * 0.5;
-                    (sdx, sdy)
-                } else { (0.0, 0.0) }
-            } else { (0.0, 0.0) };
-            node.left = Val::Px(sx);
-            node.bottom = Val::Px(sy);
+        let (sx, sy) = if let Some(sh) = shake {
+            if sh.remaining > 0.0 {
+                let tt = time.elapsed_secs() + sh.phase;
+                let amp = sh.amp * (sh.remaining.min(1.0));
+                let sdx = (tt * sh.freq).sin() * amp;
+                let sdy = (tt * sh.freq * 0.9).cos() * amp * 0.5;
+                (sdx, sdy)
+            } else { (0.0, 0.0) }
+        } else { (0.0, 0.0) };
+        let (dx, dy) = match variant {
+            Some(MuddledVariant::Wobble) => {
+                let row = q_button_rows
+                    .get(parent.parent())
+                    .map(|r| r.0)
+                    .unwrap_or(0);
+                let t = time.elapsed_secs();
+                // unique phase per button using index and row
+                let phase = (btn.index as f32) * 0.8 + (row as f32) * 0.5;
+                let amp = combat.muddled_intensity;
+                ((t * 1.9 + phase).sin() * 24.0 * amp, (t * 1.3 + phase).cos() * 12.0 * amp)
+            }
+            Some(MuddledVariant::Shuffle) => {
+                let slot = btn.index % BUTTONS_PER_ROW;
+                let to = shuffled.get(btn.index).copied().unwrap_or(slot);
+                ((to as f32 - slot as f32) * (BUTTON_SIZE + HOTBAR_GAP), 0.0)
+            }
+            Some(MuddledVariant::Blind) | None => (0.0, 0.0),
+        };
+        // Buttons sitting still aren't written, so they don't send the UI through layout
+        let (left, bottom) = (Val::Px(dx + sx), Val::Px(dy + sy));
+        if node.left != left || node.bottom != bottom {
+            node.left = left;
+            node.bottom = bottom;
         }
     }
 }
 
 // ==== Enemy timeline and effects ====
 
-#[derive(Debug, Clone)]
-enum EnemyEvent {
-    Muddled { duration: f32 },
-    HudShake { duration: f32 },
-    Enrage,
-}
-
-#[derive(Resource, Default)]
-struct EnemyTimeline {
-    t: f32,
-    idx: usize,
-    events: Vec<(f32, EnemyEvent)>,
-}
-
-impl EnemyTimeline {
-    fn ensure_default_events(&mut self) {
-        if self.events.is_empty() {
-            self.events = vec![
-                (3.0, EnemyEvent::HudShake { duration: 1.0 }),
-                (6.0, EnemyEvent::Muddled { duration: 8.0 }),
-                (15.0, EnemyEvent::HudShake { duration: 1.5 }),
-                (25.0, EnemyEvent::Enrage),
-            ];
-            self.t = 0.0;
-            self.idx = 0;
-        }
-    }
-}
-
 #[derive(Event)]
 struct HudShakeEvent(pub f32);
 
 #[derive(Event, Debug, Clone, Copy)]
 pub struct DamageEvent {
     pub amount: i32,
+    pub crit: bool,
+    pub positional: Option<bool>, // Some(hit) for positional abilities
+    pub penetration: f32,
+    pub shred: Option<ShredSpec>,
+    pub target: Option<Entity>, // None hits whatever the player has targeted
+    pub source: Option<AbilityId>, // ability the hit or DoT tick came from, None for statuses
+    pub tick: bool, // a DoT tick rather than a hit
+}
+
+/// An ability went off: an instant resolved or a cast finished
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AbilityUsedEvent {
+    pub id: AbilityId,
+}
+
+/// Damage the enemy deals to the player, before mitigation and shields
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerDamageEvent {
+    pub amount: i32,
 }
 
 #[derive(Event, Debug, Clone, Copy)]
 pub struct ApplyDotEvent {
-    pub dps: i32,
+    pub source: AbilityId,
+    pub dps: i32, // damage per tick, snapshotted when applied
     pub duration: f32,
     pub tick_every: f32,
 }
@@ -844,37 +1672,6 @@ struct UiOneShotEffect {
     base_px: f32,
 }
 
-fn run_enemy_timeline(
-    time: Res<Time>,
-    mut timeline: ResMut<EnemyTimeline>,
-    mut combat: ResMut<CombatState>,
-    mut shake_writer: EventWriter<HudShakeEvent>,
-) {
-    timeline.ensure_default_events();
-    timeline.t += time.delta_secs();
-    while timeline.idx < timeline.events.len() && timeline.t >= timeline.events[timeline.idx].0 {
-        let event = timeline.events[timeline.idx].1.clone();
-        match event {
-            EnemyEvent::Muddled { duration } => {
-                combat.muddled = Some(duration);
-            }
-            EnemyEvent::HudShake { duration } => {
-                shake_writer.write(HudShakeEvent(duration));
-            }
-            EnemyEvent::Enrage => {
-                // Simulate instant kill: brutal HUD shake and reset
-                combat.hud_shake_remaining = 2.0;
-                combat.muddled = Some(5.0);
-                // Restart cycle
-                timeline.t = 0.0;
-                timeline.idx = 0;
-                continue; // skip idx increment reset below
-            }
-        }
-        timeline.idx += 1;
-    }
-}
-
 fn apply_hud_shake(
     mut reader: EventReader<HudShakeEvent>,
     mut combat: ResMut<CombatState>,
@@ -884,26 +1681,6 @@ fn apply_hud_shake(
     }
 }
 
-fn shake_hud_node(
-    time: Res<Time>,
-    combat: Res<CombatState>,
-    mut q: Query<&mut Node, With<HudRoot>>,
-) {
-    if let Ok(mut node) = q.single_mut() {
-        if combat.hud_shake_remaining > 0.0 {
-            let t = time.elapsed_secs();
-            let amp = 6.0;
-            let dx = (t * 30.0).sin() * amp;
-            let dy = (t * 37.0).cos() * amp;
-            node.left = Val::Px(dx);
-            node.top = Val::Px(dy);
-        } else {
-            node.left = Val::Px(0.0);
-            node.top = Val::Px(0.0);
-        }
-    }
-}
-
 fn trigger_button_flash(
     textures: Res<TextureAssets>,
     mut commands: Commands,
@@ -939,14 +1716,14 @@ fn trigger_button_flash(
     }
 }
 
-fn decay_button_shake(time: Res<Time>, mut q: Query<&mut ButtonShake>) {
+fn decay_button_shake(time: Res<Time<Real>>, mut q: Query<&mut ButtonShake>) {
     let dt = time.delta_secs();
     for mut sh in &mut q {
         sh.remaining = (sh.remaining - dt).max(0.0);
     }
 }
 
-fn animate_ui_effects(time: Res<Time>, mut q: Query<(Entity, &mut Node, &mut UiOneShotEffect)>, mut commands: Commands) {
+fn animate_ui_effects(time: Res<Time<Real>>, mut q: Query<(Entity, &mut Node, &mut UiOneShotEffect)>, mut commands: Commands) {
     let dt = time.delta_secs();
     for (e, mut node, mut fx) in &mut q {
         fx.ttl -= dt;
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::timeline::EnemyTimeline;
+use super::CurrentEncounter;
+use crate::persist;
+
+// Pull bookmarks: N drops a timestamped note on the current pull, Shift+N picks which
+// preset label the next one gets. Notes are listed in the timeline sidebar, saved per
+// encounter under the earlier pulls' so they can be looked at after the session, and
+// recorded in the pull's replay, where [ and ] jump between them.
+
+pub const NOTE_PRESETS: [&str; 5] = ["Mark", "Check this weave", "Clipped here", "Mechanic", "Positioning"];
+
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    /// Seconds into the pull
+    pub t: f32,
+    pub label: &'static str,
+}
+
+impl Bookmark {
+    /// "<seconds> <label>", for the label one of [`NOTE_PRESETS`]
+    pub fn parse(text: &str) -> Option<Self> {
+        let (t, label) = text.trim().split_once(' ')?;
+        let label = NOTE_PRESETS.into_iter().find(|preset| *preset == label.trim())?;
+        Some(Bookmark { t: t.parse().ok()?, label })
+    }
+}
+
+impl std::fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} {}", self.t, self.label)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PullNotes {
+    pub bookmarks: Vec<Bookmark>,
+    /// Index into [`NOTE_PRESETS`] used for the next bookmark
+    pub preset: usize,
+    /// Encounter the bookmarks are on
+    encounter: String,
+    /// Whether the bookmarks came from a replay being watched rather than this pull
+    replayed: bool,
+    /// Notes file contents from earlier pulls by encounter, read the first time it's pulled
+    /// and kept here after, so a save still on its way doesn't lose any
+    earlier: HashMap<String, String>,
+}
+
+impl PullNotes {
+    /// Shows a replay's bookmarks while it plays, leaving them out of the saved notes
+    pub fn show_replayed(&mut self, bookmarks: &[Bookmark]) {
+        self.bookmarks = bookmarks.to_vec();
+        self.replayed = true;
+    }
+
+    // File format: each pull's bookmarks under a "pull" line, one per line, "<seconds> <label>"
+    fn this_pull(&self) -> String {
+        if self.replayed || self.bookmarks.is_empty() {
+            return String::new();
+        }
+        let lines: String = self.bookmarks.iter().map(|b| format!("{b}\n")).collect();
+        format!("pull\n{lines}")
+    }
+
+    fn save(&self) {
+        let earlier = self.earlier.get(&self.encounter).map_or("", String::as_str);
+        persist::save(&notes_file(&self.encounter), &format!("{earlier}{}", self.this_pull()));
+    }
+}
+
+fn notes_file(encounter: &str) -> String {
+    format!("notes/{encounter}.txt")
+}
+
+/// Files the last pull's notes with the earlier ones and starts on the next pull's
+pub(super) fn reset_notes(encounter: Res<CurrentEncounter>, mut notes: ResMut<PullNotes>) {
+    let last = notes.this_pull();
+    let last_encounter = notes.encounter.clone();
+    notes.earlier.entry(last_encounter).or_default().push_str(&last);
+    notes.bookmarks.clear();
+    notes.replayed = false;
+    notes.encounter.clone_from(&encounter.id);
+    if !notes.earlier.contains_key(&encounter.id) {
+        let saved = persist::load(&notes_file(&encounter.id)).unwrap_or_default();
+        notes.earlier.insert(encounter.id.clone(), saved);
+    }
+}
+
+pub(super) fn drop_bookmark(
+    keys: Res<ButtonInput<KeyCode>>,
+    timeline: Res<EnemyTimeline>,
+    mut notes: ResMut<PullNotes>,
+) {
+    if !keys.just_pressed(KeyCode::KeyN) {
+        return;
+    }
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        notes.preset = (notes.preset + 1) % NOTE_PRESETS.len();
+        info!("Next note: {}", NOTE_PRESETS[notes.preset]);
+        return;
+    }
+    let bookmark = Bookmark { t: timeline.pull_time(), label: NOTE_PRESETS[notes.preset] };
+    info!("Bookmark at {:.1}s: {}", bookmark.t, bookmark.label);
+    notes.bookmarks.push(bookmark);
+    notes.save();
+}
@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::macros::find_ability;
+use super::timeline::EnemyTimeline;
+use super::{AbilityBook, AbilityButton, AbilityId, AbilityUsedEvent};
+use crate::actions::{SimAction, SimInput};
+use crate::{persist, GameState};
+
+// Opener trainer: an expected sequence of abilities with target times, loaded from
+// `opener.txt`. The next expected button is outlined on the hotbar, and every press that goes
+// off is graded on order and on timing against the target. Times are on the pull clock and
+// taken when the button was pressed, so a queued GCD or a cast counts from the press, not
+// from when it went off. Once the last step is done the panel shows a summary of the run.
+
+const OPENER_FILE: &str = "opener.txt";
+/// Off by at most this many seconds is on time
+const PERFECT_SECS: f32 = 0.15;
+/// Off by at most this many seconds is close enough
+const GOOD_SECS: f32 = 0.4;
+/// Graded presses listed above the next steps
+const GRADES_SHOWN: usize = 4;
+const STEPS_SHOWN: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct OpenerStep {
+    pub id: AbilityId,
+    /// Seconds into the pull that this one should be pressed
+    pub at: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    Perfect,
+    Good,
+    Early,
+    Late,
+    /// Skipped over by pressing the step after it
+    Missed,
+    /// Not the next step, or the one after
+    Wrong,
+}
+
+impl Verdict {
+    fn label(self) -> &'static str {
+        match self {
+            Verdict::Perfect => "Perfect",
+            Verdict::Good => "Good",
+            Verdict::Early => "Early",
+            Verdict::Late => "Late",
+            Verdict::Missed => "Missed",
+            Verdict::Wrong => "Wrong",
+        }
+    }
+
+    /// Shown before the ability in the list
+    fn mark(self) -> &'static str {
+        match self {
+            Verdict::Perfect | Verdict::Good => "+",
+            Verdict::Early | Verdict::Late => "~",
+            Verdict::Missed | Verdict::Wrong => "x",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Grade {
+    id: AbilityId,
+    verdict: Verdict,
+    /// Seconds off the target, late positive; None when it wasn't the expected ability
+    offset: Option<f32>,
+}
+
+/// The loaded opener and how the current attempt is going
+#[derive(Resource, Debug, Default)]
+pub struct OpenerTrainer {
+    pub steps: Vec<OpenerStep>,
+    pub current: usize,
+    /// Pull time of each ability's latest press, to grade it by once it goes off
+    pressed: HashMap<AbilityId, f32>,
+    grades: Vec<Grade>,
+}
+
+impl OpenerTrainer {
+    fn done(&self) -> bool {
+        !self.steps.is_empty() && self.current >= self.steps.len()
+    }
+
+    /// Grades `id` going off, pressed `at` seconds into the pull
+    fn grade_press(&mut self, id: AbilityId, at: f32) {
+        let Some(step) = self.steps.get(self.current).copied() else { return; };
+        let timed = |step: OpenerStep| {
+            let offset = at - step.at;
+            let verdict = match offset {
+                o if o.abs() <= PERFECT_SECS => Verdict::Perfect,
+                o if o.abs() <= GOOD_SECS => Verdict::Good,
+                o if o < 0.0 => Verdict::Early,
+                _ => Verdict::Late,
+            };
+            Grade { id: step.id, verdict, offset: Some(offset) }
+        };
+        if step.id == id {
+            self.grades.push(timed(step));
+            self.current += 1;
+        } else if let Some(next) = self.steps.get(self.current + 1).copied().filter(|next| next.id == id) {
+            self.grades.push(Grade { id: step.id, verdict: Verdict::Missed, offset: None });
+            self.grades.push(timed(next));
+            self.current += 2;
+        } else {
+            self.grades.push(Grade { id, verdict: Verdict::Wrong, offset: None });
+        }
+    }
+
+    /// Steps on time, steps hit at all, wrong presses and the mean absolute offset
+    fn summary(&self) -> String {
+        let timed: Vec<f32> = self.grades.iter().filter_map(|g| g.offset).collect();
+        let on_time = self.grades.iter().filter(|g| matches!(g.verdict, Verdict::Perfect | Verdict::Good)).count();
+        let wrong = self.grades.iter().filter(|g| g.verdict == Verdict::Wrong).count();
+        let mean = timed.iter().map(|o| o.abs()).sum::<f32>() / timed.len().max(1) as f32;
+        format!(
+            "Opener done: {on_time}/{} on time, {} hit, {wrong} wrong, off by {mean:.2}s on average",
+            self.steps.len(),
+            timed.len(),
+        )
+    }
+}
+
+/// Parses `opener.txt`: one step per line, `<seconds> <ability>`, with seconds counted from
+/// the start of the pull. Blank lines and `#` comments are skipped.
+///
+/// ```text
+/// # Pre-pull hard cast, then weave
+/// 0.0 Fireball
+/// 2.5 Strike
+/// 3.1 Weave: Dash
+/// 5.0 Strike
+/// ```
+pub fn parse_opener(text: &str, book: &AbilityBook) -> Result<Vec<OpenerStep>, String> {
+    let mut steps = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (at, name) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("line {}: expected \"<seconds> <ability>\"", n + 1))?;
+        let at = at.parse::<f32>().map_err(|_| format!("line {}: bad time {at:?}", n + 1))?;
+        let name = name.trim();
+        let id = find_ability(book, name).ok_or_else(|| format!("line {}: unknown ability {name:?}", n + 1))?;
+        steps.push(OpenerStep { id, at });
+    }
+    Ok(steps)
+}
+
+#[derive(Component)]
+pub(super) struct OpenerPanel;
+
+pub(super) fn load_opener(book: Res<AbilityBook>, mut trainer: ResMut<OpenerTrainer>) {
+    *trainer = OpenerTrainer::default();
+    let Some(contents) = persist::load(OPENER_FILE) else { return; };
+    match parse_opener(&contents, &book) {
+        Ok(steps) => trainer.steps = steps,
+        Err(error) => warn!("{OPENER_FILE} {error}"),
+    }
+}
+
+pub(super) fn spawn_opener_panel(mut commands: Commands) {
+    // Left of the cheat sheet
+    commands.spawn((
+        Text::new(""),
+        TextFont { font_size: 14.0, ..default() },
+        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            right: Val::Px(240.0),
+            width: Val::Px(260.0),
+            ..default()
+        },
+        BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.5)),
+        Visibility::Hidden,
+        OpenerPanel,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+/// Stamps this tick's presses and grades what went off. Runs in the sim after the timeline,
+/// on the tick the presses were applied
+pub(super) fn grade_opener(
+    input: Res<SimInput>,
+    timeline: Res<EnemyTimeline>,
+    mut evr: EventReader<AbilityUsedEvent>,
+    mut trainer: ResMut<OpenerTrainer>,
+) {
+    let now = timeline.pull_time();
+    for action in &input.actions {
+        if let SimAction::Press(id) = action {
+            trainer.pressed.insert(*id, now);
+        }
+    }
+    for ev in evr.read() {
+        if trainer.done() {
+            continue;
+        }
+        let at = trainer.pressed.remove(&ev.id).unwrap_or(now);
+        trainer.grade_press(ev.id, at);
+        if trainer.done() {
+            info!("{}", trainer.summary());
+        }
+    }
+}
+
+pub(super) fn update_opener(
+    book: Res<AbilityBook>,
+    trainer: Res<OpenerTrainer>,
+    mut q_panel: Query<(&mut Text, &mut Visibility), With<OpenerPanel>>,
+    mut q_buttons: Query<(&AbilityButton, &mut Outline)>,
+) {
+    let next = trainer.steps.get(trainer.current).map(|s| s.id);
+    for (button, mut outline) in &mut q_buttons {
+        outline.color = if Some(button.id) == next { Color::linear_rgb(1.0, 0.85, 0.2) } else { Color::NONE };
+    }
+    let Ok((mut text, mut vis)) = q_panel.single_mut() else { return; };
+    if trainer.steps.is_empty() {
+        *vis = Visibility::Hidden;
+        return;
+    }
+    *vis = Visibility::Inherited;
+    let name = |id: AbilityId| book.by_id.get(&id).map_or("?", |a| a.name);
+    let mut out = format!("Opener {}/{}\n", trainer.current.min(trainer.steps.len()), trainer.steps.len());
+    let from = trainer.grades.len().saturating_sub(GRADES_SHOWN);
+    for grade in &trainer.grades[from..] {
+        let offset = grade.offset.map(|o| format!(" {o:+.2}s")).unwrap_or_default();
+        out.push_str(&format!("{} {} {}{offset}\n", grade.verdict.mark(), name(grade.id), grade.verdict.label()));
+    }
+    for (i, step) in trainer.steps.iter().enumerate().skip(trainer.current).take(STEPS_SHOWN) {
+        let cursor = if i == trainer.current { ">" } else { " " };
+        out.push_str(&format!("{cursor} {} @ {:.1}s\n", name(step.id), step.at));
+    }
+    if trainer.done() {
+        out.push_str(&trainer.summary());
+    }
+    text.0 = out;
+}
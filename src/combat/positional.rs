@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use super::CombatState;
+use crate::player::Player;
+use crate::world::{Enemy, Facing};
+
+/// Where the player stands relative to the enemy's facing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativePosition {
+    Front,
+    Flank,
+    Rear,
+}
+
+impl RelativePosition {
+    /// Splits the circle around the enemy into 90° front and rear cones with flanks in between
+    pub fn from_positions(enemy: Vec2, facing: Vec2, player: Vec2) -> Self {
+        let to_player = (player - enemy).normalize_or_zero();
+        let dot = facing.normalize_or_zero().dot(to_player);
+        let cone = std::f32::consts::FRAC_1_SQRT_2;
+        if dot >= cone {
+            RelativePosition::Front
+        } else if dot <= -cone {
+            RelativePosition::Rear
+        } else {
+            RelativePosition::Flank
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RelativePosition::Front => "Front",
+            RelativePosition::Flank => "Flank",
+            RelativePosition::Rear => "Rear",
+        }
+    }
+}
+
+/// Bonus potency for executing a GCD from the right spot. The bonus goes on the
+/// direct hit, or on every tick for DoT-only GCDs.
+#[derive(Debug, Clone, Copy)]
+pub struct PositionalSpec {
+    pub from: RelativePosition,
+    pub bonus: u32,
+}
+
+/// Positional GCDs landed and missed this pull
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PositionalTally {
+    pub hits: u32,
+    pub misses: u32,
+}
+
+pub(super) fn track_position(
+    mut combat: ResMut<CombatState>,
+    q_player: Query<&Transform, With<Player>>,
+    q_enemy: Query<(&Transform, &Facing), With<Enemy>>,
+) {
+    combat.position = match (q_player.single(), q_enemy.single()) {
+        (Ok(player), Ok((enemy, facing))) => Some(RelativePosition::from_positions(
+            enemy.translation.truncate(),
+            facing.0,
+            player.translation.truncate(),
+        )),
+        _ => None,
+    };
+}
@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+
+use super::buffs::BuffReport;
+use super::dps_meter::DpsMeter;
+use super::drift::CooldownDrift;
+use super::encounter::EncounterLibrary;
+use super::timeline::{CheckpointResult, EncounterProgress, EnemyTimeline, EnrageEvent};
+use super::{AbilityId, CurrentEncounter};
+use crate::player::Player;
+use crate::world::{Enemy, Health};
+use crate::{persist, GameState};
+
+// End of a pull: the boss dies, the player dies, or the boss's enrage cast goes off and
+// wipes the player. Either way the pull is added to the rotation stats and the results
+// screen takes over. Delete (or Restart in the pause menu) abandons the pull instead and
+// starts it over, by way of `GameState::Restarting`.
+//
+// Only live pulls go into the rotation stats, the leaderboard, achievements, reports and
+// combat logs; replays, striking dummies, timeline editor previews and pulls cheated on
+// from the console are left out. Those systems run if `is_live`, which holds from the start
+// of the pull to the next one, results screen included.
+
+const ROTATION_STATS_FILE: &str = "rotation_stats.txt";
+
+/// What the current pull is, or the last one while none is going
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PullOrigin {
+    #[default]
+    Live,
+    /// A replay being watched, which says so itself once the pull has started
+    Replay,
+    Dummy,
+    /// An unsaved copy from the timeline editor
+    Preview,
+    /// A console cheat ran during it
+    Console,
+}
+
+/// Run condition: the pull counts towards the player's records
+pub fn is_live(origin: Res<PullOrigin>) -> bool {
+    *origin == PullOrigin::Live
+}
+
+pub fn set_pull_origin(encounter: Res<CurrentEncounter>, library: Res<EncounterLibrary>, mut origin: ResMut<PullOrigin>) {
+    *origin = match library.get(&encounter.id) {
+        Some(def) if def.dummy => PullOrigin::Dummy,
+        Some(def) if def.preview => PullOrigin::Preview,
+        _ => PullOrigin::Live,
+    };
+}
+
+/// How the last pull ended, shown on the results screen
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PullResult {
+    pub encounter: String,
+    /// Seconds from the pull to the kill or the wipe
+    pub duration: f32,
+    pub cleared_before_enrage: bool,
+    /// Damage that landed on anything over the pull
+    pub damage: i64,
+    /// DPS checks the pull got to, in order
+    pub checkpoints: Vec<CheckpointResult>,
+    /// Vulnerability stacks taken from failed mechanics
+    pub vuln_stacks: u32,
+    /// Share of the pull each DoT was on the boss, highest first
+    pub dot_uptimes: Vec<(AbilityId, f32)>,
+    /// Buff windows and the GCDs in and out of them
+    pub buffs: BuffReport,
+    /// (cooldown, seconds it sat ready in total, uses) for the oGCDs meant to go out on cooldown
+    pub cooldown_drift: Vec<(AbilityId, f32, u32)>,
+}
+
+impl PullResult {
+    pub fn dps(&self) -> f32 {
+        self.damage as f32 / self.duration.max(1.0)
+    }
+
+    /// Uptime of the best-kept DoT, 0 if none went on
+    pub fn dot_uptime(&self) -> f32 {
+        self.dot_uptimes.first().map_or(0.0, |(_, uptime)| *uptime)
+    }
+
+    /// Letter grade from buff alignment, DoT uptime, cooldown drift and vulnerability stacks,
+    /// capped at C for a pull that didn't clear
+    pub fn grade(&self) -> &'static str {
+        let aligned = match self.buffs.possible_gcds() {
+            0 => 1.0,
+            possible => (self.buffs.buffed_gcds() as f32 / possible as f32).min(1.0),
+        };
+        let uptime = if self.dot_uptimes.is_empty() { 1.0 } else { self.dot_uptime() };
+        let drift: f32 = self.cooldown_drift.iter().map(|(_, secs, _)| secs).sum();
+        let on_cooldown = 1.0 - drift / (self.duration * self.cooldown_drift.len() as f32).max(1.0);
+        let mut score = (aligned + uptime + on_cooldown.clamp(0.0, 1.0)) / 3.0 - 0.05 * self.vuln_stacks as f32;
+        if !self.cleared_before_enrage {
+            score = score.min(0.5);
+        }
+        match score {
+            s if s >= 0.9 => "S",
+            s if s >= 0.8 => "A",
+            s if s >= 0.65 => "B",
+            s if s >= 0.5 => "C",
+            _ => "D",
+        }
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+// File format: one line per pull, "<encounter> <seconds> cleared_before_enrage=<yes|no>
+// vuln_stacks=<n>", then "<dot>_uptime=<percent>" for each DoT applied,
+// "buffed_gcds=<in windows>/<possible>", "<cooldown>_drift=<seconds>" for each tracked oGCD and, if the pull got to any DPS checks,
+// "checks=<passed>/<reached>"
+fn record_pull(result: &PullResult) {
+    let mut out = persist::load(ROTATION_STATS_FILE).unwrap_or_default();
+    out.push_str(&format!(
+        "{} {:.1} cleared_before_enrage={} vuln_stacks={}",
+        result.encounter,
+        result.duration,
+        yes_no(result.cleared_before_enrage),
+        result.vuln_stacks
+    ));
+    for (id, uptime) in &result.dot_uptimes {
+        out.push_str(&format!(" {id:?}_uptime={:.0}", uptime * 100.0));
+    }
+    out.push_str(&format!(" buffed_gcds={}/{}", result.buffs.buffed_gcds(), result.buffs.possible_gcds()));
+    for (id, drift, _) in &result.cooldown_drift {
+        out.push_str(&format!(" {id:?}_drift={drift:.1}"));
+    }
+    if !result.checkpoints.is_empty() {
+        let passed = result.checkpoints.iter().filter(|c| c.passed()).count();
+        out.push_str(&format!(" checks={passed}/{}", result.checkpoints.len()));
+    }
+    out.push('\n');
+    persist::save(ROTATION_STATS_FILE, &out);
+}
+
+/// Run condition: the pull ends this tick, for what has to wrap up before [`end_pull`] tallies it
+pub(super) fn pull_ending(
+    mut enrages: EventReader<EnrageEvent>,
+    q_enemy: Query<&Health, With<Enemy>>,
+    q_player: Query<&Health, (With<Player>, Without<Enemy>)>,
+) -> bool {
+    let enraged = enrages.read().count() > 0;
+    enraged || q_enemy.single().is_ok_and(|hp| hp.current <= 0) || q_player.single().is_ok_and(|hp| hp.current <= 0)
+}
+
+pub(super) fn end_pull(
+    mut enrages: EventReader<EnrageEvent>,
+    timeline: Res<EnemyTimeline>,
+    progress: Res<EncounterProgress>,
+    meter: Res<DpsMeter>,
+    buffs: Res<BuffReport>,
+    drift: Res<CooldownDrift>,
+    encounter: Res<CurrentEncounter>,
+    origin: Res<PullOrigin>,
+    q_enemy: Query<&Health, With<Enemy>>,
+    mut q_player: Query<&mut Health, (With<Player>, Without<Enemy>)>,
+    mut result: ResMut<PullResult>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let enraged = enrages.read().count() > 0;
+    // A kill on the same frame the enrage lands still counts
+    let killed = q_enemy.single().is_ok_and(|hp| hp.current <= 0);
+    let died = q_player.single().is_ok_and(|hp| hp.current <= 0);
+    if !enraged && !killed && !died {
+        return;
+    }
+    if enraged && !killed {
+        if let Ok(mut hp) = q_player.single_mut() {
+            hp.current = 0;
+        }
+    }
+    let duration = timeline.pull_time();
+    let mut buffs = buffs.clone();
+    buffs.finish(duration);
+    *result = PullResult {
+        encounter: encounter.id.clone(),
+        duration,
+        cleared_before_enrage: killed,
+        damage: meter.total,
+        checkpoints: timeline.checkpoints().to_vec(),
+        vuln_stacks: progress.vuln_stacks,
+        dot_uptimes: meter.dot_uptimes(duration),
+        buffs,
+        cooldown_drift: drift.report(duration),
+    };
+    info!("Pull over after {:.1}s, cleared before enrage: {}", result.duration, yes_no(killed));
+    if *origin == PullOrigin::Live {
+        record_pull(&result);
+    }
+    next_state.set(GameState::Results);
+}
+
+pub(super) fn restart_hotkey(keys: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keys.just_pressed(KeyCode::Delete) {
+        next_state.set(GameState::Restarting);
+    }
+}
+
+pub(super) fn finish_restart(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Playing);
+}
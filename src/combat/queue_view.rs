@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+
+use super::{AbilityBook, AbilityId, CombatState};
+use crate::overlay::OverlayWidget;
+use crate::GameState;
+
+// Action queue readout: the hidden input state in `CombatState` (buffered press, queued
+// next GCD, queue window and weaves used this GCD) spelled out next to the GCD bar.
+
+#[derive(Component)]
+pub(super) struct QueueViewText;
+
+const IDLE: Color = Color::linear_rgb(0.55, 0.55, 0.55);
+const ACTIVE: Color = Color::linear_rgb(0.95, 0.85, 0.4);
+
+pub(super) fn spawn_queue_view(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(146.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(-420.0)),
+                width: Val::Px(200.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.7)),
+            OverlayWidget,
+            StateScoped(GameState::Playing),
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont { font_size: 12.0, ..default() },
+            TextColor(IDLE),
+            QueueViewText,
+        ));
+}
+
+/// Seconds until the next GCD can be queued, or `None` while the window is open
+fn window_opens_in(combat: &CombatState) -> Option<f32> {
+    let rolling = match &combat.cast {
+        Some(cast) => cast.remaining,
+        None => combat.gcd_remaining,
+    };
+    (rolling > combat.gcd_queue_window).then(|| rolling - combat.gcd_queue_window)
+}
+
+fn name(book: &AbilityBook, id: AbilityId) -> &'static str {
+    book.by_id.get(&id).map_or("?", |a| a.name)
+}
+
+pub(super) fn update_queue_view(
+    book: Res<AbilityBook>,
+    combat: Res<CombatState>,
+    mut q_text: Query<(&mut Text, &mut TextColor), With<QueueViewText>>,
+) {
+    let Ok((mut text, mut color)) = q_text.single_mut() else { return; };
+    let buffered = match combat.buffer {
+        Some((id, left)) => format!("{} ({left:.2}s)", name(&book, id)),
+        None => "-".to_string(),
+    };
+    let queued = combat.gcd_queue.map_or("-", |id| name(&book, id));
+    let rolling = combat.cast.is_some() || combat.gcd_remaining > 0.0;
+    let window = match window_opens_in(&combat) {
+        Some(secs) => format!("opens in {secs:.2}s"),
+        None if rolling => format!("open, {:.2}s left", combat.cast.as_ref().map_or(combat.gcd_remaining, |c| c.remaining)),
+        None => "GCD ready".to_string(),
+    };
+    text.0 = format!(
+        "Buffered: {buffered}\nQueued GCD: {queued}\nQueue window: {window}\nWeaves: {}/2",
+        combat.weaves_in_current_gcd
+    );
+    color.0 = if combat.buffer.is_some() || combat.gcd_queue.is_some() { ACTIVE } else { IDLE };
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+
+use super::dps_meter::DpsMeter;
+use super::timeline::EnemyTimeline;
+use super::{AbilityBook, AbilityId, AbilityUsedEvent, CombatState, PullResult};
+use crate::combat_log::json_escape;
+use crate::persist;
+use crate::sim_time::SimTime;
+
+// Pull reports: when a pull ends, everything needed to graph it or compare it with other
+// sessions goes to `reports/` in the user data dir, as `pull_<n>.json` and `pull_<n>.csv`.
+// The JSON has the summary (duration, damage, DPS, grade, uptimes) plus per-ability counts
+// with their cast times, clip times and a DPS sample every second. The CSV has the same
+// timed rows, one per line:
+//
+//   t,kind,name,value
+//   3.10,cast,Slash,
+//   5.60,clip,,
+//   6.00,dps,,412.5
+//
+// with `value` the rolling DPS on `dps` rows. Pulls abandoned with a restart aren't written,
+// and neither are replays, dummy pulls or editor previews.
+
+const REPORT_DIR: &str = "reports";
+/// Seconds between DPS samples
+const SAMPLE_SECS: f32 = 1.0;
+
+/// What the pull report needs that the rest of combat doesn't keep
+#[derive(Resource, Debug, Default)]
+pub(super) struct PullReport {
+    /// (pull time, ability) for every ability that went off
+    casts: Vec<(f32, AbilityId)>,
+    /// Pull times the GCD got clipped
+    clips: Vec<f32>,
+    was_clipped: bool,
+    /// (pull time, rolling DPS, total damage so far)
+    dps_samples: Vec<(f32, f32, i64)>,
+}
+
+pub(super) fn reset_pull_report(mut report: ResMut<PullReport>) {
+    *report = PullReport::default();
+}
+
+/// Runs after the DPS meter has taken this frame's damage
+pub(super) fn track_pull_report(
+    time: SimTime,
+    combat: Res<CombatState>,
+    timeline: Res<EnemyTimeline>,
+    meter: Res<DpsMeter>,
+    mut evr: EventReader<AbilityUsedEvent>,
+    mut report: ResMut<PullReport>,
+) {
+    let now = timeline.pull_time();
+    let before = now - time.scaled_delta();
+    for ev in evr.read() {
+        report.casts.push((now, ev.id));
+    }
+    if combat.clipped && !report.was_clipped {
+        report.clips.push(now);
+    }
+    report.was_clipped = combat.clipped;
+    if (now / SAMPLE_SECS).floor() > (before / SAMPLE_SECS).floor() {
+        report.dps_samples.push((now, meter.rolling_dps(time.elapsed_secs()), meter.total));
+    }
+}
+
+pub(super) fn write_pull_report(
+    result: Res<PullResult>,
+    report: Res<PullReport>,
+    meter: Res<DpsMeter>,
+    book: Res<AbilityBook>,
+) {
+    let stem = persist::next_numbered(REPORT_DIR, "pull");
+    let name = |id: &AbilityId| book.by_id.get(id).map_or("?", |a| a.name);
+    persist::save(&format!("{stem}.json"), &report.to_json(&result, &meter, name));
+    persist::save(&format!("{stem}.csv"), &report.to_csv(name));
+    info!("Pull report saved as {stem}.json and .csv");
+}
+
+impl PullReport {
+    /// Abilities in the order they were first used, with their cast times
+    fn usage(&self) -> Vec<(AbilityId, Vec<f32>)> {
+        let mut usage: Vec<(AbilityId, Vec<f32>)> = Vec::new();
+        for (t, id) in &self.casts {
+            match usage.iter_mut().find(|(used, _)| used == id) {
+                Some((_, times)) => times.push(*t),
+                None => usage.push((*id, vec![*t])),
+            }
+        }
+        usage
+    }
+
+    fn to_json<'a>(&self, result: &PullResult, meter: &DpsMeter, name: impl Fn(&AbilityId) -> &'a str) -> String {
+        let list = |times: &[f32]| times.iter().map(|t| format!("{t:.2}")).collect::<Vec<_>>().join(", ");
+        let dots: Vec<String> = result
+            .dot_uptimes
+            .iter()
+            .map(|(id, uptime)| format!("\"{}\": {uptime:.3}", json_escape(name(id))))
+            .collect();
+        let abilities: Vec<String> = self
+            .usage()
+            .iter()
+            .map(|(id, times)| {
+                let escaped = json_escape(name(id));
+                format!("    {{\"name\": \"{escaped}\", \"count\": {}, \"casts\": [{}]}}", times.len(), list(times))
+            })
+            .collect();
+        let samples: Vec<String> = self
+            .dps_samples
+            .iter()
+            .map(|(t, dps, total)| format!("    {{\"t\": {t:.2}, \"dps\": {dps:.1}, \"total\": {total}}}"))
+            .collect();
+        format!(
+            "{{\n  \"encounter\": \"{}\",\n  \"duration\": {:.2},\n  \"cleared\": {},\n  \"damage\": {},\n  \
+             \"dps\": {:.1},\n  \"grade\": \"{}\",\n  \"vuln_stacks\": {},\n  \
+             \"uptime\": {{\"gcd\": {:.3}, \"dots\": {{{}}}}},\n  \
+             \"abilities\": [\n{}\n  ],\n  \"clips\": [{}],\n  \"dps_samples\": [\n{}\n  ]\n}}\n",
+            json_escape(&result.encounter),
+            result.duration,
+            result.cleared_before_enrage,
+            result.damage,
+            result.dps(),
+            result.grade(),
+            result.vuln_stacks,
+            meter.gcd_uptime(result.duration),
+            dots.join(", "),
+            abilities.join(",\n"),
+            list(&self.clips),
+            samples.join(",\n"),
+        )
+    }
+
+    fn to_csv<'a>(&self, name: impl Fn(&AbilityId) -> &'a str) -> String {
+        let mut rows: Vec<(f32, String)> = Vec::new();
+        rows.extend(self.casts.iter().map(|(t, id)| (*t, format!("{t:.2},cast,{},", name(id)))));
+        rows.extend(self.clips.iter().map(|t| (*t, format!("{t:.2},clip,,"))));
+        rows.extend(self.dps_samples.iter().map(|(t, dps, _)| (*t, format!("{t:.2},dps,,{dps:.1}"))));
+        // Stable, so a cast and a sample on the same frame keep that order
+        rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut out = "t,kind,name,value\n".to_string();
+        for (_, row) in rows {
+            out.push_str(&row);
+            out.push('\n');
+        }
+        out
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rhai::{Engine, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use super::timeline::{BranchCondition, EnemyEvent};
+use super::MuddledVariant;
+use crate::markers::{MarkerKind, MarkerTarget};
+use crate::party::PartyMember;
+use crate::player::Player;
+use crate::rng::GameRng;
+
+// Encounter scripts: small Rhai programs an encounter file defines by name and its timeline
+// runs with a `Script(name)` event, for boss logic the plain event list can't express. A
+// script reads the fight's state and calls functions that stand for timeline events; what it
+// called happens right away, in order, as if those events had been in the branch.
+//
+// Read: `hp` (boss HP, 0 to 1), `time` (seconds into the pull), `adds` (alive),
+// `failed` (mechanics failed this pull), `party` (people in the party, player included).
+// Call: `cast(name)`, `callout(text)`, `hit(amount)`, `mechanic(name)`, `spawn(count)`,
+// `status(id)`, `muddled(secs)` or `muddled(secs, variant, intensity)` with variant "wobble",
+// "shuffle" or "blind", `shake(secs)`, `jump(branch)`, `enrage()`,
+// `marker(kind, who, secs)` with kind "stack", "spread" or "tankbuster" and `who` a party
+// index (0 is the player), and `random(n)` for a roll from 0 to n - 1.
+//
+// Rolls come from the pull's seeded generator so replays see the same ones.
+
+/// Steps a script may take before it's stopped, so a runaway loop can't hang the pull
+const MAX_OPERATIONS: u64 = 10_000;
+
+/// A named script from an encounter file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct ScriptDef {
+    pub(super) name: String,
+    pub(super) source: String,
+}
+
+/// Scripts of the loaded encounter, compiled once when it loads
+#[derive(Default)]
+pub(super) struct CompiledScripts(Vec<(String, AST)>);
+
+impl CompiledScripts {
+    /// Compiles every script, leaving out (with a warning) the ones that don't compile
+    pub(super) fn compile(defs: &[ScriptDef]) -> Self {
+        let engine = Engine::new();
+        let scripts = defs
+            .iter()
+            .filter_map(|def| match engine.compile(&def.source) {
+                Ok(ast) => Some((def.name.clone(), ast)),
+                Err(error) => {
+                    warn!("Encounter script {:?} doesn't compile: {error}", def.name);
+                    None
+                }
+            })
+            .collect();
+        CompiledScripts(scripts)
+    }
+
+    fn get(&self, name: &str) -> Option<&AST> {
+        self.0.iter().find(|(n, _)| n == name).map(|(_, ast)| ast)
+    }
+}
+
+/// What a script can see of the fight
+pub(super) struct ScriptState {
+    pub(super) hp_frac: f32,
+    pub(super) pull_time: f32,
+    pub(super) adds_alive: u32,
+    pub(super) mechanics_failed: u32,
+}
+
+/// The pull's generator and the party, for scripts' rolls and markers
+#[derive(SystemParam)]
+pub(super) struct ScriptInputs<'w, 's> {
+    rng: ResMut<'w, GameRng>,
+    q_party: Query<'w, 's, (Entity, Has<Player>), Or<(With<Player>, With<PartyMember>)>>,
+}
+
+impl ScriptInputs<'_, '_> {
+    /// Runs the script named `name` and returns the events it called for, in order.
+    /// A missing script or one that fails partway gives whatever it called before that.
+    pub(super) fn run(&mut self, scripts: &CompiledScripts, name: &str, state: &ScriptState) -> Vec<EnemyEvent> {
+        let Some(ast) = scripts.get(name) else {
+            warn!("Encounter script {name:?} is not defined");
+            return Vec::new();
+        };
+        // Player first, then the party in spawn order
+        let mut party: Vec<(bool, Entity)> = self.q_party.iter().map(|(e, is_player)| (!is_player, e)).collect();
+        party.sort();
+        let party: Vec<Entity> = party.into_iter().map(|(_, e)| e).collect();
+        let party_size = party.len() as i64;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        // One draw from the pull's generator per run keeps the sequence the same on replays
+        let roll = Arc::new(Mutex::new(StdRng::seed_from_u64(self.rng.rng().gen())));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let push = |events: &Arc<Mutex<Vec<EnemyEvent>>>| {
+            let events = events.clone();
+            move |event: EnemyEvent| events.lock().unwrap_or_else(|e| e.into_inner()).push(event)
+        };
+        let emit = push(&events);
+        engine.register_fn("cast", move |name: &str| emit(EnemyEvent::Cast { name: name.to_string() }));
+        let emit = push(&events);
+        engine.register_fn("callout", move |text: &str| {
+            emit(EnemyEvent::Callout { text: text.to_string(), waymark: None })
+        });
+        let emit = push(&events);
+        engine.register_fn("hit", move |amount: i64| emit(EnemyEvent::Hit { amount: amount as i32 }));
+        let emit = push(&events);
+        engine.register_fn("mechanic", move |name: &str| emit(EnemyEvent::Mechanic { name: name.to_string() }));
+        let emit = push(&events);
+        engine.register_fn("spawn", move |count: i64| emit(EnemyEvent::Spawn { count: count.max(0) as u32 }));
+        let emit = push(&events);
+        engine.register_fn("status", move |id: &str| emit(EnemyEvent::Status { id: id.to_string() }));
+        let emit = push(&events);
+        engine.register_fn("muddled", move |secs: f64| {
+            emit(EnemyEvent::Muddled { duration: secs as f32, variant: MuddledVariant::Wobble, intensity: 1.0 })
+        });
+        let emit = push(&events);
+        engine.register_fn("muddled", move |secs: f64, variant: &str, intensity: f64| {
+            let variant = match variant {
+                "shuffle" => MuddledVariant::Shuffle,
+                "blind" => MuddledVariant::Blind,
+                _ => MuddledVariant::Wobble,
+            };
+            emit(EnemyEvent::Muddled { duration: secs as f32, variant, intensity: intensity as f32 })
+        });
+        let emit = push(&events);
+        engine.register_fn("shake", move |secs: f64| emit(EnemyEvent::HudShake { duration: secs as f32 }));
+        let emit = push(&events);
+        engine.register_fn("jump", move |to: &str| {
+            emit(EnemyEvent::Branch { condition: BranchCondition::Always, to: to.to_string() })
+        });
+        let emit = push(&events);
+        engine.register_fn("enrage", move || emit(EnemyEvent::Enrage));
+        let emit = push(&events);
+        engine.register_fn("marker", move |kind: &str, who: i64, secs: f64| {
+            let kind = match kind {
+                "stack" => MarkerKind::Stack,
+                "spread" => MarkerKind::Spread,
+                "tankbuster" => MarkerKind::Tankbuster,
+                _ => return,
+            };
+            let Some(entity) = usize::try_from(who).ok().and_then(|i| party.get(i)) else { return; };
+            emit(EnemyEvent::Marker { kind, target: MarkerTarget::Entity(*entity), duration: secs as f32 });
+        });
+        engine.register_fn("random", move |n: i64| {
+            if n <= 0 {
+                return 0;
+            }
+            roll.lock().unwrap_or_else(|e| e.into_inner()).gen_range(0..n)
+        });
+
+        let mut scope = Scope::new();
+        scope.push_constant("hp", state.hp_frac as f64);
+        scope.push_constant("time", state.pull_time as f64);
+        scope.push_constant("adds", state.adds_alive as i64);
+        scope.push_constant("failed", state.mechanics_failed as i64);
+        scope.push_constant("party", party_size);
+        if let Err(error) = engine.run_ast_with_scope(&mut scope, ast) {
+            warn!("Encounter script {name:?} stopped: {error}");
+        }
+        drop(engine);
+        let events = events.lock().unwrap_or_else(|e| e.into_inner());
+        events.clone()
+    }
+}
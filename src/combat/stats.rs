@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::persist;
+
+/// Character stats that turn ability potency into damage numbers.
+/// Defaults are tuned so 1 potency ~= 1 damage without crits.
+#[derive(Resource, Debug, Clone)]
+pub struct PlayerStats {
+    pub weapon_damage: f32,
+    pub main_stat: f32,
+    pub speed: f32,
+    pub crit: f32,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        Self { weapon_damage: 100.0, main_stat: 100.0, speed: 0.0, crit: 0.0 }
+    }
+}
+
+pub const CRIT_MULTIPLIER: f32 = 1.5;
+
+/// Speed values that land exactly on common GCD tiers: (gcd, speed)
+pub const SPEED_TIERS: [(f32, f32); 3] = [(2.50, 0.0), (2.40, 590.0), (2.30, 1170.0)];
+
+impl PlayerStats {
+    /// Chance to crit, 5% base plus 1% per 20 points of crit
+    pub fn crit_rate(&self) -> f32 {
+        (0.05 + self.crit / 2000.0).clamp(0.0, 1.0)
+    }
+
+    /// Damage before crits and buffs
+    pub fn base_damage(&self, potency: u32) -> f32 {
+        potency as f32 * (self.weapon_damage / 100.0) * (self.main_stat / 100.0)
+    }
+
+    /// Rolls a hit for `potency` with an extra multiplier (buffs); returns (damage, crit)
+    pub fn roll_damage(&self, potency: u32, mult: f32, rng: &mut impl Rng) -> (i32, bool) {
+        let crit = rng.gen::<f32>() < self.crit_rate();
+        let crit_mult = if crit { CRIT_MULTIPLIER } else { 1.0 };
+        ((self.base_damage(potency) * mult * crit_mult) as i32, crit)
+    }
+
+    /// Scales a recast or cast time by speed. Works in whole milliseconds and truncates to
+    /// 10ms steps, so speed moves the GCD in discrete tiers (2.50, 2.49, ...) like the real game.
+    pub fn scaled_time(&self, base: f32) -> f32 {
+        if base <= 0.0 {
+            return 0.0;
+        }
+        let base_ms = (base * 1000.0).round() as i64;
+        let reduction = ((130.0 * self.speed.max(0.0)) / 1900.0).floor() as i64;
+        let ms = base_ms * (1000 - reduction.min(500)) / 1000;
+        (ms / 10 * 10) as f32 / 1000.0
+    }
+
+    /// Moves speed to the next entry in [`SPEED_TIERS`], wrapping around
+    pub fn cycle_speed_tier(&mut self) {
+        let current = SPEED_TIERS.iter().rposition(|(_, speed)| self.speed >= *speed).unwrap_or(0);
+        self.speed = SPEED_TIERS[(current + 1) % SPEED_TIERS.len()].1;
+    }
+
+    // File format: "<stat> = <value>" per line, unknown keys are ignored
+    fn apply_overrides(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue; };
+            let Ok(value) = value.trim().parse::<f32>() else {
+                warn!("Ignoring malformed stat line {line:?}");
+                continue;
+            };
+            match key.trim() {
+                "weapon_damage" => self.weapon_damage = value,
+                "main_stat" => self.main_stat = value,
+                "speed" => self.speed = value,
+                "crit" => self.crit = value,
+                other => warn!("Unknown stat {other:?}"),
+            }
+        }
+    }
+}
+
+/// Loads `stats.txt` from the user data dir so gear setups can be tried without recompiling
+pub fn load_player_stats(mut stats: ResMut<PlayerStats>) {
+    *stats = PlayerStats::default();
+    if let Some(contents) = persist::load("stats.txt") {
+        stats.apply_overrides(&contents);
+    }
+}
@@ -0,0 +1,357 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::{AbilityBook, AbilityId, AbilityUsedEvent, CombatState, DamageEvent, PlayerDamageEvent};
+use crate::actions::{SimAction, SimInput};
+use crate::player::Player;
+use crate::sim_time::SimTime;
+use crate::vfx::VfxAuras;
+use crate::waymarks::CalloutEvent;
+use crate::world::{HealTarget, Health};
+
+// Statuses on the player defined entirely in data: `assets/statuses/*.statuses.ron`.
+// Abilities apply them through `Ability::applies`, encounters through `Status` events,
+// both by id.
+//
+// Debuffs can also get in the way: slow the player down (Heavy), stop casts (Silence) or
+// weaves (Pacify). The ones marked cleansable come off with Cleanse, which takes the one the
+// cursor was on in the status row when it was pressed, otherwise the newest one (Muddled
+// included).
+//
+// A status can name an effect to loop on the player while it's up; Swiftcast and Muddled,
+// which aren't statuses, have theirs here.
+
+/// Multipliers a status applies while it's up; several statuses multiply together
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct StatModifiers {
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub move_speed: f32,
+}
+
+impl Default for StatModifiers {
+    fn default() -> Self {
+        Self { damage_dealt: 1.0, damage_taken: 1.0, move_speed: 1.0 }
+    }
+}
+
+/// Something a status does on a tick or when it runs out
+#[derive(Debug, Clone, Deserialize)]
+pub enum StatusEffect {
+    DamagePlayer(i32),
+    HealPlayer(i32),
+    DamageEnemy(i32),
+    /// Applies another status by id
+    Apply(String),
+    Callout(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusTick {
+    pub every: f32,
+    pub effect: StatusEffect,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusDef {
+    pub id: String,
+    pub name: String,
+    /// Image shown next to the name in the status row, relative to `assets/`
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub duration: f32,
+    #[serde(default)]
+    pub modifiers: StatModifiers,
+    #[serde(default)]
+    pub tick: Option<StatusTick>,
+    #[serde(default)]
+    pub on_expire: Vec<StatusEffect>,
+    /// Abilities with a cast time can't be started while it's up
+    #[serde(default)]
+    pub silence: bool,
+    /// oGCDs other than Cleanse can't be used while it's up
+    #[serde(default)]
+    pub pacify: bool,
+    /// Cleanse can take it off
+    #[serde(default)]
+    pub cleansable: bool,
+    /// Linear RGB for its name in the status row
+    #[serde(default)]
+    pub color: Option<(f32, f32, f32)>,
+    /// Applying it while it's up adds a stack, up to this many; modifiers apply once per
+    /// stack. 1 just refreshes it
+    #[serde(default = "StatusDef::default_max_stacks")]
+    pub max_stacks: u32,
+    /// Effect id looped on the player while it's up, see `VfxAuras`
+    #[serde(default)]
+    pub vfx: Option<String>,
+}
+
+impl StatusDef {
+    fn default_max_stacks() -> u32 {
+        1
+    }
+}
+
+/// A status currently on the player. The definition is copied in, so reloading
+/// definitions doesn't change statuses that are already running.
+#[derive(Debug, Clone)]
+pub struct ActiveStatus {
+    pub def: StatusDef,
+    pub remaining: f32,
+    /// Seconds since it was applied or last refreshed
+    pub age: f32,
+    pub stacks: u32,
+    tick_accum: f32,
+}
+
+impl ActiveStatus {
+    /// A modifier with every stack applied
+    fn stacked(&self, modifier: impl Fn(&StatModifiers) -> f32) -> f32 {
+        modifier(&self.def.modifiers).powi(self.stacks as i32)
+    }
+}
+
+/// Contents of one `.statuses.ron` file
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct StatusFile {
+    pub statuses: Vec<StatusDef>,
+}
+
+impl StatusFile {
+    pub fn parse(text: &str) -> Result<Self, ron::de::SpannedError> {
+        ron::from_str(text)
+    }
+}
+
+#[derive(Default)]
+pub struct StatusLoader;
+
+impl AssetLoader for StatusLoader {
+    type Asset = StatusFile;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<StatusFile, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(StatusFile::parse(std::str::from_utf8(&bytes)?)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["statuses.ron"]
+    }
+}
+
+/// Every status definition by id
+#[derive(Resource, Debug, Default)]
+pub struct StatusBook {
+    pub by_id: HashMap<String, StatusDef>,
+}
+
+impl StatusBook {
+    pub fn add(&mut self, file: &StatusFile) {
+        for def in &file.statuses {
+            self.by_id.insert(def.id.clone(), def.clone());
+        }
+    }
+}
+
+/// A debuff Cleanse can take off
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CleansePick {
+    Muddled,
+    /// A cleansable status, by id
+    Status(String),
+}
+
+/// Puts a status on the player, or refreshes it if it's already up
+#[derive(Event, Debug, Clone)]
+pub struct ApplyStatusEvent {
+    pub id: String,
+}
+
+impl CombatState {
+    pub fn damage_dealt_mult(&self) -> f32 {
+        self.statuses.iter().map(|s| s.stacked(|m| m.damage_dealt)).product()
+    }
+
+    pub fn damage_taken_mult(&self) -> f32 {
+        self.statuses.iter().map(|s| s.stacked(|m| m.damage_taken)).product()
+    }
+
+    pub fn move_speed_mult(&self) -> f32 {
+        self.statuses.iter().map(|s| s.stacked(|m| m.move_speed)).product()
+    }
+
+    pub fn silenced(&self) -> bool {
+        self.statuses.iter().any(|s| s.def.silence)
+    }
+
+    pub fn pacified(&self) -> bool {
+        self.statuses.iter().any(|s| s.def.pacify)
+    }
+
+    /// What Cleanse can take off, in status row order
+    pub fn cleansable(&self) -> Vec<CleansePick> {
+        let muddled = self.muddled.map(|_| CleansePick::Muddled);
+        let statuses = self.statuses.iter().filter(|s| s.def.cleansable).map(|s| CleansePick::Status(s.def.id.clone()));
+        muddled.into_iter().chain(statuses).collect()
+    }
+
+    /// Takes off the debuff picked when Cleanse was pressed if it's still up, otherwise the
+    /// newest cleansable one
+    pub(super) fn cleanse(&mut self) {
+        match self.cleanse_pick.take() {
+            Some(CleansePick::Muddled) if self.muddled.is_some() => {
+                self.muddled = None;
+                return;
+            }
+            Some(CleansePick::Status(id)) => {
+                if let Some(i) = self.statuses.iter().position(|s| s.def.id == id && s.def.cleansable) {
+                    self.statuses.remove(i);
+                    return;
+                }
+            }
+            _ => {}
+        }
+        let newest = self
+            .statuses
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.def.cleansable)
+            .min_by(|a, b| a.1.age.total_cmp(&b.1.age));
+        match (newest, self.muddled.is_some()) {
+            (Some((_, status)), true) if self.muddled_age <= status.age => self.muddled = None,
+            (Some((i, _)), _) => {
+                self.statuses.remove(i);
+            }
+            (None, true) => self.muddled = None,
+            (None, false) => {}
+        }
+    }
+
+    fn apply_status(&mut self, book: &StatusBook, id: &str) {
+        let Some(def) = book.by_id.get(id) else {
+            warn!("Status {id:?} is not defined");
+            return;
+        };
+        if let Some(active) = self.statuses.iter_mut().find(|s| s.def.id == id) {
+            active.remaining = def.duration;
+            active.age = 0.0;
+            active.stacks = (active.stacks + 1).min(def.max_stacks.max(1));
+            return;
+        }
+        self.statuses.push(ActiveStatus { def: def.clone(), remaining: def.duration, age: 0.0, stacks: 1, tick_accum: 0.0 });
+    }
+}
+
+/// A debuff picked for the next Cleanse, by its place in [`CombatState::cleansable`]
+pub(super) fn pick_cleanse_target(input: Res<SimInput>, mut combat: ResMut<CombatState>) {
+    for action in &input.actions {
+        let SimAction::CleanseTarget(slot) = action else { continue; };
+        combat.cleanse_pick = slot.and_then(|slot| combat.cleansable().into_iter().nth(slot));
+    }
+}
+
+pub fn update_statuses(
+    time: SimTime,
+    abilities: Res<AbilityBook>,
+    book: Res<StatusBook>,
+    mut combat: ResMut<CombatState>,
+    mut used: EventReader<AbilityUsedEvent>,
+    mut applied: EventReader<ApplyStatusEvent>,
+    heal_target: Res<HealTarget>,
+    mut q_player: Query<&mut Health, With<Player>>,
+    mut hit_writer: EventWriter<PlayerDamageEvent>,
+    mut dmg_writer: EventWriter<DamageEvent>,
+    mut callout_writer: EventWriter<CalloutEvent>,
+) {
+    for ev in used.read() {
+        // A heal on a party member lands on them instead, see `party`
+        if ev.id == AbilityId::Heal && heal_target.0.is_some() {
+            continue;
+        }
+        if let Some(id) = abilities.by_id.get(&ev.id).and_then(|a| a.applies) {
+            combat.apply_status(&book, id);
+        }
+    }
+    for ev in applied.read() {
+        combat.apply_status(&book, &ev.id);
+    }
+
+    let dt = time.scaled_delta();
+    let mut effects = Vec::new();
+    for status in &mut combat.statuses {
+        status.remaining -= dt;
+        status.age += dt;
+        if let Some(tick) = &status.def.tick {
+            status.tick_accum += dt;
+            while tick.every > 0.0 && status.tick_accum >= tick.every {
+                status.tick_accum -= tick.every;
+                effects.push(tick.effect.clone());
+            }
+        }
+        if status.remaining <= 0.0 {
+            effects.extend(status.def.on_expire.iter().cloned());
+        }
+    }
+    combat.statuses.retain(|s| s.remaining > 0.0);
+
+    for effect in effects {
+        match effect {
+            StatusEffect::DamagePlayer(amount) => {
+                hit_writer.write(PlayerDamageEvent { amount });
+            }
+            StatusEffect::HealPlayer(amount) => {
+                if let Ok(mut hp) = q_player.single_mut() {
+                    hp.current = (hp.current + amount).min(hp.max);
+                }
+            }
+            StatusEffect::DamageEnemy(amount) => {
+                dmg_writer.write(DamageEvent {
+                    amount,
+                    crit: false,
+                    positional: None,
+                    penetration: 0.0,
+                    shred: None,
+                    target: None,
+                    source: None,
+                    tick: false,
+                });
+            }
+            StatusEffect::Apply(id) => combat.apply_status(&book, &id),
+            StatusEffect::Callout(text) => {
+                callout_writer.write(CalloutEvent { text, waymark: None });
+            }
+        }
+    }
+}
+
+/// Aura while Swiftcast is ready
+const SWIFTCAST_VFX: &str = "swift_swirl";
+/// Aura while Muddled is up
+const MUDDLED_VFX: &str = "muddled_stars";
+
+/// Keeps an aura on the player for every status up that has one
+pub(super) fn show_status_auras(combat: Res<CombatState>, mut q_player: Query<&mut VfxAuras, With<Player>>) {
+    let Ok(mut auras) = q_player.single_mut() else { return; };
+    let mut ids: Vec<&str> = combat.statuses.iter().filter_map(|s| s.def.vfx.as_deref()).collect();
+    if combat.swiftcast_remaining.is_some() {
+        ids.push(SWIFTCAST_VFX);
+    }
+    if combat.muddled.is_some() {
+        ids.push(MUDDLED_VFX);
+    }
+    auras.set(&ids);
+}
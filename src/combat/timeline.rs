@@ -0,0 +1,763 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::encounter::{EncounterDef, EncounterLibrary};
+use super::notes::PullNotes;
+use super::script::{CompiledScripts, ScriptInputs, ScriptState};
+use super::{ApplyStatusEvent, CombatState, HudShakeEvent, MuddledVariant, PlayerDamageEvent};
+use crate::adds::SpawnAddsEvent;
+use crate::console::CommandResult;
+use crate::markers::{MarkerKind, MarkerTarget, ShowMarkerEvent};
+use crate::mechanics::{MechanicDef, SpawnMechanicEvent};
+use crate::settings::Settings;
+use crate::sim_time::SimTime;
+use crate::waymarks::{CalloutEvent, Waymark};
+use crate::world::{Enemy, Health, SpawnHazardEvent};
+use crate::GameState;
+
+// ==== Enemy timeline: named branches, conditional jumps and HP sync points ====
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) enum EnemyEvent {
+    /// Scrambles the hotbar; `intensity` scales how far Wobble drifts the buttons
+    Muddled {
+        duration: f32,
+        #[serde(default)]
+        variant: MuddledVariant,
+        #[serde(default = "EnemyEvent::default_intensity")]
+        intensity: f32,
+    },
+    HudShake { duration: f32 },
+    /// Damage to the player; mitigation and shields apply
+    Hit { amount: i32 },
+    /// The boss announces a cast by name
+    Cast { name: String },
+    Callout {
+        text: String,
+        #[serde(default)]
+        waymark: Option<Waymark>,
+    },
+    Phase {
+        name: String,
+        #[serde(default)]
+        hotbar: Option<String>,
+    },
+    /// Adds join the fight; they count towards `AddsAlive` conditions
+    Spawn { count: u32 },
+    Marker { kind: MarkerKind, target: MarkerTarget, duration: f32 },
+    /// Starts one of the encounter's named ground AoEs
+    Mechanic { name: String },
+    /// Leaves a damaging puddle under the player, for `duration` seconds or the rest of the pull
+    Hazard {
+        radius: f32,
+        damage: i32,
+        #[serde(default)]
+        duration: Option<f32>,
+    },
+    /// Puts a status from the status files on the player, by id
+    Status { id: String },
+    /// Continue in another branch if the condition holds, otherwise carry on
+    Branch { condition: BranchCondition, to: String },
+    /// DPS check: enemy HP has to be below `below` by now. Passing or failing can each send
+    /// the fight to another branch; either way the result goes in the pull's stats
+    Checkpoint {
+        name: String,
+        below: f32,
+        #[serde(default)]
+        pass: Option<String>,
+        #[serde(default)]
+        fail: Option<String>,
+    },
+    Enrage,
+    /// Runs one of the encounter's named scripts, see `script`
+    Script { name: String },
+}
+
+impl EnemyEvent {
+    fn default_intensity() -> f32 {
+        1.0
+    }
+
+    pub(super) fn label(&self) -> String {
+        match self {
+            EnemyEvent::Muddled { variant: MuddledVariant::Wobble, .. } => "Muddled".to_string(),
+            EnemyEvent::Muddled { variant, .. } => format!("Muddled ({variant:?})"),
+            EnemyEvent::HudShake { .. } => "Screen shake".to_string(),
+            EnemyEvent::Hit { amount } => format!("Hit {amount}"),
+            EnemyEvent::Cast { name } => format!("Cast: {name}"),
+            EnemyEvent::Callout { text, .. } => format!("\"{text}\""),
+            EnemyEvent::Phase { name, .. } => format!("Phase: {name}"),
+            EnemyEvent::Spawn { count } => format!("{count} adds"),
+            EnemyEvent::Marker { kind, .. } => format!("{kind:?} marker"),
+            EnemyEvent::Mechanic { name } => name.clone(),
+            EnemyEvent::Hazard { .. } => "Puddle".to_string(),
+            EnemyEvent::Status { id } => format!("Status: {id}"),
+            EnemyEvent::Branch { condition, to } => format!("{} -> {to}", condition.label()),
+            EnemyEvent::Checkpoint { name, below, .. } => format!("{name}: HP < {:.0}%", below * 100.0),
+            EnemyEvent::Enrage => "Enrage".to_string(),
+            EnemyEvent::Script { name } => format!("Script: {name}"),
+        }
+    }
+
+    /// Whether the player gets a heads-up before it; flow control and the boss's own lines don't
+    fn has_callout(&self) -> bool {
+        !matches!(
+            self,
+            EnemyEvent::Callout { .. } | EnemyEvent::Phase { .. } | EnemyEvent::Branch { .. } | EnemyEvent::Script { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum BranchCondition {
+    /// Enemy HP fraction (0..1) is below the value
+    HpBelow(f32),
+    /// Enemy HP fraction (0..1) is at or above the value, e.g. a failed DPS check
+    HpAbove(f32),
+    /// At least one mechanic was failed this pull
+    MechanicFailed,
+    /// At least this many adds are alive
+    AddsAlive(u32),
+    /// Always holds; what a script's `jump` uses
+    Always,
+}
+
+impl BranchCondition {
+    fn holds(&self, ctx: &ConditionContext) -> bool {
+        match *self {
+            BranchCondition::HpBelow(frac) => ctx.hp_frac < frac,
+            BranchCondition::HpAbove(frac) => ctx.hp_frac >= frac,
+            BranchCondition::MechanicFailed => ctx.mechanics_failed > 0,
+            BranchCondition::AddsAlive(n) => ctx.adds_alive >= n,
+            BranchCondition::Always => true,
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            BranchCondition::HpBelow(frac) => format!("HP < {:.0}%", frac * 100.0),
+            BranchCondition::HpAbove(frac) => format!("HP >= {:.0}%", frac * 100.0),
+            BranchCondition::MechanicFailed => "mechanic failed".to_string(),
+            BranchCondition::AddsAlive(n) => format!("{n}+ adds alive"),
+            BranchCondition::Always => "always".to_string(),
+        }
+    }
+}
+
+/// Pull-wide counters that timeline conditions read; other systems keep them updated
+#[derive(Resource, Default, Debug)]
+pub struct EncounterProgress {
+    pub mechanics_failed: u32,
+    pub adds_alive: u32,
+    /// Vulnerability stacks the player picked up from failed mechanics
+    pub vuln_stacks: u32,
+}
+
+struct ConditionContext {
+    hp_frac: f32,
+    mechanics_failed: u32,
+    adds_alive: u32,
+}
+
+/// How a `Checkpoint` event went
+#[derive(Debug, Clone)]
+pub struct CheckpointResult {
+    pub name: String,
+    /// Seconds into the pull
+    pub at: f32,
+    /// Enemy HP fraction at the check, and what it had to be below
+    pub hp_frac: f32,
+    pub below: f32,
+}
+
+impl CheckpointResult {
+    pub fn passed(&self) -> bool {
+        self.hp_frac < self.below
+    }
+}
+
+/// Fired when the encounter moves into a new phase; `hotbar` is the set the encounter suggests
+#[derive(Event, Debug, Clone)]
+pub struct PhaseChangeEvent {
+    pub name: String,
+    pub hotbar: Option<String>,
+}
+
+/// Identifies the encounter being played; per-encounter data (waymarks, ...) is keyed by it
+#[derive(Resource, Debug, Clone)]
+pub struct CurrentEncounter {
+    pub id: String,
+}
+
+impl Default for CurrentEncounter {
+    fn default() -> Self {
+        Self { id: "default".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct TimelineBranch {
+    pub(super) name: String,
+    /// (seconds into the branch, event)
+    pub(super) events: Vec<(f32, EnemyEvent)>,
+}
+
+/// Boss phase gated on enemy HP. Entering a phase drops whatever the timeline was doing
+/// and starts the phase's branch from the top.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct EncounterPhase {
+    name: String,
+    /// Starts once enemy HP drops below this fraction; the opening phase uses 1.0
+    hp_below: f32,
+    branch: String,
+    #[serde(default)]
+    hotbar: Option<String>,
+    /// Enemy sprite tint (linear RGB) and music playback rate while the phase lasts
+    #[serde(default = "EncounterPhase::default_tint")]
+    tint: (f32, f32, f32),
+    #[serde(default = "EncounterPhase::default_music_rate")]
+    music_rate: f64,
+}
+
+impl EncounterPhase {
+    fn default_tint() -> (f32, f32, f32) {
+        (1.0, 1.0, 1.0)
+    }
+
+    fn default_music_rate() -> f64 {
+        1.0
+    }
+}
+
+/// Fired when the boss moves into a new HP phase, for visuals and audio
+#[derive(Event, Debug, Clone)]
+pub struct BossPhaseEvent {
+    pub name: String,
+    pub tint: Color,
+    pub music_rate: f64,
+}
+
+/// How long the boss casts its enrage before it goes off
+pub const ENRAGE_CAST_SECS: f32 = 10.0;
+
+/// The enrage cast finished with the boss still standing: the pull is lost
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EnrageEvent;
+
+/// Jumps to a branch as soon as the condition holds, at most once per pull
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct SyncPoint {
+    condition: BranchCondition,
+    to: String,
+    #[serde(skip)]
+    fired: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct EnemyTimeline {
+    t: f32, // time within the active branch
+    idx: usize,
+    branch: usize,
+    branches: Vec<TimelineBranch>,
+    phases: Vec<EncounterPhase>,
+    phase: usize,
+    syncs: Vec<SyncPoint>,
+    mechanics: Vec<MechanicDef>,
+    scripts: CompiledScripts,
+    pull_t: f32,
+    /// Seconds left on the enrage cast once it has started; nothing else happens meanwhile
+    enrage_cast: Option<f32>,
+    /// (pull time, branch) for every branch entered, so replays can follow the same flow
+    history: Vec<(f32, String)>,
+    /// Checkpoints reached this pull, in order
+    checkpoints: Vec<CheckpointResult>,
+}
+
+impl EnemyTimeline {
+    fn load(&mut self, def: &EncounterDef) {
+        self.branches = def.branches.clone();
+        self.phases = def.phases.clone();
+        self.syncs = def.syncs.clone();
+        self.mechanics = def.mechanics.clone();
+        self.scripts = CompiledScripts::compile(&def.scripts);
+        self.restart();
+    }
+
+    fn restart(&mut self) {
+        self.t = 0.0;
+        self.idx = 0;
+        self.branch = 0;
+        self.phase = 0;
+        self.pull_t = 0.0;
+        self.enrage_cast = None;
+        self.history.clear();
+        self.checkpoints.clear();
+        for sync in &mut self.syncs {
+            sync.fired = false;
+        }
+        if let Some(first) = self.branches.first() {
+            self.history.push((0.0, first.name.clone()));
+        }
+    }
+
+    fn enter_branch(&mut self, name: &str) {
+        let Some(idx) = self.branches.iter().position(|b| b.name == name) else {
+            warn!("Timeline branch {name:?} does not exist");
+            return;
+        };
+        self.branch = idx;
+        self.t = 0.0;
+        self.idx = 0;
+        self.history.push((self.pull_t, self.branches[idx].name.clone()));
+    }
+
+    /// Moves the active branch's clock by `secs` (back if negative). Events jumped over
+    /// forwards don't run; events jumped back over run again
+    fn skip(&mut self, secs: f32) {
+        self.t = (self.t + secs).max(0.0);
+        let t = self.t;
+        if let Some(branch) = self.branches.get(self.branch) {
+            self.idx = branch.events.iter().position(|(at, _)| *at >= t).unwrap_or(branch.events.len());
+        }
+    }
+
+    /// Index of the deepest phase whose HP gate has been passed, if it's past the current one.
+    /// Phases never go backwards, even if the enemy heals.
+    fn due_phase(&self, hp_frac: f32) -> Option<usize> {
+        let due = self.phases.iter().rposition(|p| hp_frac < p.hp_below)?;
+        (due > self.phase).then_some(due)
+    }
+
+    /// Seconds since the pull started. This is the pull's one clock: the meter, reports, buff
+    /// windows, drift and the boss's cleave all read it, after the timeline has run for the tick
+    pub fn pull_time(&self) -> f32 {
+        self.pull_t
+    }
+
+    pub(super) fn checkpoints(&self) -> &[CheckpointResult] {
+        &self.checkpoints
+    }
+
+    fn active_branch_name(&self) -> &str {
+        self.branches.get(self.branch).map(|b| b.name.as_str()).unwrap_or("-")
+    }
+
+    /// Next few events of the active branch as (seconds until, label)
+    fn upcoming(&self, count: usize) -> Vec<(f32, String)> {
+        let Some(branch) = self.branches.get(self.branch) else { return Vec::new(); };
+        branch.events[self.idx.min(branch.events.len())..]
+            .iter()
+            .take(count)
+            .map(|(at, event)| (at - self.t, event.label()))
+            .collect()
+    }
+
+    /// Where the timeline is, for the debug inspector
+    pub(super) fn inspect(&self) -> Vec<String> {
+        let events = self.branches.get(self.branch).map_or(0, |b| b.events.len());
+        let mut lines = vec![
+            format!("Branch {} ({}/{})  t {:.2}", self.active_branch_name(), self.branch + 1, self.branches.len(), self.t),
+            format!("Next event {}/{events}  pull {:.2}s", self.idx.min(events), self.pull_t),
+        ];
+        if let Some(phase) = self.phases.get(self.phase) {
+            lines.push(format!("Phase {} ({}), below {:.0}% HP", phase.name, self.phase + 1, phase.hp_below * 100.0));
+        }
+        if let Some(remaining) = self.enrage_cast {
+            lines.push(format!("Enrage cast {remaining:.2}s"));
+        }
+        for sync in &self.syncs {
+            let state = if sync.fired { "fired" } else { "waiting" };
+            lines.push(format!("Sync {:?} -> {} {state}", sync.condition, sync.to));
+        }
+        for (until, label) in self.upcoming(4) {
+            lines.push(format!("  in {until:>5.2}s  {label}"));
+        }
+        let entered: Vec<String> = self.history.iter().map(|(t, name)| format!("{name}@{t:.1}")).collect();
+        lines.push(format!("Entered {}", entered.join(", ")));
+        lines
+    }
+
+    /// Seconds until the enrage goes off: what's left of its cast, or the active branch's
+    /// Enrage event plus the cast. None when the branch as it stands never gets there.
+    fn enrage_in(&self) -> Option<f32> {
+        if let Some(remaining) = self.enrage_cast {
+            return Some(remaining);
+        }
+        let branch = self.branches.get(self.branch)?;
+        branch.events[self.idx.min(branch.events.len())..]
+            .iter()
+            .find(|(_, event)| matches!(event, EnemyEvent::Enrage))
+            .map(|(at, _)| at - self.t + ENRAGE_CAST_SECS)
+    }
+}
+
+/// Where the timeline is, for co-op: the host's position is sent to the client, which
+/// follows it so both see the same events at the same time
+#[derive(Resource, Debug, Default)]
+pub struct TimelineSync {
+    /// Active branch and seconds into it, as of the last frame
+    pub branch: String,
+    pub t: f32,
+    /// The host's position, set on a client; picked up before the timeline runs
+    pub follow: Option<(String, f32)>,
+}
+
+/// Console: `timeline skip 30` jumps half a minute ahead, `timeline branch <name>` starts a
+/// branch from the top
+pub(super) fn timeline_command(In(args): In<Vec<String>>, mut timeline: ResMut<EnemyTimeline>) -> CommandResult {
+    match args.first().map(String::as_str) {
+        Some("skip") => {
+            let secs = args.get(1).ok_or("skip how many seconds?")?;
+            let secs: f32 = secs.parse().map_err(|_| format!("can't read {secs:?} as seconds"))?;
+            timeline.skip(secs);
+        }
+        Some("branch") if args.len() > 1 => {
+            let name = args[1..].join(" ");
+            if !timeline.branches.iter().any(|b| b.name == name) {
+                return Err(format!("no branch {name:?}"));
+            }
+            timeline.enter_branch(&name);
+        }
+        _ => return Err("usage: timeline skip <seconds> | timeline branch <name>".to_string()),
+    }
+    Ok(format!("Timeline at {:.1}s into {}", timeline.t, timeline.active_branch_name()))
+}
+
+/// Drift from the host's position that's left alone, so the client doesn't stutter
+const FOLLOW_TOLERANCE_SECS: f32 = 0.25;
+
+/// Jumps to the host's branch and time when a client has drifted from it, the way `timeline
+/// skip` does: events jumped over forwards don't run, events jumped back over run again
+pub(super) fn sync_timeline(mut timeline: ResMut<EnemyTimeline>, mut sync: ResMut<TimelineSync>) {
+    if let Some((branch, t)) = sync.follow.take() {
+        if timeline.active_branch_name() != branch {
+            timeline.enter_branch(&branch);
+        }
+        let drift = t - timeline.t;
+        if drift.abs() > FOLLOW_TOLERANCE_SECS {
+            timeline.skip(drift);
+        }
+    }
+    if sync.branch != timeline.active_branch_name() {
+        sync.branch = timeline.active_branch_name().to_string();
+    }
+    sync.t = timeline.t;
+}
+
+/// Things the timeline puts into the arena, grouped to stay under the system param limit
+#[derive(SystemParam)]
+pub(super) struct SpawnWriters<'w> {
+    hazards: EventWriter<'w, SpawnHazardEvent>,
+    adds: EventWriter<'w, SpawnAddsEvent>,
+}
+
+pub(super) fn run_enemy_timeline(
+    time: SimTime,
+    mut timeline: ResMut<EnemyTimeline>,
+    progress: Res<EncounterProgress>,
+    q_enemy: Query<&Health, With<Enemy>>,
+    mut combat: ResMut<CombatState>,
+    mut shake_writer: EventWriter<HudShakeEvent>,
+    mut callout_writer: EventWriter<CalloutEvent>,
+    mut phase_writer: EventWriter<PhaseChangeEvent>,
+    mut marker_writer: EventWriter<ShowMarkerEvent>,
+    mut hit_writer: EventWriter<PlayerDamageEvent>,
+    mut boss_phase_writer: EventWriter<BossPhaseEvent>,
+    mut mechanic_writer: EventWriter<SpawnMechanicEvent>,
+    mut enrage_writer: EventWriter<EnrageEvent>,
+    mut status_writer: EventWriter<ApplyStatusEvent>,
+    mut spawns: SpawnWriters,
+    mut script_inputs: ScriptInputs,
+) {
+    let hp_frac = q_enemy
+        .single()
+        .map(|hp| if hp.max > 0 { hp.current as f32 / hp.max as f32 } else { 0.0 })
+        .unwrap_or(1.0);
+    let ctx = ConditionContext {
+        hp_frac,
+        mechanics_failed: progress.mechanics_failed,
+        adds_alive: progress.adds_alive,
+    };
+
+    let dt = time.scaled_delta();
+    timeline.pull_t += dt;
+    if let Some(remaining) = timeline.enrage_cast.as_mut() {
+        *remaining -= dt;
+        if *remaining <= 0.0 {
+            timeline.enrage_cast = None;
+            enrage_writer.write(EnrageEvent);
+        }
+        return;
+    }
+    timeline.t += dt;
+
+    let synced = timeline
+        .syncs
+        .iter_mut()
+        .find(|s| !s.fired && s.condition.holds(&ctx))
+        .map(|s| {
+            s.fired = true;
+            s.to.clone()
+        });
+    if let Some(to) = synced {
+        timeline.enter_branch(&to);
+    }
+
+    // HP phases win over everything else the timeline had queued
+    if let Some(due) = timeline.due_phase(hp_frac) {
+        timeline.phase = due;
+        let phase = &timeline.phases[due];
+        let (r, g, b) = phase.tint;
+        boss_phase_writer.write(BossPhaseEvent {
+            name: phase.name.clone(),
+            tint: Color::linear_rgb(r, g, b),
+            music_rate: phase.music_rate,
+        });
+        phase_writer.write(PhaseChangeEvent { name: phase.name.clone(), hotbar: phase.hotbar.clone() });
+        // A branch may already have skipped ahead into the phase's branch
+        let branch = phase.branch.clone();
+        if timeline.active_branch_name() != branch {
+            timeline.enter_branch(&branch);
+        }
+    }
+
+    while let Some(branch) = timeline.branches.get(timeline.branch) {
+        let Some((at, event)) = branch.events.get(timeline.idx) else { break; };
+        if timeline.t < *at {
+            break;
+        }
+        let event = event.clone();
+        timeline.idx += 1;
+        // A script stands in for the events it calls
+        let events = match event {
+            EnemyEvent::Script { name } => {
+                let state = ScriptState {
+                    hp_frac,
+                    pull_time: timeline.pull_t,
+                    adds_alive: progress.adds_alive,
+                    mechanics_failed: progress.mechanics_failed,
+                };
+                script_inputs.run(&timeline.scripts, &name, &state)
+            }
+            event => vec![event],
+        };
+        let mut enraged = false;
+        for event in events {
+            match event {
+                EnemyEvent::Muddled { duration, variant, intensity } => {
+                    combat.muddled = Some(duration);
+                    combat.muddled_age = 0.0;
+                    combat.muddled_variant = variant;
+                    combat.muddled_intensity = intensity;
+                }
+                EnemyEvent::HudShake { duration } => {
+                    shake_writer.write(HudShakeEvent(duration));
+                }
+                EnemyEvent::Hit { amount } => {
+                    hit_writer.write(PlayerDamageEvent { amount });
+                }
+                EnemyEvent::Cast { name } => {
+                    callout_writer.write(CalloutEvent { text: format!("Boss casts {name}"), waymark: None });
+                }
+                EnemyEvent::Callout { text, waymark } => {
+                    callout_writer.write(CalloutEvent { text, waymark });
+                }
+                EnemyEvent::Phase { name, hotbar } => {
+                    phase_writer.write(PhaseChangeEvent { name, hotbar });
+                }
+                EnemyEvent::Spawn { count } => {
+                    spawns.adds.write(SpawnAddsEvent { count });
+                }
+                EnemyEvent::Marker { kind, target, duration } => {
+                    marker_writer.write(ShowMarkerEvent { target, kind, duration });
+                }
+                EnemyEvent::Mechanic { name } => match timeline.mechanics.iter().find(|m| m.name == name) {
+                    Some(mechanic) => {
+                        mechanic_writer.write(SpawnMechanicEvent { mechanic: mechanic.clone() });
+                    }
+                    None => warn!("Timeline mechanic {name:?} is not defined in the encounter"),
+                },
+                EnemyEvent::Hazard { radius, damage, duration } => {
+                    spawns.hazards.write(SpawnHazardEvent { radius, damage, duration });
+                }
+                EnemyEvent::Status { id } => {
+                    status_writer.write(ApplyStatusEvent { id });
+                }
+                EnemyEvent::Branch { condition, to } => {
+                    // Never re-enter the active branch, a branch at t=0 would loop forever
+                    if to != timeline.active_branch_name() && condition.holds(&ctx) {
+                        timeline.enter_branch(&to);
+                    }
+                }
+                EnemyEvent::Checkpoint { name, below, pass, fail } => {
+                    let result = CheckpointResult { name, at: timeline.pull_t, hp_frac, below };
+                    let passed = result.passed();
+                    let verdict = if passed { "passed" } else { "failed" };
+                    info!("{} {verdict} at {:.0}% HP (needed < {:.0}%)", result.name, hp_frac * 100.0, below * 100.0);
+                    callout_writer.write(CalloutEvent { text: format!("{} {verdict}", result.name), waymark: None });
+                    timeline.checkpoints.push(result);
+                    let to = if passed { pass } else { fail };
+                    // Same rule as Branch: never re-enter the active branch
+                    if let Some(to) = to.filter(|to| to != timeline.active_branch_name()) {
+                        timeline.enter_branch(&to);
+                    }
+                }
+                EnemyEvent::Enrage => {
+                    callout_writer.write(CalloutEvent { text: "Boss casts Enrage".to_string(), waymark: None });
+                    timeline.enrage_cast = Some(ENRAGE_CAST_SECS);
+                    enraged = true;
+                    break;
+                }
+                // Scripts don't call other scripts
+                EnemyEvent::Script { .. } => {}
+            }
+        }
+        if enraged {
+            break;
+        }
+    }
+}
+
+// ==== Forecast sidebar ====
+
+#[derive(Component)]
+pub(super) struct ForecastSidebar;
+
+pub(super) fn spawn_forecast_sidebar(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont { font_size: 14.0, ..default() },
+        TextColor(Color::linear_rgb(0.85, 0.85, 0.85)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(80.0),
+            right: Val::Px(10.0),
+            width: Val::Px(220.0),
+            ..default()
+        },
+        BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.4)),
+        ForecastSidebar,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+pub(super) fn update_forecast_sidebar(
+    timeline: Res<EnemyTimeline>,
+    notes: Res<PullNotes>,
+    mut q: Query<&mut Text, With<ForecastSidebar>>,
+) {
+    let Ok(mut text) = q.single_mut() else { return; };
+    let phase = timeline.phases.get(timeline.phase).map(|p| p.name.as_str()).unwrap_or("-");
+    let mut out = format!("{phase} / {}\n", timeline.active_branch_name());
+    if let Some(remaining) = timeline.enrage_cast {
+        out.push_str(&format!("ENRAGE {:.1}s\n", remaining.max(0.0)));
+    }
+    for (in_secs, label) in timeline.upcoming(4) {
+        out.push_str(&format!("{:>5.1}s  {}\n", in_secs.max(0.0), label));
+    }
+    if timeline.history.len() > 1 {
+        let path: Vec<&str> = timeline.history.iter().map(|(_, name)| name.as_str()).collect();
+        out.push_str(&format!("Path: {}\n", path.join(" > ")));
+    }
+    if !notes.bookmarks.is_empty() {
+        out.push_str("Notes:\n");
+        for bookmark in &notes.bookmarks {
+            out.push_str(&format!("{:>5.1}s  {}\n", bookmark.t, bookmark.label));
+        }
+    }
+    text.0 = out;
+}
+
+// ==== Enrage timer and mechanic callouts ====
+
+/// Seconds ahead that upcoming mechanics are called out under the enrage timer
+const CALLOUT_LEAD_SECS: f32 = 5.0;
+
+#[derive(Component)]
+pub(super) struct EnrageTimerText;
+
+#[derive(Component)]
+pub(super) struct MechanicCalloutText;
+
+pub(super) fn spawn_enrage_timer(mut commands: Commands) {
+    // Left of the boss HP bar at top center
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(6.0),
+                right: Val::Percent(50.0),
+                margin: UiRect::right(Val::Px(220.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(1.0, 0.45, 0.35)),
+                EnrageTimerText,
+            ));
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 13.0, ..default() },
+                TextColor(Color::linear_rgb(1.0, 0.85, 0.5)),
+                MechanicCalloutText,
+            ));
+        });
+}
+
+pub(super) fn update_enrage_timer(
+    timeline: Res<EnemyTimeline>,
+    settings: Option<Res<Settings>>,
+    mut q_enrage: Query<&mut Text, (With<EnrageTimerText>, Without<MechanicCalloutText>)>,
+    mut q_callouts: Query<&mut Text, With<MechanicCalloutText>>,
+) {
+    if let Ok(mut text) = q_enrage.single_mut() {
+        text.0 = match timeline.enrage_in() {
+            Some(secs) => {
+                let secs = secs.max(0.0).ceil() as u32;
+                format!("Enrage {}:{:02}", secs / 60, secs % 60)
+            }
+            None => String::new(),
+        };
+    }
+    let Ok(mut text) = q_callouts.single_mut() else { return; };
+    if !settings.is_some_and(|s| s.mechanic_callouts) {
+        text.0.clear();
+        return;
+    }
+    let Some(branch) = timeline.branches.get(timeline.branch) else { return; };
+    let lines: Vec<String> = branch.events[timeline.idx.min(branch.events.len())..]
+        .iter()
+        .map(|(at, event)| (at - timeline.t, event))
+        .take_while(|(in_secs, _)| *in_secs <= CALLOUT_LEAD_SECS)
+        .filter(|(_, event)| event.has_callout())
+        .map(|(in_secs, event)| format!("{} in {:.0}s", event.label(), in_secs.max(0.0).ceil()))
+        .collect();
+    text.0 = lines.join("\n");
+}
+
+pub(super) fn reset_encounter_progress(mut progress: ResMut<EncounterProgress>) {
+    *progress = EncounterProgress::default();
+}
+
+/// Sets the timeline up for the encounter picked in the menu
+pub(super) fn load_encounter(
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+    mut timeline: ResMut<EnemyTimeline>,
+) {
+    match library.get(&encounter.id) {
+        Some(def) => timeline.load(def),
+        None => {
+            warn!("No encounters loaded, the enemy timeline is empty");
+            *timeline = EnemyTimeline::default();
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+
+use super::{ActionError, CombatState};
+use crate::adds::Add;
+use crate::player::Player;
+use crate::world::{Arena, CurrentTarget, Enemy, Facing};
+
+/// Whether the player can currently act on their target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetView {
+    InView,
+    /// Further away than abilities reach
+    OutOfRange,
+    /// The target is outside the cone in front of the player
+    FacingAway,
+    /// A pillar stands in between
+    Blocked,
+}
+
+impl TargetView {
+    /// Why a GCD pressed now can't hit the target
+    pub fn error(self) -> Option<ActionError> {
+        match self {
+            TargetView::InView => None,
+            TargetView::OutOfRange => Some(ActionError::OutOfRange),
+            TargetView::FacingAway => Some(ActionError::NotInView),
+            TargetView::Blocked => Some(ActionError::NoLineOfSight),
+        }
+    }
+}
+
+/// Cosine of the half-angle of the cone the player has to face the target within (120° in total)
+const FACING_CONE_COS: f32 = 0.5;
+
+/// How far abilities reach, center to center
+const ABILITY_RANGE: f32 = 400.0;
+
+pub(super) fn track_view(
+    mut combat: ResMut<CombatState>,
+    arena: Res<Arena>,
+    target: Res<CurrentTarget>,
+    q_player: Query<(&Transform, &Facing), With<Player>>,
+    q_targets: Query<&Transform, Or<(With<Enemy>, With<Add>)>>,
+    q_boss: Query<&Transform, With<Enemy>>,
+) {
+    let goal = target.0.and_then(|e| q_targets.get(e).ok()).or(q_boss.single().ok());
+    combat.view = match (q_player.single(), goal) {
+        (Ok((player, facing)), Some(goal)) => {
+            let (from, to) = (player.translation.truncate(), goal.translation.truncate());
+            // Standing right on top of the target counts as facing it
+            let dir = (to - from).normalize_or_zero();
+            if arena.blocks_sight(from, to) {
+                Some(TargetView::Blocked)
+            } else if from.distance(to) > ABILITY_RANGE {
+                Some(TargetView::OutOfRange)
+            } else if dir != Vec2::ZERO && facing.0.normalize_or_zero().dot(dir) < FACING_CONE_COS {
+                Some(TargetView::FacingAway)
+            } else {
+                Some(TargetView::InView)
+            }
+        }
+        _ => None,
+    };
+}
@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+
+use crate::combat::{is_live, AbilityBook, AbilityUsedEvent, CombatState};
+use crate::mechanics::MechanicResolvedEvent;
+use crate::sim_time::SimTime;
+use crate::world::DamageDealtEvent;
+use crate::{persist, GameSet, GameState};
+
+// Combat log: every ability used, hit landed, status gained or lost and mechanic resolved,
+// stamped with seconds into the pull. L shows the window, PageUp/PageDown scroll it and Home
+// goes back to following the newest lines. When a live pull ends the log is written to
+// `combat_logs/` in the user data dir as plain text and as JSON.
+
+pub struct CombatLogPlugin;
+
+impl Plugin for CombatLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CombatLog>()
+            .add_systems(OnEnter(GameState::Playing), (reset_log, spawn_log_window))
+            .add_systems(OnExit(GameState::Playing), export_log.run_if(is_live))
+            .add_systems(
+                FixedUpdate,
+                (log_abilities, log_damage, log_statuses, log_mechanics)
+                    .after(GameSet::Sim)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (scroll_log, update_log_window).chain().in_set(GameSet::Ui).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+const LOG_DIR: &str = "combat_logs";
+/// Lines shown in the window at once
+const VISIBLE_LINES: usize = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogKind {
+    Ability,
+    Damage,
+    StatusGained,
+    StatusLost,
+    Mechanic,
+}
+
+impl LogKind {
+    fn key(self) -> &'static str {
+        match self {
+            LogKind::Ability => "ability",
+            LogKind::Damage => "damage",
+            LogKind::StatusGained => "status_gained",
+            LogKind::StatusLost => "status_lost",
+            LogKind::Mechanic => "mechanic",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Seconds into the pull
+    pub t: f32,
+    pub kind: LogKind,
+    pub text: String,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct CombatLog {
+    pub entries: Vec<LogEntry>,
+    /// Sim time the pull started at
+    started: f32,
+    /// Names of the statuses up last frame
+    statuses: Vec<String>,
+    /// Lines scrolled up from the newest; 0 follows new lines
+    scroll: usize,
+    open: bool,
+}
+
+impl CombatLog {
+    fn push(&mut self, now: f32, kind: LogKind, text: String) {
+        self.entries.push(LogEntry { t: now - self.started, kind, text });
+        // Scrolled back: keep the same lines in view as new ones come in
+        if self.scroll > 0 {
+            self.scroll += 1;
+        }
+    }
+
+    fn to_text(&self) -> String {
+        self.entries.iter().map(|e| format!("{:>8.2} {:<13} {}\n", e.t, e.kind.key(), e.text)).collect()
+    }
+
+    fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|e| format!("  {{\"t\": {:.3}, \"kind\": \"{}\", \"text\": \"{}\"}}", e.t, e.kind.key(), json_escape(&e.text)))
+            .collect();
+        format!("[\n{}\n]\n", entries.join(",\n"))
+    }
+}
+
+pub(crate) fn json_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[derive(Component)]
+struct LogWindow;
+
+#[derive(Component)]
+struct LogText;
+
+fn reset_log(time: SimTime, mut log: ResMut<CombatLog>) {
+    let open = log.open;
+    *log = CombatLog { started: time.elapsed_secs(), open, ..default() };
+}
+
+fn spawn_log_window(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(170.0),
+                right: Val::Px(10.0),
+                width: Val::Px(360.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.03, 0.03, 0.05).with_alpha(0.85)),
+            Visibility::Hidden,
+            LogWindow,
+            StateScoped(GameState::Playing),
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont { font_size: 12.0, ..default() },
+            TextColor(Color::linear_rgb(0.85, 0.85, 0.85)),
+            LogText,
+        ));
+}
+
+fn log_abilities(time: SimTime, book: Res<AbilityBook>, mut evr: EventReader<AbilityUsedEvent>, mut log: ResMut<CombatLog>) {
+    for ev in evr.read() {
+        let name = book.by_id.get(&ev.id).map_or("?", |a| a.name);
+        log.push(time.elapsed_secs(), LogKind::Ability, format!("You use {name}"));
+    }
+}
+
+fn log_damage(time: SimTime, book: Res<AbilityBook>, mut evr: EventReader<DamageDealtEvent>, mut log: ResMut<CombatLog>) {
+    for ev in evr.read() {
+        let source = ev.source.and_then(|id| book.by_id.get(&id)).map_or("Status", |a| a.name);
+        let crit = if ev.crit { " (crit)" } else { "" };
+        log.push(time.elapsed_secs(), LogKind::Damage, format!("{source} hits for {}{crit}", ev.amount));
+    }
+}
+
+/// Compares the statuses on the player with last frame's
+fn log_statuses(time: SimTime, combat: Res<CombatState>, mut log: ResMut<CombatLog>) {
+    let now: Vec<String> = combat.statuses.iter().map(|s| s.def.name.clone()).collect();
+    let prev = std::mem::take(&mut log.statuses);
+    for name in now.iter().filter(|n| !prev.contains(n)) {
+        log.push(time.elapsed_secs(), LogKind::StatusGained, format!("You gain {name}"));
+    }
+    for name in prev.iter().filter(|n| !now.contains(n)) {
+        log.push(time.elapsed_secs(), LogKind::StatusLost, format!("{name} wears off"));
+    }
+    log.statuses = now;
+}
+
+fn log_mechanics(time: SimTime, mut evr: EventReader<MechanicResolvedEvent>, mut log: ResMut<CombatLog>) {
+    for ev in evr.read() {
+        let outcome = match (ev.failed, ev.player_hit) {
+            (true, _) => "failed",
+            (false, true) => "taken",
+            (false, false) => "avoided",
+        };
+        log.push(time.elapsed_secs(), LogKind::Mechanic, format!("{} resolves: {outcome}", ev.name));
+    }
+}
+
+fn scroll_log(keys: Res<ButtonInput<KeyCode>>, mut log: ResMut<CombatLog>) {
+    if keys.just_pressed(KeyCode::KeyL) {
+        log.open = !log.open;
+    }
+    if !log.open {
+        return;
+    }
+    let max_scroll = log.entries.len().saturating_sub(VISIBLE_LINES);
+    if keys.just_pressed(KeyCode::PageUp) {
+        log.scroll = (log.scroll + VISIBLE_LINES / 2).min(max_scroll);
+    }
+    if keys.just_pressed(KeyCode::PageDown) {
+        log.scroll = log.scroll.saturating_sub(VISIBLE_LINES / 2);
+    }
+    if keys.just_pressed(KeyCode::Home) {
+        log.scroll = 0;
+    }
+}
+
+fn update_log_window(
+    log: Res<CombatLog>,
+    mut q_window: Query<&mut Visibility, With<LogWindow>>,
+    mut q_text: Query<&mut Text, With<LogText>>,
+) {
+    if !log.is_changed() {
+        return;
+    }
+    if let Ok(mut vis) = q_window.single_mut() {
+        *vis = if log.open { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    let Ok(mut text) = q_text.single_mut() else { return; };
+    let end = log.entries.len().saturating_sub(log.scroll);
+    let start = end.saturating_sub(VISIBLE_LINES);
+    let mut lines: Vec<String> = log.entries[start..end].iter().map(|e| format!("{:>6.1}  {}", e.t, e.text)).collect();
+    let footer = if log.scroll > 0 { format!("-- {} newer (Home) --", log.scroll) } else { "L hides, PgUp/PgDn scroll".to_string() };
+    lines.push(footer);
+    text.0 = lines.join("\n");
+}
+
+fn export_log(log: Res<CombatLog>) {
+    if log.entries.is_empty() {
+        return;
+    }
+    let stem = persist::next_numbered(LOG_DIR, "log");
+    persist::save(&format!("{stem}.txt"), &log.to_text());
+    persist::save(&format!("{stem}.json"), &log.to_json());
+    info!("Combat log saved as {stem}.txt and .json");
+}
@@ -0,0 +1,278 @@
+use bevy::ecs::system::SystemId;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::{ButtonState, InputSystem};
+use bevy::prelude::*;
+
+use crate::actions::{InputFocus, TextField};
+use crate::combat::PullOrigin;
+use crate::GameSet;
+
+// Developer console: backtick opens a command line over the top of the screen, anywhere in
+// the game, for poking at a fight while building it: `sethp 50%`, `timeline skip 30`,
+// `grant swiftcast`, `spawn add 2`. `help` lists every command. While it's open the
+// keyboard goes to the console only; Up and Down go through earlier commands.
+//
+// Commands live next to the code they poke at. A plugin adds one with
+// `app.add_console_command(name, usage, system)`: the system gets the words typed after
+// the name and returns the reply to print, or an error. Commands that change the fight
+// are added with `add_console_cheat` instead; running one keeps the pull out of the
+// leaderboard and every other record.
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_console_command("help", "help: lists the commands", help)
+            .add_console_command("clear", "clear: empties the console", clear)
+            .add_systems(Startup, spawn_console)
+            .add_systems(
+                PreUpdate,
+                (type_command, run_commands).chain().after(InputSystem).before(GameSet::InputRead),
+            )
+            .add_systems(Update, update_console);
+    }
+}
+
+/// What a console command prints: a reply, or what went wrong
+pub type CommandResult = Result<String, String>;
+
+/// Adds console commands from any plugin
+pub trait ConsoleAppExt {
+    /// `name` is the first word typed; `usage` is its line in `help`
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, CommandResult, M> + 'static,
+    ) -> &mut Self;
+
+    /// A command that changes the fight; a pull it ran in doesn't count
+    fn add_console_cheat<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, CommandResult, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl ConsoleAppExt for App {
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, CommandResult, M> + 'static,
+    ) -> &mut Self {
+        register_command(self, name, usage, system, false)
+    }
+
+    fn add_console_cheat<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, CommandResult, M> + 'static,
+    ) -> &mut Self {
+        register_command(self, name, usage, system, true)
+    }
+}
+
+fn register_command<M>(
+    app: &mut App,
+    name: &'static str,
+    usage: &'static str,
+    system: impl IntoSystem<In<Vec<String>>, CommandResult, M> + 'static,
+    cheat: bool,
+) -> &mut App {
+    let system = app.register_system(system);
+    app.world_mut().get_resource_or_init::<ConsoleCommands>().commands.push(ConsoleCommand { name, usage, system, cheat });
+    app
+}
+
+struct ConsoleCommand {
+    name: &'static str,
+    usage: &'static str,
+    system: SystemId<In<Vec<String>>, CommandResult>,
+    cheat: bool,
+}
+
+#[derive(Resource, Default)]
+struct ConsoleCommands {
+    commands: Vec<ConsoleCommand>,
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+/// Output lines shown, newest at the bottom
+const VISIBLE_LINES: usize = 12;
+const MAX_INPUT_LEN: usize = 80;
+
+#[derive(Resource, Debug, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    /// Commands and replies, oldest first; errors are marked
+    lines: Vec<String>,
+    /// Commands entered, oldest first, and how far back Up has gone
+    history: Vec<String>,
+    recall: Option<usize>,
+    /// Entered this frame, to run once the console lets go of itself
+    pending: Vec<String>,
+}
+
+#[derive(Component)]
+struct ConsoleWindow;
+
+#[derive(Component)]
+struct ConsoleText;
+
+fn spawn_console(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.02, 0.02, 0.04).with_alpha(0.9)),
+            GlobalZIndex(90),
+            Visibility::Hidden,
+            ConsoleWindow,
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont { font_size: 14.0, ..default() },
+            TextColor(Color::linear_rgb(0.8, 0.95, 0.8)),
+            ConsoleText,
+        ));
+}
+
+/// Opens and closes the console and takes typing while it's open. Runs before the game reads
+/// the keyboard, and clears it when the console has it so nothing typed also plays
+fn type_command(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut typed: EventReader<KeyboardInput>,
+    mut focus: ResMut<InputFocus>,
+    mut console: ResMut<Console>,
+) {
+    // While another field has the keyboard the toggle key is just typing
+    let toggled = keys.just_pressed(TOGGLE_KEY) && focus.hold(TextField::Console, true);
+    if toggled {
+        console.open = !console.open;
+    }
+    focus.hold(TextField::Console, console.open);
+    if !console.open {
+        typed.clear();
+        if toggled {
+            keys.reset_all();
+        }
+        return;
+    }
+    for key in typed.read() {
+        if key.state != ButtonState::Pressed || key.key_code == TOGGLE_KEY {
+            continue;
+        }
+        match &key.logical_key {
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if console.input.chars().count() < MAX_INPUT_LEN {
+                        console.input.push(c);
+                    }
+                }
+            }
+            Key::Space if console.input.chars().count() < MAX_INPUT_LEN => console.input.push(' '),
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::Enter => {
+                let line = std::mem::take(&mut console.input).trim().to_string();
+                console.recall = None;
+                if !line.is_empty() {
+                    console.history.push(line.clone());
+                    console.pending.push(line);
+                }
+            }
+            Key::ArrowUp if !console.history.is_empty() => {
+                let idx = console.recall.map_or(console.history.len() - 1, |i| i.saturating_sub(1));
+                console.recall = Some(idx);
+                console.input = console.history[idx].clone();
+            }
+            Key::ArrowDown => {
+                let next = console.recall.map(|i| i + 1).filter(|i| *i < console.history.len());
+                console.recall = next;
+                console.input = next.map(|i| console.history[i].clone()).unwrap_or_default();
+            }
+            Key::Escape => console.open = false,
+            _ => {}
+        }
+    }
+    keys.reset_all();
+}
+
+/// Runs what was entered this frame. Exclusive so each command's system can have the world
+fn run_commands(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in pending {
+        let mut words = line.split_whitespace().map(str::to_string);
+        let name = words.next().unwrap_or_default().to_lowercase();
+        let args: Vec<String> = words.collect();
+        let command = world
+            .get_resource::<ConsoleCommands>()
+            .and_then(|registry| registry.commands.iter().find(|c| c.name == name))
+            .map(|c| (c.system, c.cheat));
+        let reply = match command {
+            Some((system, _)) => world.run_system_with(system, args).unwrap_or_else(|error| Err(error.to_string())),
+            None => Err(format!("unknown command {name:?}, try help")),
+        };
+        if reply.is_ok() && command.is_some_and(|(_, cheat)| cheat) {
+            if let Some(mut origin) = world.get_resource_mut::<PullOrigin>() {
+                if *origin == PullOrigin::Live {
+                    *origin = PullOrigin::Console;
+                }
+            }
+        }
+        let mut console = world.resource_mut::<Console>();
+        // `clear` has just emptied the lines, so its own line doesn't go back in
+        if name != "clear" {
+            console.lines.push(format!("> {line}"));
+        }
+        match reply {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => console.lines.extend(text.lines().map(|l| format!("  {l}"))),
+            Err(error) => console.lines.push(format!("! {error}")),
+        }
+    }
+}
+
+fn help(_: In<Vec<String>>, registry: Res<ConsoleCommands>) -> CommandResult {
+    let mut usages: Vec<&str> = registry.commands.iter().map(|c| c.usage).collect();
+    usages.sort();
+    Ok(usages.join("\n"))
+}
+
+fn clear(_: In<Vec<String>>, mut console: ResMut<Console>) -> CommandResult {
+    console.lines.clear();
+    Ok(String::new())
+}
+
+fn update_console(
+    time: Res<Time<Real>>,
+    console: Res<Console>,
+    mut q_window: Query<&mut Visibility, With<ConsoleWindow>>,
+    mut q_text: Query<&mut Text, With<ConsoleText>>,
+) {
+    if let Ok(mut vis) = q_window.single_mut() {
+        vis.set_if_neq(if console.open { Visibility::Inherited } else { Visibility::Hidden });
+    }
+    if !console.open {
+        return;
+    }
+    let Ok(mut text) = q_text.single_mut() else { return; };
+    let start = console.lines.len().saturating_sub(VISIBLE_LINES);
+    let mut lines: Vec<&str> = console.lines[start..].iter().map(String::as_str).collect();
+    let cursor = if time.elapsed_secs().fract() < 0.5 { "_" } else { " " };
+    let prompt = format!("] {}{cursor}", console.input);
+    lines.push(&prompt);
+    text.0 = lines.join("\n");
+}
@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+use std::net::{SocketAddr, UdpSocket};
+
+use crate::character::Character;
+use crate::combat::TimelineSync;
+use crate::loading::TextureAssets;
+use crate::party::{MemberDebuffs, PartyMember};
+use crate::player::Player;
+use crate::world::{DamageDealtEvent, Enemy, Enmity, Health};
+use crate::{GameSet, GameState};
+
+// Two-player co-op over UDP, off unless the game is started with `--host <port>` or
+// `--join <address:port>`. Both sides run the whole fight themselves with the same encounter;
+// the host's copy is the one that counts. Every frame each side sends where its player is
+// and how they're doing:
+//
+// - both send the player's name, which the partner's party frame shows
+// - the client sends its damage dealt to the boss so far this pull, which the host takes off it
+// - the host sends the boss's HP, with how much of the client's damage is already in it, and
+//   where its timeline is, which the client follows
+//
+// The partner shows up as a party member: a sprite in the arena, a party frame and a line in
+// the boss's enmity list. Their HP is whatever they last sent; hits on them here are only for
+// show. Start both sides on the same encounter and seed (`--seed`), or the fights won't match.
+//
+// Either side can restart the game: once nothing has come in for `TIMEOUT_SECS` the next
+// packet starts a new session, with its sequence numbers counted from scratch, and the host
+// takes a client turning up from a new address with a lower sequence number as the same
+// player back after a restart.
+
+pub struct CoopPlugin;
+
+impl Plugin for CoopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, open_link)
+            .add_systems(OnEnter(GameState::Playing), (reset_link, spawn_partner).run_if(resource_exists::<CoopLink>))
+            .add_systems(
+                Update,
+                exchange_state
+                    .before(GameSet::Ui)
+                    .run_if(resource_exists::<CoopLink>.and(in_state(GameState::Playing))),
+            );
+    }
+}
+
+/// How the game was told to connect, from the command line
+#[derive(Resource, Debug, Clone)]
+pub enum CoopConfig {
+    Host { port: u16 },
+    Join { address: String },
+}
+
+/// Seconds without a packet before the partner is shown as gone
+const TIMEOUT_SECS: f32 = 3.0;
+const PARTNER_TINT: Color = Color::linear_rgb(1.0, 0.8, 0.3);
+/// Big enough for either message
+const PACKET_BYTES: usize = 512;
+
+#[derive(Resource)]
+struct CoopLink {
+    socket: UdpSocket,
+    host: bool,
+    /// The client's address, learned from its first packet (and again after a restart), or
+    /// the host's
+    peer: Option<SocketAddr>,
+    sent: u64,
+    /// Newest packet taken in; older ones arriving late are dropped
+    received: u64,
+    /// Real time the last packet came in
+    heard_at: Option<f32>,
+    /// Damage this side dealt this pull
+    dealt: i64,
+    /// Host: the client's damage already taken off the boss. Client: the share of its own
+    /// damage the host's last boss HP had in it
+    applied: i64,
+}
+
+/// The other player's stand-in
+#[derive(Component)]
+pub struct RemotePlayer;
+
+/// What the other side sent, parsed
+#[derive(Debug, Clone, PartialEq)]
+struct PeerState {
+    seq: u64,
+    name: String,
+    pos: Vec2,
+    hp: i32,
+    max_hp: i32,
+    enmity: f32,
+    /// Client packets: damage dealt this pull
+    dealt: i64,
+    /// Host packets: boss HP, the client damage in it, and the timeline position
+    boss: Option<(i32, i64, f32, String)>,
+}
+
+// Packet format, one line of text:
+// client "c <seq> <x> <y> <hp> <max hp> <enmity> <dealt>\t<name>"
+// host   "h <seq> <x> <y> <hp> <max hp> <enmity> <boss hp> <client damage in it> <t>\t<name>\t<branch>"
+// with the name and branch after tabs since they can have spaces
+impl PeerState {
+    fn to_packet(&self) -> String {
+        let name = self.name.replace('\t', " ");
+        let common =
+            format!("{} {:.1} {:.1} {} {} {:.0}", self.seq, self.pos.x, self.pos.y, self.hp, self.max_hp, self.enmity);
+        match &self.boss {
+            Some((boss_hp, applied, t, branch)) => format!("h {common} {boss_hp} {applied} {t:.3}\t{name}\t{branch}"),
+            None => format!("c {common} {}\t{name}", self.dealt),
+        }
+    }
+}
+
+fn parse_packet(text: &str) -> Option<PeerState> {
+    let mut fields = text.split('\t');
+    let mut parts = fields.next()?.split(' ');
+    let kind = parts.next()?;
+    let mut num = || parts.next()?.parse::<f64>().ok();
+    let (seq, x, y, hp, max_hp, enmity) = (num()?, num()?, num()?, num()?, num()?, num()?);
+    let mut state = PeerState {
+        seq: seq as u64,
+        name: fields.next()?.to_string(),
+        pos: Vec2::new(x as f32, y as f32),
+        hp: hp as i32,
+        max_hp: max_hp as i32,
+        enmity: enmity as f32,
+        dealt: 0,
+        boss: None,
+    };
+    match kind {
+        "c" => state.dealt = num()? as i64,
+        "h" => {
+            let (boss_hp, applied, t) = (num()?, num()?, num()?);
+            state.boss = Some((boss_hp as i32, applied as i64, t as f32, fields.next()?.to_string()));
+        }
+        _ => return None,
+    }
+    Some(state)
+}
+
+fn open_link(mut commands: Commands, config: Option<Res<CoopConfig>>) {
+    let Some(config) = config else { return; };
+    let (bind, peer, host) = match &*config {
+        CoopConfig::Host { port } => (format!("0.0.0.0:{port}"), None, true),
+        CoopConfig::Join { address } => {
+            let Ok(peer) = address.parse::<SocketAddr>() else {
+                warn!("Co-op: can't read {address:?} as address:port");
+                return;
+            };
+            ("0.0.0.0:0".to_string(), Some(peer), false)
+        }
+    };
+    let socket = match UdpSocket::bind(&bind).and_then(|s| s.set_nonblocking(true).map(|_| s)) {
+        Ok(socket) => socket,
+        Err(error) => {
+            warn!("Co-op: couldn't open {bind}: {error}");
+            return;
+        }
+    };
+    info!("Co-op: {} on {bind}", if host { "hosting" } else { "joining" });
+    commands.insert_resource(CoopLink {
+        socket,
+        host,
+        peer,
+        sent: 0,
+        received: 0,
+        heard_at: None,
+        dealt: 0,
+        applied: 0,
+    });
+}
+
+/// Damage counts start over with every pull; the packet numbers carry on
+fn reset_link(mut link: ResMut<CoopLink>) {
+    link.dealt = 0;
+    link.applied = 0;
+}
+
+fn spawn_partner(mut commands: Commands, textures: Res<TextureAssets>) {
+    commands.spawn((
+        Sprite { image: textures.bevy.clone(), color: PARTNER_TINT, custom_size: Some(Vec2::splat(48.0)), ..default() },
+        Transform::from_xyz(0.0, 0.0, 0.95),
+        Visibility::Hidden,
+        RemotePlayer,
+        // Until their first packet says who they are
+        PartyMember { name: "Partner".to_string(), post: Vec2::ZERO, threat: 0.0 },
+        Health { current: 1000, max: 1000 },
+        Enmity::default(),
+        MemberDebuffs::default(),
+        StateScoped(GameState::Playing),
+    ));
+}
+
+fn exchange_state(
+    time: Res<Time<Real>>,
+    character: Res<Character>,
+    mut link: ResMut<CoopLink>,
+    mut sync: ResMut<TimelineSync>,
+    mut dealt: EventReader<DamageDealtEvent>,
+    q_player: Query<(&Transform, &Health, &Enmity), With<Player>>,
+    mut q_partner: Query<
+        (&mut Transform, &mut Health, &mut Enmity, &mut PartyMember, &mut Visibility),
+        (With<RemotePlayer>, Without<Player>),
+    >,
+    mut q_boss: Query<(Entity, &mut Health), (With<Enemy>, Without<Player>, Without<RemotePlayer>)>,
+) {
+    let now = time.elapsed_secs();
+    // Adds have their own HP on each side; only the boss's is shared
+    let boss = q_boss.single().ok().map(|(e, _)| e);
+    link.dealt += dealt.read().filter(|ev| Some(ev.target) == boss).map(|ev| ev.amount as i64).sum::<i64>();
+
+    let mut buf = [0u8; PACKET_BYTES];
+    let mut newest: Option<PeerState> = None;
+    while let Ok((len, from)) = link.socket.recv_from(&mut buf) {
+        let Some(state) = std::str::from_utf8(&buf[..len]).ok().and_then(parse_packet) else { continue; };
+        // Quiet for long enough and whoever turns up next is a new session
+        let quiet = link.heard_at.is_none_or(|at| now - at >= TIMEOUT_SECS);
+        let new_address = link.peer != Some(from);
+        // A restarted client binds a new port and counts from 1 again
+        let restarted = new_address && state.seq < link.received;
+        if link.host && (link.peer.is_none() || quiet || restarted) {
+            if new_address {
+                info!("Co-op: partner joined from {from}");
+            }
+            link.peer = Some(from);
+            link.received = 0;
+        } else if quiet {
+            link.received = 0;
+        }
+        if link.peer != Some(from) || state.seq <= link.received {
+            continue;
+        }
+        link.received = state.seq;
+        link.heard_at = Some(now);
+        newest = Some(state);
+    }
+
+    if let Some(state) = newest {
+        if let Ok((mut transform, mut hp, mut enmity, mut member, _)) = q_partner.single_mut() {
+            transform.translation = state.pos.extend(transform.translation.z);
+            *hp = Health { current: state.hp, max: state.max_hp };
+            enmity.0 = state.enmity;
+            if member.name != state.name {
+                member.name = state.name;
+            }
+        }
+        match state.boss {
+            None => {
+                // Host: the client's new damage comes off the boss. A smaller total means the
+                // client started a new pull
+                if state.dealt < link.applied {
+                    link.applied = 0;
+                }
+                let new_damage = state.dealt - link.applied;
+                link.applied = state.dealt;
+                if let Ok((_, mut boss)) = q_boss.single_mut() {
+                    boss.current = (boss.current as i64 - new_damage).max(0) as i32;
+                }
+            }
+            Some((boss_hp, applied, t, branch)) => {
+                // Client: the host's HP, less what was dealt here since
+                link.applied = applied.min(link.dealt);
+                if let Ok((_, mut boss)) = q_boss.single_mut() {
+                    boss.current = (boss_hp as i64 - (link.dealt - link.applied)).max(0) as i32;
+                }
+                sync.follow = Some((branch, t));
+            }
+        }
+    }
+    let connected = link.heard_at.is_some_and(|at| now - at < TIMEOUT_SECS);
+    if let Ok((.., mut vis)) = q_partner.single_mut() {
+        vis.set_if_neq(if connected { Visibility::Inherited } else { Visibility::Hidden });
+    }
+
+    let Some(peer) = link.peer else { return; };
+    let Ok((transform, hp, enmity)) = q_player.single() else { return; };
+    link.sent += 1;
+    let boss_hp = q_boss.single().map_or(0, |(_, boss)| boss.current);
+    let packet = PeerState {
+        seq: link.sent,
+        name: character.name.clone(),
+        pos: transform.translation.truncate(),
+        hp: hp.current,
+        max_hp: hp.max,
+        enmity: enmity.0,
+        dealt: link.dealt,
+        boss: link.host.then(|| (boss_hp, link.applied, sync.t, sync.branch.clone())),
+    }
+    .to_packet();
+    if let Err(error) = link.socket.send_to(packet.as_bytes(), peer) {
+        // Nobody listening yet is normal until the other side starts
+        if error.kind() != std::io::ErrorKind::ConnectionRefused {
+            warn!("Co-op: send failed: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> PeerState {
+        PeerState {
+            seq: 42,
+            name: "Some Player".to_string(),
+            pos: Vec2::new(-12.5, 80.0),
+            hp: 640,
+            max_hp: 1000,
+            enmity: 1500.0,
+            dealt: 12345,
+            boss: None,
+        }
+    }
+
+    #[test]
+    fn client_packet_round_trips() {
+        let state = client();
+        assert_eq!(parse_packet(&state.to_packet()), Some(state));
+    }
+
+    #[test]
+    fn host_packet_round_trips() {
+        let state = PeerState { dealt: 0, boss: Some((1800, 300, 12.25, "soft enrage".to_string())), ..client() };
+        assert_eq!(parse_packet(&state.to_packet()), Some(state));
+    }
+
+    #[test]
+    fn tabs_in_the_name_become_spaces() {
+        let state = PeerState { name: "Tab\tName".to_string(), ..client() };
+        assert_eq!(parse_packet(&state.to_packet()).map(|s| s.name), Some("Tab Name".to_string()));
+    }
+
+    #[test]
+    fn bad_packets_are_dropped() {
+        assert_eq!(parse_packet(""), None);
+        assert_eq!(parse_packet("x 1 0 0 1 1 0 0\tname"), None);
+        assert_eq!(parse_packet("c 1 0 0 1 1 0\tname"), None);
+        assert_eq!(parse_packet("h 1 0 0 1 1 0 1 0 0.0\tname"), None);
+        assert_eq!(parse_packet("c one 0 0 1 1 0 0\tname"), None);
+    }
+}
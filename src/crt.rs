@@ -0,0 +1,184 @@
+use bevy::core_pipeline::core_2d::graph::{Core2d, Node2d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::image::BevyDefault;
+use bevy::prelude::*;
+use bevy::render::extract_component::{
+    ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin, UniformComponentPlugin,
+};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::view::ViewTarget;
+use bevy::render::RenderApp;
+
+use crate::settings::Settings;
+
+// CRT filter: a full-screen pass over the game camera with scanlines, a little chromatic
+// aberration, a slight bulge like the glass of an old monitor and darker corners, to go with
+// the Y2K effects. Off by default, turned on from the settings panel.
+//
+// The pass runs after tonemapping and before the UI is drawn, so the HUD stays crisp on top of
+// the filtered arena. The shader is `assets/shaders/crt.wgsl`.
+
+pub struct CrtPlugin;
+
+impl Plugin for CrtPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<CrtFilter>::default(),
+            UniformComponentPlugin::<CrtFilter>::default(),
+        ))
+        .add_systems(Update, apply_crt_setting);
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else { return; };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<CrtNode>>(Core2d, CrtLabel)
+            .add_render_graph_edges(Core2d, (Node2d::Tonemapping, CrtLabel, Node2d::EndMainPassPostProcessing));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else { return; };
+        render_app.init_resource::<CrtPipeline>();
+    }
+}
+
+const SHADER_PATH: &str = "shaders/crt.wgsl";
+
+/// On a camera, runs the filter over what it renders. Four floats, which also keeps the uniform
+/// at the 16 bytes WebGL2 wants
+#[derive(Component, Debug, Clone, Copy, ExtractComponent, ShaderType)]
+pub struct CrtFilter {
+    /// How much darker every other row is, 0 to 1
+    pub scanlines: f32,
+    /// Pixels red and blue are pulled apart at the edges
+    pub aberration: f32,
+    /// How far the picture bulges; 0 is flat
+    pub curvature: f32,
+    /// How much darker the corners are, 0 to 1
+    pub vignette: f32,
+}
+
+impl Default for CrtFilter {
+    fn default() -> Self {
+        CrtFilter { scanlines: 0.25, aberration: 1.5, curvature: 0.04, vignette: 0.3 }
+    }
+}
+
+/// Puts the filter on the game camera or takes it off to match the settings
+fn apply_crt_setting(
+    mut commands: Commands,
+    settings: Option<Res<Settings>>,
+    q_cameras: Query<(Entity, Has<CrtFilter>), With<Camera2d>>,
+) {
+    let wanted = settings.is_some_and(|s| s.crt_filter);
+    for (camera, has) in &q_cameras {
+        match (wanted, has) {
+            (true, false) => {
+                commands.entity(camera).insert(CrtFilter::default());
+            }
+            (false, true) => {
+                commands.entity(camera).remove::<CrtFilter>();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct CrtLabel;
+
+#[derive(Default)]
+struct CrtNode;
+
+impl ViewNode for CrtNode {
+    type ViewQuery = (&'static ViewTarget, &'static DynamicUniformIndex<CrtFilter>);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, filter_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let crt = world.resource::<CrtPipeline>();
+        // Still compiling: the frame goes out unfiltered
+        let Some(pipeline) = world.resource::<PipelineCache>().get_render_pipeline(crt.pipeline_id) else {
+            return Ok(());
+        };
+        let Some(filters) = world.resource::<ComponentUniforms<CrtFilter>>().uniforms().binding() else {
+            return Ok(());
+        };
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "crt_bind_group",
+            &crt.layout,
+            &BindGroupEntries::sequential((post_process.source, &crt.sampler, filters.clone())),
+        );
+        let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("crt_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_render_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[filter_index.index()]);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct CrtPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for CrtPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "crt_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<CrtFilter>(true),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+        let shader = world.load_asset(SHADER_PATH);
+        let pipeline_id = world.resource_mut::<PipelineCache>().queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("crt_pipeline".into()),
+            layout: vec![layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                // The camera isn't HDR, so the main texture has the default format
+                targets: vec![Some(ColorTargetState {
+                    format: TextureFormat::bevy_default(),
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+            zero_initialize_workgroup_memory: false,
+        });
+        CrtPipeline { layout, sampler, pipeline_id }
+    }
+}
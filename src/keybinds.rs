@@ -0,0 +1,337 @@
+use bevy::prelude::*;
+
+use crate::actions::{InputFocus, TextField};
+use crate::GameState;
+
+// Keys for the ten hotbar slots, saved with the settings and changed from a panel on the
+// menu: click a slot, then press the new key. Keys that already do something else (another
+// slot, movement, targeting, ...) are refused with a note saying what they're used for.
+
+pub struct KeybindsPlugin;
+
+impl Plugin for KeybindsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Keybinds>()
+            .init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::Menu), setup_keybinds_panel)
+            .add_systems(
+                Update,
+                (click_keybind_buttons, capture_key, update_keybinds_panel).chain().run_if(in_state(GameState::Menu)),
+            );
+    }
+}
+
+const DEFAULT_SLOT_KEYS: [KeyCode; 10] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Digit0,
+];
+
+/// Keys a slot can be bound to, with their on-screen labels
+const BINDABLE: [(KeyCode, &str); 46] = [
+    (KeyCode::Digit1, "1"),
+    (KeyCode::Digit2, "2"),
+    (KeyCode::Digit3, "3"),
+    (KeyCode::Digit4, "4"),
+    (KeyCode::Digit5, "5"),
+    (KeyCode::Digit6, "6"),
+    (KeyCode::Digit7, "7"),
+    (KeyCode::Digit8, "8"),
+    (KeyCode::Digit9, "9"),
+    (KeyCode::Digit0, "0"),
+    (KeyCode::KeyA, "A"),
+    (KeyCode::KeyB, "B"),
+    (KeyCode::KeyC, "C"),
+    (KeyCode::KeyD, "D"),
+    (KeyCode::KeyE, "E"),
+    (KeyCode::KeyF, "F"),
+    (KeyCode::KeyG, "G"),
+    (KeyCode::KeyH, "H"),
+    (KeyCode::KeyI, "I"),
+    (KeyCode::KeyJ, "J"),
+    (KeyCode::KeyK, "K"),
+    (KeyCode::KeyL, "L"),
+    (KeyCode::KeyM, "M"),
+    (KeyCode::KeyN, "N"),
+    (KeyCode::KeyO, "O"),
+    (KeyCode::KeyP, "P"),
+    (KeyCode::KeyQ, "Q"),
+    (KeyCode::KeyR, "R"),
+    (KeyCode::KeyS, "S"),
+    (KeyCode::KeyT, "T"),
+    (KeyCode::KeyU, "U"),
+    (KeyCode::KeyV, "V"),
+    (KeyCode::KeyW, "W"),
+    (KeyCode::KeyX, "X"),
+    (KeyCode::KeyY, "Y"),
+    (KeyCode::KeyZ, "Z"),
+    (KeyCode::Minus, "-"),
+    (KeyCode::Equal, "="),
+    (KeyCode::BracketLeft, "["),
+    (KeyCode::Semicolon, ";"),
+    (KeyCode::Quote, "'"),
+    (KeyCode::Comma, ","),
+    (KeyCode::Period, "."),
+    (KeyCode::Slash, "/"),
+    (KeyCode::Backslash, "\\"),
+    (KeyCode::Backquote, "`"),
+];
+
+/// Bindable keys that are already taken by something other than a hotbar slot
+const RESERVED: [(KeyCode, &str); 9] = [
+    (KeyCode::KeyW, "movement"),
+    (KeyCode::KeyA, "movement"),
+    (KeyCode::KeyS, "movement"),
+    (KeyCode::KeyD, "movement"),
+    (KeyCode::KeyR, "sprint"),
+    (KeyCode::KeyM, "waymark placement"),
+    (KeyCode::KeyN, "bookmarks"),
+    (KeyCode::KeyL, "the combat log"),
+    (KeyCode::Backquote, "the console"),
+];
+
+/// Key for each hotbar slot, in slot order (row 1 then row 2)
+#[derive(Resource, Debug, Clone)]
+pub struct Keybinds {
+    pub slots: [KeyCode; 10],
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Keybinds { slots: DEFAULT_SLOT_KEYS }
+    }
+}
+
+fn key_name(key: KeyCode) -> String {
+    format!("{key:?}")
+}
+
+pub fn key_label(key: KeyCode) -> &'static str {
+    BINDABLE.iter().find(|(k, _)| *k == key).map_or("?", |(_, label)| label)
+}
+
+impl Keybinds {
+    /// Label shown on a slot's button
+    pub fn label(&self, slot: usize) -> &'static str {
+        key_label(self.slots[slot])
+    }
+
+    /// What `key` is already used for, other than `slot` itself
+    pub fn conflict(&self, slot: usize, key: KeyCode) -> Option<String> {
+        if let Some(other) = self.slots.iter().enumerate().position(|(i, k)| i != slot && *k == key) {
+            return Some(format!("slot {}", other + 1));
+        }
+        RESERVED.iter().find(|(k, _)| *k == key).map(|(_, what)| what.to_string())
+    }
+
+    /// Binds `key` to `slot`, or says why it can't
+    pub fn bind(&mut self, slot: usize, key: KeyCode) -> Result<(), String> {
+        if !BINDABLE.iter().any(|(k, _)| *k == key) {
+            return Err(format!("{} can't be bound", key_name(key)));
+        }
+        if let Some(what) = self.conflict(slot, key) {
+            return Err(format!("{} is already used for {what}", key_label(key)));
+        }
+        self.slots[slot] = key;
+        Ok(())
+    }
+
+    /// Slot keys by name, like "KeyQ" or "Digit1", as kept in the settings file
+    pub fn names(&self) -> Vec<String> {
+        self.slots.iter().map(|key| key_name(*key)).collect()
+    }
+
+    /// Reads back [`Keybinds::names`]. Unknown names and keys that clash keep the slot's default
+    pub fn from_names(names: &[String]) -> Self {
+        let mut binds = Self::default();
+        for (slot, name) in names.iter().enumerate().take(binds.slots.len()) {
+            match BINDABLE.iter().find(|(k, _)| key_name(*k) == *name) {
+                Some((key, _)) => binds.slots[slot] = *key,
+                None => warn!("Unknown key {name:?} for slot {}", slot + 1),
+            }
+        }
+        // A hand-edited file could bind one key twice; those slots go back to their defaults
+        for slot in 0..binds.slots.len() {
+            if let Some(what) = binds.conflict(slot, binds.slots[slot]) {
+                warn!("Slot {} key {} clashes with {what}, using the default", slot + 1, binds.label(slot));
+                binds.slots[slot] = DEFAULT_SLOT_KEYS[slot];
+            }
+        }
+        binds
+    }
+}
+
+/// Panel state: whether it's open, which slot waits for a key, and the last result
+#[derive(Resource, Debug, Default)]
+pub struct Rebinding {
+    open: bool,
+    listening: Option<usize>,
+    message: String,
+}
+
+impl Rebinding {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.listening = None;
+    }
+}
+
+#[derive(Component)]
+struct KeybindsPanel;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum KeybindButton {
+    Slot(usize),
+    Reset,
+}
+
+#[derive(Component)]
+struct KeybindLabel(KeybindButton);
+
+#[derive(Component)]
+struct KeybindMessage;
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+const BUTTON_LISTENING: Color = Color::linear_rgb(0.2, 0.3, 0.45);
+
+fn setup_keybinds_panel(mut commands: Commands, mut rebinding: ResMut<Rebinding>) {
+    *rebinding = Rebinding::default();
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(70.0),
+                left: Val::Px(20.0),
+                width: Val::Px(220.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                row_gap: Val::Px(4.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.9)),
+            Visibility::Hidden,
+            KeybindsPanel,
+            StateScoped(GameState::Menu),
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new("Keybinds"),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            let buttons = (0..10).map(KeybindButton::Slot).chain([KeybindButton::Reset]);
+            for button in buttons {
+                panel
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(24.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        button,
+                    ))
+                    .with_child((
+                        Text::new(""),
+                        TextFont { font_size: 14.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                        KeybindLabel(button),
+                    ));
+            }
+            panel.spawn((
+                Text::new(""),
+                TextFont { font_size: 12.0, ..default() },
+                TextColor(Color::linear_rgb(1.0, 0.6, 0.4)),
+                KeybindMessage,
+            ));
+        });
+}
+
+fn click_keybind_buttons(
+    mut binds: ResMut<Keybinds>,
+    mut rebinding: ResMut<Rebinding>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &KeybindButton), Changed<Interaction>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                KeybindButton::Slot(slot) => {
+                    rebinding.listening = Some(*slot);
+                    rebinding.message = format!("Press a key for slot {} (Esc cancels)", slot + 1);
+                }
+                KeybindButton::Reset => {
+                    *binds = Keybinds::default();
+                    rebinding.listening = None;
+                    rebinding.message = "Back to the default keys".to_string();
+                }
+            },
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn capture_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut focus: ResMut<InputFocus>,
+    mut binds: ResMut<Keybinds>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    if !focus.hold(TextField::Keybind, rebinding.listening.is_some()) {
+        return;
+    }
+    let Some(slot) = rebinding.listening else { return; };
+    let Some(key) = keys.get_just_pressed().next().copied() else { return; };
+    rebinding.listening = None;
+    rebinding.message = if key == KeyCode::Escape {
+        String::new()
+    } else {
+        match binds.bind(slot, key) {
+            Ok(()) => format!("Slot {} is now {}", slot + 1, key_label(key)),
+            Err(error) => error,
+        }
+    };
+}
+
+fn update_keybinds_panel(
+    binds: Res<Keybinds>,
+    rebinding: Res<Rebinding>,
+    mut q_panel: Query<&mut Visibility, With<KeybindsPanel>>,
+    mut q_labels: Query<(&mut Text, &KeybindLabel), Without<KeybindMessage>>,
+    mut q_message: Query<&mut Text, With<KeybindMessage>>,
+    mut q_buttons: Query<(&KeybindButton, &mut BackgroundColor, &Interaction)>,
+) {
+    if !binds.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    if let Ok(mut vis) = q_panel.single_mut() {
+        *vis = if rebinding.open { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    for (mut text, label) in &mut q_labels {
+        text.0 = match label.0 {
+            KeybindButton::Slot(slot) if rebinding.listening == Some(slot) => format!("Slot {}: ...", slot + 1),
+            KeybindButton::Slot(slot) => format!("Slot {}: {}", slot + 1, binds.label(slot)),
+            KeybindButton::Reset => "Reset to defaults".to_string(),
+        };
+    }
+    if let Ok(mut text) = q_message.single_mut() {
+        text.0 = rebinding.message.clone();
+    }
+    for (button, mut color, interaction) in &mut q_buttons {
+        if *interaction == Interaction::None {
+            let listening = matches!(button, KeybindButton::Slot(slot) if rebinding.listening == Some(*slot));
+            color.0 = if listening { BUTTON_LISTENING } else { BUTTON_NORMAL };
+        }
+    }
+}
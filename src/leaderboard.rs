@@ -0,0 +1,469 @@
+use bevy::prelude::*;
+
+use crate::combat::{is_live, CurrentEncounter, EncounterLibrary, PullResult};
+use crate::{persist, GameState};
+
+// Personal leaderboard: the best pulls per encounter by DPS, kept in the user data dir. Every
+// live pull that reaches the results screen is offered to it. The leaderboard screen, opened from
+// the menu, shows one encounter at a time (Left/Right for the others), and can clear that
+// encounter's records or export every record as CSV.
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Leaderboard>()
+            .init_resource::<LeaderboardView>()
+            .add_systems(Startup, load_leaderboard)
+            .add_systems(OnEnter(GameState::Results), record_result.run_if(is_live))
+            .add_systems(OnEnter(GameState::Leaderboard), setup_leaderboard)
+            .add_systems(
+                Update,
+                (browse_leaderboard, update_leaderboard).chain().run_if(in_state(GameState::Leaderboard)),
+            );
+    }
+}
+
+const LEADERBOARD_FILE: &str = "leaderboard.txt";
+const EXPORT_FILE: &str = "leaderboard_export.csv";
+/// Records kept per encounter, best first
+const RECORDS_PER_ENCOUNTER: usize = 10;
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+
+#[derive(Debug, Clone)]
+struct Record {
+    encounter: String,
+    dps: f32,
+    /// Uptime of the best-kept DoT, 0 to 1
+    uptime: f32,
+    grade: String,
+    /// YYYY-MM-DD
+    date: String,
+    duration: f32,
+    cleared: bool,
+}
+
+impl Record {
+    // File format: one record per line,
+    // "<dps> <uptime percent> <grade> <date> <seconds> <cleared yes|no> <encounter>". The encounter goes last
+    // and takes the rest of the line, since imported encounters are named after their files and can have spaces.
+    // Lines from before that, with the encounter first, still load.
+    fn to_line(&self) -> String {
+        format!(
+            "{:.1} {:.0} {} {} {:.1} {} {}",
+            self.dps,
+            self.uptime * 100.0,
+            self.grade,
+            self.date,
+            self.duration,
+            if self.cleared { "yes" } else { "no" },
+            self.encounter
+        )
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(7, ' ');
+        let first = parts.next()?;
+        let Ok(dps) = first.parse() else { return Self::parse_old(line); };
+        let record = Record {
+            dps,
+            uptime: parts.next()?.parse::<f32>().ok()? / 100.0,
+            grade: parts.next()?.to_string(),
+            date: parts.next()?.to_string(),
+            duration: parts.next()?.parse().ok()?,
+            cleared: parts.next()? == "yes",
+            encounter: parts.next().filter(|e| !e.is_empty())?.to_string(),
+        };
+        Some(record)
+    }
+
+    /// A line in the old encounter-first format
+    fn parse_old(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let record = Record {
+            encounter: parts.next()?.to_string(),
+            dps: parts.next()?.parse().ok()?,
+            uptime: parts.next()?.parse::<f32>().ok()? / 100.0,
+            grade: parts.next()?.to_string(),
+            date: parts.next()?.to_string(),
+            duration: parts.next()?.parse().ok()?,
+            cleared: parts.next()? == "yes",
+        };
+        Some(record)
+    }
+
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{:.1},{:.0},{},{},{:.1},{}",
+            csv_field(&self.encounter),
+            self.dps,
+            self.uptime * 100.0,
+            csv_field(&self.grade),
+            csv_field(&self.date),
+            self.duration,
+            self.cleared
+        )
+    }
+}
+
+/// A CSV field, quoted when it holds a comma, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Best records of every encounter, each encounter's sorted by DPS
+#[derive(Resource, Debug, Default)]
+struct Leaderboard {
+    records: Vec<Record>,
+}
+
+impl Leaderboard {
+    fn for_encounter<'a>(&'a self, encounter: &'a str) -> impl Iterator<Item = &'a Record> {
+        self.records.iter().filter(move |r| r.encounter == encounter)
+    }
+
+    /// Adds the record if it makes the encounter's top list; whether it did
+    fn add(&mut self, record: Record) -> bool {
+        let worst = self.for_encounter(&record.encounter).nth(RECORDS_PER_ENCOUNTER - 1);
+        if worst.is_some_and(|worst| record.dps <= worst.dps) {
+            return false;
+        }
+        let encounter = record.encounter.clone();
+        self.records.push(record);
+        self.sort();
+        let mut kept = 0;
+        self.records.retain(|r| {
+            if r.encounter == encounter {
+                kept += 1;
+            }
+            r.encounter != encounter || kept <= RECORDS_PER_ENCOUNTER
+        });
+        true
+    }
+
+    fn sort(&mut self) {
+        self.records.sort_by(|a, b| a.encounter.cmp(&b.encounter).then(b.dps.total_cmp(&a.dps)));
+    }
+
+    fn clear(&mut self, encounter: &str) {
+        self.records.retain(|r| r.encounter != encounter);
+    }
+
+    fn save(&self) {
+        let out: String = self.records.iter().map(|r| r.to_line() + "\n").collect();
+        persist::save(LEADERBOARD_FILE, &out);
+    }
+}
+
+/// Days since 1970-01-01 as a calendar date, proleptic Gregorian
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Seconds since 1970, from the browser's clock on the web where there's no system time
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_secs() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn unix_secs() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// Today's date in UTC
+fn today() -> String {
+    let secs = unix_secs();
+    let (year, month, day) = civil_date((secs / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn load_leaderboard(mut leaderboard: ResMut<Leaderboard>) {
+    let Some(contents) = persist::load(LEADERBOARD_FILE) else { return; };
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        match Record::parse(line) {
+            Some(record) => leaderboard.records.push(record),
+            None => warn!("Bad {LEADERBOARD_FILE} line {line:?}"),
+        }
+    }
+    leaderboard.sort();
+}
+
+fn record_result(result: Res<PullResult>, mut leaderboard: ResMut<Leaderboard>) {
+    let record = Record {
+        encounter: result.encounter.clone(),
+        dps: result.dps(),
+        uptime: result.dot_uptime(),
+        grade: result.grade().to_string(),
+        date: today(),
+        duration: result.duration,
+        cleared: result.cleared_before_enrage,
+    };
+    if leaderboard.add(record) {
+        info!("New leaderboard record for {}: {:.1} DPS", result.encounter, result.dps());
+        leaderboard.save();
+    }
+}
+
+/// Which encounter the screen shows, and the last thing that happened
+#[derive(Resource, Debug, Default)]
+struct LeaderboardView {
+    encounter: String,
+    status: String,
+    /// Clear was clicked once; the next click clears
+    confirm_clear: bool,
+}
+
+#[derive(Component)]
+struct LeaderboardText;
+
+#[derive(Component, Clone, Copy)]
+enum LeaderboardButton {
+    Previous,
+    Next,
+    Clear,
+    Export,
+    Back,
+}
+
+impl LeaderboardButton {
+    fn label(self) -> &'static str {
+        match self {
+            LeaderboardButton::Previous => "<",
+            LeaderboardButton::Next => ">",
+            LeaderboardButton::Clear => "Clear",
+            LeaderboardButton::Export => "Export CSV",
+            LeaderboardButton::Back => "Back",
+        }
+    }
+}
+
+/// Encounters to page through: the loaded ones, then any that only have records
+fn encounter_ids(library: &EncounterLibrary, leaderboard: &Leaderboard) -> Vec<String> {
+    let mut ids: Vec<String> = library.encounters.iter().filter(|e| !e.dummy).map(|e| e.id.clone()).collect();
+    for record in &leaderboard.records {
+        if !ids.contains(&record.encounter) {
+            ids.push(record.encounter.clone());
+        }
+    }
+    ids
+}
+
+fn setup_leaderboard(mut commands: Commands, encounter: Res<CurrentEncounter>, mut view: ResMut<LeaderboardView>) {
+    *view = LeaderboardView { encounter: encounter.id.clone(), ..default() };
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            StateScoped(GameState::Leaderboard),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                TextLayout::new_with_justify(JustifyText::Center),
+                LeaderboardText,
+            ));
+            root.spawn(Node { flex_direction: FlexDirection::Row, column_gap: Val::Px(10.0), ..default() })
+                .with_children(|row| {
+                    for button in [
+                        LeaderboardButton::Previous,
+                        LeaderboardButton::Next,
+                        LeaderboardButton::Clear,
+                        LeaderboardButton::Export,
+                        LeaderboardButton::Back,
+                    ] {
+                        row.spawn((
+                            Button,
+                            Node {
+                                height: Val::Px(36.0),
+                                padding: UiRect::horizontal(Val::Px(12.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_NORMAL),
+                            button,
+                        ))
+                        .with_child((
+                            Text::new(button.label()),
+                            TextFont { font_size: 18.0, ..default() },
+                            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                        ));
+                    }
+                });
+        });
+}
+
+fn browse_leaderboard(
+    keys: Res<ButtonInput<KeyCode>>,
+    library: Res<EncounterLibrary>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut view: ResMut<LeaderboardView>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut q_buttons: Query<(&Interaction, &mut BackgroundColor, &LeaderboardButton), Changed<Interaction>>,
+) {
+    let mut pressed = None;
+    for (interaction, mut color, button) in &mut q_buttons {
+        match *interaction {
+            Interaction::Pressed => pressed = Some(*button),
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        pressed = Some(LeaderboardButton::Previous);
+    }
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        pressed = Some(LeaderboardButton::Next);
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        pressed = Some(LeaderboardButton::Back);
+    }
+    let Some(pressed) = pressed else { return; };
+    if !matches!(pressed, LeaderboardButton::Clear) {
+        view.confirm_clear = false;
+    }
+    match pressed {
+        LeaderboardButton::Previous | LeaderboardButton::Next => {
+            let ids = encounter_ids(&library, &leaderboard);
+            if ids.is_empty() {
+                return;
+            }
+            let at = ids.iter().position(|id| *id == view.encounter).unwrap_or(0);
+            let step = if matches!(pressed, LeaderboardButton::Next) { 1 } else { ids.len() - 1 };
+            view.encounter.clone_from(&ids[(at + step) % ids.len()]);
+            view.status.clear();
+        }
+        LeaderboardButton::Clear if view.confirm_clear => {
+            let encounter = view.encounter.clone();
+            leaderboard.clear(&encounter);
+            leaderboard.save();
+            view.confirm_clear = false;
+            view.status = "Records cleared".to_string();
+        }
+        LeaderboardButton::Clear => {
+            view.confirm_clear = true;
+            view.status = "Click Clear again to delete this encounter's records".to_string();
+        }
+        LeaderboardButton::Export => {
+            let mut out = "encounter,dps,uptime,grade,date,duration,cleared\n".to_string();
+            for record in &leaderboard.records {
+                out.push_str(&record.to_csv());
+                out.push('\n');
+            }
+            persist::save(EXPORT_FILE, &out);
+            view.status = format!("Exported {} records to {EXPORT_FILE}", leaderboard.records.len());
+        }
+        LeaderboardButton::Back => next_state.set(GameState::Menu),
+    }
+}
+
+fn update_leaderboard(
+    library: Res<EncounterLibrary>,
+    leaderboard: Res<Leaderboard>,
+    view: Res<LeaderboardView>,
+    mut q_text: Query<&mut Text, With<LeaderboardText>>,
+) {
+    if !view.is_changed() && !leaderboard.is_changed() {
+        return;
+    }
+    let Ok(mut text) = q_text.single_mut() else { return; };
+    let name = library.get(&view.encounter).map_or(view.encounter.as_str(), |e| e.name.as_str());
+    let mut out = format!("Leaderboard: {name}\n\n");
+    let mut any = false;
+    for (rank, record) in leaderboard.for_encounter(&view.encounter).enumerate() {
+        any = true;
+        out.push_str(&format!(
+            "{}. {:.1} DPS  {} uptime {:.0}%  {:.1}s{}  {}\n",
+            rank + 1,
+            record.dps,
+            record.grade,
+            record.uptime * 100.0,
+            record.duration,
+            if record.cleared { "" } else { " (enraged)" },
+            record.date
+        ));
+    }
+    if !any {
+        out.push_str("No records yet\n");
+    }
+    out.push('\n');
+    out.push_str(&view.status);
+    text.0 = out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(encounter: &str) -> Record {
+        Record {
+            encounter: encounter.to_string(),
+            dps: 1234.5,
+            uptime: 0.87,
+            grade: "A".to_string(),
+            date: "2026-10-15".to_string(),
+            duration: 312.4,
+            cleared: true,
+        }
+    }
+
+    #[test]
+    fn line_round_trips_with_spaces_in_the_encounter() {
+        let original = record("import-My Fight");
+        let parsed = Record::parse(&original.to_line()).unwrap();
+        assert_eq!(parsed.encounter, "import-My Fight");
+        assert_eq!(parsed.dps, original.dps);
+        assert_eq!(parsed.uptime, original.uptime);
+        assert_eq!(parsed.grade, original.grade);
+        assert_eq!(parsed.date, original.date);
+        assert_eq!(parsed.duration, original.duration);
+        assert!(parsed.cleared);
+    }
+
+    #[test]
+    fn old_lines_still_load() {
+        let parsed = Record::parse("trial 1234.5 87 A 2026-10-15 312.4 no").unwrap();
+        assert_eq!(parsed.encounter, "trial");
+        assert_eq!(parsed.dps, 1234.5);
+        assert!(!parsed.cleared);
+    }
+
+    #[test]
+    fn bad_lines_are_dropped() {
+        assert!(Record::parse("").is_none());
+        assert!(Record::parse("1234.5 87 A 2026-10-15 312.4 yes").is_none());
+        assert!(Record::parse("1234.5 eighty A 2026-10-15 312.4 yes trial").is_none());
+    }
+
+    #[test]
+    fn csv_quotes_fields_with_commas_and_quotes() {
+        let line = record("import-Boss, \"hard\"").to_csv();
+        assert_eq!(line, "\"import-Boss, \"\"hard\"\"\",1234.5,87,A,2026-10-15,312.4,true");
+    }
+}
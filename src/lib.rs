@@ -1,25 +1,95 @@
-#![allow(clippy::type_complexity)]
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
 
+mod achievements;
+mod adds;
 mod actions;
 mod audio;
+mod background;
+mod battle_text;
+mod budget;
+mod calibration;
+mod camera_fx;
+mod capture;
+mod character;
+mod keybinds;
+mod leaderboard;
 mod loading;
+mod markers;
+mod mechanics;
+mod missing_assets;
+mod nameplates;
+mod overlay;
+mod mistakes;
 mod menu;
+mod party;
+mod pause;
+mod picker;
 mod player;
+mod replay;
+mod results;
 mod combat;
+mod combat_log;
+mod console;
+mod coop;
+mod crt;
 mod world;
 mod vfx;
+mod persist;
+mod rng;
+mod settings;
+mod sim_time;
+mod unit_frames;
+mod waymarks;
 
-use crate::actions::ActionsPlugin;
+pub mod testing;
+
+pub use crate::coop::CoopConfig;
+pub use crate::overlay::StreamOverlay;
+pub use crate::rng::GameRng;
+
+use crate::achievements::AchievementsPlugin;
+use crate::actions::{ActionSet, ActionsPlugin};
+use crate::adds::AddsPlugin;
 use crate::audio::InternalAudioPlugin;
+use crate::background::BackgroundPlugin;
+use crate::calibration::CalibrationPlugin;
+use crate::camera_fx::CameraFxPlugin;
+use crate::capture::CapturePlugin;
+use crate::character::CharacterPlugin;
+use crate::keybinds::KeybindsPlugin;
+use crate::leaderboard::LeaderboardPlugin;
 use crate::loading::LoadingPlugin;
+use crate::markers::MarkersPlugin;
+use crate::mechanics::MechanicsPlugin;
+use crate::missing_assets::MissingAssetsPlugin;
+use crate::mistakes::MistakesPlugin;
 use crate::menu::MenuPlugin;
+use crate::party::PartyPlugin;
+use crate::pause::PausePlugin;
+use crate::persist::PersistPlugin;
+use crate::nameplates::NameplatesPlugin;
+use crate::overlay::OverlayPlugin;
+use crate::picker::PickerPlugin;
 use crate::player::PlayerPlugin;
+use crate::replay::ReplayPlugin;
+use crate::results::ResultsPlugin;
+use crate::rng::RngPlugin;
+use crate::battle_text::BattleTextPlugin;
+use crate::budget::BudgetPlugin;
+use crate::settings::SettingsPlugin;
+use crate::sim_time::SimTimePlugin;
 use crate::combat::CombatPlugin;
+use crate::combat_log::CombatLogPlugin;
+use crate::console::ConsolePlugin;
+use crate::coop::CoopPlugin;
+use crate::crt::CrtPlugin;
+use crate::unit_frames::UnitFramesPlugin;
 use crate::world::WorldPlugin;
 use crate::vfx::VfxPlugin;
+use crate::waymarks::WaymarksPlugin;
 
 use bevy::app::App;
-#[cfg(debug_assertions)]
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 
@@ -27,6 +97,7 @@ use bevy::prelude::*;
 // See https://bevy-cheatbook.github.io/programming/states.html
 // Or https://github.com/bevyengine/bevy/blob/main/examples/ecs/state.rs
 #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
+#[states(scoped_entities)]
 enum GameState {
     // During the loading State the LoadingPlugin will load our assets
     #[default]
@@ -35,6 +106,28 @@ enum GameState {
     Playing,
     // Here the menu is drawn and waiting for player interaction
     Menu,
+    // After a pull ends: the outcome and a way back in. Everything tagged
+    // `StateScoped(GameState::Playing)` is gone by now
+    Results,
+    // Tapping along to a beat to measure audio and display lag, opened from the menu
+    Calibration,
+    // Moving the picked encounter's timeline events around, opened from the menu
+    Editor,
+    // Best pulls per encounter, opened from the menu
+    Leaderboard,
+    // One frame between a pull and its restart: leaving Playing tears the pull down and
+    // entering it again sets up a fresh one
+    Restarting,
+}
+
+// While playing: the pull runs, or it sits frozen under the pause menu
+#[derive(SubStates, Default, Clone, Eq, PartialEq, Debug, Hash)]
+#[source(GameState = GameState::Playing)]
+#[states(scoped_entities)]
+enum PlayState {
+    #[default]
+    Running,
+    Paused,
 }
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
@@ -47,15 +140,32 @@ pub enum GameSet {
 
 pub struct GamePlugin;
 
+// Input is read every frame in `PreUpdate`; the sim takes it in and runs on fixed ticks,
+// and the HUD catches up once a frame in `Update`
+fn configure_game_sets(app: &mut App) {
+    app.configure_sets(PreUpdate, GameSet::InputRead.run_if(actions::keyboard_free))
+        .configure_sets(
+            FixedUpdate,
+            (
+                (ActionSet::Collect, ActionSet::Record, ActionSet::Apply).chain().in_set(GameSet::InputApply),
+                GameSet::InputApply.before(GameSet::Sim).run_if(pull_continues),
+                GameSet::Sim.run_if(pull_continues),
+            ),
+        )
+        .configure_sets(Update, GameSet::Ui);
+}
+
+/// Run condition: no state change is pending, so a pull that ended on one tick doesn't go
+/// on through the rest of the frame's ticks
+fn pull_continues(next: Res<NextState<GameState>>) -> bool {
+    matches!(*next, NextState::Unchanged)
+}
+
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<GameState>()
-            .configure_sets(
-                PreUpdate,
-                (GameSet::InputRead, GameSet::InputApply.after(GameSet::InputRead)),
-            )
-            .configure_sets(Update, (GameSet::Sim, GameSet::Ui.after(GameSet::Sim)))
-            .add_plugins((
+        app.init_state::<GameState>().add_sub_state::<PlayState>();
+        configure_game_sets(app);
+        app.add_plugins((
             LoadingPlugin,
             MenuPlugin,
             ActionsPlugin,
@@ -64,9 +174,47 @@ impl Plugin for GamePlugin {
             CombatPlugin,
             WorldPlugin,
             VfxPlugin,
+            WaymarksPlugin,
+            MarkersPlugin,
+            MechanicsPlugin,
+            SimTimePlugin,
+            RngPlugin,
+            AchievementsPlugin,
+            // Nested, as a tuple of plugins takes at most 15
+            (
+                PartyPlugin,
+                ResultsPlugin,
+                BackgroundPlugin,
+                AddsPlugin,
+                CharacterPlugin,
+                CalibrationPlugin,
+                MistakesPlugin,
+                MissingAssetsPlugin,
+                PickerPlugin,
+                PersistPlugin,
+                KeybindsPlugin,
+                ReplayPlugin,
+                CombatLogPlugin,
+                PausePlugin,
+                (
+                    SettingsPlugin,
+                    UnitFramesPlugin,
+                    BattleTextPlugin,
+                    NameplatesPlugin,
+                    LeaderboardPlugin,
+                    CoopPlugin,
+                    OverlayPlugin,
+                    CapturePlugin,
+                    ConsolePlugin,
+                    BudgetPlugin,
+                    CrtPlugin,
+                    CameraFxPlugin,
+                ),
+            ),
         ));
 
-        #[cfg(debug_assertions)]
+        // Logging frame times every second floods the browser console
+        #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
         {
             app.add_plugins((
                 FrameTimeDiagnosticsPlugin::default(),
@@ -1,3 +1,10 @@
+use crate::combat::{
+    import_logs, EncounterDef, EncounterLibrary, EncounterLoader, StatusBook, StatusFile, StatusLoader,
+    USER_ENCOUNTER_DIR,
+};
+use crate::persist;
+use crate::settings::load_settings;
+use crate::vfx::{VfxFile, VfxLibrary, VfxLoader};
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
@@ -10,12 +17,34 @@ pub struct LoadingPlugin;
 /// If interested, take a look at <https://bevy-cheatbook.github.io/features/assets.html>
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading)
-                .continue_to_state(GameState::Menu)
-                .load_collection::<AudioAssets>()
-                .load_collection::<TextureAssets>(),
-        );
+        app.add_systems(Startup, load_settings)
+            .init_asset::<EncounterDef>()
+            .init_asset_loader::<EncounterLoader>()
+            .init_asset::<StatusFile>()
+            .init_asset_loader::<StatusLoader>()
+            .init_asset::<VfxFile>()
+            .init_asset_loader::<VfxLoader>()
+            .add_loading_state(
+                LoadingState::new(GameState::Loading)
+                    .continue_to_state(GameState::Menu)
+                    // A missing file shouldn't leave the game stuck here; see `missing_assets`
+                    .on_failure_continue_to_state(GameState::Menu)
+                    .load_collection::<AudioAssets>()
+                    .load_collection::<TextureAssets>()
+                    .load_collection::<EncounterAssets>()
+                    .load_collection::<StatusAssets>()
+                    .load_collection::<VfxAssets>(),
+            )
+            .add_systems(
+                OnExit(GameState::Loading),
+                (init_failed_collections, (fill_encounter_library, fill_status_book, fill_vfx_library)).chain(),
+            )
+            // With the `dev` feature the asset files are watched; an edited VFX file comes back
+            // through here and its effects replace the old ones
+            .add_systems(
+                Update,
+                fill_vfx_library.run_if(resource_exists::<VfxAssets>.and(on_event::<AssetEvent<VfxFile>>)),
+            );
     }
 }
 
@@ -42,3 +71,68 @@ pub struct TextureAssets {
     #[asset(path = "textures/y2kstar.png")]
     pub y2k_star: Handle<Image>,
 }
+
+#[derive(AssetCollection, Resource)]
+pub struct EncounterAssets {
+    #[asset(
+        paths("encounters/default.encounter.ron", "encounters/gauntlet.encounter.ron", "encounters/dummy.encounter.ron"),
+        collection(typed)
+    )]
+    pub encounters: Vec<Handle<EncounterDef>>,
+}
+
+/// After a failed load the collections aren't inserted; build them from whatever did load,
+/// with failed handles left for the placeholders to stand in for
+fn init_failed_collections(world: &mut World) {
+    world.init_collection::<AudioAssets>();
+    world.init_collection::<TextureAssets>();
+    world.init_collection::<EncounterAssets>();
+    world.init_collection::<StatusAssets>();
+    world.init_collection::<VfxAssets>();
+}
+
+fn fill_encounter_library(
+    handles: Res<EncounterAssets>,
+    encounters: Res<Assets<EncounterDef>>,
+    mut library: ResMut<EncounterLibrary>,
+) {
+    library.encounters.clear();
+    for def in handles.encounters.iter().filter_map(|h| encounters.get(h)) {
+        library.add(def.clone());
+    }
+    // Saved from the timeline editor; one with a built-in encounter's id is an edit of it
+    for name in persist::list(USER_ENCOUNTER_DIR).into_iter().filter(|name| name.ends_with(".encounter.ron")) {
+        let Some(text) = persist::load(&format!("{USER_ENCOUNTER_DIR}/{name}")) else { continue; };
+        match EncounterDef::parse(&text) {
+            Ok(def) => library.replace(def),
+            Err(error) => warn!("Ignoring {USER_ENCOUNTER_DIR}/{name}: {error}"),
+        }
+    }
+    // Fight logs dropped in the imports folder that haven't been turned into encounters yet
+    import_logs(&mut library);
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct StatusAssets {
+    #[asset(paths("statuses/base.statuses.ron"), collection(typed))]
+    pub files: Vec<Handle<StatusFile>>,
+}
+
+fn fill_status_book(handles: Res<StatusAssets>, files: Res<Assets<StatusFile>>, mut book: ResMut<StatusBook>) {
+    for file in handles.files.iter().filter_map(|h| files.get(h)) {
+        book.add(file);
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct VfxAssets {
+    #[asset(paths("vfx/base.vfx.ron"), collection(typed))]
+    pub files: Vec<Handle<VfxFile>>,
+}
+
+fn fill_vfx_library(handles: Res<VfxAssets>, files: Res<Assets<VfxFile>>, mut library: ResMut<VfxLibrary>) {
+    library.clear();
+    for file in handles.files.iter().filter_map(|h| files.get(h)) {
+        library.add(file);
+    }
+}
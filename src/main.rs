@@ -3,16 +3,18 @@
 
 use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use bevy::window::{CompositeAlphaMode, PrimaryWindow};
 use bevy::winit::WinitWindows;
 use bevy::DefaultPlugins;
-use bevy_game::GamePlugin; // ToDo: Replace bevy_game with your new crate name.
+use bevy_game::{CoopConfig, GameRng, GamePlugin, StreamOverlay}; // ToDo: Replace bevy_game with your new crate name.
 use std::io::Cursor;
 use winit::window::Icon;
 
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(Color::linear_rgb(0.4, 0.4, 0.4)))
+    let overlay = overlay_from_args();
+    let transparent = overlay == Some(StreamOverlay::Transparent);
+    let mut app = App::new();
+    app.insert_resource(ClearColor(Color::linear_rgb(0.4, 0.4, 0.4)))
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
@@ -23,6 +25,15 @@ fn main() {
                         fit_canvas_to_parent: true,
                         // Tells wasm not to override default event handling, like F5 and Ctrl+R
                         prevent_default_event_handling: false,
+                        // See-through for the transparent stream overlay; how is up to the platform
+                        transparent,
+                        composite_alpha_mode: if transparent && cfg!(target_os = "macos") {
+                            CompositeAlphaMode::PostMultiplied
+                        } else if transparent && cfg!(target_os = "linux") {
+                            CompositeAlphaMode::PreMultiplied
+                        } else {
+                            CompositeAlphaMode::Auto
+                        },
                         ..default()
                     }),
                     ..default()
@@ -33,8 +44,44 @@ fn main() {
                 }),
         )
         .add_plugins(GamePlugin)
-        .add_systems(Startup, set_window_icon)
-        .run();
+        .add_systems(Startup, set_window_icon);
+    if let Some(seed) = seed_from_args() {
+        app.insert_resource(GameRng::new(seed));
+    }
+    if let Some(coop) = coop_from_args() {
+        app.insert_resource(coop);
+    }
+    if let Some(overlay) = overlay {
+        app.insert_resource(overlay);
+    }
+    app.run();
+}
+
+// `--seed <n>` replays a pull with the same random rolls
+fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    let idx = args.iter().position(|a| a == "--seed")?;
+    args.get(idx + 1)?.parse().ok()
+}
+
+// `--host <port>` waits for a co-op partner, `--join <address:port>` connects to one
+fn coop_from_args() -> Option<CoopConfig> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(idx) = args.iter().position(|a| a == "--host") {
+        return Some(CoopConfig::Host { port: args.get(idx + 1)?.parse().ok()? });
+    }
+    let idx = args.iter().position(|a| a == "--join")?;
+    Some(CoopConfig::Join { address: args.get(idx + 1)?.clone() })
+}
+
+// `--overlay chroma` or `--overlay transparent` shows only the telemetry widgets, for streaming
+fn overlay_from_args() -> Option<StreamOverlay> {
+    let args: Vec<String> = std::env::args().collect();
+    let idx = args.iter().position(|a| a == "--overlay")?;
+    match args.get(idx + 1).map(String::as_str) {
+        Some("transparent") => Some(StreamOverlay::Transparent),
+        _ => Some(StreamOverlay::ChromaKey),
+    }
 }
 
 // Sets the icon on windows and X11
@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::camera_fx::CameraRest;
+use crate::player::Player;
+use crate::sim_time::SimTime;
+use crate::world::Enemy;
+use crate::{GameSet, GameState};
+
+// Overhead markers (stack, spread, numbers, tankbusters, ...) shared by every mechanic.
+// Markers are UI nodes that follow an entity on screen and stick to the screen edge
+// when the entity is off screen; the countdown is also drawn as a pie around the entity.
+
+pub struct MarkersPlugin;
+
+impl Plugin for MarkersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MarkerSettings>()
+            .add_event::<ShowMarkerEvent>()
+            .add_systems(
+                Update,
+                (spawn_markers, tick_markers, position_markers, draw_marker_pies)
+                    .chain()
+                    .in_set(GameSet::Ui)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MarkerKind {
+    Stack,
+    Spread,
+    Number(u8),
+    Tankbuster,
+}
+
+impl MarkerKind {
+    fn glyph(self) -> String {
+        match self {
+            MarkerKind::Stack => "S".to_string(),
+            MarkerKind::Spread => "X".to_string(),
+            MarkerKind::Number(n) => n.to_string(),
+            MarkerKind::Tankbuster => "!".to_string(),
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            MarkerKind::Stack => Color::linear_rgb(1.0, 0.8, 0.2),
+            MarkerKind::Spread => Color::linear_rgb(0.8, 0.3, 1.0),
+            MarkerKind::Number(_) => Color::linear_rgb(0.3, 0.8, 1.0),
+            MarkerKind::Tankbuster => Color::linear_rgb(1.0, 0.2, 0.2),
+        }
+    }
+}
+
+/// Who a marker goes on. Encounter scripts use the roles, code can pass any entity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MarkerTarget {
+    #[serde(skip)]
+    Entity(Entity),
+    Player,
+    Enemy,
+}
+
+/// Shows a marker over `target` for `duration` seconds
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShowMarkerEvent {
+    pub target: MarkerTarget,
+    pub kind: MarkerKind,
+    pub duration: f32,
+}
+
+#[derive(Resource, Debug)]
+pub struct MarkerSettings {
+    pub scale: f32,
+    /// Height above the entity origin in world units
+    pub offset: f32,
+}
+
+impl Default for MarkerSettings {
+    fn default() -> Self {
+        Self { scale: 1.0, offset: 70.0 }
+    }
+}
+
+#[derive(Component)]
+pub struct OverheadMarker {
+    pub target: Entity,
+    pub kind: MarkerKind,
+    pub remaining: f32,
+    pub duration: f32,
+    size: f32,
+}
+
+#[derive(Component)]
+struct MarkerCountdown;
+
+const MARKER_SIZE: f32 = 40.0;
+const EDGE_MARGIN: f32 = 8.0;
+
+fn spawn_markers(
+    mut commands: Commands,
+    settings: Res<MarkerSettings>,
+    mut evr: EventReader<ShowMarkerEvent>,
+    q_player: Query<Entity, With<Player>>,
+    q_enemy: Query<Entity, With<Enemy>>,
+    q_existing: Query<(Entity, &OverheadMarker)>,
+) {
+    for ev in evr.read() {
+        let target = match ev.target {
+            MarkerTarget::Entity(e) => Some(e),
+            MarkerTarget::Player => q_player.iter().next(),
+            MarkerTarget::Enemy => q_enemy.iter().next(),
+        };
+        let Some(target) = target else { continue; };
+        // One marker of a kind per entity, reapplying restarts it
+        for (e, existing) in &q_existing {
+            if existing.target == target && existing.kind == ev.kind {
+                commands.entity(e).despawn();
+            }
+        }
+        let size = MARKER_SIZE * settings.scale;
+        commands
+            .spawn((
+                Node {
+                    width: Val::Px(size),
+                    height: Val::Px(size),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                BorderColor(ev.kind.color()),
+                BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.55)),
+                Visibility::Hidden,
+                OverheadMarker { target, kind: ev.kind, remaining: ev.duration, duration: ev.duration, size },
+                StateScoped(GameState::Playing),
+            ))
+            .with_children(|m| {
+                m.spawn((
+                    Text::new(ev.kind.glyph()),
+                    TextFont { font_size: 20.0 * settings.scale, ..default() },
+                    TextColor(ev.kind.color()),
+                ));
+                m.spawn((
+                    Text::new(""),
+                    TextFont { font_size: 11.0 * settings.scale, ..default() },
+                    TextColor(Color::WHITE),
+                    MarkerCountdown,
+                ));
+            });
+    }
+}
+
+fn tick_markers(
+    time: SimTime,
+    mut commands: Commands,
+    mut q: Query<(Entity, &mut OverheadMarker, &Children)>,
+    q_targets: Query<(), With<GlobalTransform>>,
+    mut q_text: Query<&mut Text, With<MarkerCountdown>>,
+) {
+    let dt = time.scaled_delta();
+    for (e, mut marker, children) in &mut q {
+        marker.remaining -= dt;
+        if marker.remaining <= 0.0 || q_targets.get(marker.target).is_err() {
+            commands.entity(e).despawn();
+            continue;
+        }
+        for child in children.iter() {
+            if let Ok(mut text) = q_text.get_mut(child) {
+                text.0 = format!("{:.1}", marker.remaining);
+            }
+        }
+    }
+}
+
+fn position_markers(
+    settings: Res<MarkerSettings>,
+    camera: Query<(&Camera, &GlobalTransform, Option<&CameraRest>), With<Camera2d>>,
+    q_targets: Query<&GlobalTransform>,
+    mut q: Query<(&OverheadMarker, &mut Node, &mut Visibility, &mut BackgroundColor)>,
+) {
+    let Ok((camera, camera_transform, rest)) = camera.single() else { return; };
+    // HUD, so it stays put while the camera shakes
+    let camera_transform = rest.map_or(*camera_transform, CameraRest::global);
+    let Some(viewport) = camera.logical_viewport_size() else { return; };
+    for (marker, mut node, mut vis, mut bg) in &mut q {
+        let Ok(target) = q_targets.get(marker.target) else { continue; };
+        let world = target.translation() + Vec3::Y * settings.offset;
+        let Ok(screen) = camera.world_to_viewport(&camera_transform, world) else { continue; };
+        let half = marker.size * 0.5;
+        let min = Vec2::splat(EDGE_MARGIN + half);
+        let max = (viewport - Vec2::splat(EDGE_MARGIN + half)).max(min);
+        let clamped = screen.clamp(min, max);
+        node.left = Val::Px(clamped.x - half);
+        node.top = Val::Px(clamped.y - half);
+        *vis = Visibility::Inherited;
+        // Pinned to the edge: make it stand out since the entity itself isn't visible
+        let alpha = if clamped != screen { 0.9 } else { 0.55 };
+        bg.0 = bg.0.with_alpha(alpha);
+    }
+}
+
+fn draw_marker_pies(settings: Res<MarkerSettings>, mut gizmos: Gizmos, q: Query<&OverheadMarker>, q_targets: Query<&GlobalTransform>) {
+    for marker in &q {
+        let Ok(target) = q_targets.get(marker.target) else { continue; };
+        let frac = if marker.duration > 0.0 { (marker.remaining / marker.duration).clamp(0.0, 1.0) } else { 0.0 };
+        let center = target.translation().truncate();
+        let radius = 36.0 * settings.scale;
+        gizmos.arc_2d(Isometry2d::from_translation(center), std::f32::consts::TAU * frac, radius, marker.kind.color());
+    }
+}
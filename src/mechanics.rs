@@ -0,0 +1,494 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+use crate::character::Character;
+use crate::combat::{ApplyStatusEvent, EncounterProgress, EnemyTimeline, PlayerDamageEvent, RelativePosition, StatusBook};
+use crate::markers::{MarkerKind, MarkerTarget, ShowMarkerEvent};
+use crate::party::{hit_member, MemberDebuffs, PartyMember};
+use crate::player::Player;
+use crate::sim_time::SimTime;
+use crate::vfx::{GroundShape, TelegraphVfx};
+use crate::world::{Arena, ArenaShape, Enemy, Enmity, Facing, Hazard, Health};
+use crate::{GameSet, GameState};
+
+// Telegraphed ground AoEs. A mechanic is drawn on the floor for its wind-up, then
+// resolves: the player takes its damage if standing inside, and it disappears.
+// Encounter files define mechanics by name and the timeline fires them.
+//
+// Stack and spread mechanics follow people instead of sitting on the ground: a stack
+// is split between everyone inside it, a spread hits everyone near its target, so
+// standing in two spreads takes the damage twice.
+//
+// Failing a mechanic by where the player stood (in a ground AoE, outside a stack, sharing a
+// spread) also leaves a stack of Vulnerability Up on them, so every mistake makes the next hit
+// hurt more; the stacks taken are counted for the results. A stack a party member missed
+// fails without one.
+//
+// Between mechanics the boss turns to whoever holds enmity and auto-attacks with a frontal
+// cleave, so the tank takes it along with anyone else standing in front; flanks and rear
+// are safe. The cleave goes by the pull clock, first swinging a full interval into the
+// pull. On its own rhythm the boss also swings at whoever holds enmity, shown by the swing
+// timer under its HP bar.
+//
+// The telegraphs themselves are drawn by `vfx`. The arena edge and lingering hazard puddles
+// from `world` are drawn here, with the rest of what's on the floor.
+
+pub struct MechanicsPlugin;
+
+/// Status stacked on the player for each mechanic they failed
+const FAILED_MECHANIC_STATUS: &str = "failed_mechanic";
+
+impl Plugin for MechanicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnMechanicEvent>()
+            .add_event::<MechanicResolvedEvent>()
+            .add_systems(OnEnter(GameState::Playing), clear_telegraphs)
+            .add_systems(
+                FixedUpdate,
+                (spawn_telegraphs, follow_targets, resolve_telegraphs, boss_cleave, auto_attack)
+                    .chain()
+                    .in_set(GameSet::Sim)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (draw_arena, fill_telegraphs, draw_boss_facing).in_set(GameSet::Ui).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Area covered by a mechanic, in world units. Cones and lines point along the aim direction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AoeShape {
+    Circle { radius: f32 },
+    /// `angle` is the full width of the cone in degrees
+    Cone { radius: f32, angle: f32 },
+    Line { length: f32, width: f32 },
+    /// Everything between the two radii; standing close in is safe
+    Donut { inner: f32, outer: f32 },
+}
+
+impl AoeShape {
+    /// Whether `offset` (from the mechanic's origin) is inside the shape aimed along `dir`
+    pub fn contains(&self, offset: Vec2, dir: Vec2) -> bool {
+        match *self {
+            AoeShape::Circle { radius } => offset.length() <= radius,
+            AoeShape::Cone { radius, angle } => {
+                offset.length() <= radius
+                    && (offset == Vec2::ZERO || dir.angle_to(offset).abs() <= angle.to_radians() * 0.5)
+            }
+            AoeShape::Line { length, width } => {
+                let along = offset.dot(dir);
+                let across = offset.perp_dot(dir).abs();
+                (0.0..=length).contains(&along) && across <= width * 0.5
+            }
+            AoeShape::Donut { inner, outer } => (inner..=outer).contains(&offset.length()),
+        }
+    }
+
+    fn ground(self) -> GroundShape {
+        match self {
+            AoeShape::Circle { radius } => GroundShape::Circle { radius },
+            AoeShape::Cone { radius, angle } => GroundShape::Cone { radius, angle },
+            AoeShape::Line { length, width } => GroundShape::Line { length, width },
+            AoeShape::Donut { inner, outer } => GroundShape::Donut { inner, outer },
+        }
+    }
+}
+
+/// Where a mechanic is placed when it starts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AoeAnchor {
+    /// On the player's position at the time of the cast, so moving away dodges it
+    Player,
+    /// On the enemy, aimed at where the player stood
+    Enemy,
+}
+
+/// How a mechanic picks its targets and deals its damage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MechanicKind {
+    /// Fixed on the ground; whoever is inside when it resolves takes the full damage
+    #[default]
+    Ground,
+    /// Follows the player; the damage is split between everyone inside
+    Stack,
+    /// One on the player and every party member; each hits everyone inside it
+    Spread,
+    /// Follows whoever has the most enmity and hits everyone inside, meant to be mitigated
+    Buster,
+}
+
+/// A named mechanic from an encounter file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MechanicDef {
+    pub name: String,
+    #[serde(default)]
+    pub kind: MechanicKind,
+    pub shape: AoeShape,
+    /// Where ground mechanics are placed; the other kinds follow their targets
+    #[serde(default = "MechanicDef::default_anchor")]
+    pub anchor: AoeAnchor,
+    /// Seconds between the telegraph appearing and the damage
+    pub windup: f32,
+    pub damage: i32,
+}
+
+impl MechanicDef {
+    fn default_anchor() -> AoeAnchor {
+        AoeAnchor::Player
+    }
+}
+
+/// Starts a telegraph for `mechanic`
+#[derive(Event, Debug, Clone)]
+pub struct SpawnMechanicEvent {
+    pub mechanic: MechanicDef,
+}
+
+/// A telegraph went off; `failed` when it wasn't handled (stood in, stack missed, ...)
+#[derive(Event, Debug, Clone)]
+pub struct MechanicResolvedEvent {
+    pub name: String,
+    pub failed: bool,
+    pub player_hit: bool,
+}
+
+#[derive(Component)]
+pub struct Telegraph {
+    pub name: String,
+    pub kind: MechanicKind,
+    /// Entity the telegraph moves with, for stacks and spreads
+    pub follow: Option<Entity>,
+    pub shape: AoeShape,
+    pub dir: Vec2,
+    pub damage: i32,
+    pub remaining: f32,
+    pub windup: f32,
+}
+
+const TELEGRAPH_COLOR: Color = Color::linear_rgb(1.0, 0.45, 0.1);
+
+/// Frontal auto-attack at whoever holds enmity: every `every` seconds of the pull, hits
+/// everyone in front within `range`
+#[derive(Component, Debug)]
+pub struct BossCleave {
+    pub every: f32,
+    pub damage: i32,
+    pub range: f32,
+    /// Swings so far this pull
+    swings: u32,
+    /// Seconds left on the swing highlight
+    flash: f32,
+}
+
+impl Default for BossCleave {
+    fn default() -> Self {
+        Self { every: 3.0, damage: 120, range: 220.0, swings: 0, flash: 0.0 }
+    }
+}
+
+const CLEAVE_FLASH_SECS: f32 = 0.25;
+
+/// The boss's regular swing at its enmity target
+#[derive(Component, Debug)]
+pub struct AutoAttack {
+    pub every: f32,
+    pub damage: i32,
+    /// Seconds until the next swing
+    pub timer: f32,
+    /// Who the next swing goes to, for the swing timer
+    pub target: Option<String>,
+}
+
+impl Default for AutoAttack {
+    fn default() -> Self {
+        Self { every: 3.0, damage: 40, timer: 3.0, target: None }
+    }
+}
+
+fn spawn_telegraphs(
+    mut commands: Commands,
+    mut evr: EventReader<SpawnMechanicEvent>,
+    q_player: Query<(Entity, &Transform), With<Player>>,
+    q_enemy: Query<&Transform, With<Enemy>>,
+    q_party: Query<(Entity, &Health), With<PartyMember>>,
+    q_enmity: Query<(Entity, &Enmity, &Health)>,
+    mut marker_writer: EventWriter<ShowMarkerEvent>,
+) {
+    let (player_entity, player) = match q_player.single() {
+        Ok((e, t)) => (Some(e), t.translation.truncate()),
+        Err(_) => (None, Vec2::ZERO),
+    };
+    let enemy = q_enemy.single().map(|t| t.translation.truncate()).unwrap_or(Vec2::ZERO);
+    for ev in evr.read() {
+        let mechanic = &ev.mechanic;
+        let dir = (player - enemy).try_normalize().unwrap_or(Vec2::NEG_X);
+        let telegraph = |follow| Telegraph {
+            name: mechanic.name.clone(),
+            kind: mechanic.kind,
+            follow,
+            shape: mechanic.shape,
+            dir,
+            damage: mechanic.damage,
+            remaining: mechanic.windup,
+            windup: mechanic.windup,
+        };
+        let vfx = || TelegraphVfx::new(mechanic.shape.ground(), dir, TELEGRAPH_COLOR);
+        let targets: Vec<Entity> = match mechanic.kind {
+            MechanicKind::Ground => {
+                let origin = match mechanic.anchor {
+                    AoeAnchor::Player => player,
+                    AoeAnchor::Enemy => enemy,
+                };
+                commands.spawn((Transform::from_translation(origin.extend(0.0)), telegraph(None), vfx()));
+                continue;
+            }
+            MechanicKind::Stack => player_entity.into_iter().collect(),
+            MechanicKind::Spread => player_entity
+                .into_iter()
+                .chain(q_party.iter().filter(|(_, hp)| hp.current > 0).map(|(e, _)| e))
+                .collect(),
+            MechanicKind::Buster => q_enmity
+                .iter()
+                .filter(|(_, _, hp)| hp.current > 0)
+                .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+                .map(|(e, ..)| e)
+                .into_iter()
+                .collect(),
+        };
+        let marker = match mechanic.kind {
+            MechanicKind::Stack => MarkerKind::Stack,
+            MechanicKind::Buster => MarkerKind::Tankbuster,
+            _ => MarkerKind::Spread,
+        };
+        for target in targets {
+            commands.spawn((Transform::default(), telegraph(Some(target)), vfx()));
+            marker_writer.write(ShowMarkerEvent { target: MarkerTarget::Entity(target), kind: marker, duration: mechanic.windup });
+        }
+    }
+}
+
+/// Keeps stack and spread telegraphs on their targets until they resolve
+fn follow_targets(
+    mut q: Query<(&mut Transform, &Telegraph)>,
+    q_targets: Query<&GlobalTransform, Without<Telegraph>>,
+) {
+    for (mut transform, telegraph) in &mut q {
+        let Some(target) = telegraph.follow.and_then(|e| q_targets.get(e).ok()) else { continue; };
+        transform.translation = target.translation().truncate().extend(0.0);
+    }
+}
+
+fn resolve_telegraphs(
+    time: SimTime,
+    mut commands: Commands,
+    mut q: Query<(Entity, &Transform, &mut Telegraph)>,
+    q_player: Query<(Entity, &Transform), With<Player>>,
+    mut q_party: Query<(Entity, &Transform, &mut Health, &PartyMember, &mut MemberDebuffs), Without<Player>>,
+    statuses: Res<StatusBook>,
+    mut progress: ResMut<EncounterProgress>,
+    mut hit_writer: EventWriter<PlayerDamageEvent>,
+    mut resolved_writer: EventWriter<MechanicResolvedEvent>,
+    mut status_writer: EventWriter<ApplyStatusEvent>,
+) {
+    let dt = time.scaled_delta();
+    let mut vuln = |progress: &mut EncounterProgress| {
+        progress.vuln_stacks += 1;
+        status_writer.write(ApplyStatusEvent { id: FAILED_MECHANIC_STATUS.to_string() });
+    };
+    for (e, transform, mut telegraph) in &mut q {
+        telegraph.remaining -= dt;
+        if telegraph.remaining > 0.0 {
+            continue;
+        }
+        commands.entity(e).despawn();
+        let origin = transform.translation.truncate();
+        let inside = |pos: Vec3| telegraph.shape.contains(pos.truncate() - origin, telegraph.dir);
+        let player = q_player.single().ok().filter(|(_, t)| inside(t.translation)).map(|(e, _)| e);
+        if telegraph.kind == MechanicKind::Ground {
+            if player.is_some() {
+                hit_writer.write(PlayerDamageEvent { amount: telegraph.damage });
+                progress.mechanics_failed += 1;
+                vuln(&mut progress);
+            }
+            let hit = player.is_some();
+            resolved_writer.write(MechanicResolvedEvent { name: telegraph.name.clone(), failed: hit, player_hit: hit });
+            continue;
+        }
+        let party_hit: Vec<Entity> = q_party
+            .iter()
+            .filter(|(_, t, hp, ..)| hp.current > 0 && inside(t.translation))
+            .map(|(e, ..)| e)
+            .collect();
+        let hit_count = party_hit.len() + player.iter().len();
+        // Whether the player's own spot failed it, not just someone else's
+        let (damage, failed, player_at_fault) = match telegraph.kind {
+            // Everyone alive should be in the stack
+            MechanicKind::Stack => {
+                let alive = 1 + q_party.iter().filter(|(_, _, hp, ..)| hp.current > 0).count();
+                let left_out = q_player.single().is_ok() && player.is_none();
+                (telegraph.damage / hit_count.max(1) as i32, hit_count < alive, left_out)
+            }
+            // Only the target should be in its own spread
+            MechanicKind::Spread => (telegraph.damage, hit_count > 1, player.is_some() && hit_count > 1),
+            // Busters are meant to be taken, just not by the wrong person
+            _ => (telegraph.damage, false, false),
+        };
+        if failed {
+            progress.mechanics_failed += 1;
+            if player_at_fault {
+                vuln(&mut progress);
+            }
+        }
+        resolved_writer.write(MechanicResolvedEvent { name: telegraph.name.clone(), failed, player_hit: player.is_some() });
+        if player.is_some() {
+            hit_writer.write(PlayerDamageEvent { amount: damage });
+        }
+        // Members at fault pick up the same debuff the player would: everyone left out of a
+        // missed stack, everyone in an overlapped spread
+        let at_fault: Vec<Entity> = match telegraph.kind {
+            MechanicKind::Stack if failed => q_party
+                .iter()
+                .filter(|(e, _, hp, ..)| hp.current > 0 && !party_hit.contains(e))
+                .map(|(e, ..)| e)
+                .collect(),
+            MechanicKind::Spread if failed => party_hit.clone(),
+            _ => Vec::new(),
+        };
+        for member in party_hit {
+            let Ok((_, _, mut hp, info, debuffs)) = q_party.get_mut(member) else { continue; };
+            hit_member(info, &debuffs, &mut hp, damage);
+        }
+        if let Some(def) = statuses.by_id.get(FAILED_MECHANIC_STATUS) {
+            for member in at_fault {
+                if let Ok((.., mut debuffs)) = q_party.get_mut(member) {
+                    debuffs.apply(def);
+                }
+            }
+        }
+    }
+}
+
+fn boss_cleave(
+    time: SimTime,
+    timeline: Res<EnemyTimeline>,
+    mut q_enemy: Query<(&Transform, &mut Facing, &mut BossCleave), With<Enemy>>,
+    mut q_targets: Query<(&Transform, &Enmity, &mut Health, Option<(&PartyMember, &MemberDebuffs)>), Without<Enemy>>,
+    mut hit_writer: EventWriter<PlayerDamageEvent>,
+) {
+    let Ok((enemy, mut facing, mut cleave)) = q_enemy.single_mut() else { return; };
+    let enemy = enemy.translation.truncate();
+    cleave.flash = (cleave.flash - time.scaled_delta()).max(0.0);
+    let holder = q_targets
+        .iter()
+        .filter(|(_, _, hp, _)| hp.current > 0)
+        .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+        .map(|(tf, ..)| tf.translation.truncate());
+    if let Some(dir) = holder.and_then(|at| (at - enemy).try_normalize()) {
+        facing.0 = dir;
+    }
+    let swings = (timeline.pull_time() / cleave.every.max(0.1)) as u32;
+    if swings <= cleave.swings {
+        return;
+    }
+    cleave.swings = swings;
+    cleave.flash = CLEAVE_FLASH_SECS;
+    for (tf, _, mut hp, member) in &mut q_targets {
+        let at = tf.translation.truncate();
+        let in_front = RelativePosition::from_positions(enemy, facing.0, at) == RelativePosition::Front;
+        if !in_front || enemy.distance(at) > cleave.range || hp.current <= 0 {
+            continue;
+        }
+        match member {
+            // The player goes through mitigation and shields like any other hit
+            None => {
+                hit_writer.write(PlayerDamageEvent { amount: cleave.damage });
+            }
+            Some((member, debuffs)) => hit_member(member, debuffs, &mut hp, cleave.damage),
+        }
+    }
+}
+
+fn auto_attack(
+    time: SimTime,
+    character: Res<Character>,
+    mut q_enemy: Query<&mut AutoAttack, With<Enemy>>,
+    mut q_targets: Query<(&Enmity, &mut Health, Option<(&PartyMember, &MemberDebuffs)>), Without<Enemy>>,
+    mut hit_writer: EventWriter<PlayerDamageEvent>,
+) {
+    let Ok(mut auto) = q_enemy.single_mut() else { return; };
+    let target = q_targets
+        .iter_mut()
+        .filter(|(_, hp, _)| hp.current > 0)
+        .max_by(|a, b| a.0 .0.total_cmp(&b.0 .0));
+    auto.target = target.as_ref().map(|(_, _, member)| member.map_or_else(|| character.name.clone(), |(m, _)| m.name.clone()));
+    auto.timer -= time.scaled_delta();
+    if auto.timer > 0.0 {
+        return;
+    }
+    auto.timer += auto.every;
+    match target {
+        // The player goes through mitigation and shields like any other hit
+        Some((_, _, None)) => {
+            hit_writer.write(PlayerDamageEvent { amount: auto.damage });
+        }
+        Some((_, mut hp, Some((member, debuffs)))) => hit_member(member, debuffs, &mut hp, auto.damage),
+        None => {}
+    }
+}
+
+fn clear_telegraphs(mut commands: Commands, q: Query<Entity, With<Telegraph>>) {
+    for e in &q {
+        commands.entity(e).despawn();
+    }
+}
+
+const ARENA_EDGE_COLOR: Color = Color::linear_rgb(0.6, 0.6, 0.7);
+const DEADLY_EDGE_COLOR: Color = Color::linear_rgb(0.9, 0.2, 0.2);
+const HAZARD_COLOR: Color = Color::linear_rgb(0.5, 0.1, 0.8);
+const PILLAR_COLOR: Color = Color::linear_rgb(0.75, 0.7, 0.6);
+
+fn draw_arena(mut gizmos: Gizmos, arena: Res<Arena>, q_hazards: Query<(&Transform, &Hazard)>) {
+    let edge = if arena.deadly_edge { DEADLY_EDGE_COLOR } else { ARENA_EDGE_COLOR };
+    match arena.shape {
+        ArenaShape::Circle { radius } => {
+            gizmos.circle_2d(Vec2::ZERO, radius, edge);
+        }
+        ArenaShape::Square { half_size } => {
+            gizmos.rect_2d(Isometry2d::IDENTITY, Vec2::splat(half_size * 2.0), edge);
+        }
+    }
+    for pillar in &arena.pillars {
+        gizmos.circle_2d(pillar.center(), pillar.radius, PILLAR_COLOR);
+        gizmos.circle_2d(pillar.center(), pillar.radius * 0.8, PILLAR_COLOR.with_alpha(0.5));
+    }
+    for (transform, hazard) in &q_hazards {
+        let origin = transform.translation.truncate();
+        gizmos.circle_2d(origin, hazard.radius, HAZARD_COLOR);
+        gizmos.circle_2d(origin, hazard.radius * 0.6, HAZARD_COLOR.with_alpha(0.5));
+    }
+}
+
+/// Fills each telegraph in as its wind-up runs out
+fn fill_telegraphs(mut q: Query<(&Telegraph, &mut TelegraphVfx)>) {
+    for (telegraph, mut vfx) in &mut q {
+        vfx.progress = if telegraph.windup > 0.0 { 1.0 - telegraph.remaining / telegraph.windup } else { 1.0 };
+    }
+}
+
+/// Faint cone over the cleave area that flares up on every swing
+fn draw_boss_facing(mut gizmos: Gizmos, q_enemy: Query<(&Transform, &Facing, &BossCleave), With<Enemy>>) {
+    let Ok((transform, facing, cleave)) = q_enemy.single() else { return; };
+    let origin = transform.translation.truncate();
+    // Same 90° front cone that positionals use
+    let half = std::f32::consts::FRAC_PI_4;
+    let alpha = if cleave.flash > 0.0 { 0.8 } else { 0.2 };
+    let color = Color::linear_rgb(1.0, 0.3, 0.3).with_alpha(alpha);
+    let start = Rot2::radians(facing.0.to_angle() - half - FRAC_PI_2);
+    gizmos.arc_2d(Isometry2d::new(origin, start), half * 2.0, cleave.range, color);
+    for edge in [-half, half] {
+        gizmos.line_2d(origin, origin + Vec2::from_angle(edge).rotate(facing.0.normalize_or_zero()) * cleave.range, color);
+    }
+}
@@ -1,4 +1,13 @@
+use crate::achievements::{Achievement, AchievementProfile, CLEARS_BEFORE_ENRAGE};
+use crate::camera_fx::CameraRest;
+use crate::combat::{CurrentEncounter, EncounterLibrary, HotbarLayout, Metronome};
+use crate::keybinds::Rebinding;
 use crate::loading::TextureAssets;
+use crate::picker::EncounterPicker;
+use crate::player::{Mutator, Mutators};
+use crate::replay::ReplayBrowser;
+use crate::rng::GameRng;
+use crate::settings::SettingsPanel;
 use crate::GameState;
 use bevy::prelude::*;
 
@@ -9,7 +18,7 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Menu), setup_menu)
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
+            .add_systems(Update, (click_play_button, update_encounter_label).run_if(in_state(GameState::Menu)))
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
 }
@@ -32,9 +41,23 @@ impl Default for ButtonColors {
 #[derive(Component)]
 struct Menu;
 
-fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
+fn setup_menu(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    rng: Res<GameRng>,
+    mutators: Res<Mutators>,
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+    achievements: Res<AchievementProfile>,
+    layout: Res<HotbarLayout>,
+    metronome: Res<Metronome>,
+    q_camera: Query<(), With<Camera2d>>,
+) {
     info!("menu");
-    commands.spawn((Camera2d, Msaa::Off));
+    // The camera stays around for the game, so coming back from the results screen reuses it
+    if q_camera.is_empty() {
+        commands.spawn((Camera2d, Msaa::Off, CameraRest::default()));
+    }
     commands
         .spawn((
             Node {
@@ -71,6 +94,338 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
                     },
                     TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
                 ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ChangeState(GameState::Calibration),
+                ))
+                .with_child((
+                    Text::new("Calibrate latency"),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ChangeState(GameState::Editor),
+                ))
+                .with_child((
+                    Text::new("Edit timeline"),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ChangeState(GameState::Leaderboard),
+                ))
+                .with_child((
+                    Text::new("Leaderboard"),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            // Clicking opens the encounter picker
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    OpenPicker,
+                ))
+                .with_child((
+                    Text::new(encounter_label(&library, &encounter)),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    EncounterLabel,
+                ));
+            // Clicking rolls a new seed; pass --seed to replay a specific one
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    RerollSeed,
+                ))
+                .with_child((
+                    Text::new(format!("Seed: {}", rng.seed())),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    SeedLabel,
+                ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ToggleGallery,
+                ))
+                .with_child((
+                    Text::new(format!("Achievements ({}/{})", achievements.unlocked.len(), Achievement::ALL.len())),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            // Keyboard rows or the gamepad crossbar on the HUD
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    CycleHotbarLayout,
+                ))
+                .with_child((
+                    Text::new(layout_label(*layout)),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ToggleKeybinds,
+                ))
+                .with_child((
+                    Text::new("Keybinds"),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ToggleReplays,
+                ))
+                .with_child((
+                    Text::new("Replays"),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ToggleSettings,
+                ))
+                .with_child((
+                    Text::new("Settings"),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            // Tick on every GCD, for drilling the rhythm
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        height: Val::Px(30.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        padding: UiRect::horizontal(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(ButtonColors::default().normal),
+                    ButtonColors::default(),
+                    ToggleMetronome,
+                ))
+                .with_child((
+                    Text::new(metronome_label(*metronome)),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            // Movement mutators for the next pull
+            for mutator in Mutator::ALL {
+                children
+                    .spawn((
+                        Button,
+                        Node {
+                            height: Val::Px(30.0),
+                            margin: UiRect::top(Val::Px(6.0)),
+                            padding: UiRect::horizontal(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        BackgroundColor(ButtonColors::default().normal),
+                        ButtonColors::default(),
+                        ToggleMutator(mutator),
+                    ))
+                    .with_child((
+                        Text::new(mutator_label(mutator, &mutators)),
+                        TextFont {
+                            font_size: 16.0,
+                            ..default()
+                        },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+            }
+        });
+    // Achievements gallery, opened from its button
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                right: Val::Px(20.0),
+                width: Val::Px(320.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.9)),
+            Visibility::Hidden,
+            AchievementGallery,
+            Menu,
+        ))
+        .with_children(|gallery| {
+            for achievement in Achievement::ALL {
+                let unlocked = achievements.is_unlocked(achievement);
+                let color = if unlocked { Color::linear_rgb(1.0, 0.85, 0.3) } else { Color::linear_rgb(0.5, 0.5, 0.5) };
+                gallery.spawn((
+                    Text::new(format!("{} {}", if unlocked { "[x]" } else { "[ ]" }, achievement.title())),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(color),
+                ));
+                let mut description = achievement.description().to_string();
+                if achievement == Achievement::EnragePractice && !unlocked {
+                    description.push_str(&format!(" ({}/{CLEARS_BEFORE_ENRAGE})", achievements.clears_before_enrage));
+                }
+                gallery.spawn((
+                    Text::new(description),
+                    TextFont {
+                        font_size: 12.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.75, 0.75, 0.75)),
+                    Node {
+                        margin: UiRect::bottom(Val::Px(6.0)),
+                        ..default()
+                    },
+                ));
+            }
         });
     commands
         .spawn((
@@ -168,8 +523,73 @@ struct ChangeState(GameState);
 #[derive(Component)]
 struct OpenLink(&'static str);
 
+#[derive(Component)]
+struct RerollSeed;
+
+#[derive(Component)]
+struct SeedLabel;
+
+#[derive(Component)]
+struct ToggleMutator(Mutator);
+
+#[derive(Component)]
+struct OpenPicker;
+
+#[derive(Component)]
+struct EncounterLabel;
+
+#[derive(Component)]
+struct ToggleGallery;
+
+#[derive(Component)]
+struct CycleHotbarLayout;
+
+#[derive(Component)]
+struct ToggleKeybinds;
+
+#[derive(Component)]
+struct ToggleMetronome;
+
+#[derive(Component)]
+struct ToggleReplays;
+
+#[derive(Component)]
+struct ToggleSettings;
+
+#[derive(Component)]
+struct AchievementGallery;
+
+fn encounter_label(library: &EncounterLibrary, encounter: &CurrentEncounter) -> String {
+    let name = library.get(&encounter.id).map(|e| e.name.as_str()).unwrap_or("none");
+    format!("Encounter: {name}")
+}
+
+fn layout_label(layout: HotbarLayout) -> String {
+    format!("Hotbar: {}", layout.label())
+}
+
+fn metronome_label(metronome: Metronome) -> String {
+    format!("GCD metronome: {}", if metronome.enabled { "on" } else { "off" })
+}
+
+fn mutator_label(mutator: Mutator, mutators: &Mutators) -> String {
+    let state = if mutators.is_active(mutator) { "on" } else { "off" };
+    format!("{}: {state}", mutator.label())
+}
+
 fn click_play_button(
     mut next_state: ResMut<NextState<GameState>>,
+    mut rng: ResMut<GameRng>,
+    mut seed_label: Query<&mut Text, With<SeedLabel>>,
+    mut mutators: ResMut<Mutators>,
+    mut picker: ResMut<EncounterPicker>,
+    mut layout: ResMut<HotbarLayout>,
+    mut rebinding: ResMut<Rebinding>,
+    mut metronome: ResMut<Metronome>,
+    mut replays: ResMut<ReplayBrowser>,
+    mut settings_panel: ResMut<SettingsPanel>,
+    mut button_labels: Query<&mut Text, Without<SeedLabel>>,
+    mut gallery: Query<&mut Visibility, With<AchievementGallery>>,
     mut interaction_query: Query<
         (
             &Interaction,
@@ -177,15 +597,65 @@ fn click_play_button(
             &ButtonColors,
             Option<&ChangeState>,
             Option<&OpenLink>,
+            Option<&RerollSeed>,
+            Option<&ToggleMutator>,
+            Option<&OpenPicker>,
+            Option<&ToggleGallery>,
+            Option<&CycleHotbarLayout>,
+            Option<&ToggleKeybinds>,
+            Option<&ToggleMetronome>,
+            Option<&ToggleReplays>,
+            Option<&ToggleSettings>,
+            &Children,
         ),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color, button_colors, change_state, open_link) in &mut interaction_query {
+    for (interaction, mut color, button_colors, change_state, open_link, reroll, mutator, open_picker, toggle_gallery, cycle_layout, toggle_keybinds, toggle_metronome, toggle_replays, toggle_settings, children) in
+        &mut interaction_query
+    {
         match *interaction {
             Interaction::Pressed => {
                 if let Some(state) = change_state {
                     next_state.set(state.0.clone());
+                } else if let Some(ToggleMutator(mutator)) = mutator {
+                    mutators.toggle(*mutator);
+                    for child in children.iter() {
+                        if let Ok(mut text) = button_labels.get_mut(child) {
+                            text.0 = mutator_label(*mutator, &mutators);
+                        }
+                    }
+                } else if open_picker.is_some() {
+                    picker.toggle();
+                } else if cycle_layout.is_some() {
+                    layout.cycle();
+                    for child in children.iter() {
+                        if let Ok(mut text) = button_labels.get_mut(child) {
+                            text.0 = layout_label(*layout);
+                        }
+                    }
+                } else if toggle_keybinds.is_some() {
+                    rebinding.toggle();
+                } else if toggle_replays.is_some() {
+                    replays.toggle();
+                } else if toggle_settings.is_some() {
+                    settings_panel.toggle();
+                } else if toggle_metronome.is_some() {
+                    metronome.toggle();
+                    for child in children.iter() {
+                        if let Ok(mut text) = button_labels.get_mut(child) {
+                            text.0 = metronome_label(*metronome);
+                        }
+                    }
+                } else if toggle_gallery.is_some() {
+                    if let Ok(mut vis) = gallery.single_mut() {
+                        *vis = if *vis == Visibility::Hidden { Visibility::Inherited } else { Visibility::Hidden };
+                    }
+                } else if reroll.is_some() {
+                    rng.reroll();
+                    if let Ok(mut text) = seed_label.single_mut() {
+                        text.0 = format!("Seed: {}", rng.seed());
+                    }
                 } else if let Some(link) = open_link {
                     if let Err(error) = webbrowser::open(link.0) {
                         warn!("Failed to open link {error:?}");
@@ -202,6 +672,19 @@ fn click_play_button(
     }
 }
 
+fn update_encounter_label(
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+    mut q_label: Query<&mut Text, With<EncounterLabel>>,
+) {
+    if !encounter.is_changed() && !library.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = q_label.single_mut() {
+        text.0 = encounter_label(&library, &encounter);
+    }
+}
+
 fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<Menu>>) {
     for entity in menu.iter() {
         commands.entity(entity).despawn();
@@ -0,0 +1,165 @@
+use bevy::asset::{AssetLoadFailedEvent, LoadState, RenderAssetUsages};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_kira_audio::AudioSource;
+
+use crate::actions::keyboard_free;
+use crate::combat::{EncounterDef, StatusFile};
+use crate::GameSet;
+
+// Degraded mode for assets that fail to load, mostly paths typed into user-authored
+// encounter and status files. A missing image shows as a magenta box, a missing sound
+// never plays, and every failure is listed in a diagnostics panel (Insert toggles it)
+// instead of the game panicking or quietly drawing nothing.
+
+pub struct MissingAssetsPlugin;
+
+impl Plugin for MissingAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MissingAssets>()
+            .add_systems(Startup, (create_placeholder, spawn_diagnostics_panel))
+            .add_systems(
+                Update,
+                (
+                    (
+                        record_failures::<Image>,
+                        record_failures::<AudioSource>,
+                        record_failures::<EncounterDef>,
+                        record_failures::<StatusFile>,
+                    ),
+                    swap_in_placeholders,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (toggle_diagnostics_panel.run_if(keyboard_free), update_diagnostics_panel).chain().in_set(GameSet::Ui),
+            );
+    }
+}
+
+/// Placeholders are drawn this big when the sprite doesn't ask for a size
+const PLACEHOLDER_SIZE: f32 = 64.0;
+const PLACEHOLDER_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+#[derive(Debug, Clone)]
+pub struct MissingAsset {
+    pub path: String,
+    pub error: String,
+}
+
+/// Every asset that failed to load this session, once per path
+#[derive(Resource, Debug, Default)]
+pub struct MissingAssets {
+    pub entries: Vec<MissingAsset>,
+    placeholder: Handle<Image>,
+    panel_hidden: bool,
+}
+
+#[derive(Component)]
+struct DiagnosticsPanel;
+
+#[derive(Component)]
+struct DiagnosticsText;
+
+fn create_placeholder(mut missing: ResMut<MissingAssets>, mut images: ResMut<Assets<Image>>) {
+    missing.placeholder = images.add(Image::new_fill(
+        Extent3d { width: 2, height: 2, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &PLACEHOLDER_COLOR,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+}
+
+fn record_failures<A: Asset>(mut evr: EventReader<AssetLoadFailedEvent<A>>, mut missing: ResMut<MissingAssets>) {
+    for ev in evr.read() {
+        let path = ev.path.to_string();
+        if missing.entries.iter().any(|e| e.path == path) {
+            continue;
+        }
+        warn!("Missing asset {path}: {}", ev.error);
+        missing.entries.push(MissingAsset { path, error: ev.error.to_string() });
+    }
+}
+
+/// Sprites and UI images pointing at a failed image get the magenta placeholder instead.
+/// Nothing is needed for sounds: playing a failed one just stays silent
+fn swap_in_placeholders(
+    asset_server: Res<AssetServer>,
+    missing: Res<MissingAssets>,
+    mut q_sprites: Query<&mut Sprite>,
+    mut q_nodes: Query<&mut ImageNode>,
+) {
+    if missing.entries.is_empty() {
+        return;
+    }
+    let failed = |id: AssetId<Image>| matches!(asset_server.load_state(id), LoadState::Failed(_));
+    for mut sprite in &mut q_sprites {
+        if failed(sprite.image.id()) {
+            sprite.image = missing.placeholder.clone();
+            sprite.color = Color::WHITE;
+            sprite.texture_atlas = None;
+            sprite.custom_size = sprite.custom_size.or(Some(Vec2::splat(PLACEHOLDER_SIZE)));
+        }
+    }
+    for mut node in &mut q_nodes {
+        if failed(node.image.id()) {
+            node.image = missing.placeholder.clone();
+            node.color = Color::WHITE;
+            node.texture_atlas = None;
+        }
+    }
+}
+
+/// Outlives every state, so failures during loading and in the menu show up too
+fn spawn_diagnostics_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                right: Val::Px(10.0),
+                max_width: Val::Px(420.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                border: UiRect::all(Val::Px(1.0)),
+                ..default()
+            },
+            BorderColor(Color::linear_rgb(1.0, 0.0, 1.0)),
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.85)),
+            GlobalZIndex(10),
+            Visibility::Hidden,
+            DiagnosticsPanel,
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont { font_size: 12.0, ..default() },
+            TextColor(Color::linear_rgb(1.0, 0.6, 1.0)),
+            DiagnosticsText,
+        ));
+}
+
+fn toggle_diagnostics_panel(keys: Res<ButtonInput<KeyCode>>, mut missing: ResMut<MissingAssets>) {
+    if keys.just_pressed(KeyCode::Insert) {
+        missing.panel_hidden = !missing.panel_hidden;
+    }
+}
+
+/// Shown as soon as the first failure comes in, until hidden
+fn update_diagnostics_panel(
+    missing: Res<MissingAssets>,
+    mut q_panel: Query<&mut Visibility, With<DiagnosticsPanel>>,
+    mut q_text: Query<&mut Text, With<DiagnosticsText>>,
+) {
+    if !missing.is_changed() {
+        return;
+    }
+    if let Ok(mut vis) = q_panel.single_mut() {
+        *vis = if missing.entries.is_empty() || missing.panel_hidden { Visibility::Hidden } else { Visibility::Inherited };
+    }
+    if let Ok(mut text) = q_text.single_mut() {
+        let mut lines = vec![format!("{} missing asset(s) - Insert hides", missing.entries.len())];
+        lines.extend(missing.entries.iter().map(|e| format!("{}: {}", e.path, e.error)));
+        text.0 = lines.join("\n");
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::combat::{AbilityBook, AbilityId, AbilityUsedEvent, ApplyDotEvent, CombatState, HotbarSets};
+use crate::player::{Mutator, Mutators};
+use crate::rng::GameRng;
+use crate::sim_time::SimTime;
+use crate::waymarks::CalloutEvent;
+use crate::world::{DotEffects, Enemy};
+use crate::{GameSet, GameState};
+
+// Mistake injection (a menu mutator): at seeded times the pull throws a disruption at the
+// player (an interrupted cast, a dropped DoT, a dead key) and times how long it takes to get
+// the rotation going again. Times are rolled from their own generator derived from the pull
+// seed, so turning this on doesn't change crits or damage rolls.
+
+pub struct MistakesPlugin;
+
+impl Plugin for MistakesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MistakeLog>()
+            .add_systems(OnEnter(GameState::Playing), schedule_mistakes)
+            .add_systems(
+                FixedUpdate,
+                (inject_mistakes, track_recovery)
+                    .chain()
+                    .in_set(GameSet::Sim)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// How long an interrupt keeps the player from acting
+const INTERRUPT_SECS: f32 = 1.0;
+const KEY_LOCK_SECS: f32 = 2.0;
+/// Range of the gap between injections, in seconds
+const GAP_SECS: (f32, f32) = (12.0, 25.0);
+/// Nothing is injected this early; the opener is practiced separately
+const FIRST_AFTER_SECS: f32 = 10.0;
+/// Covers any pull the timeline runs
+const SCHEDULE_SECS: f32 = 600.0;
+/// Mixed into the pull seed for the injection schedule
+const SEED_SALT: u64 = 0x6d69_7374_616b_6573;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MistakeKind {
+    Interrupt,
+    DroppedDot(AbilityId),
+    LockedKey(AbilityId),
+}
+
+impl MistakeKind {
+    pub fn label(&self, book: &AbilityBook) -> String {
+        let name = |id| book.by_id.get(id).map(|a| a.name).unwrap_or("?");
+        match self {
+            MistakeKind::Interrupt => "Interrupted".to_string(),
+            MistakeKind::DroppedDot(id) => format!("{} dropped", name(id)),
+            MistakeKind::LockedKey(id) => format!("{} locked", name(id)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InjectedMistake {
+    /// Seconds into the pull
+    pub at: f32,
+    pub kind: MistakeKind,
+    /// Seconds until the rotation was back on track, None while still waiting
+    pub recovered_in: Option<f32>,
+}
+
+impl InjectedMistake {
+    pub fn grade(&self) -> &'static str {
+        match self.recovered_in {
+            Some(t) if t <= 1.5 => "Clean",
+            Some(t) if t <= 3.0 => "OK",
+            Some(_) => "Slow",
+            None => "Not recovered",
+        }
+    }
+}
+
+/// Injections for the current pull: what's still to come and what already happened
+#[derive(Resource, Debug, Default)]
+pub struct MistakeLog {
+    pub injected: Vec<InjectedMistake>,
+    /// Times still to come, soonest last
+    pending: Vec<f32>,
+    rng: Option<StdRng>,
+    t: f32,
+}
+
+fn schedule_mistakes(mut log: ResMut<MistakeLog>, mutators: Res<Mutators>, game_rng: Res<GameRng>) {
+    *log = MistakeLog::default();
+    if !mutators.is_active(Mutator::Mistakes) {
+        return;
+    }
+    let mut rng = StdRng::seed_from_u64(game_rng.seed() ^ SEED_SALT);
+    let mut at = FIRST_AFTER_SECS;
+    while at < SCHEDULE_SECS {
+        at += rng.gen_range(GAP_SECS.0..GAP_SECS.1);
+        log.pending.push(at);
+    }
+    log.pending.reverse();
+    log.rng = Some(rng);
+}
+
+fn inject_mistakes(
+    time: SimTime,
+    book: Res<AbilityBook>,
+    sets: Res<HotbarSets>,
+    mut log: ResMut<MistakeLog>,
+    mut combat: ResMut<CombatState>,
+    mut q_enemy: Query<&mut DotEffects, With<Enemy>>,
+    mut callout_writer: EventWriter<CalloutEvent>,
+) {
+    let log = &mut *log;
+    let Some(rng) = log.rng.as_mut() else { return; };
+    log.t += time.scaled_delta();
+    while log.pending.last().is_some_and(|at| *at <= log.t) {
+        log.pending.pop();
+        let mut ticking = q_enemy.single_mut().ok().filter(|d| !d.dots.is_empty());
+        let kind = match (rng.gen_range(0..3), ticking.as_mut()) {
+            (1, Some(dots)) => {
+                let idx = rng.gen_range(0..dots.dots.len());
+                let dropped = dots.dots.remove(idx);
+                MistakeKind::DroppedDot(dropped.source)
+            }
+            (2, _) => {
+                let slots = sets.active_slots();
+                let id = slots[rng.gen_range(0..slots.len())];
+                combat.locked_ability = Some((id, KEY_LOCK_SECS));
+                MistakeKind::LockedKey(id)
+            }
+            // Also what a DoT drop falls back to when nothing is ticking
+            _ => {
+                combat.cast = None;
+                combat.gcd_queue = None;
+                combat.buffer = None;
+                combat.ani_lock_remaining = combat.ani_lock_remaining.max(INTERRUPT_SECS);
+                MistakeKind::Interrupt
+            }
+        };
+        info!("Injected mistake at {:.1}s: {}", log.t, kind.label(&book));
+        callout_writer.write(CalloutEvent { text: kind.label(&book), waymark: None });
+        log.injected.push(InjectedMistake { at: log.t, kind, recovered_in: None });
+    }
+}
+
+/// Interrupts and locked keys are recovered by the next GCD that goes off; a dropped DoT
+/// by putting it back up
+fn track_recovery(
+    book: Res<AbilityBook>,
+    mut log: ResMut<MistakeLog>,
+    mut used: EventReader<AbilityUsedEvent>,
+    mut dots: EventReader<ApplyDotEvent>,
+) {
+    let gcd_used = used.read().filter(|ev| book.by_id.get(&ev.id).is_some_and(|a| a.triggers_gcd)).count() > 0;
+    let reapplied: Vec<AbilityId> = dots.read().map(|ev| ev.source).collect();
+    let t = log.t;
+    // Anything from the frame of the injection itself was pressed before it
+    for mistake in log.injected.iter_mut().filter(|m| m.recovered_in.is_none() && m.at < t) {
+        let recovered = match mistake.kind {
+            MistakeKind::DroppedDot(source) => reapplied.contains(&source),
+            MistakeKind::Interrupt | MistakeKind::LockedKey(_) => gcd_used,
+        };
+        if recovered {
+            let took = t - mistake.at;
+            mistake.recovered_in = Some(took);
+            info!("Recovered from {} in {:.1}s ({})", mistake.kind.label(&book), took, mistake.grade());
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::adds::Add;
+use crate::mechanics::Telegraph;
+use crate::world::{Armor, Enemy, Health};
+use crate::{GameSet, GameState};
+
+// Nameplates: name, HP percent, armor icon and a cast bar floating above each enemy. They're children of
+// the enemy sprite so they follow it around. With only the boss up the HP bar at the top of
+// the screen says it all, so nameplates only show while adds are out, and the top bar steps
+// aside for them.
+
+pub struct NameplatesPlugin;
+
+impl Plugin for NameplatesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (attach_nameplates, update_nameplates).chain().in_set(GameSet::Ui).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+const PLATE_WIDTH: f32 = 80.0;
+/// Height above the sprite's center when the sprite doesn't say how big it is
+const BOSS_PLATE_OFFSET: f32 = 90.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Root,
+    Label,
+    Hp,
+    /// Left of the HP bar, orange while shredded
+    Armor,
+    Cast,
+    CastLabel,
+}
+
+/// One piece of a nameplate and the enemy it belongs to
+#[derive(Component, Debug, Clone, Copy)]
+struct Nameplate {
+    owner: Entity,
+    part: Part,
+}
+
+fn attach_nameplates(
+    mut commands: Commands,
+    q_new: Query<(Entity, Option<&Sprite>), Or<(Added<Enemy>, Added<Add>)>>,
+) {
+    for (owner, sprite) in &q_new {
+        let offset = sprite.and_then(|s| s.custom_size).map_or(BOSS_PLATE_OFFSET, |size| size.y / 2.0 + 30.0);
+        let part = |part| Nameplate { owner, part };
+        let bar = |color| Sprite { color, custom_size: Some(Vec2::new(PLATE_WIDTH, 5.0)), ..default() };
+        // Fills grow from the left edge of their bar
+        let fill = |color| Sprite { color, custom_size: Some(Vec2::new(0.0, 5.0)), anchor: Anchor::CenterLeft, ..default() };
+        commands.entity(owner).with_children(|e| {
+            e.spawn((Transform::from_xyz(0.0, offset, 2.0), Visibility::Hidden, part(Part::Root))).with_children(|plate| {
+                plate.spawn((
+                    Text2d::new(""),
+                    TextFont { font_size: 12.0, ..default() },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    Transform::from_xyz(0.0, 12.0, 0.0),
+                    part(Part::Label),
+                ));
+                plate.spawn((bar(Color::linear_rgb(0.05, 0.05, 0.05)), Transform::default()));
+                plate.spawn((
+                    fill(Color::linear_rgb(0.8, 0.2, 0.2)),
+                    Transform::from_xyz(-PLATE_WIDTH / 2.0, 0.0, 0.1),
+                    part(Part::Hp),
+                ));
+                plate.spawn((
+                    Sprite { custom_size: Some(Vec2::new(6.0, 8.0)), ..default() },
+                    Transform::from_xyz(-PLATE_WIDTH / 2.0 - 6.0, 0.0, 0.1),
+                    part(Part::Armor),
+                ));
+                plate.spawn((bar(Color::linear_rgb(0.05, 0.05, 0.05)), Transform::from_xyz(0.0, -7.0, 0.0)));
+                plate.spawn((
+                    fill(Color::linear_rgb(0.9, 0.6, 0.2)),
+                    Transform::from_xyz(-PLATE_WIDTH / 2.0, -7.0, 0.1),
+                    part(Part::Cast),
+                ));
+                plate.spawn((
+                    Text2d::new(""),
+                    TextFont { font_size: 10.0, ..default() },
+                    TextColor(Color::linear_rgb(0.9, 0.7, 0.4)),
+                    Transform::from_xyz(0.0, -17.0, 0.0),
+                    part(Part::CastLabel),
+                ));
+            });
+        });
+    }
+}
+
+fn update_nameplates(
+    q_units: Query<(&Health, Option<&Armor>, Option<&Add>, Has<Enemy>)>,
+    q_adds: Query<(), With<Add>>,
+    q_telegraphs: Query<&Telegraph>,
+    mut q_parts: Query<(&Nameplate, &mut Visibility, Option<&mut Sprite>, Option<&mut Text2d>)>,
+) {
+    let shown = !q_adds.is_empty();
+    // Whichever telegraph goes off next is what the boss is casting
+    let boss_cast = q_telegraphs
+        .iter()
+        .min_by(|a, b| a.remaining.total_cmp(&b.remaining))
+        .map(|t| (t.name.as_str(), 1.0 - t.remaining / t.windup.max(f32::EPSILON)));
+    for (plate, mut vis, sprite, text) in &mut q_parts {
+        let Ok((hp, armor, add, is_boss)) = q_units.get(plate.owner) else { continue; };
+        let cast = if is_boss { boss_cast } else { None };
+        let pct = if hp.max > 0 { (hp.current as f32 / hp.max as f32).clamp(0.0, 1.0) } else { 0.0 };
+        match plate.part {
+            Part::Root => *vis = if shown { Visibility::Inherited } else { Visibility::Hidden },
+            Part::Label => {
+                if let Some(mut text) = text {
+                    let name = add.map_or("Boss", |add| add.name.as_str());
+                    text.0 = format!("{name}  {:.0}%", pct * 100.0);
+                }
+            }
+            Part::Hp => {
+                if let Some(mut sprite) = sprite {
+                    sprite.custom_size = Some(Vec2::new(PLATE_WIDTH * pct, 5.0));
+                }
+            }
+            Part::Armor => {
+                if let Some(mut sprite) = sprite {
+                    sprite.color = armor.map_or(Color::NONE, Armor::icon_color);
+                }
+            }
+            Part::Cast => {
+                if let Some(mut sprite) = sprite {
+                    let p = cast.map_or(0.0, |(_, p)| p.clamp(0.0, 1.0));
+                    sprite.custom_size = Some(Vec2::new(PLATE_WIDTH * p, 5.0));
+                }
+            }
+            Part::CastLabel => {
+                if let Some(mut text) = text {
+                    text.0 = cast.map(|(name, _)| name.to_string()).unwrap_or_default();
+                }
+            }
+        }
+    }
+}
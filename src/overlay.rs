@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use std::collections::HashSet;
+
+use crate::{GameSet, GameState};
+
+// Stream overlay: started with `--overlay chroma` or `--overlay transparent`, the window
+// shows only the telemetry widgets (cast bar, DPS meter, GCD queue) while a pull runs, over
+// a flat green to key out or a see-through window, so it can be captured on top of a stream.
+// The arena isn't drawn at all, and every other HUD node is hidden after the HUD has had its
+// say each frame. Menus and the results screen show as normal.
+
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, set_backdrop.run_if(resource_exists::<StreamOverlay>))
+            .add_systems(Update, hide_world.run_if(resource_exists::<StreamOverlay>))
+            .add_systems(
+                Update,
+                hide_other_hud
+                    .after(GameSet::Ui)
+                    .run_if(resource_exists::<StreamOverlay>.and(in_state(GameState::Playing))),
+            );
+    }
+}
+
+/// How the overlay window is backed, from the command line
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamOverlay {
+    /// Pure green, for a chroma key filter
+    ChromaKey,
+    /// A see-through window, where the platform supports one
+    Transparent,
+}
+
+/// HUD node shown in the overlay, along with everything inside it
+#[derive(Component)]
+pub struct OverlayWidget;
+
+/// Render layer nothing in the arena is on, so the camera draws only the UI
+const OVERLAY_LAYER: usize = 31;
+
+fn set_backdrop(overlay: Res<StreamOverlay>, mut clear: ResMut<ClearColor>) {
+    clear.0 = match *overlay {
+        StreamOverlay::ChromaKey => Color::srgb(0.0, 1.0, 0.0),
+        StreamOverlay::Transparent => Color::NONE,
+    };
+}
+
+fn hide_world(mut commands: Commands, q_camera: Query<Entity, Added<Camera2d>>) {
+    for camera in &q_camera {
+        commands.entity(camera).insert(RenderLayers::layer(OVERLAY_LAYER));
+    }
+}
+
+/// Hides every UI node that isn't a widget, inside one, or holding one
+fn hide_other_hud(
+    q_widgets: Query<Entity, With<OverlayWidget>>,
+    q_children: Query<&Children>,
+    q_parents: Query<&ChildOf>,
+    mut q_nodes: Query<(Entity, &mut Visibility), With<Node>>,
+) {
+    let mut shown = HashSet::new();
+    for widget in &q_widgets {
+        shown.insert(widget);
+        shown.extend(q_children.iter_descendants(widget));
+        shown.extend(q_parents.iter_ancestors(widget));
+    }
+    for (entity, mut vis) in &mut q_nodes {
+        if !shown.contains(&entity) {
+            vis.set_if_neq(Visibility::Hidden);
+        }
+    }
+}
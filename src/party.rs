@@ -0,0 +1,448 @@
+use bevy::prelude::*;
+
+use crate::actions::{ActionSet, Actions, SimAction, SimInput};
+use crate::combat::{update_statuses, AbilityBook, AbilityId, AbilityUsedEvent, CombatState, StatusDef};
+use crate::coop::RemotePlayer;
+use crate::loading::TextureAssets;
+use crate::mechanics::{MechanicKind, Telegraph};
+use crate::player::Player;
+use crate::sim_time::SimTime;
+use crate::world::{DamageDealtEvent, Enmity, HealTarget, Health};
+use crate::{GameSet, GameState};
+
+// NPC party members standing in for the rest of a light party. They follow simple
+// rules so stack and spread mechanics have someone to resolve with: join stacks on
+// the player, run to their own spot for spreads, and otherwise idle at their post.
+// The player is the tank: their damage builds enmity in tank stance, so they hold the
+// boss and take its tankbusters unless the NPCs out-threat them. Members who fail a
+// mechanic pick up the same debuff the player would, and the healer casts Cure on
+// whoever is hurt while standing at their post; running for a mechanic cuts it off.
+//
+// Party frames on the left list the player and every member with HP, statuses and what
+// they're casting. Clicking a frame makes that member the target of Heal, which heals them
+// outright instead of leaving a regen on the player; clicking the player's own frame goes
+// back to self heals.
+//
+// A co-op partner (see `coop`) is a party member too, moved by their own player instead.
+
+pub struct PartyPlugin;
+
+impl Plugin for PartyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), (spawn_party, spawn_party_frames))
+            .add_systems(
+                FixedUpdate,
+                (
+                    (move_party, cast_cures, tick_member_debuffs).chain(),
+                    gain_enmity,
+                    // Before the player's statuses see the Heal, so one on a member who just went down
+                    // comes back to the player as a regen
+                    heal_party_member.before(update_statuses),
+                )
+                    .in_set(GameSet::Sim)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(FixedUpdate, set_heal_target.in_set(ActionSet::Apply).run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                (click_party_frame, update_party_frames).chain().in_set(GameSet::Ui).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct PartyMember {
+    pub name: String,
+    /// Where the member idles and goes for spreads
+    pub post: Vec2,
+    /// Enmity generated per second, standing in for the member's own damage
+    pub threat: f32,
+}
+
+/// A debuff a mechanic left on a member; members only carry what they pick up from mechanics
+#[derive(Debug, Clone)]
+pub struct MemberDebuff {
+    pub def: StatusDef,
+    pub remaining: f32,
+    pub stacks: u32,
+}
+
+/// Debuffs on a party member
+#[derive(Component, Debug, Default)]
+pub struct MemberDebuffs(pub Vec<MemberDebuff>);
+
+impl MemberDebuffs {
+    /// Puts a status on the member, or refreshes it and adds a stack if it's already up
+    pub fn apply(&mut self, def: &StatusDef) {
+        if let Some(debuff) = self.0.iter_mut().find(|d| d.def.id == def.id) {
+            debuff.remaining = def.duration;
+            debuff.stacks = (debuff.stacks + 1).min(def.max_stacks.max(1));
+            return;
+        }
+        self.0.push(MemberDebuff { def: def.clone(), remaining: def.duration, stacks: 1 });
+    }
+
+    pub fn damage_taken_mult(&self) -> f32 {
+        self.0.iter().map(|d| d.def.modifiers.damage_taken.powi(d.stacks as i32)).product()
+    }
+}
+
+/// A party member taking a hit, through their debuffs
+pub fn hit_member(member: &PartyMember, debuffs: &MemberDebuffs, hp: &mut Health, amount: i32) {
+    hp.current = (hp.current - (amount as f32 * debuffs.damage_taken_mult()) as i32).max(0);
+    if hp.current == 0 {
+        info!("{} is down", member.name);
+    }
+}
+
+/// What a member is casting: (name, seconds left, cast time)
+#[derive(Component, Debug, Default)]
+pub struct MemberCast(pub Option<(&'static str, f32, f32)>);
+
+/// The NPC healer, with the seconds until it looks for someone to Cure
+#[derive(Component, Default)]
+struct Healer(f32);
+
+const CURE_CAST: f32 = 2.0;
+/// Seconds from one Cure starting to the healer looking for the next
+const CURE_EVERY: f32 = 6.0;
+/// Members under this share of their HP get a Cure
+const CURE_BELOW: f32 = 0.8;
+
+/// Enmity per point of damage the player deals
+const TANK_STANCE: f32 = 5.0;
+
+/// What Heal restores on a party member, about what its regen does for the player
+const MEMBER_HEAL: i32 = 300;
+
+const PARTY_SPEED: f32 = 130.0;
+/// How close to the player members get when stacking
+const STACK_DISTANCE: f32 = 25.0;
+
+/// (name, post, tint, threat per second, casts Cure)
+const PARTY: [(&str, Vec2, Color, f32, bool); 3] = [
+    ("Tank", Vec2::new(120.0, 0.0), Color::linear_rgb(0.4, 0.6, 1.0), 150.0, false),
+    ("Healer", Vec2::new(-40.0, 160.0), Color::linear_rgb(0.4, 1.0, 0.5), 40.0, true),
+    ("Caster", Vec2::new(-40.0, -160.0), Color::linear_rgb(1.0, 0.5, 0.9), 120.0, false),
+];
+
+fn spawn_party(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    q_existing: Query<Entity, (With<PartyMember>, Without<RemotePlayer>)>,
+) {
+    for e in &q_existing {
+        commands.entity(e).despawn();
+    }
+    for (name, post, tint, threat, heals) in PARTY {
+        let mut member = commands.spawn((
+            Sprite { image: textures.bevy.clone(), color: tint, custom_size: Some(Vec2::splat(48.0)), ..default() },
+            Transform::from_translation(post.extend(0.9)),
+            PartyMember { name: name.to_string(), post, threat },
+            Health { current: 1000, max: 1000 },
+            Enmity::default(),
+            MemberDebuffs::default(),
+            MemberCast::default(),
+            StateScoped(GameState::Playing),
+        ));
+        if heals {
+            member.insert(Healer::default());
+        }
+    }
+}
+
+fn move_party(
+    time: SimTime,
+    q_player: Query<&Transform, (With<Player>, Without<PartyMember>)>,
+    q_telegraphs: Query<&Telegraph>,
+    mut q_party: Query<(&mut Transform, &PartyMember, &Health), Without<RemotePlayer>>,
+) {
+    let Ok(player) = q_player.single() else { return; };
+    let player = player.translation.truncate();
+    let stacking = q_telegraphs.iter().any(|t| t.kind == MechanicKind::Stack);
+    let step = PARTY_SPEED * time.scaled_delta();
+    for (mut transform, member, hp) in &mut q_party {
+        if hp.current <= 0 {
+            continue;
+        }
+        let pos = transform.translation.truncate();
+        let goal = if stacking {
+            // Stop just short of the player instead of standing on them
+            player + (pos - player).normalize_or_zero() * STACK_DISTANCE
+        } else {
+            member.post
+        };
+        let to_goal = goal - pos;
+        let moved = if to_goal.length() <= step { goal } else { pos + to_goal.normalize() * step };
+        transform.translation = moved.extend(transform.translation.z);
+    }
+}
+
+/// The healer Cures the most hurt member while standing at their post
+fn cast_cures(
+    time: SimTime,
+    mut q_healer: Query<(Entity, &Transform, &PartyMember, &mut Healer, &mut MemberCast)>,
+    mut q_party: Query<(Entity, &mut Health), With<PartyMember>>,
+) {
+    let dt = time.scaled_delta();
+    let most_hurt = |q_party: &Query<(Entity, &mut Health), With<PartyMember>>| {
+        q_party
+            .iter()
+            .filter(|(_, hp)| hp.current > 0 && (hp.current as f32) < hp.max as f32 * CURE_BELOW)
+            .min_by(|a, b| (a.1.current as f32 / a.1.max as f32).total_cmp(&(b.1.current as f32 / b.1.max as f32)))
+            .map(|(e, _)| e)
+    };
+    for (entity, transform, member, mut healer, mut cast) in &mut q_healer {
+        healer.0 -= dt;
+        let alive = q_party.get(entity).is_ok_and(|(_, hp)| hp.current > 0);
+        if !alive || transform.translation.truncate() != member.post {
+            cast.0 = None;
+            continue;
+        }
+        match &mut cast.0 {
+            Some((_, remaining, _)) => {
+                *remaining -= dt;
+                if *remaining > 0.0 {
+                    continue;
+                }
+                cast.0 = None;
+                if let Some((_, mut hp)) = most_hurt(&q_party).and_then(|e| q_party.get_mut(e).ok()) {
+                    hp.current = (hp.current + MEMBER_HEAL).min(hp.max);
+                }
+            }
+            None if healer.0 <= 0.0 && most_hurt(&q_party).is_some() => {
+                cast.0 = Some(("Cure", CURE_CAST, CURE_CAST));
+                healer.0 = CURE_EVERY;
+            }
+            None => {}
+        }
+    }
+}
+
+fn tick_member_debuffs(time: SimTime, mut q_party: Query<(&mut MemberDebuffs, &Health)>) {
+    let dt = time.scaled_delta();
+    for (mut debuffs, hp) in &mut q_party {
+        // Nothing stays on the dead
+        if hp.current <= 0 {
+            debuffs.0.clear();
+        }
+        for debuff in &mut debuffs.0 {
+            debuff.remaining -= dt;
+        }
+        debuffs.0.retain(|d| d.remaining > 0.0);
+    }
+}
+
+fn gain_enmity(
+    time: SimTime,
+    mut evr: EventReader<DamageDealtEvent>,
+    mut q_player: Query<&mut Enmity, With<Player>>,
+    mut q_party: Query<(&mut Enmity, &PartyMember, &Health), Without<Player>>,
+) {
+    if let Ok(mut enmity) = q_player.single_mut() {
+        enmity.0 += evr.read().map(|ev| ev.amount as f32 * TANK_STANCE).sum::<f32>();
+    }
+    let dt = time.scaled_delta();
+    for (mut enmity, member, hp) in &mut q_party {
+        if hp.current > 0 {
+            enmity.0 += member.threat * dt;
+        }
+    }
+}
+
+fn heal_party_member(
+    mut used: EventReader<AbilityUsedEvent>,
+    mut heal_target: ResMut<HealTarget>,
+    mut q_party: Query<&mut Health, With<PartyMember>>,
+) {
+    // Nobody heals the dead; Heal goes back to the player
+    if heal_target.0.is_some_and(|e| q_party.get(e).map_or(true, |hp| hp.current <= 0)) {
+        heal_target.0 = None;
+    }
+    let heals = used.read().filter(|ev| ev.id == AbilityId::Heal).count() as i32;
+    if heals == 0 {
+        return;
+    }
+    let Some(mut hp) = heal_target.0.and_then(|e| q_party.get_mut(e).ok()) else { return; };
+    hp.current = (hp.current + MEMBER_HEAL * heals).min(hp.max);
+}
+
+// ==== Party frames ====
+
+/// A clickable frame; slot 0 is the player, then members in spawn order
+#[derive(Component)]
+struct PartyFrame(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FramePart {
+    Name,
+    Hp,
+    Statuses,
+    Cast,
+}
+
+#[derive(Component)]
+struct PartyFrameText(usize, FramePart);
+
+#[derive(Component)]
+struct PartyFrameHp(usize);
+
+const FRAME_NORMAL: Color = Color::linear_rgb(0.05, 0.05, 0.05);
+const FRAME_HEAL_TARGET: Color = Color::linear_rgb(0.1, 0.25, 0.12);
+
+fn spawn_party_frames(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(300.0),
+                left: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|list| {
+            // One more than the NPCs, for a co-op partner
+            for slot in 0..=PARTY.len() + 1 {
+                list.spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(180.0),
+                        padding: UiRect::all(Val::Px(4.0)),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(2.0),
+                        ..default()
+                    },
+                    BackgroundColor(FRAME_NORMAL.with_alpha(0.7)),
+                    PartyFrame(slot),
+                ))
+                .with_children(|frame| {
+                    frame.spawn((
+                        Text::new(""),
+                        TextFont { font_size: 13.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                        PartyFrameText(slot, FramePart::Name),
+                    ));
+                    frame
+                        .spawn((Node { height: Val::Px(8.0), ..default() }, BackgroundColor(Color::linear_rgb(0.02, 0.02, 0.02))))
+                        .with_child((
+                            Node { width: Val::Percent(100.0), height: Val::Percent(100.0), ..default() },
+                            BackgroundColor(Color::linear_rgb(0.3, 0.8, 0.35)),
+                            PartyFrameHp(slot),
+                        ));
+                    for part in [FramePart::Hp, FramePart::Statuses, FramePart::Cast] {
+                        frame.spawn((
+                            Text::new(""),
+                            TextFont { font_size: 10.0, ..default() },
+                            TextColor(Color::linear_rgb(0.75, 0.75, 0.75)),
+                            PartyFrameText(slot, part),
+                        ));
+                    }
+                });
+            }
+        });
+}
+
+/// Entities behind the frames, in slot order
+fn frame_units(q_player: &Query<Entity, With<Player>>, q_party: &Query<(Entity, &PartyMember, &Health)>) -> Vec<Option<Entity>> {
+    let mut members: Vec<Entity> = q_party.iter().map(|(e, ..)| e).collect();
+    members.sort();
+    std::iter::once(q_player.single().ok()).chain(members.into_iter().map(Some)).collect()
+}
+
+fn click_party_frame(mut actions: ResMut<Actions>, q_frames: Query<(&Interaction, &PartyFrame), Changed<Interaction>>) {
+    for (interaction, PartyFrame(slot)) in &q_frames {
+        if *interaction == Interaction::Pressed {
+            actions.queue(SimAction::HealTarget(*slot));
+        }
+    }
+}
+
+fn set_heal_target(
+    input: Res<SimInput>,
+    mut heal_target: ResMut<HealTarget>,
+    q_player: Query<Entity, With<Player>>,
+    q_party: Query<(Entity, &PartyMember, &Health)>,
+) {
+    for action in &input.actions {
+        let SimAction::HealTarget(slot) = action else { continue; };
+        // Slot 0 is the player: heals go back to self
+        let unit = frame_units(&q_player, &q_party).get(*slot).copied().flatten();
+        heal_target.0 = unit.filter(|e| q_party.get(*e).is_ok_and(|(.., hp)| hp.current > 0));
+    }
+}
+
+fn update_party_frames(
+    combat: Res<CombatState>,
+    book: Res<AbilityBook>,
+    heal_target: Res<HealTarget>,
+    q_player: Query<Entity, With<Player>>,
+    q_player_hp: Query<&Health, With<Player>>,
+    q_party: Query<(Entity, &PartyMember, &Health)>,
+    q_member_state: Query<(&MemberDebuffs, Option<&MemberCast>)>,
+    mut q_frames: Query<(&PartyFrame, &mut BackgroundColor, &mut Visibility)>,
+    mut q_hp: Query<(&PartyFrameHp, &mut Node)>,
+    mut q_text: Query<(&PartyFrameText, &mut Text)>,
+) {
+    let units = frame_units(&q_player, &q_party);
+    // (current, max) per slot, None when nobody is behind it
+    let hps: Vec<Option<(i32, i32)>> = units
+        .iter()
+        .enumerate()
+        .map(|(slot, unit)| match slot {
+            0 => q_player_hp.single().ok().map(|hp| (hp.current, hp.max)),
+            _ => unit.and_then(|e| q_party.get(e).ok()).map(|(.., hp)| (hp.current, hp.max)),
+        })
+        .collect();
+    let health = |slot: usize| hps.get(slot).copied().flatten();
+    for (PartyFrame(slot), mut color, mut vis) in &mut q_frames {
+        *vis = if health(*slot).is_some() { Visibility::Inherited } else { Visibility::Hidden };
+        let targeted = match *slot {
+            0 => heal_target.0.is_none(),
+            _ => heal_target.0.is_some() && heal_target.0 == units.get(*slot).copied().flatten(),
+        };
+        color.0 = if targeted { FRAME_HEAL_TARGET } else { FRAME_NORMAL }.with_alpha(0.7);
+    }
+    for (PartyFrameHp(slot), mut node) in &mut q_hp {
+        let pct = health(*slot).map_or(0.0, |(current, max)| if max > 0 { (current as f32 / max as f32).clamp(0.0, 1.0) } else { 0.0 });
+        node.width = Val::Percent(pct * 100.0);
+    }
+    for (PartyFrameText(slot, part), mut text) in &mut q_text {
+        let Some((current, max)) = health(*slot) else { continue; };
+        let member_state = units.get(*slot).copied().flatten().and_then(|e| q_member_state.get(e).ok());
+        let line = match (part, *slot) {
+            (FramePart::Name, 0) => "You".to_string(),
+            (FramePart::Name, _) => {
+                let member = units.get(*slot).copied().flatten().and_then(|e| q_party.get(e).ok());
+                member.map_or(String::new(), |(_, m, _)| m.name.clone())
+            }
+            (FramePart::Hp, _) if current <= 0 => "Down".to_string(),
+            (FramePart::Hp, _) => format!("{current} / {max}"),
+            (FramePart::Statuses, 0) => combat.statuses.iter().map(|s| s.def.name.as_str()).collect::<Vec<_>>().join(", "),
+            (FramePart::Cast, 0) => combat
+                .cast
+                .as_ref()
+                .map(|cast| format!("Casting {}", book.by_id.get(&cast.ability).map_or("?", |a| a.name)))
+                .unwrap_or_default(),
+            (FramePart::Statuses, _) => member_state
+                .map(|(debuffs, _)| {
+                    let names: Vec<String> = debuffs
+                        .0
+                        .iter()
+                        .map(|d| if d.stacks > 1 { format!("{} x{}", d.def.name, d.stacks) } else { d.def.name.clone() })
+                        .collect();
+                    names.join(", ")
+                })
+                .unwrap_or_default(),
+            (FramePart::Cast, _) => member_state
+                .and_then(|(_, cast)| cast?.0)
+                .map(|(name, ..)| format!("Casting {name}"))
+                .unwrap_or_default(),
+        };
+        if text.0 != line {
+            text.0 = line;
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+
+use crate::combat::Metronome;
+use crate::sim_time::SimTimeScale;
+use crate::{GameSet, GameState, PlayState};
+
+// Pause menu: Escape during a pull stops the virtual clock, which freezes everything that
+// ticks on it (combat timers, DoTs, the enemy timeline, VFX) and holds back input. Casts,
+// queued GCDs and buffered presses stay as they were and carry on once resumed.
+//
+// The clock stops from the frame after Escape and starts again the frame after Resume, so
+// a frame either runs in full or not at all. No ticks run while frozen, so replays never see it.
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            toggle_pause.in_set(GameSet::InputRead).run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnEnter(PlayState::Paused), (freeze_clock, spawn_pause_menu))
+        .add_systems(OnExit(PlayState::Paused), thaw_clock)
+        .add_systems(Update, click_pause_button.run_if(in_state(PlayState::Paused)));
+    }
+}
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+
+#[derive(Component, Debug, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Settings,
+    Restart,
+    Quit,
+    Metronome,
+    Speed,
+}
+
+/// Settings that make sense mid-pull, folded away until Settings is pressed
+#[derive(Component)]
+struct PauseSettings;
+
+fn metronome_label(metronome: &Metronome) -> String {
+    format!("GCD metronome: {}", if metronome.enabled { "on" } else { "off" })
+}
+
+fn speed_label(scale: &SimTimeScale) -> String {
+    format!("Sim speed: x{}", scale.scale)
+}
+
+fn toggle_pause(keys: Res<ButtonInput<KeyCode>>, state: Res<State<PlayState>>, mut next_state: ResMut<NextState<PlayState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(match state.get() {
+            PlayState::Running => PlayState::Paused,
+            PlayState::Paused => PlayState::Running,
+        });
+    }
+}
+
+fn freeze_clock(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn thaw_clock(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn spawn_button(parent: &mut ChildSpawnerCommands, label: String, button: PauseButton) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(200.0),
+                height: Val::Px(36.0),
+                margin: UiRect::top(Val::Px(8.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_NORMAL),
+            button,
+        ))
+        .with_child((
+            Text::new(label),
+            TextFont { font_size: 20.0, ..default() },
+            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+        ));
+}
+
+fn spawn_pause_menu(mut commands: Commands, metronome: Res<Metronome>, scale: Res<SimTimeScale>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.6)),
+            GlobalZIndex(10),
+            StateScoped(PlayState::Paused),
+        ))
+        .with_children(|children| {
+            children.spawn((
+                Text::new("Paused"),
+                TextFont { font_size: 40.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            spawn_button(children, "Resume".to_string(), PauseButton::Resume);
+            spawn_button(children, "Settings".to_string(), PauseButton::Settings);
+            children
+                .spawn((
+                    Node { flex_direction: FlexDirection::Column, align_items: AlignItems::Center, ..default() },
+                    Visibility::Hidden,
+                    PauseSettings,
+                ))
+                .with_children(|settings| {
+                    spawn_button(settings, metronome_label(&metronome), PauseButton::Metronome);
+                    spawn_button(settings, speed_label(&scale), PauseButton::Speed);
+                });
+            spawn_button(children, "Restart pull (Del)".to_string(), PauseButton::Restart);
+            spawn_button(children, "Quit to menu".to_string(), PauseButton::Quit);
+        });
+}
+
+fn click_pause_button(
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_play_state: ResMut<NextState<PlayState>>,
+    mut metronome: ResMut<Metronome>,
+    mut scale: ResMut<SimTimeScale>,
+    mut q_settings: Query<&mut Visibility, With<PauseSettings>>,
+    mut q_labels: Query<&mut Text>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &PauseButton, &Children), Changed<Interaction>>,
+) {
+    for (interaction, mut color, button, children) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                let label = match button {
+                    PauseButton::Resume => {
+                        next_play_state.set(PlayState::Running);
+                        None
+                    }
+                    PauseButton::Settings => {
+                        if let Ok(mut vis) = q_settings.single_mut() {
+                            *vis = if *vis == Visibility::Hidden { Visibility::Inherited } else { Visibility::Hidden };
+                        }
+                        None
+                    }
+                    PauseButton::Restart => {
+                        next_game_state.set(GameState::Restarting);
+                        None
+                    }
+                    PauseButton::Quit => {
+                        next_game_state.set(GameState::Menu);
+                        None
+                    }
+                    PauseButton::Metronome => {
+                        metronome.toggle();
+                        Some(metronome_label(&metronome))
+                    }
+                    PauseButton::Speed => {
+                        scale.cycle();
+                        Some(speed_label(&scale))
+                    }
+                };
+                let Some(label) = label else { continue; };
+                for child in children.iter() {
+                    if let Ok(mut text) = q_labels.get_mut(child) {
+                        text.0 = label.clone();
+                    }
+                }
+            }
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
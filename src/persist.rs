@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::IoTaskPool;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+use std::sync::{LazyLock, Mutex};
+
+// Small helpers for user data that lives next to the game (waymarks, profile, ...)
+//
+// Saves run on the IO task pool so a write never stalls a frame. Each one goes to a
+// temporary file that is then renamed over the real one, so a crash mid-write leaves the
+// old contents intact. Until it lands, loading the file gets what's being written, so
+// reading one back to add to it (like the rotation stats after every pull) can't drop an
+// earlier save still on its way. While any save is in flight a small "Saving..." note shows.
+//
+// In the browser there's no file system to write to, so the same names are keys in the
+// page's LocalStorage instead (prefixed with the data dir), written right away.
+
+pub struct PersistPlugin;
+
+impl Plugin for PersistPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_saving_indicator).add_systems(Update, update_saving_indicator);
+    }
+}
+
+/// Saves started but not yet on disk
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+/// Latest save number per path, with its contents until they're on disk; an older write
+/// finishing late is dropped instead of overwriting a newer one
+#[cfg(not(target_arch = "wasm32"))]
+static LATEST: LazyLock<Mutex<HashMap<PathBuf, (u64, Option<Arc<[u8]>>)>>> = LazyLock::new(Default::default);
+/// Last number handed out per `<dir>/<prefix>`, so two names reserved before either file
+/// is written still differ
+static RESERVED: LazyLock<Mutex<HashMap<String, u32>>> = LazyLock::new(Default::default);
+
+/// Root directory for saved user data. Override with the `JRPG_DATA_DIR` env var.
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("JRPG_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("userdata"))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use web::{list, load, remove, save, save_bytes};
+
+/// Reserves the next free `<dir>/<prefix>_<n>` (n zero-padded to 4 digits), one past both
+/// the highest saved and the highest handed out this session; add the extension to it.
+/// Files that share a number, like a report's `.json` and `.csv`, share one reservation
+pub fn next_numbered(dir: &str, prefix: &str) -> String {
+    let saved = list(dir)
+        .iter()
+        .filter_map(|name| name.strip_prefix(prefix)?.strip_prefix('_')?.split('.').next()?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+    let mut reserved = RESERVED.lock().unwrap_or_else(|e| e.into_inner());
+    let last = reserved.entry(format!("{dir}/{prefix}")).or_default();
+    *last = saved.max(*last) + 1;
+    format!("{dir}/{prefix}_{:04}", *last)
+}
+
+/// Reads a file relative to [`data_dir`], `None` if it doesn't exist yet. A save still being
+/// written is newer than the file, so that's what comes back
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(name: &str) -> Option<String> {
+    let path = data_dir().join(name);
+    let latest = LATEST.lock().unwrap_or_else(|e| e.into_inner());
+    match latest.get(&path).and_then(|(_, unwritten)| unwritten.as_deref()) {
+        Some(contents) => std::str::from_utf8(contents).ok().map(str::to_owned),
+        None => std::fs::read_to_string(path).ok(),
+    }
+}
+
+/// Names of the files in a directory relative to [`data_dir`], sorted; empty if there is none
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list(dir: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(data_dir().join(dir)) else { return Vec::new(); };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| !name.ends_with(".tmp"))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Writes a file relative to [`data_dir`] in the background, creating parent directories
+/// as needed. Without a task pool (outside the app) the write happens right away
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(name: &str, contents: &str) {
+    save_bytes(name, contents.as_bytes().to_vec());
+}
+
+/// [`save`] for binary files (screenshots, clips)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_bytes(name: &str, contents: Vec<u8>) {
+    let path = data_dir().join(name);
+    let contents: Arc<[u8]> = contents.into();
+    let generation = {
+        let mut latest = LATEST.lock().unwrap_or_else(|e| e.into_inner());
+        let (generation, unwritten) = latest.entry(path.clone()).or_default();
+        *generation += 1;
+        *unwritten = Some(contents.clone());
+        *generation
+    };
+    let Some(pool) = IoTaskPool::try_get() else {
+        write_atomic(&path, &contents, generation);
+        return;
+    };
+    PENDING.fetch_add(1, Ordering::SeqCst);
+    pool.spawn(async move {
+        write_atomic(&path, &contents, generation);
+        PENDING.fetch_sub(1, Ordering::SeqCst);
+    })
+    .detach();
+}
+
+/// Deletes a file relative to [`data_dir`], if it's there
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(name: &str) {
+    let path = data_dir().join(name);
+    // A save still on its way would bring the file back
+    if let Some((generation, unwritten)) = LATEST.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&path) {
+        *generation += 1;
+        *unwritten = None;
+    }
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => warn!("Failed to remove {name:?}: {error:?}"),
+        _ => {}
+    }
+}
+
+/// Whether any save is still being written
+pub fn saving() -> bool {
+    PENDING.load(Ordering::SeqCst) > 0
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_atomic(path: &Path, contents: &[u8], generation: u64) {
+    if let Some(parent) = path.parent() {
+        if let Err(error) = std::fs::create_dir_all(parent) {
+            warn!("Failed to create {parent:?}: {error:?}");
+            return;
+        }
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{generation}.tmp"));
+    let tmp = PathBuf::from(tmp);
+    if let Err(error) = std::fs::write(&tmp, contents) {
+        warn!("Failed to save {path:?}: {error:?}");
+        return;
+    }
+    // Hold the lock over the rename so a newer save can't land in between the check and it,
+    // and a load can't read the file between the rename and the contents being let go
+    let mut latest = LATEST.lock().unwrap_or_else(|e| e.into_inner());
+    let Some((_, unwritten)) = latest.get_mut(path).filter(|(newest, _)| *newest == generation) else {
+        let _ = std::fs::remove_file(&tmp);
+        return;
+    };
+    match std::fs::rename(&tmp, path) {
+        Ok(()) => *unwritten = None,
+        Err(error) => {
+            warn!("Failed to save {path:?}: {error:?}");
+            let _ = std::fs::remove_file(&tmp);
+        }
+    }
+}
+
+/// LocalStorage stand-ins for the file functions, keyed by the path under [`data_dir`]
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use bevy::prelude::*;
+    use web_sys::Storage;
+
+    fn storage() -> Option<Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(name: &str) -> String {
+        format!("{}/{name}", super::data_dir().display())
+    }
+
+    pub fn load(name: &str) -> Option<String> {
+        storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub fn list(dir: &str) -> Vec<String> {
+        let Some(storage) = storage() else { return Vec::new(); };
+        let prefix = key(&format!("{dir}/"));
+        let len = storage.length().unwrap_or(0);
+        let mut names: Vec<String> = (0..len)
+            .filter_map(|i| storage.key(i).ok()?)
+            .filter_map(|k| Some(k.strip_prefix(&prefix)?.to_string()))
+            .filter(|name| !name.contains('/'))
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn save(name: &str, contents: &str) {
+        let Some(storage) = storage() else {
+            warn!("No LocalStorage to save {name:?} to");
+            return;
+        };
+        if let Err(error) = storage.set_item(&key(name), contents) {
+            warn!("Failed to save {name:?}: {error:?}");
+        }
+    }
+
+    pub fn remove(name: &str) {
+        if let Some(storage) = storage() {
+            let _ = storage.remove_item(&key(name));
+        }
+    }
+
+    /// LocalStorage only holds text, so binary files aren't kept in the browser
+    pub fn save_bytes(name: &str, _contents: Vec<u8>) {
+        warn!("Can't save {name:?} in the browser");
+    }
+}
+
+#[derive(Component)]
+struct SavingIndicator;
+
+/// Outlives every state; saves happen from the menu, mid-pull and on the results screen
+fn spawn_saving_indicator(mut commands: Commands) {
+    commands.spawn((
+        Text::new("Saving..."),
+        TextFont { font_size: 12.0, ..default() },
+        TextColor(Color::linear_rgb(0.7, 0.7, 0.7)),
+        Node { position_type: PositionType::Absolute, top: Val::Px(4.0), right: Val::Px(8.0), ..default() },
+        GlobalZIndex(10),
+        Visibility::Hidden,
+        SavingIndicator,
+    ));
+}
+
+fn update_saving_indicator(mut q: Query<&mut Visibility, With<SavingIndicator>>) {
+    let Ok(mut vis) = q.single_mut() else { return; };
+    vis.set_if_neq(if saving() { Visibility::Inherited } else { Visibility::Hidden });
+}
@@ -0,0 +1,357 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+use crate::actions::{InputFocus, TextField};
+use crate::combat::{CurrentEncounter, Difficulty, EncounterDef, EncounterLibrary};
+use crate::GameState;
+
+// Encounter picker: a menu panel listing every loaded encounter with its metadata, opened
+// from the encounter button. Typing searches names, authors and tags; the buttons on top
+// filter by difficulty or tag and change the sort order. Clicking an entry picks it.
+
+pub struct PickerPlugin;
+
+impl Plugin for PickerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EncounterPicker>()
+            .add_systems(OnEnter(GameState::Menu), setup_picker_panel)
+            .add_systems(
+                Update,
+                (click_picker_buttons, type_search, update_picker).chain().run_if(in_state(GameState::Menu)),
+            );
+    }
+}
+
+const MAX_SEARCH_LEN: usize = 24;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum PickerSort {
+    #[default]
+    Name,
+    Difficulty,
+    Duration,
+    Author,
+}
+
+impl PickerSort {
+    const ALL: [PickerSort; 4] = [PickerSort::Name, PickerSort::Difficulty, PickerSort::Duration, PickerSort::Author];
+
+    fn label(self) -> &'static str {
+        match self {
+            PickerSort::Name => "Name",
+            PickerSort::Difficulty => "Difficulty",
+            PickerSort::Duration => "Duration",
+            PickerSort::Author => "Author",
+        }
+    }
+}
+
+/// What the picker shows; the search and filters stick around between visits to the menu
+#[derive(Resource, Debug, Default)]
+pub struct EncounterPicker {
+    open: bool,
+    search: String,
+    difficulty: Option<Difficulty>,
+    tag: Option<String>,
+    sort: PickerSort,
+}
+
+impl EncounterPicker {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn matches(&self, def: &EncounterDef) -> bool {
+        let search = self.search.trim().to_lowercase();
+        let found = search.is_empty()
+            || def.name.to_lowercase().contains(&search)
+            || def.meta.author.as_ref().is_some_and(|a| a.to_lowercase().contains(&search))
+            || def.meta.tags.iter().any(|t| t.to_lowercase().contains(&search));
+        found
+            && self.difficulty.is_none_or(|d| def.meta.difficulty == d)
+            && self.tag.as_ref().is_none_or(|tag| def.meta.tags.contains(tag))
+    }
+
+    /// Matching encounters in the chosen order
+    fn list<'a>(&self, library: &'a EncounterLibrary) -> Vec<&'a EncounterDef> {
+        let mut list: Vec<_> = library.encounters.iter().filter(|e| self.matches(e)).collect();
+        list.sort_by(|a, b| match self.sort {
+            PickerSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            PickerSort::Difficulty => a.meta.difficulty.cmp(&b.meta.difficulty),
+            // Encounters without an expected duration go last
+            PickerSort::Duration => a
+                .meta
+                .expected_duration
+                .unwrap_or(f32::MAX)
+                .total_cmp(&b.meta.expected_duration.unwrap_or(f32::MAX)),
+            PickerSort::Author => a.meta.author.cmp(&b.meta.author),
+        });
+        list
+    }
+}
+
+#[derive(Component)]
+struct PickerPanel;
+
+#[derive(Component)]
+struct PickerList;
+
+#[derive(Component)]
+struct SearchLabel;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum PickerButton {
+    Difficulty,
+    Tag,
+    Sort,
+    Close,
+}
+
+#[derive(Component)]
+struct PickerButtonLabel(PickerButton);
+
+#[derive(Component)]
+struct PickEncounter(String);
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+const BUTTON_SELECTED: Color = Color::linear_rgb(0.2, 0.3, 0.45);
+
+fn setup_picker_panel(mut commands: Commands, mut picker: ResMut<EncounterPicker>) {
+    picker.open = false;
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                left: Val::Percent(30.0),
+                width: Val::Px(440.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                row_gap: Val::Px(6.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.95)),
+            GlobalZIndex(1),
+            Visibility::Hidden,
+            PickerPanel,
+            StateScoped(GameState::Menu),
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new("Encounters"),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            panel.spawn((
+                Text::new(""),
+                TextFont { font_size: 15.0, ..default() },
+                TextColor(Color::linear_rgb(0.8, 0.9, 1.0)),
+                SearchLabel,
+            ));
+            panel
+                .spawn(Node { flex_direction: FlexDirection::Row, column_gap: Val::Px(6.0), ..default() })
+                .with_children(|row| {
+                    for button in [PickerButton::Difficulty, PickerButton::Tag, PickerButton::Sort, PickerButton::Close] {
+                        row.spawn((
+                            Button,
+                            Node {
+                                height: Val::Px(26.0),
+                                padding: UiRect::horizontal(Val::Px(8.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_NORMAL),
+                            button,
+                        ))
+                        .with_child((
+                            Text::new(""),
+                            TextFont { font_size: 14.0, ..default() },
+                            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                            PickerButtonLabel(button),
+                        ));
+                    }
+                });
+            panel.spawn((Node { flex_direction: FlexDirection::Column, row_gap: Val::Px(4.0), ..default() }, PickerList));
+            panel.spawn((
+                Text::new("Type to search names, authors and tags"),
+                TextFont { font_size: 11.0, ..default() },
+                TextColor(Color::linear_rgb(0.6, 0.6, 0.6)),
+            ));
+        });
+}
+
+/// Every tag used by a loaded encounter, sorted
+fn all_tags(library: &EncounterLibrary) -> Vec<String> {
+    let mut tags: Vec<String> = library.encounters.iter().flat_map(|e| e.meta.tags.iter().cloned()).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Steps through `None`, then each option, then back to `None`
+fn cycle<T: Clone + PartialEq>(current: &Option<T>, options: &[T]) -> Option<T> {
+    let next = match current {
+        None => 0,
+        Some(value) => options.iter().position(|o| o == value).map_or(options.len(), |i| i + 1),
+    };
+    options.get(next).cloned()
+}
+
+fn click_picker_buttons(
+    mut picker: ResMut<EncounterPicker>,
+    mut encounter: ResMut<CurrentEncounter>,
+    library: Res<EncounterLibrary>,
+    mut q_buttons: Query<(&Interaction, &mut BackgroundColor, &PickerButton), Changed<Interaction>>,
+    mut q_entries: Query<(&Interaction, &mut BackgroundColor, &PickEncounter), (Changed<Interaction>, Without<PickerButton>)>,
+) {
+    for (interaction, mut color, button) in &mut q_buttons {
+        match *interaction {
+            Interaction::Pressed => match button {
+                PickerButton::Difficulty => picker.difficulty = cycle(&picker.difficulty, &Difficulty::ALL),
+                PickerButton::Tag => picker.tag = cycle(&picker.tag, &all_tags(&library)),
+                PickerButton::Sort => {
+                    let idx = PickerSort::ALL.iter().position(|s| *s == picker.sort).unwrap_or(0);
+                    picker.sort = PickerSort::ALL[(idx + 1) % PickerSort::ALL.len()];
+                }
+                PickerButton::Close => picker.open = false,
+            },
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+    for (interaction, mut color, PickEncounter(id)) in &mut q_entries {
+        match *interaction {
+            Interaction::Pressed => {
+                encounter.id = id.clone();
+                picker.open = false;
+            }
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None if *id == encounter.id => *color = BUTTON_SELECTED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn type_search(mut keys: EventReader<KeyboardInput>, mut focus: ResMut<InputFocus>, mut picker: ResMut<EncounterPicker>) {
+    if !focus.hold(TextField::EncounterSearch, picker.open) {
+        keys.clear();
+        return;
+    }
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+        match &key.logical_key {
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if picker.search.chars().count() < MAX_SEARCH_LEN {
+                        picker.search.push(c);
+                    }
+                }
+            }
+            Key::Space if picker.search.chars().count() < MAX_SEARCH_LEN => picker.search.push(' '),
+            Key::Backspace => {
+                picker.search.pop();
+            }
+            Key::Escape => picker.open = false,
+            _ => {}
+        }
+    }
+}
+
+fn button_label(button: PickerButton, picker: &EncounterPicker) -> String {
+    match button {
+        PickerButton::Difficulty => format!("Difficulty: {}", picker.difficulty.map_or("All", |d| d.label())),
+        PickerButton::Tag => format!("Tag: {}", picker.tag.as_deref().unwrap_or("All")),
+        PickerButton::Sort => format!("Sort: {}", picker.sort.label()),
+        PickerButton::Close => "Close".to_string(),
+    }
+}
+
+/// Second line of an entry: difficulty, length, author and version
+fn entry_details(def: &EncounterDef) -> String {
+    let mut details = vec![def.meta.difficulty.label().to_string()];
+    if let Some(secs) = def.meta.expected_duration {
+        let secs = secs.round() as u32;
+        details.push(format!("~{}:{:02}", secs / 60, secs % 60));
+    }
+    if let Some(author) = &def.meta.author {
+        details.push(format!("by {author}"));
+    }
+    if let Some(version) = &def.meta.version {
+        details.push(format!("v{version}"));
+    }
+    if !def.meta.tags.is_empty() {
+        details.push(def.meta.tags.iter().map(|t| format!("#{t}")).collect::<Vec<_>>().join(" "));
+    }
+    details.join(" | ")
+}
+
+fn update_picker(
+    mut commands: Commands,
+    picker: Res<EncounterPicker>,
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+    mut q_panel: Query<&mut Visibility, With<PickerPanel>>,
+    mut q_search: Query<&mut Text, With<SearchLabel>>,
+    mut q_labels: Query<(&mut Text, &PickerButtonLabel), Without<SearchLabel>>,
+    q_list: Query<Entity, With<PickerList>>,
+    q_children: Query<&Children>,
+) {
+    if !picker.is_changed() && !library.is_changed() && !encounter.is_changed() {
+        return;
+    }
+    if let Ok(mut vis) = q_panel.single_mut() {
+        *vis = if picker.open { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    if let Ok(mut text) = q_search.single_mut() {
+        text.0 = format!("Search: {}_", picker.search);
+    }
+    for (mut text, label) in &mut q_labels {
+        text.0 = button_label(label.0, &picker);
+    }
+    let Ok(list) = q_list.single() else { return; };
+    if let Ok(children) = q_children.get(list) {
+        for child in children.iter() {
+            commands.entity(child).despawn();
+        }
+    }
+    let entries = picker.list(&library);
+    commands.entity(list).with_children(|list| {
+        if entries.is_empty() {
+            list.spawn((
+                Text::new("No encounters match"),
+                TextFont { font_size: 14.0, ..default() },
+                TextColor(Color::linear_rgb(0.6, 0.6, 0.6)),
+            ));
+        }
+        for def in entries {
+            let selected = def.id == encounter.id;
+            list.spawn((
+                Button,
+                Node {
+                    padding: UiRect::all(Val::Px(6.0)),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                BackgroundColor(if selected { BUTTON_SELECTED } else { BUTTON_NORMAL }),
+                PickEncounter(def.id.clone()),
+            ))
+            .with_children(|entry| {
+                entry.spawn((
+                    Text::new(def.name.clone()),
+                    TextFont { font_size: 16.0, ..default() },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+                entry.spawn((
+                    Text::new(entry_details(def)),
+                    TextFont { font_size: 12.0, ..default() },
+                    TextColor(Color::linear_rgb(0.7, 0.7, 0.7)),
+                ));
+            });
+        }
+    });
+}
@@ -1,6 +1,11 @@
-use crate::actions::Actions;
+use crate::actions::{SimAction, SimInput};
+use crate::adds::Add;
+use crate::character::Character;
+use crate::combat::{AbilityBook, AbilityMovement, AbilityUsedEvent, CombatState};
 use crate::loading::TextureAssets;
-use crate::GameState;
+use crate::sim_time::SimTime;
+use crate::world::{keep_in_arena, Arena, CurrentTarget, Enemy, Enmity, Facing, Health};
+use crate::{vfx, GameSet, GameState};
 use bevy::prelude::*;
 
 pub struct PlayerPlugin;
@@ -8,38 +13,284 @@ pub struct PlayerPlugin;
 #[derive(Component)]
 pub struct Player;
 
+/// Velocity and wind state used by the movement mutators
+#[derive(Component, Default)]
+struct PlayerMotion {
+    velocity: Vec2,
+    wind_clock: f32,
+}
+
+/// Sprint, toggled on and off; runs on a gauge that drains while sprinting and refills otherwise
+#[derive(Component)]
+struct Sprint {
+    active: bool,
+    /// 0..=1
+    gauge: f32,
+}
+
+impl Default for Sprint {
+    fn default() -> Self {
+        Sprint { active: false, gauge: 1.0 }
+    }
+}
+
+#[derive(Component)]
+struct SprintGauge;
+
+#[derive(Component)]
+struct SprintGaugeFill;
+
+/// Optional impairments for practicing mechanics and recoveries, picked in the menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutator {
+    /// Momentum: the player speeds up and slides to a stop instead of moving instantly
+    SlipperyFloor,
+    /// Periodic gusts push the player, from a different direction each time
+    Wind,
+    /// Seeded disruptions to the rotation (see `mistakes`)
+    Mistakes,
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 3] = [Mutator::SlipperyFloor, Mutator::Wind, Mutator::Mistakes];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Mutator::SlipperyFloor => "Slippery floor",
+            Mutator::Wind => "Wind",
+            Mutator::Mistakes => "Mistake injection",
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Mutators {
+    pub active: Vec<Mutator>,
+}
+
+impl Mutators {
+    pub fn is_active(&self, mutator: Mutator) -> bool {
+        self.active.contains(&mutator)
+    }
+
+    pub fn toggle(&mut self, mutator: Mutator) {
+        if let Some(idx) = self.active.iter().position(|m| *m == mutator) {
+            self.active.remove(idx);
+        } else {
+            self.active.push(mutator);
+        }
+    }
+}
+
+const PLAYER_SPEED: f32 = 150.;
+const SPRINT_SPEED_MULT: f32 = 1.6;
+/// Gauge used per second of sprinting while moving
+const SPRINT_DRAIN: f32 = 0.25;
+/// Gauge regained per second while not sprinting
+const SPRINT_REFILL: f32 = 0.15;
+const SPRINT_GAUGE_WIDTH: f32 = 60.0;
+/// Jump lands this far short of the target's center, on the player's side
+const JUMP_LANDING_RANGE: f32 = 60.0;
+/// How quickly velocity follows input on a slippery floor, per second
+const SLIPPERY_ACCEL: f32 = 2.5;
+const WIND_PERIOD: f32 = 8.0;
+const WIND_GUST_SECS: f32 = 3.0;
+const WIND_SPEED: f32 = 90.0;
+
 /// This plugin handles player related stuff like movement
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_player)
-            .add_systems(Update, move_player.run_if(in_state(GameState::Playing)));
+        app.init_resource::<Mutators>()
+            .add_systems(OnEnter(GameState::Playing), spawn_player)
+            .add_systems(
+                FixedUpdate,
+                (move_player, apply_ability_movement)
+                    .chain()
+                    .before(keep_in_arena)
+                    .in_set(GameSet::Sim)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (update_sprint_gauge, draw_wind, draw_player_facing).chain().run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
-fn spawn_player(mut commands: Commands, textures: Res<TextureAssets>) {
-    commands.spawn((
-        Sprite::from_image(textures.bevy.clone()),
-        Transform::from_translation(Vec3::new(0., 0., 1.)),
-        Player,
-    ));
+fn spawn_player(mut commands: Commands, textures: Res<TextureAssets>, character: Res<Character>) {
+    let size = character.sprite.size();
+    commands
+        .spawn((
+            Sprite {
+                image: character.sprite.image(&textures),
+                color: character.color(),
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            Transform::from_translation(Vec3::new(0., 0., 1.)),
+            Player,
+            Health { current: 1000, max: 1000 },
+            Enmity::default(),
+            Facing(Vec2::X),
+            PlayerMotion::default(),
+            Sprint::default(),
+            vfx::VfxAuras::default(),
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|player| {
+            // Nameplate
+            player.spawn((
+                Text2d::new(character.name.clone()),
+                TextFont { font_size: 16.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                Transform::from_translation(Vec3::new(0.0, size / 2.0 + 12.0, 0.1)),
+            ));
+            // Sprint gauge under the feet, only shown while it isn't full
+            player
+                .spawn((
+                    Sprite::from_color(Color::linear_rgb(0.05, 0.05, 0.05), Vec2::new(SPRINT_GAUGE_WIDTH, 6.0)),
+                    Transform::from_translation(Vec3::new(0.0, -size / 2.0 - 8.0, 0.1)),
+                    Visibility::Hidden,
+                    SprintGauge,
+                ))
+                .with_child((
+                    Sprite {
+                        color: Color::linear_rgb(0.4, 0.9, 0.5),
+                        custom_size: Some(Vec2::new(SPRINT_GAUGE_WIDTH, 6.0)),
+                        anchor: bevy::sprite::Anchor::CenterLeft,
+                        ..default()
+                    },
+                    Transform::from_translation(Vec3::new(-SPRINT_GAUGE_WIDTH / 2.0, 0.0, 0.1)),
+                    SprintGaugeFill,
+                ));
+        });
+}
+
+/// Direction of the current gust, if one is blowing
+fn wind(motion: &PlayerMotion) -> Option<Vec2> {
+    let gust = (motion.wind_clock / WIND_PERIOD).floor();
+    let into_period = motion.wind_clock - gust * WIND_PERIOD;
+    // Gusts blow at the end of each period; each one turns 135 degrees from the last
+    (into_period >= WIND_PERIOD - WIND_GUST_SECS).then(|| Vec2::from_angle(gust * 3.0 * std::f32::consts::FRAC_PI_4))
 }
 
 fn move_player(
-    time: Res<Time>,
-    actions: Res<Actions>,
-    mut player_query: Query<&mut Transform, With<Player>>,
+    time: SimTime,
+    input: Res<SimInput>,
+    mutators: Res<Mutators>,
+    combat: Res<CombatState>,
+    mut player_query: Query<(&mut Transform, &mut Sprite, &mut Facing, &mut PlayerMotion, &mut Sprint), With<Player>>,
+) {
+    let dt = time.scaled_delta();
+    for (mut player_transform, mut sprite, mut facing, mut motion, mut sprint) in &mut player_query {
+        if input.has(SimAction::ToggleSprint) {
+            sprint.active = !sprint.active && sprint.gauge > 0.0;
+        }
+        let moving = input.movement.is_some();
+        if sprint.active && moving {
+            sprint.gauge = (sprint.gauge - SPRINT_DRAIN * dt).max(0.0);
+            sprint.active = sprint.gauge > 0.0;
+        } else if !sprint.active {
+            sprint.gauge = (sprint.gauge + SPRINT_REFILL * dt).min(1.0);
+        }
+        let speed = if sprint.active { PLAYER_SPEED * SPRINT_SPEED_MULT } else { PLAYER_SPEED };
+        // Heavy and the like
+        let speed = speed * combat.move_speed_mult();
+        let steering = input.movement.unwrap_or(Vec2::ZERO) * speed;
+        // Keep facing the last direction moved in
+        if let Some(dir) = input.movement {
+            facing.0 = dir;
+            if dir.x != 0.0 {
+                sprite.flip_x = dir.x < 0.0;
+            }
+        }
+        motion.velocity = if mutators.is_active(Mutator::SlipperyFloor) {
+            motion.velocity.lerp(steering, (SLIPPERY_ACCEL * dt).min(1.0))
+        } else {
+            steering
+        };
+        let mut step = motion.velocity * dt;
+        if mutators.is_active(Mutator::Wind) {
+            motion.wind_clock += dt;
+            if let Some(dir) = wind(&motion) {
+                step += dir * WIND_SPEED * dt;
+            }
+        }
+        player_transform.translation += step.extend(0.);
+    }
+}
+
+/// Dashes and leaps move the player the moment the ability goes off
+fn apply_ability_movement(
+    mut evr: EventReader<AbilityUsedEvent>,
+    book: Res<AbilityBook>,
+    arena: Res<Arena>,
+    target: Res<CurrentTarget>,
+    q_targets: Query<&Transform, (Or<(With<Enemy>, With<Add>)>, Without<Player>)>,
+    q_boss: Query<&Transform, (With<Enemy>, Without<Player>)>,
+    mut commands: Commands,
+    mut q_player: Query<(&mut Transform, &Sprite, &Facing), With<Player>>,
+) {
+    let Ok((mut transform, sprite, facing)) = q_player.single_mut() else { return; };
+    for ev in evr.read() {
+        let Some(movement) = book.by_id.get(&ev.id).and_then(|a| a.movement) else { continue; };
+        let from = transform.translation.truncate();
+        let to = match movement {
+            AbilityMovement::Dash { distance } => from + facing.0.normalize_or_zero() * distance,
+            AbilityMovement::LeapToTarget => {
+                let Some(goal) = target.0.and_then(|e| q_targets.get(e).ok()).or(q_boss.single().ok()) else {
+                    continue;
+                };
+                let goal = goal.translation.truncate();
+                goal + (from - goal).normalize_or(Vec2::NEG_X) * JUMP_LANDING_RANGE
+            }
+        };
+        // Stop at the edge rather than carrying the player off it
+        let to = arena.clamp(to).extend(transform.translation.z);
+        if matches!(movement, AbilityMovement::Dash { .. }) {
+            vfx::vfx_motion_trail(&mut commands, sprite, transform.translation, to);
+        }
+        transform.translation = to;
+    }
+}
+
+fn update_sprint_gauge(
+    q_player: Query<&Sprint, With<Player>>,
+    mut q_gauge: Query<&mut Visibility, With<SprintGauge>>,
+    mut q_fill: Query<(&mut Sprite, &mut Transform), With<SprintGaugeFill>>,
 ) {
-    let Some(movement) = actions.player_movement else {
+    let Ok(sprint) = q_player.single() else { return; };
+    if let Ok(mut vis) = q_gauge.single_mut() {
+        *vis = if sprint.active || sprint.gauge < 1.0 { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    if let Ok((mut sprite, mut transform)) = q_fill.single_mut() {
+        transform.scale.x = sprint.gauge;
+        sprite.color = if sprint.active { Color::linear_rgb(1.0, 0.8, 0.3) } else { Color::linear_rgb(0.4, 0.9, 0.5) };
+    }
+}
+
+/// A short tick out from the player in the direction they're facing
+fn draw_player_facing(mut gizmos: Gizmos, q: Query<(&Transform, &Facing), With<Player>>) {
+    for (transform, facing) in &q {
+        let pos = transform.translation.truncate();
+        let dir = facing.0.normalize_or_zero();
+        gizmos.line_2d(pos + dir * 40.0, pos + dir * 60.0, Color::linear_rgb(0.9, 0.9, 1.0));
+    }
+}
+
+fn draw_wind(
+    mutators: Res<Mutators>,
+    mut gizmos: Gizmos,
+    q: Query<(&Transform, &PlayerMotion), With<Player>>,
+) {
+    if !mutators.is_active(Mutator::Wind) {
         return;
-    };
-    let speed = 150.;
-    let movement = Vec3::new(
-        movement.x * speed * time.delta_secs(),
-        movement.y * speed * time.delta_secs(),
-        0.,
-    );
-    for mut player_transform in &mut player_query {
-        player_transform.translation += movement;
+    }
+    for (transform, motion) in &q {
+        if let Some(dir) = wind(motion) {
+            let from = transform.translation.truncate() - dir * 60.0;
+            gizmos.arrow_2d(from, from + dir * 40.0, Color::linear_rgb(0.6, 0.9, 1.0));
+        }
     }
 }
@@ -0,0 +1,1100 @@
+use bevy::ecs::system::SystemState;
+use bevy::input::InputSystem;
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use std::time::Duration;
+
+use crate::actions::{ActionSet, SimAction, SimInput};
+use crate::character::Character;
+use crate::combat::{
+    set_pull_origin, AbilityBook, Bookmark, CombatState, CurrentEncounter, PlayerStats, PullNotes, PullOrigin,
+};
+use crate::player::{Mutator, Mutators};
+use crate::rng::GameRng;
+use crate::sim_time::{SimTimeScale, SIM_HZ};
+use crate::waymarks::{Waymark, Waymarks};
+use crate::world::DamageDealtEvent;
+use crate::{persist, GameSet, GameState};
+
+// Replays: every pull is recorded as the stream of `SimAction`s the sim took in, by fixed
+// tick, whatever device they came from (keyboard, gamepad crossbar, touch, mouse, macros,
+// presses held back by the simulated latency), along with everything else the sim depends
+// on: seed, encounter, mutators, stats, the queue and buffer windows and the waymarks down
+// at the start. Playing one back swaps each tick's live actions for the recorded ones, so
+// the fight re-simulates exactly whatever the frame rate.
+//
+// During playback Space pauses, Left and Right seek 5 seconds, [ and ] jump to the previous
+// and next bookmark dropped during the pull, Backspace starts over and Escape stops. Seeking
+// forward runs the ticks up to the target as fast as a frame allows; seeking back starts the
+// pull over and runs forward from there, as the sim only goes one way.
+//
+// A replay can also be picked as a ghost to race in later pulls of the same encounter: its
+// hotbar presses show as ticks on a strip with a cursor for how far into the pull you are,
+// and its DPS is charted next to yours, a column pair every few seconds. Replays keep the
+// character name they were played under, which the ghost goes by.
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        add_recording(app);
+        app.init_resource::<ReplayBrowser>()
+            .init_resource::<Ghost>()
+            .add_systems(OnEnter(GameState::Playing), (spawn_playback_bar, spawn_ghost_overlay))
+            .add_systems(OnExit(GameState::Playing), (save_recording, stop_playback).chain())
+            .add_systems(
+                PreUpdate,
+                play_keys.after(InputSystem).before(GameSet::InputRead).run_if(playing_back),
+            )
+            .add_systems(Last, advance_playback.run_if(playing_back))
+            .add_systems(
+                Update,
+                (update_playback_bar, update_ghost_overlay).in_set(GameSet::Ui).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Menu), setup_replay_panel)
+            .add_systems(
+                Update,
+                (click_replay_buttons, click_ghost_buttons, update_replay_panel)
+                    .chain()
+                    .run_if(in_state(GameState::Menu)),
+            );
+    }
+}
+
+/// Recording every pull and swapping a replay's actions in for live ones, without the playback
+/// controls, menu or ghost; all the headless test app needs
+pub(crate) fn add_recording(app: &mut App) {
+    app.init_resource::<Recorder>()
+        .init_resource::<Playback>()
+        .add_systems(OnEnter(GameState::Playing), start_recording.after(set_pull_origin))
+        .add_systems(
+            FixedUpdate,
+            (
+                (play_tick.run_if(playing_back), record_tick).chain().in_set(ActionSet::Record),
+                record_damage.after(GameSet::Sim),
+            )
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Sets up the next pull to play back `contents`, a replay file; how many ticks it runs for
+pub(crate) fn play(world: &mut World, contents: &str) -> Result<u32, String> {
+    let replay = Replay::parse(contents, world.resource::<AbilityBook>())?;
+    let ticks = replay.ticks;
+    let mut state =
+        SystemState::<(ResMut<Playback>, ResMut<GameRng>, ResMut<CurrentEncounter>, ResMut<Mutators>)>::new(world);
+    let (mut playback, mut rng, mut encounter, mut mutators) = state.get_mut(world);
+    begin_playback(replay, &mut playback, &mut rng, &mut encounter, &mut mutators);
+    Ok(ticks)
+}
+
+/// The pull recorded so far as a replay file, if it's being recorded
+pub(crate) fn recording(world: &World) -> Option<String> {
+    world.resource::<Recorder>().replay.as_ref().map(Replay::to_text)
+}
+
+const REPLAY_DIR: &str = "replays";
+/// First line of every replay file; older files are skipped
+const REPLAY_VERSION: &str = "replay 2";
+/// Replays listed in the menu, newest first
+const LISTED: usize = 8;
+/// Columns in the ghost DPS chart at most; longer pulls get wider columns
+const GHOST_COLUMNS: usize = 24;
+/// Seconds per chart column at least
+const GHOST_MIN_COLUMN_SECS: usize = 5;
+/// Seconds Left and Right seek by during playback
+const SEEK_STEP_SECS: f64 = 5.0;
+/// Seconds before a bookmark that jumping to it lands, so the moment plays out
+const BOOKMARK_LEAD_SECS: f64 = 2.0;
+/// Real time a frame covers at most while seeking, so the screen keeps up
+const SEEK_FRAME_SECS: f64 = 0.25;
+
+fn tick_secs(tick: u32) -> f32 {
+    (f64::from(tick) / SIM_HZ) as f32
+}
+
+fn point_text(point: Option<Vec2>) -> String {
+    point.map_or("-".to_string(), |p| format!("{},{}", p.x, p.y))
+}
+
+/// "x,y" or "-" for none
+fn parse_point(text: &str) -> Option<Option<Vec2>> {
+    if text == "-" {
+        return Some(None);
+    }
+    let (x, y) = text.split_once(',')?;
+    Some(Some(Vec2::new(x.parse().ok()?, y.parse().ok()?)))
+}
+
+fn action_text(action: SimAction) -> String {
+    match action {
+        SimAction::Press(id) => format!("press {id:?}"),
+        SimAction::Move(movement) => format!("move {}", point_text(movement)),
+        SimAction::ToggleSprint => "sprint".to_string(),
+        SimAction::CycleTarget => "target".to_string(),
+        SimAction::HealTarget(slot) => format!("heal {slot}"),
+        SimAction::CleanseTarget(slot) => format!("cleanse {}", slot.map_or("-".to_string(), |slot| slot.to_string())),
+        SimAction::CycleSpeedTier => "speed".to_string(),
+        SimAction::Waymark(mark, pos) => format!("waymark {} {}", mark.label(), point_text(pos)),
+    }
+}
+
+fn parse_action(text: &str, book: &AbilityBook) -> Option<SimAction> {
+    let mut parts = text.split_whitespace();
+    let action = match parts.next()? {
+        "press" => {
+            let name = parts.next()?;
+            SimAction::Press(book.by_id.keys().copied().find(|id| format!("{id:?}") == name)?)
+        }
+        "move" => SimAction::Move(parse_point(parts.next()?)?),
+        "sprint" => SimAction::ToggleSprint,
+        "target" => SimAction::CycleTarget,
+        "heal" => SimAction::HealTarget(parts.next()?.parse().ok()?),
+        "cleanse" => SimAction::CleanseTarget(match parts.next()? {
+            "-" => None,
+            slot => Some(slot.parse().ok()?),
+        }),
+        "speed" => SimAction::CycleSpeedTier,
+        "waymark" => SimAction::Waymark(Waymark::from_label(parts.next()?)?, parse_point(parts.next()?)?),
+        _ => return None,
+    };
+    Some(action)
+}
+
+#[derive(Debug, Clone, Default)]
+struct Replay {
+    seed: u64,
+    encounter: String,
+    /// Character name of whoever played it
+    player: String,
+    mutators: Vec<Mutator>,
+    stats: PlayerStats,
+    gcd_queue_window: f32,
+    buffer_window: f32,
+    /// Waymarks down when the pull started
+    waymarks: Vec<(Waymark, Vec2)>,
+    /// Fixed ticks the pull ran for
+    ticks: u32,
+    damage: i64,
+    /// Damage dealt by the end of each whole second of the pull
+    curve: Vec<i64>,
+    /// Notes dropped during the pull
+    bookmarks: Vec<Bookmark>,
+    /// What the sim took in, by tick, oldest first
+    actions: Vec<(u32, SimAction)>,
+}
+
+impl Replay {
+    fn duration(&self) -> f32 {
+        tick_secs(self.ticks)
+    }
+
+    /// Takes down what the pull's first tick starts from
+    fn capture_setup(&mut self, stats: &PlayerStats, combat: &CombatState, waymarks: &Waymarks) {
+        self.stats = stats.clone();
+        self.gcd_queue_window = combat.gcd_queue_window;
+        self.buffer_window = combat.buffer_window;
+        self.waymarks = Waymark::ALL
+            .into_iter()
+            .filter_map(|mark| waymarks.positions.get(&mark).map(|pos| (mark, *pos)))
+            .collect();
+    }
+
+    fn apply_setup(&self, stats: &mut PlayerStats, combat: &mut CombatState, waymarks: &mut Waymarks) {
+        *stats = self.stats.clone();
+        combat.gcd_queue_window = self.gcd_queue_window;
+        combat.buffer_window = self.buffer_window;
+        waymarks.positions = self.waymarks.iter().copied().collect();
+    }
+
+    fn actions_at(&self, tick: u32) -> impl Iterator<Item = SimAction> + '_ {
+        let from = self.actions.partition_point(|(t, _)| *t < tick);
+        self.actions[from..].iter().take_while(move |(t, _)| *t == tick).map(|(_, action)| *action)
+    }
+
+    // File format: the version line, "key = value" header lines, then "actions" and one line
+    // per action: "<tick> <action>", e.g. "120 press Strike", "121 move 0.6,0.8", "300 move -"
+    fn to_text(&self) -> String {
+        let mutators: Vec<String> = self.mutators.iter().map(|m| format!("{m:?}")).collect();
+        let waymarks: Vec<String> =
+            self.waymarks.iter().map(|(mark, pos)| format!("{} {}", mark.label(), point_text(Some(*pos)))).collect();
+        let mut out = format!(
+            "{REPLAY_VERSION}\nseed = {}\nencounter = {}\nplayer = {}\nmutators = {}\nweapon_damage = {}\nmain_stat = {}\n\
+             speed = {}\ncrit = {}\ngcd_queue_window = {}\nbuffer_window = {}\n\
+             waymarks = {}\nticks = {}\ndamage = {}\ncurve = {}\nbookmarks = {}\nactions\n",
+            self.seed,
+            self.encounter,
+            self.player,
+            mutators.join(","),
+            self.stats.weapon_damage,
+            self.stats.main_stat,
+            self.stats.speed,
+            self.stats.crit,
+            self.gcd_queue_window,
+            self.buffer_window,
+            waymarks.join(";"),
+            self.ticks,
+            self.damage,
+            self.curve.iter().map(i64::to_string).collect::<Vec<_>>().join(","),
+            self.bookmarks.iter().map(Bookmark::to_string).collect::<Vec<_>>().join(";"),
+        );
+        for (tick, action) in &self.actions {
+            out.push_str(&format!("{tick} {}\n", action_text(*action)));
+        }
+        out
+    }
+
+    fn parse(contents: &str, book: &AbilityBook) -> Result<Self, String> {
+        let mut lines = contents.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line.trim() == REPLAY_VERSION => {}
+            _ => return Err(format!("not a {REPLAY_VERSION:?} file")),
+        }
+        let mut replay = Replay::default();
+        for (n, line) in lines.by_ref() {
+            if line.trim() == "actions" {
+                break;
+            }
+            let Some((key, value)) = line.split_once('=') else { return Err(format!("line {}: expected key = value", n + 1)); };
+            let value = value.trim();
+            let list = |sep: char| value.split(sep).map(str::trim).filter(|s| !s.is_empty());
+            let number = || value.parse::<f32>().map_err(|_| format!("line {}: bad number", n + 1));
+            match key.trim() {
+                "seed" => replay.seed = value.parse().map_err(|_| format!("line {}: bad seed", n + 1))?,
+                "encounter" => replay.encounter = value.to_string(),
+                "player" => replay.player = value.to_string(),
+                "mutators" => {
+                    replay.mutators = list(',')
+                        .map(|name| Mutator::ALL.into_iter().find(|m| format!("{m:?}") == name))
+                        .collect::<Option<_>>()
+                        .ok_or(format!("line {}: unknown mutator", n + 1))?
+                }
+                "weapon_damage" => replay.stats.weapon_damage = number()?,
+                "main_stat" => replay.stats.main_stat = number()?,
+                "speed" => replay.stats.speed = number()?,
+                "crit" => replay.stats.crit = number()?,
+                "gcd_queue_window" => replay.gcd_queue_window = number()?,
+                "buffer_window" => replay.buffer_window = number()?,
+                "waymarks" => {
+                    replay.waymarks = list(';')
+                        .map(|entry| {
+                            let (label, pos) = entry.split_once(' ')?;
+                            Some((Waymark::from_label(label)?, parse_point(pos.trim())??))
+                        })
+                        .collect::<Option<_>>()
+                        .ok_or(format!("line {}: bad waymark", n + 1))?
+                }
+                "ticks" => replay.ticks = value.parse().map_err(|_| format!("line {}: bad tick count", n + 1))?,
+                "damage" => replay.damage = value.parse().unwrap_or(0),
+                "curve" => replay.curve = list(',').filter_map(|v| v.parse().ok()).collect(),
+                "bookmarks" => {
+                    replay.bookmarks =
+                        list(';').map(Bookmark::parse).collect::<Option<_>>().ok_or(format!("line {}: bad bookmark", n + 1))?
+                }
+                other => warn!("Unknown replay setting {other:?}"),
+            }
+        }
+        for (n, line) in lines {
+            let bad = || format!("line {}: bad action", n + 1);
+            let (tick, action) = line.split_once(' ').ok_or_else(bad)?;
+            let tick: u32 = tick.parse().map_err(|_| bad())?;
+            if replay.actions.last().is_some_and(|(last, _)| *last > tick) {
+                return Err(format!("line {}: actions out of order", n + 1));
+            }
+            replay.actions.push((tick, parse_action(action, book).ok_or_else(bad)?));
+        }
+        Ok(replay)
+    }
+
+    /// Seconds into the replay at which an ability was pressed
+    fn presses(&self) -> Vec<f32> {
+        self.actions
+            .iter()
+            .filter(|(_, action)| matches!(action, SimAction::Press(_)))
+            .map(|(tick, _)| tick_secs(*tick))
+            .collect()
+    }
+
+    /// Damage dealt by `secs` into the pull, read off the curve between whole seconds
+    fn damage_at(&self, secs: f32) -> f32 {
+        let by = |whole: usize| match whole {
+            0 => 0,
+            n => self.curve.get(n - 1).or(self.curve.last()).copied().unwrap_or(0),
+        };
+        let whole = secs.max(0.0).floor() as usize;
+        let (from, to) = (by(whole) as f32, by(whole + 1) as f32);
+        from + (to - from) * secs.fract()
+    }
+}
+
+/// The pull being recorded, saved when it ends
+#[derive(Resource, Debug, Default)]
+struct Recorder {
+    replay: Option<Replay>,
+}
+
+/// Settings a replay swaps in, put back when it stops
+#[derive(Debug)]
+struct Restore {
+    seed: u64,
+    encounter: String,
+    mutators: Vec<Mutator>,
+}
+
+#[derive(Resource, Debug, Default)]
+struct Playback {
+    replay: Option<Replay>,
+    /// Ticks played so far
+    tick: u32,
+    /// Tick being sought
+    seek: Option<u32>,
+    paused: bool,
+    /// Set while Playing is left and re-entered to start over, so playback isn't stopped
+    restarting: bool,
+    restore: Option<Restore>,
+}
+
+/// Run condition: a replay is being watched rather than played
+pub fn playing_back(playback: Res<Playback>) -> bool {
+    playback.replay.is_some()
+}
+
+fn start_recording(
+    playback: Res<Playback>,
+    rng: Res<GameRng>,
+    encounter: Res<CurrentEncounter>,
+    character: Res<Character>,
+    mutators: Res<Mutators>,
+    mut origin: ResMut<PullOrigin>,
+    mut recorder: ResMut<Recorder>,
+) {
+    if playback.replay.is_some() {
+        *origin = PullOrigin::Replay;
+    }
+    let replay = playback.replay.is_none().then(|| Replay {
+        seed: rng.seed(),
+        encounter: encounter.id.clone(),
+        player: character.name.clone(),
+        mutators: mutators.active.clone(),
+        ..default()
+    });
+    *recorder = Recorder { replay };
+}
+
+/// Writes down the tick's actions, and on the first tick what the pull starts from
+fn record_tick(
+    input: Res<SimInput>,
+    stats: Res<PlayerStats>,
+    combat: Res<CombatState>,
+    waymarks: Res<Waymarks>,
+    mut recorder: ResMut<Recorder>,
+) {
+    let Some(replay) = recorder.replay.as_mut() else { return; };
+    if replay.ticks == 0 {
+        replay.capture_setup(&stats, &combat, &waymarks);
+    }
+    let tick = replay.ticks;
+    replay.actions.extend(input.actions.iter().map(|action| (tick, *action)));
+    replay.ticks += 1;
+}
+
+/// Runs once the tick's sim is done, so its damage counts towards it
+fn record_damage(mut dealt: EventReader<DamageDealtEvent>, mut recorder: ResMut<Recorder>) {
+    let Some(replay) = recorder.replay.as_mut() else { return; };
+    replay.damage += dealt.read().map(|ev| ev.amount as i64).sum::<i64>();
+    while replay.curve.len() < (f64::from(replay.ticks) / SIM_HZ) as usize {
+        replay.curve.push(replay.damage);
+    }
+}
+
+fn save_recording(notes: Res<PullNotes>, mut recorder: ResMut<Recorder>) {
+    let Some(mut replay) = recorder.replay.take() else { return; };
+    if replay.ticks == 0 {
+        return;
+    }
+    replay.bookmarks = notes.bookmarks.clone();
+    let stem = persist::next_numbered(REPLAY_DIR, "replay");
+    persist::save(&format!("{stem}.txt"), &replay.to_text());
+}
+
+fn begin_playback(
+    replay: Replay,
+    playback: &mut Playback,
+    rng: &mut GameRng,
+    encounter: &mut CurrentEncounter,
+    mutators: &mut Mutators,
+) {
+    if playback.restore.is_none() {
+        playback.restore = Some(Restore {
+            seed: rng.seed(),
+            encounter: encounter.id.clone(),
+            mutators: mutators.active.clone(),
+        });
+    }
+    *rng = GameRng::new(replay.seed);
+    encounter.id = replay.encounter.clone();
+    mutators.active = replay.mutators.clone();
+    *playback = Playback { replay: Some(replay), restore: playback.restore.take(), ..default() };
+}
+
+fn stop_playback(
+    mut playback: ResMut<Playback>,
+    mut scale: ResMut<SimTimeScale>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut real: ResMut<Time<Real>>,
+    mut rng: ResMut<GameRng>,
+    mut encounter: ResMut<CurrentEncounter>,
+    mut mutators: ResMut<Mutators>,
+) {
+    if playback.restarting {
+        playback.restarting = false;
+        return;
+    }
+    if playback.replay.is_none() {
+        return;
+    }
+    if playback.seek.is_some() {
+        resync_real_clock(&mut strategy, &mut real);
+    }
+    scale.paused = false;
+    if let Some(restore) = playback.restore.take() {
+        *rng = GameRng::new(restore.seed);
+        encounter.id = restore.encounter;
+        mutators.active = restore.mutators;
+    }
+    *playback = Playback::default();
+}
+
+/// Seeking runs the real clock ahead of the wall clock; this puts it back in step
+fn resync_real_clock(strategy: &mut TimeUpdateStrategy, real: &mut Time<Real>) {
+    *strategy = TimeUpdateStrategy::Automatic;
+    real.update_with_instant(Instant::now());
+}
+
+/// Takes the live keyboard off the sim; live keys only steer playback
+fn play_keys(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut playback: ResMut<Playback>,
+    mut scale: ResMut<SimTimeScale>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let ticks = playback.replay.as_ref().map_or(0, |r| r.ticks);
+    let step = (SEEK_STEP_SECS * SIM_HZ) as u32;
+    let from = playback.seek.unwrap_or(playback.tick);
+    if keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+        if playback.seek.is_none() {
+            scale.paused = playback.paused;
+        }
+    }
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        playback.seek = Some((from + step).min(ticks));
+    }
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        playback.seek = Some(from.saturating_sub(step));
+    }
+    // Bookmarks by the tick jumping to them lands on
+    let marks: Vec<u32> = playback.replay.as_ref().map_or(Vec::new(), |replay| {
+        let lead = (BOOKMARK_LEAD_SECS * SIM_HZ) as u32;
+        replay.bookmarks.iter().map(|b| ((f64::from(b.t) * SIM_HZ) as u32).saturating_sub(lead)).collect()
+    });
+    if keys.just_pressed(KeyCode::BracketRight) {
+        if let Some(next) = marks.iter().copied().filter(|t| *t > from).min() {
+            playback.seek = Some(next.min(ticks));
+        }
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        if let Some(previous) = marks.iter().copied().filter(|t| *t < from).max() {
+            playback.seek = Some(previous);
+        }
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        playback.seek = Some(0);
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+    keys.reset_all();
+}
+
+/// Swaps the tick's live actions for the recorded ones; the first tick also puts back what
+/// the pull started from and lists the pull's bookmarks
+fn play_tick(
+    mut playback: ResMut<Playback>,
+    mut input: ResMut<SimInput>,
+    mut stats: ResMut<PlayerStats>,
+    mut combat: ResMut<CombatState>,
+    mut waymarks: ResMut<Waymarks>,
+    mut notes: ResMut<PullNotes>,
+) {
+    let playback = &mut *playback;
+    let Some(replay) = &playback.replay else { return; };
+    if playback.tick == 0 {
+        replay.apply_setup(&mut stats, &mut combat, &mut waymarks);
+        notes.show_replayed(&replay.bookmarks);
+    }
+    input.actions = replay.actions_at(playback.tick).collect();
+    playback.tick += 1;
+}
+
+/// Sets up the clock for the next frame: as many ticks as a frame allows while seeking
+/// forward, a restart to seek back, and back to the menu once the recorded ticks run out
+fn advance_playback(
+    state: Res<State<GameState>>,
+    fixed: Res<Time<Fixed>>,
+    mut playback: ResMut<Playback>,
+    mut scale: ResMut<SimTimeScale>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut real: ResMut<Time<Real>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Until the replay's first frame has entered Playing there is nothing to advance
+    if *state.get() != GameState::Playing || playback.restarting {
+        return;
+    }
+    let Some(ticks) = playback.replay.as_ref().map(|r| r.ticks) else { return; };
+    match playback.seek {
+        Some(target) if target < playback.tick => {
+            // Starting over takes a trip out of Playing and back, which resets the pull
+            *playback = Playback {
+                replay: playback.replay.take(),
+                seek: Some(target),
+                paused: playback.paused,
+                restarting: true,
+                restore: playback.restore.take(),
+                ..default()
+            };
+            next_state.set(GameState::Restarting);
+        }
+        Some(target) if playback.tick < target => {
+            if scale.paused {
+                scale.paused = false;
+            }
+            // Exactly the ticks still owed, less what the fixed clock already has banked
+            let owed = fixed.timestep() * (target - playback.tick) - fixed.overstep();
+            let real_secs = owed.as_secs_f64() / f64::from(scale.scale.max(0.01));
+            *strategy = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(real_secs.min(SEEK_FRAME_SECS)));
+        }
+        Some(_) => {
+            playback.seek = None;
+            if scale.paused != playback.paused {
+                scale.paused = playback.paused;
+            }
+            resync_real_clock(&mut strategy, &mut real);
+        }
+        // A pull left from the menu ends without a kill or a wipe to end the playback
+        None if playback.tick >= ticks && matches!(*next_state, NextState::Unchanged) => {
+            next_state.set(GameState::Menu)
+        }
+        None => {}
+    }
+}
+
+#[derive(Component)]
+struct PlaybackBarFill;
+
+#[derive(Component)]
+struct PlaybackBarText;
+
+const BAR_WIDTH: f32 = 400.0;
+
+fn spawn_playback_bar(mut commands: Commands, playback: Res<Playback>) {
+    let Some(replay) = &playback.replay else { return; };
+    let duration = replay.duration().max(0.001);
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(-BAR_WIDTH / 2.0)),
+                width: Val::Px(BAR_WIDTH),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 13.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                PlaybackBarText,
+            ));
+            root.spawn((
+                Node { width: Val::Percent(100.0), height: Val::Px(10.0), ..default() },
+                BackgroundColor(Color::linear_rgb(0.1, 0.1, 0.1).with_alpha(0.8)),
+            ))
+            .with_children(|bar| {
+                bar.spawn((
+                    Node { position_type: PositionType::Absolute, height: Val::Percent(100.0), ..default() },
+                    BackgroundColor(Color::linear_rgb(0.35, 0.55, 0.8)),
+                    PlaybackBarFill,
+                ));
+                // A tick wherever an ability was pressed
+                for t in replay.presses() {
+                    bar.spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(t / duration * 100.0),
+                            width: Val::Px(1.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::linear_rgb(1.0, 0.85, 0.3)),
+                    ));
+                }
+            });
+        });
+}
+
+fn update_playback_bar(
+    playback: Res<Playback>,
+    mut q_fill: Query<&mut Node, With<PlaybackBarFill>>,
+    mut q_text: Query<&mut Text, With<PlaybackBarText>>,
+) {
+    let Some(replay) = &playback.replay else { return; };
+    let elapsed = tick_secs(playback.tick);
+    let duration = replay.duration();
+    if let Ok(mut node) = q_fill.single_mut() {
+        node.width = Val::Percent((elapsed / duration.max(0.001) * 100.0).min(100.0));
+    }
+    if let Ok(mut text) = q_text.single_mut() {
+        let state = match (playback.seek, playback.paused) {
+            (Some(_), _) => "seeking",
+            (None, true) => "paused",
+            (None, false) => "playing",
+        };
+        text.0 = format!(
+            "Replay {elapsed:.1}/{duration:.1}s ({state}) - Space pause, Left/Right seek, [/] bookmarks, \
+             Backspace restart, Esc stop"
+        );
+    }
+}
+
+/// The replay raced in live pulls, by file name
+#[derive(Resource, Debug, Default)]
+struct Ghost(Option<(String, Replay)>);
+
+#[derive(Component)]
+struct GhostCursor;
+
+#[derive(Component)]
+struct GhostText;
+
+/// Live half of the chart column at this index
+#[derive(Component)]
+struct GhostLiveColumn(usize);
+
+/// Seconds each chart column covers and how many columns there are
+fn ghost_columns(ghost: &Replay) -> (usize, usize) {
+    let secs = ghost.curve.len().div_ceil(GHOST_COLUMNS).max(GHOST_MIN_COLUMN_SECS);
+    (secs, ghost.curve.len().div_ceil(secs))
+}
+
+/// DPS over chart column `column`
+fn column_dps(replay: &Replay, column: usize, secs: usize) -> f32 {
+    let from = (column * secs) as f32;
+    (replay.damage_at(from + secs as f32) - replay.damage_at(from)) / secs as f32
+}
+
+fn spawn_ghost_overlay(
+    mut commands: Commands,
+    ghost: Res<Ghost>,
+    playback: Res<Playback>,
+    encounter: Res<CurrentEncounter>,
+) {
+    let Some((_, replay)) = &ghost.0 else { return; };
+    if playback.replay.is_some() || replay.encounter != encounter.id {
+        return;
+    }
+    let duration = replay.duration().max(0.001);
+    let (secs, columns) = ghost_columns(replay);
+    let peak = (0..columns).map(|c| column_dps(replay, c, secs)).fold(1.0, f32::max);
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(60.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(-BAR_WIDTH / 2.0)),
+                width: Val::Px(BAR_WIDTH),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 13.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                GhostText,
+            ));
+            root.spawn((
+                Node { width: Val::Percent(100.0), height: Val::Px(10.0), ..default() },
+                BackgroundColor(Color::linear_rgb(0.1, 0.1, 0.1).with_alpha(0.8)),
+            ))
+            .with_children(|strip| {
+                for t in replay.presses() {
+                    strip.spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(t / duration * 100.0),
+                            width: Val::Px(1.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::linear_rgb(0.6, 0.6, 0.7)),
+                    ));
+                }
+                strip.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(2.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::linear_rgb(0.35, 0.75, 1.0)),
+                    GhostCursor,
+                ));
+            });
+            // Ghost on the left of each pair, you on the right, all against the ghost's best column
+            root.spawn((
+                Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(40.0),
+                    align_items: AlignItems::FlexEnd,
+                    column_gap: Val::Px(2.0),
+                    ..default()
+                },
+                BackgroundColor(Color::linear_rgb(0.1, 0.1, 0.1).with_alpha(0.5)),
+            ))
+            .with_children(|chart| {
+                for column in 0..columns {
+                    chart
+                        .spawn(Node {
+                            flex_grow: 1.0,
+                            height: Val::Percent(100.0),
+                            align_items: AlignItems::FlexEnd,
+                            ..default()
+                        })
+                        .with_children(|pair| {
+                            pair.spawn((
+                                Node {
+                                    flex_grow: 1.0,
+                                    height: Val::Percent(column_dps(replay, column, secs) / peak * 100.0),
+                                    ..default()
+                                },
+                                BackgroundColor(Color::linear_rgb(0.6, 0.6, 0.7)),
+                            ));
+                            pair.spawn((
+                                Node { flex_grow: 1.0, height: Val::Percent(0.0), ..default() },
+                                BackgroundColor(Color::linear_rgb(0.35, 0.75, 1.0)),
+                                GhostLiveColumn(column),
+                            ));
+                        });
+                }
+            });
+        });
+}
+
+fn update_ghost_overlay(
+    ghost: Res<Ghost>,
+    recorder: Res<Recorder>,
+    mut q_cursor: Query<&mut Node, With<GhostCursor>>,
+    mut q_columns: Query<(&mut Node, &GhostLiveColumn), Without<GhostCursor>>,
+    mut q_text: Query<&mut Text, With<GhostText>>,
+) {
+    let (Some((name, replay)), Some(live)) = (&ghost.0, &recorder.replay) else { return; };
+    let now = live.duration();
+    if let Ok(mut node) = q_cursor.single_mut() {
+        node.left = Val::Percent((now / replay.duration().max(0.001) * 100.0).min(100.0));
+    }
+    let (secs, columns) = ghost_columns(replay);
+    let peak = (0..columns).map(|c| column_dps(replay, c, secs)).fold(1.0, f32::max);
+    for (mut node, GhostLiveColumn(column)) in &mut q_columns {
+        // Columns not reached yet stay empty rather than dropping to zero
+        let start = (*column * secs) as f32;
+        let height = if now <= start { 0.0 } else { column_dps(live, *column, secs) / peak * 100.0 };
+        node.height = Val::Percent(height.min(100.0));
+    }
+    if let Ok(mut text) = q_text.single_mut() {
+        // Replays from before names were recorded go by their file
+        let name = if replay.player.is_empty() { name } else { &replay.player };
+        let span = now.max(1.0);
+        let (theirs, yours) = (replay.damage_at(now), live.damage as f32);
+        text.0 = format!(
+            "Ghost {name}: {:.0} DPS, you {:.0} ({:+.0} damage)",
+            theirs / span,
+            yours / span,
+            yours - theirs
+        );
+    }
+}
+
+/// Replay list on the menu
+#[derive(Resource, Debug, Default)]
+pub struct ReplayBrowser {
+    open: bool,
+    entries: Vec<(String, Replay)>,
+}
+
+impl ReplayBrowser {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+}
+
+#[derive(Component)]
+struct ReplayPanel;
+
+#[derive(Component)]
+struct PlayReplay(usize);
+
+/// Picks the replay at this index as the ghost, or drops it if it already is
+#[derive(Component)]
+struct GhostReplay(usize);
+
+const GHOST_PICKED: Color = Color::linear_rgb(0.35, 0.75, 1.0);
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+
+fn setup_replay_panel(
+    mut commands: Commands,
+    book: Res<AbilityBook>,
+    mut browser: ResMut<ReplayBrowser>,
+    ghost: Res<Ghost>,
+) {
+    browser.open = false;
+    browser.entries = persist::list(REPLAY_DIR)
+        .into_iter()
+        .rev()
+        .filter_map(|name| {
+            let contents = persist::load(&format!("{REPLAY_DIR}/{name}"))?;
+            match Replay::parse(&contents, &book) {
+                Ok(replay) => Some((name, replay)),
+                Err(error) => {
+                    warn!("Skipping replay {name}: {error}");
+                    None
+                }
+            }
+        })
+        .take(LISTED)
+        .collect();
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                right: Val::Px(20.0),
+                width: Val::Px(320.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                row_gap: Val::Px(4.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.9)),
+            Visibility::Hidden,
+            ReplayPanel,
+            StateScoped(GameState::Menu),
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new(if browser.entries.is_empty() { "No replays yet" } else { "Replays" }),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            for (i, (name, replay)) in browser.entries.iter().enumerate() {
+                let picked = ghost.0.as_ref().is_some_and(|(ghost, _)| ghost == name);
+                let row = Node { width: Val::Percent(100.0), column_gap: Val::Px(4.0), ..default() };
+                panel.spawn(row).with_children(|row| {
+                    row.spawn((
+                        Button,
+                        Node {
+                            flex_grow: 1.0,
+                            height: Val::Px(24.0),
+                            padding: UiRect::horizontal(Val::Px(6.0)),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        PlayReplay(i),
+                    ))
+                    .with_child((
+                        Text::new(format!(
+                            "{}  {}  {:.1}s  {} dmg  seed {}",
+                            replay.encounter,
+                            replay.player,
+                            replay.duration(),
+                            replay.damage,
+                            replay.seed
+                        )),
+                        TextFont { font_size: 13.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+                    row.spawn((
+                        Button,
+                        Node {
+                            height: Val::Px(24.0),
+                            padding: UiRect::horizontal(Val::Px(6.0)),
+                            border: UiRect::all(Val::Px(1.0)),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor(if picked { GHOST_PICKED } else { Color::NONE }),
+                        GhostReplay(i),
+                    ))
+                    .with_child((
+                        Text::new("Ghost"),
+                        TextFont { font_size: 13.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+                });
+            }
+        });
+}
+
+fn click_replay_buttons(
+    browser: Res<ReplayBrowser>,
+    mut playback: ResMut<Playback>,
+    mut rng: ResMut<GameRng>,
+    mut encounter: ResMut<CurrentEncounter>,
+    mut mutators: ResMut<Mutators>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &PlayReplay), Changed<Interaction>>,
+) {
+    for (interaction, mut color, PlayReplay(i)) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                let Some((_, replay)) = browser.entries.get(*i) else { continue; };
+                begin_playback(replay.clone(), &mut playback, &mut rng, &mut encounter, &mut mutators);
+                next_state.set(GameState::Playing);
+            }
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn click_ghost_buttons(
+    browser: Res<ReplayBrowser>,
+    mut ghost: ResMut<Ghost>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &GhostReplay), Changed<Interaction>>,
+    mut q_borders: Query<(&mut BorderColor, &GhostReplay)>,
+) {
+    for (interaction, mut color, GhostReplay(i)) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                let Some((name, replay)) = browser.entries.get(*i) else { continue; };
+                let same = ghost.0.as_ref().is_some_and(|(ghost, _)| ghost == name);
+                ghost.0 = (!same).then(|| (name.clone(), replay.clone()));
+                for (mut border, GhostReplay(j)) in &mut q_borders {
+                    border.0 = if *j == *i && !same { GHOST_PICKED } else { Color::NONE };
+                }
+            }
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn update_replay_panel(browser: Res<ReplayBrowser>, mut q_panel: Query<&mut Visibility, With<ReplayPanel>>) {
+    if !browser.is_changed() {
+        return;
+    }
+    if let Ok(mut vis) = q_panel.single_mut() {
+        *vis = if browser.open { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::AbilityId;
+
+    #[test]
+    fn points_parse() {
+        assert_eq!(parse_point("-"), Some(None));
+        assert_eq!(parse_point("1.5,-2"), Some(Some(Vec2::new(1.5, -2.0))));
+        assert_eq!(parse_point(&point_text(Some(Vec2::new(0.6, 0.8)))), Some(Some(Vec2::new(0.6, 0.8))));
+        assert_eq!(parse_point("1.5"), None);
+        assert_eq!(parse_point("x,2"), None);
+    }
+
+    #[test]
+    fn actions_round_trip() {
+        let book = AbilityBook::default();
+        let actions = [
+            SimAction::Press(AbilityId::Strike),
+            SimAction::Move(Some(Vec2::new(0.6, -0.8))),
+            SimAction::Move(None),
+            SimAction::ToggleSprint,
+            SimAction::CycleTarget,
+            SimAction::HealTarget(3),
+            SimAction::CleanseTarget(Some(1)),
+            SimAction::CleanseTarget(None),
+            SimAction::CycleSpeedTier,
+            SimAction::Waymark(Waymark::Two, Some(Vec2::new(-40.0, 12.5))),
+            SimAction::Waymark(Waymark::A, None),
+        ];
+        for action in actions {
+            assert_eq!(parse_action(&action_text(action), &book), Some(action), "{}", action_text(action));
+        }
+        assert_eq!(parse_action("press Nothing", &book), None);
+        assert_eq!(parse_action("heal", &book), None);
+        assert_eq!(parse_action("jump", &book), None);
+    }
+
+    #[test]
+    fn replay_round_trips() {
+        let book = AbilityBook::default();
+        let replay = Replay {
+            seed: 42,
+            encounter: "trial".to_string(),
+            player: "Some One".to_string(),
+            mutators: vec![Mutator::Wind, Mutator::Mistakes],
+            stats: PlayerStats { weapon_damage: 120.0, main_stat: 2500.0, speed: 0.95, crit: 0.18 },
+            gcd_queue_window: 0.5,
+            buffer_window: 0.25,
+            waymarks: vec![(Waymark::A, Vec2::new(0.0, 150.0)), (Waymark::Four, Vec2::new(-75.5, -20.0))],
+            ticks: 600,
+            damage: 4321,
+            curve: vec![0, 250, 1100, 4321],
+            bookmarks: vec![Bookmark::parse("3.5 Clipped here").unwrap()],
+            actions: vec![
+                (0, SimAction::Press(AbilityId::Strike)),
+                (0, SimAction::Move(Some(Vec2::new(1.0, 0.0)))),
+                (90, SimAction::Move(None)),
+                (150, SimAction::Press(AbilityId::Strike)),
+                (151, SimAction::Waymark(Waymark::B, Some(Vec2::new(10.0, 20.0)))),
+            ],
+        };
+        let text = replay.to_text();
+        let parsed = Replay::parse(&text, &book).unwrap();
+        assert_eq!(parsed.seed, 42);
+        assert_eq!(parsed.encounter, "trial");
+        assert_eq!(parsed.player, "Some One");
+        assert_eq!(parsed.mutators, replay.mutators);
+        assert_eq!(parsed.waymarks, replay.waymarks);
+        assert_eq!(parsed.ticks, 600);
+        assert_eq!(parsed.curve, replay.curve);
+        assert_eq!(parsed.actions, replay.actions);
+        assert_eq!(parsed.to_text(), text);
+    }
+
+    #[test]
+    fn bad_replays_are_refused() {
+        let book = AbilityBook::default();
+        assert!(Replay::parse("replay 1\nseed = 1\nactions\n", &book).is_err());
+        assert!(Replay::parse(&format!("{REPLAY_VERSION}\nseed = x\nactions\n"), &book).is_err());
+        let out_of_order = format!("{REPLAY_VERSION}\nseed = 1\nactions\n5 sprint\n4 sprint\n");
+        assert!(Replay::parse(&out_of_order, &book).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::combat::{AbilityBook, BuffReport, EncounterLibrary, PullResult};
+use crate::mistakes::MistakeLog;
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct ResultsPlugin;
+
+/// Shown once a pull is over, with the outcome and buttons to go again or back to the menu
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Results), setup_results)
+            .add_systems(Update, click_results_button.run_if(in_state(GameState::Results)));
+    }
+}
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+const STRIP_WIDTH: f32 = 500.0;
+
+#[derive(Component)]
+struct ResultsButton(GameState);
+
+fn format_time(secs: f32) -> String {
+    let secs = secs.max(0.0) as u32;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn setup_results(
+    mut commands: Commands,
+    result: Res<PullResult>,
+    library: Res<EncounterLibrary>,
+    mistakes: Res<MistakeLog>,
+    book: Res<AbilityBook>,
+) {
+    let name = library.get(&result.encounter).map(|e| e.name.as_str()).unwrap_or(result.encounter.as_str());
+    let (headline, color) = if result.cleared_before_enrage {
+        ("Cleared", Color::linear_rgb(0.5, 1.0, 0.5))
+    } else {
+        ("Enraged", Color::linear_rgb(1.0, 0.35, 0.3))
+    };
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            StateScoped(GameState::Results),
+        ))
+        .with_children(|children| {
+            children.spawn((Text::new(headline), TextFont { font_size: 48.0, ..default() }, TextColor(color)));
+            children.spawn((
+                Text::new(format!("{name} - {}", format_time(result.duration))),
+                TextFont { font_size: 20.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            children.spawn((
+                Text::new(format!(
+                    "Cleared before enrage: {}",
+                    if result.cleared_before_enrage { "yes" } else { "no" }
+                )),
+                TextFont { font_size: 20.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            children.spawn((
+                Text::new(format!("{:.1} DPS, grade {}", result.dps(), result.grade())),
+                TextFont { font_size: 20.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            children.spawn((
+                Text::new(format!("Vulnerability stacks from failed mechanics: {}", result.vuln_stacks)),
+                TextFont { font_size: 16.0, ..default() },
+                TextColor(if result.vuln_stacks == 0 {
+                    Color::linear_rgb(0.9, 0.9, 0.9)
+                } else {
+                    Color::linear_rgb(1.0, 0.6, 0.4)
+                }),
+            ));
+            for (id, uptime) in &result.dot_uptimes {
+                let name = book.by_id.get(id).map_or("?", |a| a.name);
+                children.spawn((
+                    Text::new(format!("{name} uptime: {:.0}%", uptime * 100.0)),
+                    TextFont { font_size: 16.0, ..default() },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            }
+            let buffs = &result.buffs;
+            children.spawn((
+                Text::new(format!("Buffed GCDs: {}/{}", buffs.buffed_gcds(), buffs.possible_gcds())),
+                TextFont { font_size: 16.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            if !buffs.windows.is_empty() {
+                spawn_buff_strip(children, buffs, result.duration);
+            }
+            for (id, drift, uses) in &result.cooldown_drift {
+                let name = book.by_id.get(id).map_or("?", |a| a.name);
+                children.spawn((
+                    Text::new(format!("{name} drift: {drift:.1}s over {uses} uses")),
+                    TextFont { font_size: 16.0, ..default() },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
+            }
+            for check in &result.checkpoints {
+                let (verdict, color) = if check.passed() {
+                    ("passed", Color::linear_rgb(0.5, 1.0, 0.5))
+                } else {
+                    ("failed", Color::linear_rgb(1.0, 0.35, 0.3))
+                };
+                children.spawn((
+                    Text::new(format!(
+                        "{} {} {verdict} at {:.0}% HP (needed < {:.0}%)",
+                        format_time(check.at),
+                        check.name,
+                        check.hp_frac * 100.0,
+                        check.below * 100.0
+                    )),
+                    TextFont { font_size: 16.0, ..default() },
+                    TextColor(color),
+                ));
+            }
+            // Injected mistakes and how quickly each was recovered from
+            for mistake in &mistakes.injected {
+                let recovery = mistake.recovered_in.map(|t| format!("{t:.1}s, ")).unwrap_or_default();
+                children.spawn((
+                    Text::new(format!(
+                        "{} {} - {recovery}{}",
+                        format_time(mistake.at),
+                        mistake.kind.label(&book),
+                        mistake.grade()
+                    )),
+                    TextFont { font_size: 14.0, ..default() },
+                    TextColor(Color::linear_rgb(0.75, 0.75, 0.75)),
+                ));
+            }
+            for (label, state) in [("Retry", GameState::Playing), ("Menu", GameState::Menu)] {
+                children
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(140.0),
+                            height: Val::Px(40.0),
+                            margin: UiRect::top(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        ResultsButton(state),
+                    ))
+                    .with_child((
+                        Text::new(label),
+                        TextFont { font_size: 24.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+            }
+        });
+}
+
+/// Buff windows as shaded spans over the pull, with a tick per GCD: gold inside a window,
+/// grey outside
+fn spawn_buff_strip(parent: &mut ChildSpawnerCommands, buffs: &BuffReport, duration: f32) {
+    let span = duration.max(0.001);
+    let pct = |t: f32| Val::Percent((t / span * 100.0).clamp(0.0, 100.0));
+    parent
+        .spawn((
+            Node { width: Val::Px(STRIP_WIDTH), height: Val::Px(18.0), ..default() },
+            BackgroundColor(Color::linear_rgb(0.1, 0.1, 0.1)),
+        ))
+        .with_children(|strip| {
+            for window in &buffs.windows {
+                let end = window.end.unwrap_or(duration);
+                strip.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        left: pct(window.start),
+                        width: Val::Percent(((end - window.start) / span * 100.0).max(0.0)),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::linear_rgb(0.9, 0.6, 0.1).with_alpha(0.35)),
+                ));
+            }
+            for (at, buffed) in &buffs.gcds {
+                let color = if *buffed { Color::linear_rgb(1.0, 0.85, 0.3) } else { Color::linear_rgb(0.6, 0.6, 0.6) };
+                strip.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        left: pct(*at),
+                        width: Val::Px(2.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(color),
+                ));
+            }
+        });
+}
+
+fn click_results_button(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &ResultsButton), Changed<Interaction>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => next_state.set(button.0.clone()),
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
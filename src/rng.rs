@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::GameState;
+
+// Every gameplay random roll (crits, damage jitter, procs) draws from one seeded
+// generator, so a pull started with the same seed and inputs plays out identically.
+// Purely cosmetic randomness (VFX particles) doesn't need to go through it.
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>().add_systems(OnEnter(GameState::Playing), restart_rng);
+    }
+}
+
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts the sequence over from the current seed
+    pub fn restart(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+
+    /// Switches to a fresh random seed
+    pub fn reroll(&mut self) {
+        *self = Self::new(rand::thread_rng().gen());
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+/// Seed from `JRPG_SEED` if set, otherwise a random one (logged so the run can be repeated)
+impl Default for GameRng {
+    fn default() -> Self {
+        match std::env::var("JRPG_SEED").ok().and_then(|v| v.parse::<u64>().ok()) {
+            Some(seed) => Self::new(seed),
+            None => Self::new(rand::thread_rng().gen()),
+        }
+    }
+}
+
+fn restart_rng(mut rng: ResMut<GameRng>) {
+    rng.restart();
+    info!("Pull seed {}", rng.seed());
+}
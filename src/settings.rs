@@ -0,0 +1,573 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowResized};
+use serde::{Deserialize, Serialize};
+
+use crate::battle_text::BattleTextSettings;
+use crate::combat::{HudAnchor, Metronome};
+use crate::keybinds::Keybinds;
+use crate::vfx::DEFAULT_MAX_PARTICLES;
+use crate::{persist, GameState};
+
+// Player settings kept between runs in one RON file in the user data dir: audio volumes,
+// hotbar keys, simulated latency, GCD input windows, the metronome, HUD scale, where the
+// hotbars sit, the cast bar's size, position and ticks, how battle text behaves, whether
+// upcoming mechanics are called out under the enrage timer, how many particles effects may
+// show at once, the CRT filter, how hard the camera shakes and how long big crits stop the
+// pull for. The file is read once at startup (see `LoadingPlugin`) and written whenever
+// something in it changes. Keys and the metronome live in their own resources while the game
+// runs and are copied in here on save; the rest is read from `Settings` where it's used.
+//
+// The UI scale shrinks further when the window is too narrow to fit the HUD at the chosen
+// scale, and is worked out again whenever the window is resized.
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsPanel>()
+            .add_systems(OnEnter(GameState::Menu), setup_settings_panel)
+            .add_systems(
+                Update,
+                (click_settings_buttons, update_settings_panel).chain().run_if(in_state(GameState::Menu)),
+            )
+            .add_systems(Update, (apply_hud_scale, save_settings));
+    }
+}
+
+const SETTINGS_FILE: &str = "settings.ron";
+/// Where keys and the metronome were kept before the settings file; read once to fill it in
+const LEGACY_KEYBINDS_FILE: &str = "keybinds.txt";
+const LEGACY_METRONOME_FILE: &str = "metronome.txt";
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// 0 to 1, the looping track
+    pub music_volume: f32,
+    /// 0 to 1, clicks and ticks
+    pub effects_volume: f32,
+    /// Hotbar slot keys by name, like "KeyQ" or "Digit1"
+    pub keybinds: Vec<String>,
+    /// Simulated round trip when no trace or env override is set; 0 is off
+    pub latency_ms: f32,
+    /// Seconds before the GCD comes back that a GCD press queues instead of buffering
+    pub gcd_queue_window: f32,
+    /// Seconds a press too early for the queue is held before it's dropped, at most 0.4
+    pub buffer_window: f32,
+    pub metronome: bool,
+    pub hud_scale: f32,
+    pub hud_anchor: HudAnchor,
+    pub cast_bar_width: f32,
+    pub cast_bar_height: f32,
+    /// Left edge, in percent of the screen width
+    pub cast_bar_left: f32,
+    /// Logical pixels from the bottom of the screen
+    pub cast_bar_bottom: f32,
+    /// Slidecast and queue window marks on the cast bar
+    pub cast_bar_ticks: bool,
+    pub battle_text: BattleTextSettings,
+    /// "Muddled in 5s" under the enrage timer
+    pub mechanic_callouts: bool,
+    /// Particles showing at once; past most of it effects leave out their small stuff
+    pub max_particles: usize,
+    /// Scanlines and a curved-glass look over the arena, see `crt`
+    pub crt_filter: bool,
+    /// Keep the last few seconds on screen so holding F12 saves them as a GIF, see `capture`
+    pub clip_buffer: bool,
+    /// How hard the camera shakes, 1 as designed; 0 is off
+    pub screen_shake: f32,
+    /// How long big crits stop the pull for, 1 as designed; 0 is off
+    pub hit_stop: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            music_volume: 1.0,
+            effects_volume: 1.0,
+            keybinds: Keybinds::default().names(),
+            latency_ms: 0.0,
+            gcd_queue_window: 0.6,
+            buffer_window: 0.6,
+            metronome: false,
+            hud_scale: 1.0,
+            hud_anchor: HudAnchor::default(),
+            cast_bar_width: 400.0,
+            cast_bar_height: 18.0,
+            cast_bar_left: 50.0,
+            cast_bar_bottom: 100.0,
+            cast_bar_ticks: true,
+            battle_text: BattleTextSettings::default(),
+            mechanic_callouts: true,
+            max_particles: DEFAULT_MAX_PARTICLES,
+            crt_filter: false,
+            clip_buffer: false,
+            screen_shake: 1.0,
+            hit_stop: 0.0,
+        }
+    }
+}
+
+impl Settings {
+    fn load() -> Self {
+        let Some(contents) = persist::load(SETTINGS_FILE) else { return Settings::migrate(); };
+        // The old files were carried over when this one was first written
+        persist::remove(LEGACY_KEYBINDS_FILE);
+        persist::remove(LEGACY_METRONOME_FILE);
+        ron::from_str(&contents).unwrap_or_else(|error| {
+            warn!("Ignoring {SETTINGS_FILE}: {error}");
+            Settings::default()
+        })
+    }
+
+    /// Defaults plus whatever `keybinds.txt` and `metronome.txt` held, written out right away
+    /// when there was any. The old files go on the next start, once this one is on disk
+    fn migrate() -> Self {
+        let mut settings = Settings::default();
+        let keybinds = persist::load(LEGACY_KEYBINDS_FILE);
+        let metronome = persist::load(LEGACY_METRONOME_FILE);
+        if keybinds.is_none() && metronome.is_none() {
+            return settings;
+        }
+        // One "slot<n> = <key>" line per changed slot
+        for line in keybinds.as_deref().unwrap_or("").lines() {
+            let Some((slot, key)) = line.split_once('=') else { continue; };
+            let slot = slot.trim().strip_prefix("slot").and_then(|n| n.parse::<usize>().ok());
+            match slot.and_then(|n| settings.keybinds.get_mut(n.wrapping_sub(1))) {
+                Some(name) => *name = key.trim().to_string(),
+                None => warn!("Unknown keybind slot in {LEGACY_KEYBINDS_FILE}: {line:?}"),
+            }
+        }
+        match metronome.as_deref().map(str::trim) {
+            None => {}
+            Some("on") => settings.metronome = true,
+            Some("off") => settings.metronome = false,
+            Some(other) => warn!("Unknown metronome setting {other:?}"),
+        }
+        info!("Moved {LEGACY_KEYBINDS_FILE} and {LEGACY_METRONOME_FILE} into {SETTINGS_FILE}");
+        settings.save();
+        settings
+    }
+
+    fn save(&self) {
+        match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => persist::save(SETTINGS_FILE, &contents),
+            Err(error) => warn!("Failed to write settings: {error}"),
+        }
+    }
+}
+
+/// Startup: reads the settings file and hands keys and the metronome to their resources
+pub fn load_settings(mut commands: Commands, mut keybinds: ResMut<Keybinds>, mut metronome: ResMut<Metronome>) {
+    let settings = Settings::load();
+    *keybinds = Keybinds::from_names(&settings.keybinds);
+    metronome.enabled = settings.metronome;
+    commands.insert_resource(settings);
+}
+
+/// Writes the file when anything in it changed since the last write (or the load)
+fn save_settings(
+    keybinds: Res<Keybinds>,
+    metronome: Res<Metronome>,
+    settings: Option<Res<Settings>>,
+    mut saved: Local<Option<Settings>>,
+) {
+    let Some(settings) = settings else { return; };
+    if !keybinds.is_changed() && !metronome.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let current = Settings { keybinds: keybinds.names(), metronome: metronome.enabled, ..settings.clone() };
+    match saved.as_ref() {
+        None => *saved = Some(current),
+        Some(previous) if *previous == current => {}
+        Some(_) => {
+            current.save();
+            *saved = Some(current);
+        }
+    }
+}
+
+/// Logical width the HUD needs at scale 1 without overlapping itself
+const MIN_HUD_WIDTH: f32 = 1000.0;
+
+fn apply_hud_scale(
+    settings: Option<Res<Settings>>,
+    mut resized: EventReader<WindowResized>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Some(settings) = settings else { return; };
+    let resized = resized.read().count() > 0;
+    if !settings.is_changed() && !resized {
+        return;
+    }
+    let fits = q_window.single().map_or(f32::MAX, |window| window.width() / MIN_HUD_WIDTH);
+    ui_scale.0 = settings.hud_scale.min(fits);
+}
+
+/// Settings with a row on the panel: name, step per click, range and how the value reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingField {
+    MusicVolume,
+    EffectsVolume,
+    LatencyMs,
+    GcdQueueWindow,
+    BufferWindow,
+    HudScale,
+    CastBarWidth,
+    CastBarHeight,
+    CastBarLeft,
+    CastBarBottom,
+    ScreenShake,
+    HitStop,
+}
+
+impl SettingField {
+    const ALL: [SettingField; 12] = [
+        SettingField::MusicVolume,
+        SettingField::EffectsVolume,
+        SettingField::LatencyMs,
+        SettingField::GcdQueueWindow,
+        SettingField::BufferWindow,
+        SettingField::HudScale,
+        SettingField::CastBarWidth,
+        SettingField::CastBarHeight,
+        SettingField::CastBarLeft,
+        SettingField::CastBarBottom,
+        SettingField::ScreenShake,
+        SettingField::HitStop,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SettingField::MusicVolume => "Music",
+            SettingField::EffectsVolume => "Effects",
+            SettingField::LatencyMs => "Latency",
+            SettingField::GcdQueueWindow => "Queue window",
+            SettingField::BufferWindow => "Input buffer",
+            SettingField::HudScale => "HUD scale",
+            SettingField::CastBarWidth => "Cast bar width",
+            SettingField::CastBarHeight => "Cast bar height",
+            SettingField::CastBarLeft => "Cast bar left",
+            SettingField::CastBarBottom => "Cast bar raise",
+            SettingField::ScreenShake => "Screen shake",
+            SettingField::HitStop => "Hit-stop",
+        }
+    }
+
+    /// (step, min, max)
+    fn range(self) -> (f32, f32, f32) {
+        match self {
+            SettingField::MusicVolume | SettingField::EffectsVolume => (0.1, 0.0, 1.0),
+            SettingField::LatencyMs => (25.0, 0.0, 500.0),
+            SettingField::GcdQueueWindow => (0.1, 0.0, 1.5),
+            SettingField::BufferWindow => (0.05, 0.0, 1.0),
+            SettingField::HudScale => (0.1, 0.5, 2.0),
+            SettingField::CastBarWidth => (50.0, 150.0, 800.0),
+            SettingField::CastBarHeight => (2.0, 6.0, 40.0),
+            SettingField::CastBarLeft => (5.0, 0.0, 90.0),
+            SettingField::CastBarBottom => (10.0, 0.0, 600.0),
+            SettingField::ScreenShake => (0.25, 0.0, 2.0),
+            SettingField::HitStop => (0.25, 0.0, 2.0),
+        }
+    }
+
+    fn value(self, settings: &mut Settings) -> &mut f32 {
+        match self {
+            SettingField::MusicVolume => &mut settings.music_volume,
+            SettingField::EffectsVolume => &mut settings.effects_volume,
+            SettingField::LatencyMs => &mut settings.latency_ms,
+            SettingField::GcdQueueWindow => &mut settings.gcd_queue_window,
+            SettingField::BufferWindow => &mut settings.buffer_window,
+            SettingField::HudScale => &mut settings.hud_scale,
+            SettingField::CastBarWidth => &mut settings.cast_bar_width,
+            SettingField::CastBarHeight => &mut settings.cast_bar_height,
+            SettingField::CastBarLeft => &mut settings.cast_bar_left,
+            SettingField::CastBarBottom => &mut settings.cast_bar_bottom,
+            SettingField::ScreenShake => &mut settings.screen_shake,
+            SettingField::HitStop => &mut settings.hit_stop,
+        }
+    }
+
+    fn get(self, settings: &Settings) -> f32 {
+        match self {
+            SettingField::MusicVolume => settings.music_volume,
+            SettingField::EffectsVolume => settings.effects_volume,
+            SettingField::LatencyMs => settings.latency_ms,
+            SettingField::GcdQueueWindow => settings.gcd_queue_window,
+            SettingField::BufferWindow => settings.buffer_window,
+            SettingField::HudScale => settings.hud_scale,
+            SettingField::CastBarWidth => settings.cast_bar_width,
+            SettingField::CastBarHeight => settings.cast_bar_height,
+            SettingField::CastBarLeft => settings.cast_bar_left,
+            SettingField::CastBarBottom => settings.cast_bar_bottom,
+            SettingField::ScreenShake => settings.screen_shake,
+            SettingField::HitStop => settings.hit_stop,
+        }
+    }
+
+    fn format(self, value: f32) -> String {
+        match self {
+            SettingField::MusicVolume | SettingField::EffectsVolume => format!("{:.0}%", value * 100.0),
+            SettingField::LatencyMs if value <= 0.0 => "off".to_string(),
+            SettingField::LatencyMs => format!("{value:.0} ms"),
+            SettingField::GcdQueueWindow | SettingField::BufferWindow => format!("{value:.2}s"),
+            SettingField::HudScale => format!("x{value:.1}"),
+            SettingField::CastBarWidth | SettingField::CastBarHeight | SettingField::CastBarBottom => {
+                format!("{value:.0} px")
+            }
+            SettingField::CastBarLeft => format!("{value:.0}%"),
+            SettingField::ScreenShake | SettingField::HitStop if value <= 0.0 => "off".to_string(),
+            SettingField::ScreenShake | SettingField::HitStop => format!("{:.0}%", value * 100.0),
+        }
+    }
+
+    fn step(self, settings: &mut Settings, direction: f32) {
+        let (step, min, max) = self.range();
+        let value = self.value(settings);
+        // Rounded to the step so repeated clicks don't drift
+        *value = ((*value + step * direction) / step).round() * step;
+        *value = value.clamp(min, max);
+    }
+}
+
+/// Whether the panel on the menu is open
+#[derive(Resource, Debug, Default)]
+pub struct SettingsPanel {
+    open: bool,
+}
+
+impl SettingsPanel {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+}
+
+#[derive(Component)]
+struct SettingsPanelRoot;
+
+#[derive(Component, Debug, Clone, Copy)]
+struct SettingStep {
+    field: SettingField,
+    direction: f32,
+}
+
+#[derive(Component)]
+struct SettingValue(SettingField);
+
+/// Settings with a single button that flips or cycles them
+#[derive(Component, Debug, Clone, Copy)]
+enum SettingToggle {
+    HudAnchor,
+    CastBarTicks,
+    CombineTicks,
+    MergeHits,
+    BigCrits,
+    ScrollDirection,
+    MaxBattleText,
+    MinimalBattleText,
+    MechanicCallouts,
+    MaxParticles,
+    CrtFilter,
+    ClipBuffer,
+}
+
+impl SettingToggle {
+    const ALL: [SettingToggle; 12] = [
+        SettingToggle::HudAnchor,
+        SettingToggle::CastBarTicks,
+        SettingToggle::CombineTicks,
+        SettingToggle::MergeHits,
+        SettingToggle::BigCrits,
+        SettingToggle::ScrollDirection,
+        SettingToggle::MaxBattleText,
+        SettingToggle::MinimalBattleText,
+        SettingToggle::MechanicCallouts,
+        SettingToggle::MaxParticles,
+        SettingToggle::CrtFilter,
+        SettingToggle::ClipBuffer,
+    ];
+
+    fn label(self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let text = &settings.battle_text;
+        match self {
+            SettingToggle::HudAnchor => format!("Hotbars: {}", settings.hud_anchor.label()),
+            SettingToggle::CastBarTicks => {
+                format!("Cast bar ticks: {}", on_off(settings.cast_bar_ticks))
+            }
+            SettingToggle::CombineTicks => format!("Combine DoT ticks: {}", on_off(text.combine_ticks)),
+            SettingToggle::MergeHits => format!("Merge close hits: {}", on_off(text.merge_hits)),
+            SettingToggle::BigCrits => format!("Big crits: {}", on_off(text.big_crits)),
+            SettingToggle::ScrollDirection => format!("Battle text scrolls: {}", text.direction.label()),
+            SettingToggle::MaxBattleText => format!("Battle text on screen: {}", text.max_on_screen),
+            SettingToggle::MinimalBattleText => format!("Big hits only: {}", on_off(text.minimal)),
+            SettingToggle::MechanicCallouts => format!("Mechanic callouts: {}", on_off(settings.mechanic_callouts)),
+            SettingToggle::MaxParticles => format!("Particle budget: {}", settings.max_particles),
+            SettingToggle::CrtFilter => format!("CRT filter: {}", on_off(settings.crt_filter)),
+            SettingToggle::ClipBuffer => format!("Clip buffer (hold F12): {}", on_off(settings.clip_buffer)),
+        }
+    }
+
+    fn press(self, settings: &mut Settings) {
+        match self {
+            SettingToggle::HudAnchor => settings.hud_anchor = settings.hud_anchor.cycle(),
+            SettingToggle::CastBarTicks => settings.cast_bar_ticks = !settings.cast_bar_ticks,
+            SettingToggle::CombineTicks => settings.battle_text.combine_ticks = !settings.battle_text.combine_ticks,
+            SettingToggle::MergeHits => settings.battle_text.merge_hits = !settings.battle_text.merge_hits,
+            SettingToggle::BigCrits => settings.battle_text.big_crits = !settings.battle_text.big_crits,
+            SettingToggle::ScrollDirection => settings.battle_text.direction = settings.battle_text.direction.cycle(),
+            SettingToggle::MaxBattleText => settings.battle_text.cycle_max(),
+            SettingToggle::MinimalBattleText => settings.battle_text.minimal = !settings.battle_text.minimal,
+            SettingToggle::MechanicCallouts => settings.mechanic_callouts = !settings.mechanic_callouts,
+            SettingToggle::MaxParticles => {
+                settings.max_particles = match settings.max_particles {
+                    ..=500 => 1000,
+                    501..=1000 => 2000,
+                    1001..=2000 => 4000,
+                    _ => 500,
+                }
+            }
+            SettingToggle::CrtFilter => settings.crt_filter = !settings.crt_filter,
+            SettingToggle::ClipBuffer => settings.clip_buffer = !settings.clip_buffer,
+        }
+    }
+}
+
+const BUTTON_NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+
+fn setup_settings_panel(mut commands: Commands, settings: Res<Settings>, mut panel: ResMut<SettingsPanel>) {
+    panel.open = false;
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(70.0),
+                right: Val::Px(20.0),
+                width: Val::Px(260.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                row_gap: Val::Px(4.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.9)),
+            Visibility::Hidden,
+            SettingsPanelRoot,
+            StateScoped(GameState::Menu),
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new("Settings"),
+                TextFont { font_size: 18.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            for field in SettingField::ALL {
+                panel
+                    .spawn(Node { align_items: AlignItems::Center, column_gap: Val::Px(6.0), ..default() })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text::new(field.label()),
+                            TextFont { font_size: 14.0, ..default() },
+                            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                            Node { width: Val::Px(110.0), ..default() },
+                        ));
+                        for (label, direction) in [("-", -1.0), ("+", 1.0)] {
+                            row.spawn((
+                                Button,
+                                Node {
+                                    width: Val::Px(24.0),
+                                    height: Val::Px(24.0),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                BackgroundColor(BUTTON_NORMAL),
+                                SettingStep { field, direction },
+                            ))
+                            .with_child((
+                                Text::new(label),
+                                TextFont { font_size: 14.0, ..default() },
+                                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                            ));
+                        }
+                        row.spawn((
+                            Text::new(""),
+                            TextFont { font_size: 14.0, ..default() },
+                            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                            SettingValue(field),
+                        ));
+                    });
+            }
+            for toggle in SettingToggle::ALL {
+                panel
+                    .spawn((
+                        Button,
+                        Node {
+                            height: Val::Px(24.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        toggle,
+                    ))
+                    .with_child((
+                        Text::new(toggle.label(&settings)),
+                        TextFont { font_size: 14.0, ..default() },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+            }
+            panel.spawn((
+                Text::new("Latency, input windows and layout apply from the next pull"),
+                TextFont { font_size: 11.0, ..default() },
+                TextColor(Color::linear_rgb(0.6, 0.6, 0.6)),
+            ));
+        });
+}
+
+fn click_settings_buttons(
+    mut settings: ResMut<Settings>,
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &SettingStep), (Without<SettingToggle>, Changed<Interaction>)>,
+    mut toggle_query: Query<(&Interaction, &mut BackgroundColor, &SettingToggle, &Children), Changed<Interaction>>,
+    mut q_labels: Query<&mut Text>,
+) {
+    for (interaction, mut color, step) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => step.field.step(&mut settings, step.direction),
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+    for (interaction, mut color, toggle, children) in &mut toggle_query {
+        match *interaction {
+            Interaction::Pressed => {
+                toggle.press(&mut settings);
+                for child in children.iter() {
+                    if let Ok(mut text) = q_labels.get_mut(child) {
+                        text.0 = toggle.label(&settings);
+                    }
+                }
+            }
+            Interaction::Hovered => *color = BUTTON_HOVERED.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn update_settings_panel(
+    settings: Res<Settings>,
+    panel: Res<SettingsPanel>,
+    mut q_panel: Query<&mut Visibility, With<SettingsPanelRoot>>,
+    mut q_values: Query<(&mut Text, &SettingValue)>,
+) {
+    if !settings.is_changed() && !panel.is_changed() {
+        return;
+    }
+    if let Ok(mut vis) = q_panel.single_mut() {
+        *vis = if panel.open { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    for (mut text, SettingValue(field)) in &mut q_values {
+        text.0 = field.format(field.get(&settings));
+    }
+}
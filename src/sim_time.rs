@@ -0,0 +1,108 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::{GameSet, GameState};
+
+// Simulation clock shared by every timer in the game. The whole sim (input application,
+// combat timers, the enemy timeline, statuses, mechanics, movement) runs in `FixedUpdate`
+// at `SIM_HZ`, so every tick is the same 1/60s whatever the frame rate and a pull comes out
+// the same given the same input on the same ticks.
+// Slow motion and fast-forward change how fast the virtual clock runs, which is how many
+// ticks a frame gets; the pause menu stops it. Systems that count down, move things or
+// animate effects read `SimTime` instead of `Time`: its delta is the tick inside
+// `FixedUpdate` and the scaled frame time everywhere else, and its clock counts ticks.
+// Only input feedback on the HUD (button presses, hotbar paging) stays on real time.
+
+pub struct SimTimePlugin;
+
+impl Plugin for SimTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimTimeScale>()
+            .insert_resource(Time::<Fixed>::from_hz(SIM_HZ))
+            .add_systems(First, apply_time_scale.run_if(resource_changed::<SimTimeScale>))
+            .add_systems(OnEnter(GameState::Playing), align_fixed_clock)
+            .add_systems(
+                PreUpdate,
+                cycle_time_scale.in_set(GameSet::InputRead).run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Fixed ticks per second for the combat sim
+pub const SIM_HZ: f64 = 60.0;
+
+/// Presets cycled with F8
+const SCALE_PRESETS: [f32; 4] = [1.0, 0.5, 0.25, 2.0];
+
+#[derive(Resource, Debug)]
+pub struct SimTimeScale {
+    /// 1.0 is real time, 0.5 half speed, 2.0 fast-forward
+    pub scale: f32,
+    pub paused: bool,
+}
+
+impl Default for SimTimeScale {
+    fn default() -> Self {
+        Self { scale: 1.0, paused: false }
+    }
+}
+
+impl SimTimeScale {
+    pub fn effective(&self) -> f32 {
+        if self.paused { 0.0 } else { self.scale.max(0.0) }
+    }
+
+    /// Steps to the next speed preset
+    pub fn cycle(&mut self) {
+        let idx = SCALE_PRESETS.iter().position(|s| *s == self.scale).unwrap_or(0);
+        self.scale = SCALE_PRESETS[(idx + 1) % SCALE_PRESETS.len()];
+    }
+}
+
+/// Run condition: false for frames the pause menu froze, when there is no sim to feed input to
+pub fn sim_running(time: Res<Time<Virtual>>) -> bool {
+    !time.is_paused()
+}
+
+/// The sim's view of time: fixed ticks in `FixedUpdate`, the scaled frame clock elsewhere
+#[derive(SystemParam)]
+pub struct SimTime<'w> {
+    time: Res<'w, Time>,
+    fixed: Res<'w, Time<Fixed>>,
+}
+
+impl SimTime<'_> {
+    /// Seconds since the last tick (or frame), with the time scale and pause applied
+    pub fn scaled_delta(&self) -> f32 {
+        self.time.delta_secs()
+    }
+
+    /// Sim seconds since startup, as of the latest tick
+    pub fn elapsed_secs(&self) -> f32 {
+        self.fixed.elapsed_secs()
+    }
+
+    /// How far this frame is past the last fixed tick, as a fraction of a tick
+    pub fn tick_alpha(&self) -> f32 {
+        self.fixed.overstep_fraction()
+    }
+}
+
+/// The scale sets the virtual clock's speed, which the fixed ticks are paced by
+fn apply_time_scale(scale: Res<SimTimeScale>, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(scale.effective());
+}
+
+/// Starts every pull on a tick boundary, so the first tick of a pull always comes a whole
+/// tick after it starts
+fn align_fixed_clock(mut fixed: ResMut<Time<Fixed>>) {
+    let overstep = fixed.overstep();
+    fixed.discard_overstep(overstep);
+}
+
+fn cycle_time_scale(keys: Res<ButtonInput<KeyCode>>, mut scale: ResMut<SimTimeScale>) {
+    if keys.just_pressed(KeyCode::F8) {
+        scale.cycle();
+        info!("Time scale x{}", scale.scale);
+    }
+}
@@ -0,0 +1,518 @@
+//! Scripted pulls for integration tests of combat behavior.
+//!
+//! A [`PullScript`] lists ability presses at fixed times plus expectations about the
+//! outcome. Running it drives the combat and world plugins headlessly in fixed 1/60s
+//! steps and returns a [`PullOutcome`] with one [`AssertionResult`] per expectation.
+//!
+//! ```
+//! use bevy_game::testing::{AbilityId, PullScript};
+//!
+//! PullScript::new()
+//!     .with_seed(7)
+//!     .press_at(0.0, AbilityId::Strike)
+//!     .press_at(0.8, AbilityId::WeaveDash)
+//!     .press_at(2.5, AbilityId::Strike)
+//!     .run_for(4.0)
+//!     .expect_damage_between(200, 600)
+//!     .expect_no_clip()
+//!     .run()
+//!     .assert_passed();
+//!
+//! // The same opener as a macro, to compare how it clips
+//! PullScript::new()
+//!     .macro_at(0.0, "Strike, wait 0.8, Weave: Dash, wait 1.7, Strike")
+//!     .run_for(4.0)
+//!     .expect_damage_between(200, 600)
+//!     .expect_no_clip()
+//!     .run()
+//!     .assert_passed();
+//! ```
+//!
+//! [`SteppedPull`] runs the same app one frame at a time against a striking dummy, for
+//! benchmarks and anything else that wants to drive the simulation itself, and
+//! [`headless_app`] is the bare app underneath both.
+//!
+//! Besides damage, an outcome lists every ability that went off and how the pull ended,
+//! enough to check GCD, queue and weave behavior:
+//!
+//! ```
+//! use bevy_game::testing::{AbilityId, PullScript};
+//!
+//! let outcome = PullScript::new()
+//!     .with_seed(3)
+//!     .press_at(0.0, AbilityId::Strike)
+//!     // Weaved while the GCD rolls
+//!     .press_at(0.5, AbilityId::WeaveDash)
+//!     // Inside the queue window: goes off as soon as the GCD is back
+//!     .press_at(2.2, AbilityId::Strike)
+//!     .run_for(4.0)
+//!     .expect_uses(AbilityId::Strike, 2)
+//!     .expect_uses(AbilityId::WeaveDash, 1)
+//!     .run();
+//! outcome.assert_passed();
+//! assert!(outcome.result.is_none(), "the training boss outlasts four seconds");
+//! ```
+//!
+//! Every pull is recorded as a replay, which [`PullScript::play_replay`] plays back:
+//!
+//! ```
+//! use bevy_game::testing::{AbilityId, PullScript};
+//!
+//! let live = PullScript::new().with_seed(5).press_at(0.0, AbilityId::Strike).run_for(2.0).run();
+//! let replayed = PullScript::new().play_replay(live.replay.as_deref().unwrap()).run();
+//! assert_eq!(replayed.hits, live.hits);
+//! ```
+
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use std::time::Duration;
+
+pub use crate::combat::{AbilityId, PlayerStats, PositionalTally, PullResult};
+use crate::actions::{collect_actions, ActionSet, Actions, SimInput};
+use crate::adds::SpawnAddsEvent;
+use crate::character::Character;
+use crate::combat::{
+    parse_macro_steps, AbilityBook, AbilityUsedEvent, ApplyDotEvent, ApplyStatusEvent, CombatPlugin, CombatState, CurrentEncounter,
+    EncounterDef, EncounterLibrary, InputLatency, MacroRunner, StatusBook, StatusFile,
+};
+use crate::keybinds::Keybinds;
+use crate::loading::TextureAssets;
+use crate::markers::ShowMarkerEvent;
+use crate::mechanics::SpawnMechanicEvent;
+use crate::rng::{GameRng, RngPlugin};
+use crate::sim_time::{SimTimePlugin, SIM_HZ};
+use crate::waymarks::{CalloutEvent, Waymarks};
+use crate::player::{Mutators, Player};
+use crate::replay;
+use crate::world::{DamageDealtEvent, Enemy, Enmity, Facing, Health, WorldPlugin};
+use crate::{configure_game_sets, GameSet, GameState};
+
+/// Simulation step used by the harness, one fixed tick
+pub const STEP_SECS: f32 = (1.0 / SIM_HZ) as f32;
+
+#[derive(Debug, Clone)]
+enum Expectation {
+    DamageBetween { min: i64, max: i64 },
+    NoClip,
+    Uses { ability: AbilityId, count: usize },
+}
+
+/// Scripted inputs and expectations for one pull
+#[derive(Debug, Clone, Default)]
+pub struct PullScript {
+    presses: Vec<(f32, AbilityId)>,
+    macros: Vec<(f32, String)>,
+    expectations: Vec<Expectation>,
+    duration: Option<f32>,
+    stats: Option<PlayerStats>,
+    seed: Option<u64>,
+    replay: Option<String>,
+}
+
+/// Result of a single expectation
+#[derive(Debug, Clone)]
+pub struct AssertionResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Everything observed during a scripted pull
+#[derive(Debug, Clone)]
+pub struct PullOutcome {
+    pub total_damage: i64,
+    /// (seconds into the pull, amount) for every damage event
+    pub hits: Vec<(f32, i32)>,
+    /// How many of the hits were critical
+    pub crits: usize,
+    /// Times at which a GCD was clipped by animation lock
+    pub clips: Vec<f32>,
+    pub positionals: PositionalTally,
+    /// (seconds into the pull, ability) for every ability that went off, casts when they finish
+    pub used: Vec<(f32, AbilityId)>,
+    /// How the pull ended, if the boss died or the player wiped before the script ran out
+    pub result: Option<PullResult>,
+    /// The pull as a replay file, for [`PullScript::play_replay`]; none for a pull that was itself a playback
+    pub replay: Option<String>,
+    pub assertions: Vec<AssertionResult>,
+}
+
+impl PullScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Presses `ability` at `t` seconds into the pull. It's handed to the simulated latency
+    /// where a read key press would be, so latency, queueing, buffering and the GCD apply as
+    /// in the game; the key read itself (hotbar slots, locked keys) is skipped
+    pub fn press_at(mut self, t: f32, ability: AbilityId) -> Self {
+        self.presses.push((t, ability));
+        self
+    }
+
+    /// Fires a macro at `t`, written like a `macros.txt` entry: "Strike, wait 0.5, Weave: Dash"
+    pub fn macro_at(mut self, t: f32, steps: &str) -> Self {
+        self.macros.push((t, steps.to_string()));
+        self
+    }
+
+    /// How long to simulate; defaults to 5s after the last press
+    pub fn run_for(mut self, secs: f32) -> Self {
+        self.duration = Some(secs);
+        self
+    }
+
+    /// Uses fixed stats instead of the defaults (or the user's `stats.txt`)
+    pub fn with_stats(mut self, stats: PlayerStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Fixes the random seed so crits and other rolls repeat exactly between runs
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Plays back `replay`, a file from [`PullOutcome::replay`] or the replays folder, in place of the
+    /// script's presses, macros, stats and seed. Runs as long as the recording unless [`Self::run_for`] says otherwise
+    pub fn play_replay(mut self, replay: &str) -> Self {
+        self.replay = Some(replay.to_string());
+        self
+    }
+
+    /// Total damage dealt over the pull must be within `min..=max`
+    pub fn expect_damage_between(mut self, min: i64, max: i64) -> Self {
+        self.expectations.push(Expectation::DamageBetween { min, max });
+        self
+    }
+
+    /// No GCD may be delayed by a weave's animation lock
+    pub fn expect_no_clip(mut self) -> Self {
+        self.expectations.push(Expectation::NoClip);
+        self
+    }
+
+    /// `ability` must go off exactly `count` times
+    pub fn expect_uses(mut self, ability: AbilityId, count: usize) -> Self {
+        self.expectations.push(Expectation::Uses { ability, count });
+        self
+    }
+
+    pub fn run(mut self) -> PullOutcome {
+        if self.replay.is_some() {
+            self.presses.clear();
+            self.macros.clear();
+            self.stats = None;
+        }
+        self.presses.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.macros.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let last_input = self
+            .presses
+            .iter()
+            .map(|(t, _)| *t)
+            .chain(self.macros.iter().map(|(t, _)| *t))
+            .fold(0.0, f32::max);
+        let mut duration = self.duration.unwrap_or(last_input + 5.0);
+
+        let mut app = headless_app();
+        if let Some(seed) = self.seed {
+            app.insert_resource(GameRng::new(seed));
+        }
+        if let Some(text) = &self.replay {
+            let ticks = replay::play(app.world_mut(), text).unwrap_or_else(|error| panic!("bad replay: {error}"));
+            duration = self.duration.unwrap_or(ticks as f32 * STEP_SECS);
+        }
+        // First update runs OnEnter(Playing), which resets combat and loads stats
+        app.update();
+        if let Some(stats) = self.stats.take() {
+            app.insert_resource(stats);
+        }
+
+        let macros: Vec<_> = {
+            let book = app.world().resource::<AbilityBook>();
+            self.macros
+                .iter()
+                .map(|(t, text)| match parse_macro_steps(text, book) {
+                    Ok(steps) => (*t, steps),
+                    Err(error) => panic!("bad macro {text:?}: {error}"),
+                })
+                .collect()
+        };
+
+        let mut next_press = 0;
+        let mut next_macro = 0;
+        let mut clips = Vec::new();
+        let mut was_clipped = false;
+        let mut t = 0.0;
+        while t < duration {
+            while next_press < self.presses.len() && self.presses[next_press].0 <= t {
+                let id = self.presses[next_press].1;
+                app.world_mut().resource_mut::<InputLatency>().send(id);
+                next_press += 1;
+            }
+            while next_macro < macros.len() && macros[next_macro].0 <= t {
+                let steps = macros[next_macro].1.clone();
+                app.world_mut().resource_mut::<MacroRunner>().start(steps);
+                next_macro += 1;
+            }
+            app.update();
+            t += STEP_SECS;
+
+            let clipped = app.world().resource::<CombatState>().clipped;
+            if clipped && !was_clipped {
+                clips.push(t);
+            }
+            was_clipped = clipped;
+        }
+
+        let recorded = app.world().resource::<Recorded>();
+        let (hits, crits, used) = (recorded.hits.clone(), recorded.crits, recorded.used.clone());
+        let positionals = app.world().resource::<CombatState>().positionals;
+        let ended = *app.world().resource::<State<GameState>>().get() == GameState::Results;
+        let result = ended.then(|| app.world().resource::<PullResult>().clone());
+        let total_damage = hits.iter().map(|(_, amount)| *amount as i64).sum();
+        let replay = replay::recording(app.world());
+        let assertions = self
+            .expectations
+            .iter()
+            .map(|e| evaluate(e, total_damage, &clips, &used))
+            .collect();
+        PullOutcome { total_damage, hits, crits, clips, positionals, used, result, replay, assertions }
+    }
+}
+
+impl PullOutcome {
+    pub fn passed(&self) -> bool {
+        self.assertions.iter().all(|a| a.passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &AssertionResult> {
+        self.assertions.iter().filter(|a| !a.passed)
+    }
+
+    /// Panics listing every failed expectation
+    pub fn assert_passed(&self) {
+        if self.passed() {
+            return;
+        }
+        let report: Vec<String> = self.failures().map(|a| format!("{}: {}", a.name, a.detail)).collect();
+        panic!("pull expectations failed:\n  {}", report.join("\n  "));
+    }
+}
+
+fn evaluate(expectation: &Expectation, total_damage: i64, clips: &[f32], used: &[(f32, AbilityId)]) -> AssertionResult {
+    match expectation {
+        Expectation::DamageBetween { min, max } => AssertionResult {
+            name: format!("damage between {min} and {max}"),
+            passed: (*min..=*max).contains(&total_damage),
+            detail: format!("dealt {total_damage}"),
+        },
+        Expectation::NoClip => AssertionResult {
+            name: "no clip".to_string(),
+            passed: clips.is_empty(),
+            detail: format!("clipped at {:?}", clips),
+        },
+        Expectation::Uses { ability, count } => {
+            let times: Vec<f32> = used.iter().filter(|(_, id)| id == ability).map(|(t, _)| *t).collect();
+            AssertionResult {
+                name: format!("{ability:?} used {count} times"),
+                passed: times.len() == *count,
+                detail: format!("used {} times, at {:?}", times.len(), times),
+            }
+        }
+    }
+}
+
+/// A pull against a striking dummy, advanced one frame at a time by the caller.
+///
+/// The dummy has an empty timeline and its HP is topped up every frame, so the pull
+/// never ends on its own: an hour of stepping is an hour of simulation.
+///
+/// ```
+/// use bevy_game::testing::{AbilityId, SteppedPull};
+///
+/// let mut pull = SteppedPull::new(7);
+/// pull.apply_statuses(10, 60.0);
+/// pull.press(AbilityId::Strike);
+/// pull.run_for(2.0);
+/// assert_eq!(pull.active_statuses(), 10);
+/// assert!(pull.total_damage() > 0);
+/// ```
+pub struct SteppedPull {
+    app: App,
+}
+
+const DUMMY_ENCOUNTER: &str = r#"(
+    id: "dummy",
+    name: "Striking Dummy",
+    branches: [(name: "main", events: [])],
+)"#;
+
+impl SteppedPull {
+    pub fn new(seed: u64) -> Self {
+        let def = EncounterDef::parse(DUMMY_ENCOUNTER).expect("dummy encounter should parse");
+        let mut app = headless_app();
+        app.insert_resource(GameRng::new(seed))
+            .insert_resource(EncounterLibrary { encounters: vec![def] })
+            .insert_resource(CurrentEncounter { id: "dummy".to_string() })
+            .add_systems(FixedUpdate, refill_dummy.before(GameSet::Sim));
+        // First update runs OnEnter(Playing), which resets combat and spawns the HUD
+        app.update();
+        Self { app }
+    }
+
+    /// Presses `ability` as a read key would, past the hotbar slot and key lock checks, into
+    /// the simulated latency; it goes off on a later step
+    pub fn press(&mut self, ability: AbilityId) {
+        self.app.world_mut().resource_mut::<InputLatency>().send(ability);
+    }
+
+    /// Puts a DoT on the dummy as if `ability` had applied it
+    pub fn apply_dot(&mut self, source: AbilityId, dps: i32, duration: f32, tick_every: f32) {
+        self.app.world_mut().send_event(ApplyDotEvent { source, dps, duration, tick_every });
+    }
+
+    /// Defines `count` extra statuses that each tick the dummy once a second, and puts
+    /// them all on the player for `duration` seconds
+    pub fn apply_statuses(&mut self, count: usize, duration: f32) {
+        let defs: Vec<String> = (0..count)
+            .map(|i| {
+                format!(
+                    "(id: \"stepped_{i}\", name: \"Status {i}\", duration: {duration:?}, \
+                     tick: Some((every: 1.0, effect: DamageEnemy(1))))"
+                )
+            })
+            .collect();
+        let file = StatusFile::parse(&format!("[{}]", defs.join(","))).expect("generated statuses should parse");
+        self.app.world_mut().resource_mut::<StatusBook>().add(&file);
+        for i in 0..count {
+            self.app.world_mut().send_event(ApplyStatusEvent { id: format!("stepped_{i}") });
+        }
+    }
+
+    /// Turns the HUD update systems on or off, to time a frame with and without them
+    pub fn set_hud(&mut self, enabled: bool) {
+        self.app.world_mut().resource_mut::<HudEnabled>().0 = enabled;
+    }
+
+    /// Advances the simulation by one [`STEP_SECS`] frame
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    pub fn run_for(&mut self, secs: f32) {
+        let steps = (secs / STEP_SECS).round() as u64;
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    pub fn active_statuses(&self) -> usize {
+        self.app.world().resource::<CombatState>().statuses.len()
+    }
+
+    pub fn total_damage(&self) -> i64 {
+        self.app.world().resource::<Recorded>().hits.iter().map(|(_, amount)| *amount as i64).sum()
+    }
+}
+
+fn refill_dummy(mut q_enemy: Query<&mut Health, With<Enemy>>) {
+    for mut hp in &mut q_enemy {
+        hp.current = hp.max;
+    }
+}
+
+/// Whether `GameSet::Ui` runs in the headless app
+#[derive(Resource)]
+struct HudEnabled(bool);
+
+#[derive(Resource, Default)]
+struct Recorded {
+    hits: Vec<(f32, i32)>,
+    crits: usize,
+    used: Vec<(f32, AbilityId)>,
+}
+
+fn record_damage(time: Res<Time>, mut evr: EventReader<DamageDealtEvent>, mut recorded: ResMut<Recorded>) {
+    for ev in evr.read() {
+        recorded.hits.push((time.elapsed_secs(), ev.amount));
+        recorded.crits += ev.crit as usize;
+    }
+}
+
+fn record_uses(time: Res<Time>, mut evr: EventReader<AbilityUsedEvent>, mut recorded: ResMut<Recorded>) {
+    for ev in evr.read() {
+        recorded.used.push((time.elapsed_secs(), ev.id));
+    }
+}
+
+/// Pulls run against the default encounter, read from the file the game ships with
+fn default_encounters() -> EncounterLibrary {
+    let text = include_str!("../assets/encounters/default.encounter.ron");
+    let def = EncounterDef::parse(text).expect("default encounter file should parse");
+    EncounterLibrary { encounters: vec![def] }
+}
+
+/// Statuses come from the shipped status file too, so buffs match the game
+fn base_statuses() -> StatusBook {
+    let text = include_str!("../assets/statuses/base.statuses.ron");
+    let mut book = StatusBook::default();
+    book.add(&StatusFile::parse(text).expect("base status file should parse"));
+    book
+}
+
+/// The player where the game puts them, without sprite or movement, so ranged hits fly
+/// and positionals and facing checks work as in a real pull
+fn spawn_player(mut commands: Commands) {
+    commands.spawn((
+        Transform::from_translation(Vec3::new(0., 0., 1.)),
+        Player,
+        Health { current: 1000, max: 1000 },
+        Enmity::default(),
+        Facing(Vec2::X),
+        StateScoped(GameState::Playing),
+    ));
+}
+
+/// Combat and world simulation without window, renderer, audio or asset loading, stepped
+/// one fixed tick of [`STEP_SECS`] per `update()` whatever the wall clock does. Starts in
+/// the playing state against the default encounter; the first update runs the pull setup.
+/// Pulls are recorded as replays the way the game records them, without saving them.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / SIM_HZ)))
+        .init_resource::<ButtonInput<KeyCode>>()
+        .init_resource::<Keybinds>()
+        .init_resource::<Actions>()
+        .init_resource::<SimInput>()
+        .init_resource::<Character>()
+        .init_resource::<Mutators>()
+        .init_resource::<Waymarks>()
+        .insert_resource(TextureAssets {
+            bevy: Handle::default(),
+            github: Handle::default(),
+            smallstar: Handle::default(),
+            hollowstar: Handle::default(),
+            y2k_star: Handle::default(),
+        })
+        .add_event::<CalloutEvent>()
+        .add_event::<ShowMarkerEvent>()
+        .add_event::<SpawnMechanicEvent>()
+        .add_event::<SpawnAddsEvent>()
+        .insert_state(GameState::Playing);
+    configure_game_sets(&mut app);
+    app.insert_resource(HudEnabled(true))
+        .configure_sets(Update, GameSet::Ui.run_if(|hud: Res<HudEnabled>| hud.0));
+    app.add_plugins((SimTimePlugin, RngPlugin, CombatPlugin, WorldPlugin))
+        .insert_resource(default_encounters())
+        .insert_resource(base_statuses())
+        .init_resource::<Recorded>()
+        .add_systems(OnEnter(GameState::Playing), spawn_player)
+        .add_systems(FixedUpdate, collect_actions.in_set(ActionSet::Collect))
+        .add_systems(FixedUpdate, (record_damage, record_uses).after(GameSet::Sim));
+    replay::add_recording(&mut app);
+    app
+}
@@ -0,0 +1,240 @@
+use bevy::prelude::*;
+
+use crate::adds::Add;
+use crate::combat::{AbilityBook, CombatState};
+use crate::mechanics::Telegraph;
+use crate::party::PartyMember;
+use crate::player::Player;
+use crate::waymarks::WaymarkPlacement;
+use crate::world::{CurrentTarget, Enemy, Enmity, Health};
+use crate::{GameSet, GameState};
+
+// Unit frames next to the boss HP bar: target of target and focus target. Target of target
+// is whoever the current target is attacking, which for the boss is the top of its enmity
+// list, so a tank can see at a glance whether they still hold it. Shift+Tab focuses the
+// current target, or clears the focus when pressed on it again. Each frame has the unit's
+// name, HP and, while it's casting, a cast bar.
+
+pub struct UnitFramesPlugin;
+
+impl Plugin for UnitFramesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FocusTarget>()
+            .add_systems(OnEnter(GameState::Playing), (reset_focus, spawn_unit_frames))
+            .add_systems(PreUpdate, set_focus.in_set(GameSet::InputRead).run_if(in_state(GameState::Playing)))
+            .add_systems(Update, update_unit_frames.in_set(GameSet::Ui).run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Enemy picked with Shift+Tab to keep an eye on
+#[derive(Resource, Debug, Default)]
+pub struct FocusTarget(pub Option<Entity>);
+
+const FRAME_WIDTH: f32 = 170.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnitFrame {
+    TargetOfTarget,
+    Focus,
+}
+
+impl UnitFrame {
+    fn title(self) -> &'static str {
+        match self {
+            UnitFrame::TargetOfTarget => "Target of target",
+            UnitFrame::Focus => "Focus",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Root,
+    Name,
+    Hp,
+    Cast,
+    CastLabel,
+}
+
+/// One piece of a frame; a single component keeps all the pieces in one query
+#[derive(Component, Debug, Clone, Copy)]
+struct FramePart {
+    frame: UnitFrame,
+    part: Part,
+}
+
+fn reset_focus(mut focus: ResMut<FocusTarget>) {
+    focus.0 = None;
+}
+
+fn set_focus(
+    keys: Res<ButtonInput<KeyCode>>,
+    placement: Res<WaymarkPlacement>,
+    target: Res<CurrentTarget>,
+    mut focus: ResMut<FocusTarget>,
+    q_boss: Query<Entity, With<Enemy>>,
+) {
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if placement.active || !shift || !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let current = target.0.or(q_boss.single().ok());
+    focus.0 = if focus.0 == current { None } else { current };
+}
+
+fn spawn_unit_frames(mut commands: Commands) {
+    // To the right of the boss HP bar at top center
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(230.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|root| {
+            for frame in [UnitFrame::TargetOfTarget, UnitFrame::Focus] {
+                spawn_frame(root, frame);
+            }
+        });
+}
+
+fn spawn_frame(parent: &mut ChildSpawnerCommands, frame: UnitFrame) {
+    let part = |part| FramePart { frame, part };
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(FRAME_WIDTH),
+                padding: UiRect::all(Val::Px(4.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgb(0.0, 0.0, 0.0).with_alpha(0.5)),
+            Visibility::Hidden,
+            part(Part::Root),
+        ))
+        .with_children(|f| {
+            f.spawn((
+                Text::new(frame.title()),
+                TextFont { font_size: 10.0, ..default() },
+                TextColor(Color::linear_rgb(0.6, 0.6, 0.6)),
+            ));
+            f.spawn((
+                Text::new(""),
+                TextFont { font_size: 13.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                part(Part::Name),
+            ));
+            f.spawn((Node { height: Val::Px(8.0), ..default() }, BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05))))
+                .with_child((
+                    Node { width: Val::Percent(100.0), height: Val::Percent(100.0), ..default() },
+                    BackgroundColor(Color::linear_rgb(0.8, 0.2, 0.2)),
+                    part(Part::Hp),
+                ));
+            f.spawn((Node { height: Val::Px(6.0), ..default() }, BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05))))
+                .with_child((
+                    Node { width: Val::Percent(0.0), height: Val::Percent(100.0), ..default() },
+                    BackgroundColor(Color::linear_rgb(0.9, 0.6, 0.2)),
+                    part(Part::Cast),
+                ));
+            f.spawn((
+                Text::new(""),
+                TextFont { font_size: 10.0, ..default() },
+                TextColor(Color::linear_rgb(0.9, 0.7, 0.4)),
+                part(Part::CastLabel),
+            ));
+        });
+}
+
+/// What a frame shows: name, HP fraction and the running cast with its progress
+struct UnitView {
+    name: String,
+    hp: f32,
+    cast: Option<(String, f32)>,
+}
+
+fn update_unit_frames(
+    target: Res<CurrentTarget>,
+    mut focus: ResMut<FocusTarget>,
+    combat: Res<CombatState>,
+    book: Res<AbilityBook>,
+    q_boss: Query<Entity, With<Enemy>>,
+    q_units: Query<(&Health, Option<&PartyMember>, Option<&Add>, Has<Player>, Has<Enemy>)>,
+    q_enmity: Query<(Entity, &Enmity, &Health)>,
+    q_telegraphs: Query<&Telegraph>,
+    mut q_parts: Query<(&FramePart, &mut Node, &mut Visibility, Option<&mut Text>)>,
+) {
+    // A focused add that died is gone for good
+    if focus.0.is_some_and(|e| !q_units.contains(e)) {
+        focus.0 = None;
+    }
+    let boss = q_boss.single().ok();
+    let current = target.0.filter(|e| q_units.contains(*e)).or(boss);
+    // Only the boss keeps an enmity list; adds don't fight back
+    let target_of_target = current.filter(|e| Some(*e) == boss).and_then(|_| {
+        q_enmity
+            .iter()
+            .filter(|(_, _, hp)| hp.current > 0)
+            .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+            .map(|(e, ..)| e)
+    });
+
+    let view = |entity: Option<Entity>| -> Option<UnitView> {
+        let (hp, member, add, is_player, is_boss) = q_units.get(entity?).ok()?;
+        let name = match (member, add) {
+            (Some(member), _) => member.name.clone(),
+            (_, Some(add)) => add.name.clone(),
+            _ if is_player => "You".to_string(),
+            _ => "Boss".to_string(),
+        };
+        let cast = if is_player {
+            combat.cast.as_ref().map(|cast| {
+                let name = book.by_id.get(&cast.ability).map_or("?", |a| a.name);
+                (name.to_string(), 1.0 - cast.remaining / cast.total.max(f32::EPSILON))
+            })
+        } else if is_boss {
+            // Whichever telegraph goes off next is what the boss is casting
+            q_telegraphs
+                .iter()
+                .min_by(|a, b| a.remaining.total_cmp(&b.remaining))
+                .map(|t| (t.name.clone(), 1.0 - t.remaining / t.windup.max(f32::EPSILON)))
+        } else {
+            None
+        };
+        let hp = if hp.max > 0 { (hp.current as f32 / hp.max as f32).clamp(0.0, 1.0) } else { 0.0 };
+        Some(UnitView { name, hp, cast })
+    };
+    let tot = view(target_of_target);
+    let focused = view(focus.0);
+
+    for (part, mut node, mut vis, text) in &mut q_parts {
+        let unit = match part.frame {
+            UnitFrame::TargetOfTarget => tot.as_ref(),
+            UnitFrame::Focus => focused.as_ref(),
+        };
+        match (part.part, unit) {
+            (Part::Root, unit) => *vis = if unit.is_some() { Visibility::Inherited } else { Visibility::Hidden },
+            (_, None) => {}
+            (Part::Name, Some(unit)) => {
+                if let Some(mut text) = text {
+                    text.0.clone_from(&unit.name);
+                }
+            }
+            (Part::Hp, Some(unit)) => node.width = Val::Percent(unit.hp * 100.0),
+            (Part::Cast, Some(unit)) => {
+                node.width = Val::Percent(unit.cast.as_ref().map_or(0.0, |(_, p)| p.clamp(0.0, 1.0) * 100.0));
+            }
+            (Part::CastLabel, Some(unit)) => {
+                if let Some(mut text) = text {
+                    text.0 = unit.cast.as_ref().map(|(name, _)| name.clone()).unwrap_or_default();
+                }
+            }
+        }
+    }
+}
@@ -1,248 +1,945 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, RenderAssetUsages};
 use bevy::prelude::*;
-use crate::GameSet;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::view::NoFrustumCulling;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::{FRAC_PI_2, TAU};
+use std::sync::Arc;
+use crate::{GameSet, GameState};
 use crate::loading::TextureAssets;
+use crate::settings::Settings;
+use crate::sim_time::SimTime;
 
 // 2D VFX port for Bevy 0.16
+//
+// Effects are defined in data, `assets/vfx/*.vfx.ron` (the format is at the top of
+// `base.vfx.ron`), and played by id with `vfx_play`. In builds with the `dev` feature the files
+// are watched, and saving one swaps its effects in while the game runs; effects already going
+// keep what they started with.
+//
+// Explosion sparks, flashes and Y2K stars aren't entities: they're plain data, moved each frame
+// and drawn as quads into one mesh per texture and layer, so a screen full of them is a handful
+// of draw calls whatever the count. The layer is the depth of whatever the effect went off on,
+// lifted a little, so a hit on the boss still goes under the player as it did when they were
+// sprites. Motion trails copy the player's sprite, so they stay sprites, pooled: a
+// finished one is hidden and handed out again instead of being despawned.
+//
+// The number showing at once is capped by the particle budget on the settings panel; past most
+// of it the small stuff (flickers, trail images) is skipped, and at the budget nothing new goes
+// up until some finish.
+//
+// Auras are looping effects that stay on an entity while something (a status, a DoT) is up:
+// set them on its `VfxAuras`.
+//
+// Telegraphs are ground shapes (circles, cones, lines, donuts) that whoever owns them fills in
+// over a wind-up; `mechanics` puts one on every AoE. They're all drawn into one more mesh.
 
 pub struct VfxPlugin;
 
 impl Plugin for VfxPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                retro_explosion_system,
-                tick_vfx_particles,
-                tick_vfx_flash,
-                tick_y2k_stars,
-            )
-                .in_set(GameSet::Ui),
-        );
+        app.init_resource::<Particles>()
+            .init_resource::<BatchMaterials>()
+            .init_resource::<VfxLibrary>()
+            .add_systems(OnEnter(GameState::Playing), (reset_particles, init_batch_materials, spawn_telegraph_batch))
+            .add_systems(
+                Update,
+                (
+                    (tick_emitters, tick_auras, tick_particles, draw_particle_batches).chain(),
+                    tick_afterimages,
+                    draw_telegraphs,
+                )
+                    .in_set(GameSet::Ui),
+            );
     }
 }
 
-// Public API — spawn an explosion at a world position
-pub fn vfx_retro_explosion(commands: &mut Commands, origin: Vec3, time: f32) {
-    commands.spawn(VfxExplosion {
-        origin,
-        time_spawned: time,
-        last_emitted: -1.0,
-        color: Color::linear_rgb(1.0, 0.6, 0.8),
-    });
+// =========================
+// Particles and budget
+// =========================
 
-    vfx_retro_explosion_flash(commands, origin, Color::linear_rgb(1.0, 0.6, 0.8));
+/// Particles showing at once when there are no settings to go by
+pub const DEFAULT_MAX_PARTICLES: usize = 2000;
+/// Share of the budget past which detail particles are skipped
+const DETAIL_SHARE: f32 = 0.75;
+/// How far above its origin an effect is drawn
+const EFFECT_LIFT: f32 = 0.8;
+
+/// How much a particle matters when the budget is tight
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum Lod {
+    /// Bursts, flashes and stars: only skipped at the budget
+    #[default]
+    Key,
+    /// Flickers and trail images: skipped once most of the budget is used
+    Detail,
 }
 
-// Components and systems
+/// Which mesh a particle is drawn into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Batch {
+    /// Flat colored squares
+    Plain,
+    SmallStar,
+    HollowStar,
+}
 
-#[derive(Component)]
-struct VfxExplosion {
-    origin: Vec3,
-    time_spawned: f32,
-    last_emitted: f32,
-    color: Color,
+/// How a particle moves and fades, with its emitter's numbers
+#[derive(Debug, Clone)]
+enum Motion {
+    Spark { drag: f32, fade: f32 },
+    Flash { fade: f32 },
+    Star { spin: f32, gravity: f32, damping: f32, shrink: f32, blit_index: i32, palette: Arc<[Color]> },
 }
 
-#[derive(Component)]
-struct VfxParticle {
+#[derive(Debug, Clone)]
+struct Particle {
+    batch: Batch,
+    motion: Motion,
+    pos: Vec2,
+    /// Depth of the layer it's drawn in
+    z: f32,
     vel: Vec2,
     ttl: f32,
+    /// Quad size before `scale`; stars go by their texture instead
+    size: Vec2,
+    scale: f32,
+    rotation: f32,
+    color: Color,
+}
+
+#[derive(Resource, Debug, Default)]
+struct Particles {
+    /// Sparks, flashes and stars, drawn by the batches
+    batched: Vec<Particle>,
+    /// Hidden trail images free to be shown again
+    free_afterimages: Vec<Entity>,
+    /// Trail images showing
+    afterimages: usize,
+}
+
+impl Particles {
+    fn live(&self) -> usize {
+        self.batched.len() + self.afterimages
+    }
+
+    /// Whether the budget has room for one more particle of this importance
+    fn admits(&self, lod: Lod, budget: usize) -> bool {
+        let limit = match lod {
+            Lod::Key => budget,
+            Lod::Detail => (budget as f32 * DETAIL_SHARE) as usize,
+        };
+        self.live() < limit
+    }
+
+    fn emit(&mut self, lod: Lod, budget: usize, particle: Particle) {
+        if self.admits(lod, budget) {
+            self.batched.push(particle);
+        }
+    }
+
+    /// Hides a finished trail image and keeps it for the next one
+    fn release_afterimage(&mut self, e: Entity, vis: &mut Visibility) {
+        *vis = Visibility::Hidden;
+        self.free_afterimages.push(e);
+        self.afterimages = self.afterimages.saturating_sub(1);
+    }
+}
+
+fn budget(settings: Option<&Settings>) -> usize {
+    settings.map_or(DEFAULT_MAX_PARTICLES, |s| s.max_particles)
+}
+
+/// Last pull's particles and pooled entities went with its state, so this starts empty too
+fn reset_particles(mut particles: ResMut<Particles>) {
+    *particles = Particles::default();
 }
 
+// =========================
+// Batches
+// =========================
+
+/// A batch's mesh for one layer
 #[derive(Component)]
-struct VfxFlash {
-    ttl: f32,
+struct ParticleBatch {
+    batch: Batch,
+    z: f32,
 }
 
-fn retro_explosion_system(
+/// Each batch's material and texture, shared by its layers
+#[derive(Resource, Default)]
+struct BatchMaterials(HashMap<Batch, (Handle<ColorMaterial>, Option<Handle<Image>>)>);
+
+fn init_batch_materials(
     mut commands: Commands,
-    time: Res<Time>,
-    mut q: Query<(Entity, &mut VfxExplosion)>,
+    textures: Res<TextureAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let t = time.elapsed_secs();
+    let batches = [
+        (Batch::Plain, None),
+        (Batch::SmallStar, Some(textures.smallstar.clone())),
+        (Batch::HollowStar, Some(textures.hollowstar.clone())),
+    ];
+    let batches = batches.into_iter().map(|(batch, image)| {
+        let material = match &image {
+            Some(image) => ColorMaterial::from(image.clone()),
+            // White and blended, so colors and fades come from the vertices
+            None => ColorMaterial::default(),
+        };
+        (batch, (materials.add(material), image))
+    });
+    // The meshes come with the first particles on each layer
+    commands.insert_resource(BatchMaterials(batches.collect()));
+}
 
-    for (e, mut explosion) in &mut q {
-        if t - explosion.last_emitted < 0.02 {
+/// Corners of a quad and their texture coordinates, counter-clockwise from the bottom left
+const QUAD_CORNERS: [(Vec2, [f32; 2]); 4] = [
+    (Vec2::new(-1.0, -1.0), [0.0, 1.0]),
+    (Vec2::new(1.0, -1.0), [1.0, 1.0]),
+    (Vec2::new(1.0, 1.0), [1.0, 0.0]),
+    (Vec2::new(-1.0, 1.0), [0.0, 0.0]),
+];
+
+/// Writes every particle into the mesh for its batch and layer as a quad, flashes first so
+/// they sit underneath. A layer showing for the first time gets its mesh spawned
+fn draw_particle_batches(
+    mut commands: Commands,
+    particles: Res<Particles>,
+    materials: Res<BatchMaterials>,
+    images: Res<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut q_batches: Query<(&ParticleBatch, &Mesh2d, &mut Visibility)>,
+) {
+    let mut layers: HashMap<(Batch, u32), Vec<&Particle>> = HashMap::new();
+    for p in &particles.batched {
+        layers.entry((p.batch, p.z.to_bits())).or_default().push(p);
+    }
+    let texture_size = |batch: Batch| {
+        let image = materials.0.get(&batch).and_then(|(_, image)| image.as_ref());
+        image.and_then(|image| images.get(image)).map(|image| image.size_f32())
+    };
+
+    for (batch, mesh, mut vis) in &mut q_batches {
+        // An empty mesh is left as it was and just hidden
+        let Some(drawn) = layers.remove(&(batch.batch, batch.z.to_bits())) else {
+            vis.set_if_neq(Visibility::Hidden);
             continue;
+        };
+        vis.set_if_neq(Visibility::Inherited);
+        let Some(mesh) = meshes.get_mut(&mesh.0) else { continue; };
+        write_quads(mesh, drawn, texture_size(batch.batch));
+    }
+    for ((batch, z), drawn) in layers {
+        let Some((material, _)) = materials.0.get(&batch) else { continue; };
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+        write_quads(&mut mesh, drawn, texture_size(batch));
+        let z = f32::from_bits(z);
+        commands.spawn((
+            Mesh2d(meshes.add(mesh)),
+            MeshMaterial2d(material.clone()),
+            Transform::from_xyz(0.0, 0.0, z),
+            // The quads move every frame, so bounds worked out once would be wrong
+            NoFrustumCulling,
+            ParticleBatch { batch, z },
+            StateScoped(GameState::Playing),
+        ));
+    }
+}
+
+/// Replaces `mesh`'s contents with one quad per particle, sized by the batch's texture if it
+/// has one
+fn write_quads(mesh: &mut Mesh, mut drawn: Vec<&Particle>, texture_size: Option<Vec2>) {
+    drawn.sort_by_key(|p| !matches!(p.motion, Motion::Flash { .. }));
+    let mut positions = Vec::with_capacity(drawn.len() * 4);
+    let mut colors = Vec::with_capacity(drawn.len() * 4);
+    let mut uvs = Vec::with_capacity(drawn.len() * 4);
+    let mut indices = Vec::with_capacity(drawn.len() * 6);
+    for p in drawn {
+        let half = texture_size.unwrap_or(p.size) * p.scale / 2.0;
+        let rotation = Vec2::from_angle(p.rotation);
+        let first = positions.len() as u32;
+        for (corner, uv) in QUAD_CORNERS {
+            let at = p.pos + rotation.rotate(corner * half);
+            positions.push([at.x, at.y, 0.0]);
+            colors.push(p.color.to_linear().to_f32_array());
+            uvs.push(uv);
+        }
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+}
+
+// =========================
+// Effect definitions
+// =========================
+
+/// Linear RGB, as written in the files
+type Rgb = (f32, f32, f32);
+
+fn rgb((r, g, b): Rgb) -> Color {
+    Color::linear_rgb(r, g, b)
+}
+
+/// Contents of one `.vfx.ron` file
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct VfxFile {
+    pub effects: Vec<VfxDef>,
+}
+
+impl VfxFile {
+    pub fn parse(text: &str) -> Result<Self, ron::de::SpannedError> {
+        ron::from_str(text)
+    }
+}
+
+#[derive(Default)]
+pub struct VfxLoader;
+
+impl AssetLoader for VfxLoader {
+    type Asset = VfxFile;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<VfxFile, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(VfxFile::parse(std::str::from_utf8(&bytes)?)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vfx.ron"]
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VfxDef {
+    pub id: String,
+    pub emitters: Vec<EmitterDef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum EmitterKind {
+    /// Flies out along a fixed spread of directions, keeping `drag` of its speed every 60th of
+    /// a second, fading over `fade` seconds and shrinking to half as it does
+    Spark { speed: f32, drag: f32, fade: f32 },
+    /// Sparks evenly spread around a circle, flying straight out
+    Ring { speed: f32, drag: f32, fade: f32 },
+    /// Sparks evenly spread along an arc `radius` out, from `from` to `to` degrees (0 is to the
+    /// right, counter-clockwise), each flying straight out
+    Arc { radius: f32, from: f32, to: f32, speed: f32, drag: f32, fade: f32 },
+    /// Stays put, growing by half as it fades over its ttl
+    Flash,
+    /// Pops up and out, spinning, falling and wobbling, shrinking by `shrink` a second
+    Star { speed: f32, lift: f32, spin: f32, gravity: f32, damping: f32, shrink: f32 },
+    /// Sparks evenly spread around a circle `radius` out that turns `turn` degrees a second,
+    /// each flying out at `speed`; emitted often it draws a swirl
+    Orbit { radius: f32, turn: f32, speed: f32, fade: f32 },
+    /// Sparks anywhere up to `spread` to either side, drifting straight up at `speed`, or down
+    /// when it's negative: rising flames, falling drips
+    Drift { spread: f32, speed: f32, fade: f32 },
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub enum Look {
+    #[default]
+    Square,
+    SmallStar,
+    HollowStar,
+    /// Hollow every nth, small otherwise
+    Stars(u32),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmitterDef {
+    pub kind: EmitterKind,
+    #[serde(default)]
+    pub look: Look,
+    /// Particles each time it emits
+    #[serde(default = "EmitterDef::default_count")]
+    pub count: u32,
+    /// Pixels for squares, scale for stars
+    pub size: f32,
+    /// Added to the size for each particle after the first
+    #[serde(default)]
+    pub size_step: f32,
+    /// Seconds, for sparks and flashes
+    #[serde(default)]
+    pub ttl: f32,
+    /// Seconds between emissions after the first, up to `until` seconds in; 0 emits once
+    #[serde(default)]
+    pub every: f32,
+    #[serde(default)]
+    pub until: f32,
+    /// Without one it takes the tint the effect is played with, or white
+    #[serde(default)]
+    pub color: Option<Rgb>,
+    /// Where it emits from, relative to where the effect plays
+    #[serde(default)]
+    pub offset: (f32, f32),
+    /// Colors stars blink through
+    #[serde(default)]
+    pub palette: Vec<Rgb>,
+    #[serde(default)]
+    pub lod: Lod,
+}
+
+impl EmitterDef {
+    fn default_count() -> u32 {
+        1
+    }
+
+    /// One emission's particles, `age` seconds after the effect started
+    fn emit(&self, particles: &mut Particles, budget: usize, origin: Vec3, tint: Option<Color>, age: f32) {
+        let color = self.color.map(rgb).or(tint).unwrap_or(Color::WHITE);
+        let z = origin.z + EFFECT_LIFT;
+        let origin = origin.truncate() + Vec2::from(self.offset);
+        for i in 0..self.count {
+            let f = i as f32;
+            let size = self.size + self.size_step * f;
+            let batch = match self.look {
+                Look::Square => Batch::Plain,
+                Look::SmallStar => Batch::SmallStar,
+                Look::HollowStar => Batch::HollowStar,
+                Look::Stars(n) if n > 0 && i % n == 0 => Batch::HollowStar,
+                Look::Stars(_) => Batch::SmallStar,
+            };
+            let mut offset = Vec2::ZERO;
+            let (motion, vel, scale) = match self.kind {
+                EmitterKind::Spark { speed, drag, fade } => {
+                    let dir = Vec2::new((f * 2.3).sin(), (f * 5.1).cos()).normalize_or_zero();
+                    (Motion::Spark { drag, fade }, dir * speed, 1.0)
+                }
+                EmitterKind::Ring { speed, drag, fade } => {
+                    let dir = Vec2::from_angle(TAU * f / self.count as f32);
+                    (Motion::Spark { drag, fade }, dir * speed, 1.0)
+                }
+                EmitterKind::Arc { radius, from, to, speed, drag, fade } => {
+                    let along = if self.count > 1 { f / (self.count - 1) as f32 } else { 0.5 };
+                    let dir = Vec2::from_angle(from.lerp(to, along).to_radians());
+                    offset = dir * radius;
+                    (Motion::Spark { drag, fade }, dir * speed, 1.0)
+                }
+                EmitterKind::Flash => (Motion::Flash { fade: self.ttl }, Vec2::ZERO, 1.0),
+                EmitterKind::Star { speed, lift, spin, gravity, damping, shrink } => {
+                    let rand = Vec2::new((f * 200.0).sin(), (f * 700.0).sin() * 0.5 + 0.5);
+                    let palette: Arc<[Color]> = if self.palette.is_empty() {
+                        Arc::new([color])
+                    } else {
+                        self.palette.iter().copied().map(rgb).collect()
+                    };
+                    let motion = Motion::Star { spin, gravity, damping, shrink, blit_index: i as i32, palette };
+                    (motion, rand * speed + Vec2::Y * lift, size)
+                }
+                EmitterKind::Orbit { radius, turn, speed, fade } => {
+                    let dir = Vec2::from_angle((age * turn).to_radians() + TAU * f / self.count as f32);
+                    offset = dir * radius;
+                    (Motion::Spark { drag: 1.0, fade }, dir * speed, 1.0)
+                }
+                EmitterKind::Drift { spread, speed, fade } => {
+                    // Scattered by the emission's age, so each one lands somewhere else
+                    let across = ((f * 12.9898 + age * 78.233).sin() * 43758.547).rem_euclid(1.0) * 2.0 - 1.0;
+                    offset = Vec2::X * across * spread;
+                    (Motion::Spark { drag: 1.0, fade }, Vec2::Y * speed, 1.0)
+                }
+            };
+            let particle = Particle {
+                batch,
+                motion,
+                pos: origin + offset,
+                z,
+                vel,
+                ttl: self.ttl,
+                size: Vec2::splat(size),
+                scale,
+                rotation: 0.0,
+                color,
+            };
+            particles.emit(self.lod, budget, particle);
         }
+    }
+}
 
-        if explosion.last_emitted < 0.0 {
-            // Initial burst of chunky square particles
-            for i in 0..10 {
-                let f = i as f32;
-                let dir = Vec2::new((f * 2.3).sin(), (f * 5.1).cos()).normalize_or_zero();
-                let vel = dir * 120.0;
-                commands.spawn((
-                    Sprite::from_color(explosion.color, Vec2::splat(6.0)),
-                    Transform::from_translation(explosion.origin + Vec3::new(0.0, 0.0, 0.8)),
-                    VfxParticle { vel, ttl: 0.35 },
-                ));
-            }
+/// Every effect definition by id
+#[derive(Resource, Debug, Default)]
+pub struct VfxLibrary {
+    by_id: HashMap<String, VfxDef>,
+    /// Ids played that aren't defined, so each is only warned about once
+    missing: HashSet<String>,
+}
+
+impl VfxLibrary {
+    pub fn clear(&mut self) {
+        self.by_id.clear();
+        self.missing.clear();
+    }
+
+    pub fn add(&mut self, file: &VfxFile) {
+        for def in &file.effects {
+            self.by_id.insert(def.id.clone(), def.clone());
         }
+    }
 
-        explosion.last_emitted = t;
+    /// The effect `id`, warning the first time one isn't defined
+    fn find(&mut self, id: &str) -> Option<VfxDef> {
+        let def = self.by_id.get(id).cloned();
+        if def.is_none() && self.missing.insert(id.to_string()) {
+            warn!("No effect {id:?} in the VFX files");
+        }
+        def
+    }
+}
 
-        // Small flickers near the center
-        let flicker = Sprite::from_color(explosion.color, Vec2::splat(10.0));
-        commands.spawn((
-            flicker,
-            Transform::from_translation(explosion.origin + Vec3::new(0.0, 0.0, 0.9)),
-            VfxParticle { vel: Vec2::ZERO, ttl: 0.06 },
-        ));
+/// An emitter still going after an effect's first emission
+#[derive(Component, Debug)]
+struct VfxEmitter {
+    def: EmitterDef,
+    origin: Vec3,
+    tint: Option<Color>,
+    age: f32,
+    last_emitted: f32,
+}
 
-        // End after a short duration
-        if t - explosion.time_spawned > 0.7 {
-            commands.entity(e).despawn();
+impl VfxEmitter {
+    fn new(def: EmitterDef, origin: Vec3, tint: Option<Color>) -> Self {
+        VfxEmitter { def, origin, tint, age: 0.0, last_emitted: 0.0 }
+    }
+
+    /// Ages it and emits if it's due
+    fn tick(&mut self, dt: f32, particles: &mut Particles, budget: usize) {
+        self.age += dt;
+        if self.age - self.last_emitted >= self.def.every {
+            self.last_emitted = self.age;
+            self.def.emit(particles, budget, self.origin, self.tint, self.age);
         }
     }
 }
 
-fn tick_vfx_particles(
-    time: Res<Time>,
-    mut q: Query<(Entity, &mut Transform, &mut Sprite, &mut VfxParticle)>,
+/// Plays the effect `id` at `origin`, in `tint` where it doesn't give its own color. Does
+/// nothing in an app without the plugin, like the headless test app
+pub fn vfx_play(commands: &mut Commands, id: &str, origin: Vec3, tint: Option<Color>) {
+    let id = id.to_string();
+    commands.queue(move |world: &mut World| {
+        let Some(mut library) = world.get_resource_mut::<VfxLibrary>() else { return; };
+        let Some(def) = library.find(&id) else { return; };
+        let budget = budget(world.get_resource::<Settings>());
+        let Some(mut particles) = world.get_resource_mut::<Particles>() else { return; };
+        for emitter in &def.emitters {
+            emitter.emit(&mut particles, budget, origin, tint, 0.0);
+        }
+        for emitter in def.emitters.into_iter().filter(|e| e.every > 0.0) {
+            world.spawn((VfxEmitter::new(emitter, origin, tint), StateScoped(GameState::Playing)));
+        }
+    });
+}
+
+fn tick_emitters(
     mut commands: Commands,
+    time: SimTime,
+    settings: Option<Res<Settings>>,
+    mut particles: ResMut<Particles>,
+    mut q: Query<(Entity, &mut VfxEmitter)>,
 ) {
-    let dt = time.delta_secs();
-    for (e, mut tf, mut sprite, mut p) in &mut q {
-        p.ttl -= dt;
-        tf.translation.x += p.vel.x * dt;
-        tf.translation.y += p.vel.y * dt;
-        // Simple drag
-        p.vel *= 0.9_f32.powf(60.0 * dt);
-        // Fade and shrink
-        let a = (p.ttl / 0.35).clamp(0.0, 1.0);
-        sprite.color = sprite.color.with_alpha(a);
-        let s = 0.5 + 0.5 * a;
-        tf.scale = Vec3::splat(s);
-        if p.ttl <= 0.0 {
+    let dt = time.scaled_delta();
+    let budget = budget(settings.as_deref());
+    for (e, mut emitter) in &mut q {
+        emitter.tick(dt, &mut particles, budget);
+        if emitter.age > emitter.def.until {
             commands.entity(e).despawn();
         }
     }
 }
 
-pub fn vfx_retro_explosion_flash(commands: &mut Commands, origin: Vec3, color: Color) {
-    commands.spawn((
-        Sprite::from_color(color, Vec2::splat(90.0)),
-        Transform::from_translation(origin + Vec3::new(0.0, 0.0, 0.7)),
-        VfxFlash { ttl: 0.12 },
-    ));
+/// Moves, fades and shrinks every batched particle, dropping the finished ones
+fn tick_particles(time: SimTime, mut particles: ResMut<Particles>) {
+    let t = time.elapsed_secs();
+    let dt = time.scaled_delta();
+    particles.batched.retain_mut(|p| match &p.motion {
+        Motion::Spark { drag, fade } => {
+            p.ttl -= dt;
+            p.pos += p.vel * dt;
+            // Simple drag
+            p.vel *= drag.powf(60.0 * dt);
+            // Fade and shrink
+            let a = (p.ttl / fade).clamp(0.0, 1.0);
+            p.color = p.color.with_alpha(a);
+            p.scale = 0.5 + 0.5 * a;
+            p.ttl > 0.0
+        }
+        Motion::Flash { fade } => {
+            p.ttl -= dt;
+            let a = (p.ttl / fade).clamp(0.0, 1.0);
+            p.color = p.color.with_alpha(a);
+            p.scale = 1.0 + (1.0 - a) * 0.5;
+            p.ttl > 0.0
+        }
+        Motion::Star { spin, gravity, damping, shrink, blit_index, palette } => {
+            // Integrate position and rotation
+            p.pos += p.vel * dt;
+            p.rotation += spin * dt;
+
+            // Gravity and simple damping
+            p.vel.y -= gravity * dt;
+            let damp = (1.0 - (damping * dt)).max(0.0);
+            p.vel *= damp;
+
+            // Drift down slightly like the 3D version
+            p.pos.y -= dt * 2.0;
+
+            // Wobble based on position and time
+            let wobble = (p.pos.length_squared() + t * 30.0).sin() * 20.0 * dt;
+            p.pos += Vec2::splat(wobble);
+
+            p.scale = (p.scale - dt * shrink).max(0.0);
+
+            // Blink color from palette with index offset, with a slight fade as it shrinks
+            let idx = (((t * 15.0) as i32 + blit_index) % palette.len() as i32) as usize;
+            p.color = palette[idx].with_alpha((p.scale / 3.0).clamp(0.0, 1.0));
+            p.scale > 0.0
+        }
+    });
 }
 
-fn tick_vfx_flash(
-    time: Res<Time>,
-    mut q: Query<(Entity, &mut Transform, &mut Sprite, &mut VfxFlash)>,
-    mut commands: Commands,
+// =========================
+// Auras
+// =========================
+
+/// One looping effect on an entity
+#[derive(Debug)]
+struct Aura {
+    id: String,
+    /// Its repeating emitters, moved to the entity every frame
+    emitters: Vec<VfxEmitter>,
+}
+
+/// Looping effects on an entity, like a status's aura. Each plays once when it's set, then its
+/// repeating emitters keep going around the entity, `until` aside, for as long as it stays set
+#[derive(Component, Debug, Default)]
+pub struct VfxAuras {
+    wanted: Vec<String>,
+    playing: Vec<Aura>,
+}
+
+impl VfxAuras {
+    /// The effects that should be playing; ones already going carry on where they are
+    pub fn set(&mut self, ids: &[&str]) {
+        if self.wanted.iter().map(String::as_str).ne(ids.iter().copied()) {
+            self.wanted = ids.iter().map(|id| id.to_string()).collect();
+        }
+    }
+}
+
+fn tick_auras(
+    time: SimTime,
+    settings: Option<Res<Settings>>,
+    mut library: ResMut<VfxLibrary>,
+    mut particles: ResMut<Particles>,
+    mut q: Query<(&GlobalTransform, &mut VfxAuras)>,
 ) {
-    let dt = time.delta_secs();
-    for (e, mut tf, mut sprite, mut flash) in &mut q {
-        flash.ttl -= dt;
-        let a = (flash.ttl / 0.12).clamp(0.0, 1.0);
-        sprite.color = sprite.color.with_alpha(a);
-        tf.scale = Vec3::splat(1.0 + (1.0 - a) * 0.5);
-        if flash.ttl <= 0.0 { commands.entity(e).despawn(); }
+    let dt = time.scaled_delta();
+    let budget = budget(settings.as_deref());
+    for (transform, mut auras) in &mut q {
+        let origin = transform.translation();
+        let VfxAuras { wanted, playing } = &mut *auras;
+        playing.retain(|aura| wanted.contains(&aura.id));
+        for id in wanted.iter() {
+            if playing.iter().any(|aura| aura.id == *id) {
+                continue;
+            }
+            // An unknown id still counts as playing, so it's only looked up once
+            let emitters = library.find(id).map(|def| def.emitters).unwrap_or_default();
+            for emitter in &emitters {
+                emitter.emit(&mut particles, budget, origin, None, 0.0);
+            }
+            let emitters = emitters.into_iter().filter(|e| e.every > 0.0).map(|e| VfxEmitter::new(e, origin, None));
+            playing.push(Aura { id: id.clone(), emitters: emitters.collect() });
+        }
+        for emitter in playing.iter_mut().flat_map(|aura| &mut aura.emitters) {
+            emitter.origin = origin;
+            emitter.tick(dt, &mut particles, budget);
+        }
     }
 }
 
 // =========================
-// Y2K Stars (2D port)
+// Motion trail
 // =========================
 
 #[derive(Component)]
-struct Y2KStar {
-    vel: Vec2,
-    angle: f32,
-    ang_vel: f32,
-    size: f32,
-    gravity: f32,
-    damping: f32,
-    blit_index: i32,
-}
-
-pub fn vfx_y2k_stars(commands: &mut Commands, textures: &Res<TextureAssets>, origin: Vec3) {
-    for i in 0..6 {
-        let f = i as f32;
-        let rand = Vec2::new((f * 200.0).sin(), (f * 700.0).sin() * 0.5 + 0.5);
-
-        let use_hollow = i % 3 == 0;
-        let image = if use_hollow {
-            textures.hollowstar.clone()
-        } else {
-            textures.smallstar.clone()
-        };
+struct Afterimage {
+    ttl: f32,
+    max_ttl: f32,
+    alpha: f32,
+}
 
-        let sprite = Sprite::from_image(image);
+const TRAIL_IMAGES: usize = 5;
+const TRAIL_SECS: f32 = 0.25;
+
+/// Fading copies of `sprite` along the way from `from` to `to`, oldest at `from`
+pub fn vfx_motion_trail(commands: &mut Commands, sprite: &Sprite, from: Vec3, to: Vec3) {
+    for i in 0..TRAIL_IMAGES {
+        let f = i as f32 / TRAIL_IMAGES as f32;
+        let alpha = 0.15 + 0.35 * f;
+        let mut ghost = sprite.clone();
+        ghost.color = sprite.color.with_alpha(alpha);
+        // Later images stick around a little longer so the trail shrinks back towards the player
+        let ttl = TRAIL_SECS * (0.5 + 0.5 * f);
+        let image = (
+            ghost,
+            Transform::from_translation(from.lerp(to, f) - Vec3::Z * 0.05),
+            Afterimage { ttl, max_ttl: ttl, alpha },
+        );
+        commands.queue(move |world: &mut World| {
+            let budget = budget(world.get_resource::<Settings>());
+            let Some(mut particles) = world.get_resource_mut::<Particles>() else { return; };
+            if !particles.admits(Lod::Detail, budget) {
+                return;
+            }
+            particles.afterimages += 1;
+            let reused = particles.free_afterimages.pop();
+            match reused.and_then(|e| world.get_entity_mut(e).ok()) {
+                Some(mut entity) => {
+                    entity.insert((image, Visibility::Inherited));
+                }
+                None => {
+                    world.spawn((image, Visibility::Inherited, StateScoped(GameState::Playing)));
+                }
+            }
+        });
+    }
+}
 
-        let pos = origin + Vec3::new(0.0, 0.0, 0.8);
-        let size = (f + 10.0) / 5.0 + 0.5;
-        let vel = rand * 25.0 + Vec2::Y * 9.0;
+fn tick_afterimages(
+    time: SimTime,
+    mut particles: ResMut<Particles>,
+    mut q: Query<(Entity, &mut Sprite, &mut Afterimage, &mut Visibility)>,
+) {
+    let dt = time.scaled_delta();
+    for (e, mut sprite, mut ghost, mut vis) in &mut q {
+        if *vis == Visibility::Hidden {
+            continue;
+        }
+        ghost.ttl -= dt;
+        sprite.color = sprite.color.with_alpha(ghost.alpha * (ghost.ttl / ghost.max_ttl).clamp(0.0, 1.0));
+        if ghost.ttl <= 0.0 {
+            particles.release_afterimage(e, &mut vis);
+        }
+    }
+}
 
-        commands.spawn((
-            sprite,
-            Transform::from_translation(pos).with_scale(Vec3::splat(size)),
-            Y2KStar {
-                vel,
-                angle: 0.0,
-                ang_vel: 6.0,
-                size,
-                gravity: 35.0,
-                damping: 1.0,
-                blit_index: i,
-            },
-        ));
+// =========================
+// Telegraphs
+// =========================
+
+/// Ground area a telegraph covers, in world units. Cones and lines point along the telegraph's
+/// `dir`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroundShape {
+    Circle { radius: f32 },
+    /// `angle` is the full width of the cone in degrees
+    Cone { radius: f32, angle: f32 },
+    Line { length: f32, width: f32 },
+    /// The ring between the two radii; inside `inner` is safe
+    Donut { inner: f32, outer: f32 },
+}
+
+impl GroundShape {
+    /// The part a growing fill covers `frac` of the way through: out from the origin, or out
+    /// from the safe spot for a donut
+    fn grown(self, frac: f32) -> GroundShape {
+        match self {
+            GroundShape::Circle { radius } => GroundShape::Circle { radius: radius * frac },
+            GroundShape::Cone { radius, angle } => GroundShape::Cone { radius: radius * frac, angle },
+            GroundShape::Line { length, width } => GroundShape::Line { length: length * frac, width },
+            GroundShape::Donut { inner, outer } => GroundShape::Donut { inner, outer: inner + (outer - inner) * frac },
+        }
     }
 }
 
-fn tick_y2k_stars(
-    time: Res<Time>,
-    mut q: Query<(Entity, &mut Transform, &mut Sprite, &mut Y2KStar)>,
+/// How a telegraph shows its wind-up running out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Windup {
+    /// A fill grows across the area and reaches the edge as it goes off
+    #[default]
+    Grow,
+    /// The whole area darkens from the base to the full opacity
+    Fill,
+}
+
+/// Drawn on the floor where its entity is, under the player and enemies: a faint base over the
+/// whole area, a fill showing how far through the wind-up it is and an outline. Whatever owns
+/// the telegraph moves `progress` from 0 to 1
+#[derive(Component, Debug, Clone)]
+pub struct TelegraphVfx {
+    pub shape: GroundShape,
+    pub dir: Vec2,
+    pub color: Color,
+    /// Alpha of the fill once it's complete; the base is a share of it
+    pub opacity: f32,
+    pub outline: bool,
+    pub windup: Windup,
+    pub progress: f32,
+}
+
+impl TelegraphVfx {
+    /// Half-opaque and outlined, growing
+    pub fn new(shape: GroundShape, dir: Vec2, color: Color) -> Self {
+        TelegraphVfx { shape, dir, color, opacity: 0.5, outline: true, windup: Windup::Grow, progress: 0.0 }
+    }
+}
+
+/// Telegraphs are drawn over the background and under everything standing on the floor
+const TELEGRAPH_Z: f32 = -1.0;
+/// Share of the opacity the base over the whole area gets
+const TELEGRAPH_BASE_SHARE: f32 = 0.3;
+/// Segments in a full circle; arcs get their share
+const CIRCLE_SEGMENTS: usize = 48;
+
+/// The one mesh every telegraph is drawn into
+#[derive(Component)]
+struct TelegraphBatch;
+
+fn spawn_telegraph_batch(
     mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let t = time.elapsed_secs();
-    let dt = time.delta_secs();
-
-    // simple six-color palette similar to DDclone "crazy colors"
-    let palette = [
-        Color::linear_rgb(0.7, 0.4, 1.0), // violet-ish
-        Color::linear_rgb(1.0, 1.0, 1.0),
-        Color::linear_rgb(1.0, 0.6, 0.8), // pink-ish
-        Color::linear_rgb(1.0, 1.0, 1.0),
-        Color::linear_rgb(0.6, 0.2, 0.8), // purple-ish
-        Color::linear_rgb(1.0, 0.2, 1.0), // fuchsia-ish
-    ];
+    commands.spawn((
+        Mesh2d(meshes.add(Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default()))),
+        MeshMaterial2d(materials.add(ColorMaterial::default())),
+        Transform::from_xyz(0.0, 0.0, TELEGRAPH_Z),
+        NoFrustumCulling,
+        Visibility::Hidden,
+        TelegraphBatch,
+        StateScoped(GameState::Playing),
+    ));
+}
 
-    for (e, mut tf, mut sprite, mut star) in &mut q {
-        // Integrate position and rotation
-        tf.translation.x += star.vel.x * dt;
-        tf.translation.y += star.vel.y * dt;
-        star.angle += star.ang_vel * dt;
-
-        // Gravity and simple damping
-        star.vel.y -= star.gravity * dt;
-        let damp = (1.0 - (star.damping * dt)).max(0.0);
-        star.vel *= damp;
-
-        // Drift down slightly like the 3D version
-        tf.translation.y -= dt * 2.0;
-
-        // Wobble based on position and time
-        let dot = tf.translation.x * tf.translation.x + tf.translation.y * tf.translation.y;
-        let wobble = (dot + t * 30.0).sin() * 20.0 * dt;
-        tf.translation.x += wobble;
-        tf.translation.y += wobble;
-
-        // Apply rotation and scale
-        tf.rotation = Quat::from_rotation_z(star.angle);
-        star.size -= dt * 5.0;
-        tf.scale = Vec3::splat(star.size.max(0.0));
-
-        // Blink color from palette with index offset
-        let idx = (((t * 15.0) as i32 + star.blit_index) % palette.len() as i32) as usize;
-        // Slight fade as it shrinks
-        let alpha = (star.size / 3.0).clamp(0.0, 1.0);
-        sprite.color = palette[idx].with_alpha(alpha);
-
-        if star.size <= 0.0 {
-            commands.entity(e).despawn();
+/// Vertices of the telegraph mesh being built
+#[derive(Default)]
+struct GroundMesh {
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl GroundMesh {
+    /// Quads between two equally long rows of points, like the inside and outside edges of a
+    /// ring; a row can be one point repeated for a fan
+    fn band(&mut self, inner: &[Vec2], outer: &[Vec2], color: Color) {
+        let color = color.to_linear().to_f32_array();
+        for (a, b) in inner.windows(2).zip(outer.windows(2)) {
+            let first = self.positions.len() as u32;
+            for p in [a[0], a[1], b[1], b[0]] {
+                self.positions.push([p.x, p.y, 0.0]);
+                self.colors.push(color);
+            }
+            self.indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+
+    fn shape(&mut self, origin: Vec2, dir: Vec2, shape: GroundShape, color: Color) {
+        match shape {
+            GroundShape::Circle { radius } => {
+                let rim = arc_points(origin, radius, 0.0, TAU);
+                self.band(&vec![origin; rim.len()], &rim, color);
+            }
+            GroundShape::Cone { radius, angle } => {
+                let sweep = angle.to_radians();
+                let rim = arc_points(origin, radius, dir.to_angle() - sweep * 0.5, sweep);
+                self.band(&vec![origin; rim.len()], &rim, color);
+            }
+            GroundShape::Line { length, width } => {
+                let side = dir.perp() * width * 0.5;
+                let end = origin + dir * length;
+                self.band(&[origin - side, end - side], &[origin + side, end + side], color);
+            }
+            GroundShape::Donut { inner, outer } => {
+                self.band(&arc_points(origin, inner, 0.0, TAU), &arc_points(origin, outer, 0.0, TAU), color);
+            }
+        }
+    }
+}
+
+/// Points along an arc `sweep` radians long from `start`, both ends included
+fn arc_points(center: Vec2, radius: f32, start: f32, sweep: f32) -> Vec<Vec2> {
+    let segments = ((CIRCLE_SEGMENTS as f32 * sweep.abs() / TAU).ceil() as usize).max(2);
+    (0..=segments)
+        .map(|i| center + Vec2::from_angle(start + sweep * i as f32 / segments as f32) * radius)
+        .collect()
+}
+
+fn outline_telegraph(gizmos: &mut Gizmos, origin: Vec2, dir: Vec2, shape: GroundShape, color: Color) {
+    match shape {
+        GroundShape::Circle { radius } => {
+            gizmos.circle_2d(origin, radius, color);
+        }
+        GroundShape::Cone { radius, angle } => {
+            let half = angle.to_radians() * 0.5;
+            let start = Rot2::radians(dir.to_angle() - half - FRAC_PI_2);
+            gizmos.arc_2d(Isometry2d::new(origin, start), angle.to_radians(), radius, color);
+            for edge in [-half, half] {
+                gizmos.line_2d(origin, origin + Vec2::from_angle(edge).rotate(dir) * radius, color);
+            }
+        }
+        GroundShape::Line { length, width } => {
+            let center = origin + dir * length * 0.5;
+            gizmos.rect_2d(Isometry2d::new(center, Rot2::radians(dir.to_angle())), Vec2::new(length, width), color);
+        }
+        GroundShape::Donut { inner, outer } => {
+            gizmos.circle_2d(origin, inner, color);
+            gizmos.circle_2d(origin, outer, color);
+        }
+    }
+}
+
+/// Rebuilds the telegraph mesh from every telegraph showing, base first so the fill sits on top
+fn draw_telegraphs(
+    mut gizmos: Gizmos,
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_telegraphs: Query<(&GlobalTransform, &TelegraphVfx)>,
+    mut q_batch: Query<(&Mesh2d, &mut Visibility), With<TelegraphBatch>>,
+) {
+    let Ok((mesh, mut vis)) = q_batch.single_mut() else { return; };
+    vis.set_if_neq(if q_telegraphs.is_empty() { Visibility::Hidden } else { Visibility::Inherited });
+    if q_telegraphs.is_empty() {
+        return;
+    }
+    let Some(mesh) = meshes.get_mut(&mesh.0) else { return; };
+    let mut ground = GroundMesh::default();
+    for (transform, telegraph) in &q_telegraphs {
+        let origin = transform.translation().truncate();
+        let dir = telegraph.dir.try_normalize().unwrap_or(Vec2::X);
+        let progress = telegraph.progress.clamp(0.0, 1.0);
+        let base = telegraph.color.with_alpha(telegraph.opacity * TELEGRAPH_BASE_SHARE);
+        ground.shape(origin, dir, telegraph.shape, base);
+        match telegraph.windup {
+            Windup::Grow => {
+                let fill = telegraph.color.with_alpha(telegraph.opacity);
+                ground.shape(origin, dir, telegraph.shape.grown(progress), fill);
+            }
+            Windup::Fill => {
+                let fill = telegraph.color.with_alpha(telegraph.opacity * (1.0 - TELEGRAPH_BASE_SHARE) * progress);
+                ground.shape(origin, dir, telegraph.shape, fill);
+            }
+        }
+        if telegraph.outline {
+            outline_telegraph(&mut gizmos, origin, dir, telegraph.shape, telegraph.color);
         }
     }
+    let uvs = vec![[0.0, 0.0]; ground.positions.len()];
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, ground.positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, ground.colors);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(ground.indices));
 }
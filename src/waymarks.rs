@@ -0,0 +1,394 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::actions::{ActionSet, Actions, SimAction, SimInput};
+use crate::camera_fx::CameraRest;
+use crate::combat::CurrentEncounter;
+use crate::markers::{MarkerKind, MarkerTarget, ShowMarkerEvent};
+use crate::replay::playing_back;
+use crate::sim_time::SimTime;
+use crate::{persist, GameSet, GameState};
+
+// Arena floor waymarks (A-D, 1-4) placed by the player and referenced by callouts.
+// Placing one is a `SimAction` like any other input, so replays show them going down; only
+// pulls actually played save them.
+
+pub struct WaymarksPlugin;
+
+impl Plugin for WaymarksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Waymarks>()
+            .init_resource::<WaymarkPlacement>()
+            .init_resource::<CalloutHighlight>()
+            .add_event::<CalloutEvent>()
+            .add_systems(OnEnter(GameState::Playing), (load_waymarks, spawn_waymark_ui))
+            .add_systems(
+                PreUpdate,
+                (toggle_placement_mode, place_waymarks)
+                    .chain()
+                    .in_set(GameSet::InputRead)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (apply_waymarks, save_placed_waymarks.run_if(not(playing_back)))
+                    .chain()
+                    .in_set(ActionSet::Apply)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    sync_waymark_sprites,
+                    update_placement_hint,
+                    show_callouts,
+                    mark_called_waymark,
+                    fade_callouts,
+                    pulse_called_waymark,
+                )
+                    .chain()
+                    .in_set(GameSet::Ui)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Waymark {
+    A,
+    B,
+    C,
+    D,
+    One,
+    Two,
+    Three,
+    Four,
+}
+
+impl Waymark {
+    pub const ALL: [Waymark; 8] = [
+        Waymark::A,
+        Waymark::B,
+        Waymark::C,
+        Waymark::D,
+        Waymark::One,
+        Waymark::Two,
+        Waymark::Three,
+        Waymark::Four,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Waymark::A => "A",
+            Waymark::B => "B",
+            Waymark::C => "C",
+            Waymark::D => "D",
+            Waymark::One => "1",
+            Waymark::Two => "2",
+            Waymark::Three => "3",
+            Waymark::Four => "4",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|w| w.label() == label)
+    }
+
+    // Same palette as in game: A/1 red, B/2 yellow, C/3 blue, D/4 purple
+    fn color(self) -> Color {
+        match self {
+            Waymark::A | Waymark::One => Color::linear_rgb(1.0, 0.25, 0.25),
+            Waymark::B | Waymark::Two => Color::linear_rgb(1.0, 0.9, 0.2),
+            Waymark::C | Waymark::Three => Color::linear_rgb(0.3, 0.6, 1.0),
+            Waymark::D | Waymark::Four => Color::linear_rgb(0.8, 0.3, 1.0),
+        }
+    }
+
+    fn is_letter(self) -> bool {
+        matches!(self, Waymark::A | Waymark::B | Waymark::C | Waymark::D)
+    }
+
+    fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|w| *w == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// Waymark positions in world space for the current encounter
+#[derive(Resource, Default, Debug)]
+pub struct Waymarks {
+    pub positions: HashMap<Waymark, Vec2>,
+}
+
+#[derive(Resource, Debug)]
+pub struct WaymarkPlacement {
+    /// Placement mode takes over Tab and the mouse
+    pub active: bool,
+    selected: Waymark,
+}
+
+impl Default for WaymarkPlacement {
+    fn default() -> Self {
+        Self { active: false, selected: Waymark::A }
+    }
+}
+
+/// A spoken-style instruction shown on screen, optionally pointing at a waymark ("Stack at A")
+#[derive(Event, Debug, Clone)]
+pub struct CalloutEvent {
+    pub text: String,
+    pub waymark: Option<Waymark>,
+}
+
+#[derive(Resource, Default)]
+struct CalloutHighlight {
+    waymark: Option<Waymark>,
+    remaining: f32,
+}
+
+#[derive(Component)]
+struct WaymarkSprite(Waymark);
+
+#[derive(Component)]
+struct PlacementHint;
+
+#[derive(Component)]
+struct CalloutBanner {
+    ttl: f32,
+}
+
+const CALLOUT_TTL: f32 = 3.0;
+const WAYMARK_SIZE: f32 = 56.0;
+
+fn waymark_file(encounter: &CurrentEncounter) -> String {
+    format!("waymarks/{}.txt", encounter.id)
+}
+
+// File format: one waymark per line, "<label> <x> <y>"
+fn load_waymarks(encounter: Res<CurrentEncounter>, mut waymarks: ResMut<Waymarks>) {
+    waymarks.positions.clear();
+    let Some(contents) = persist::load(&waymark_file(&encounter)) else { return; };
+    for line in contents.lines() {
+        let mut parts = line.split_whitespace();
+        let (Some(label), Some(x), Some(y)) = (parts.next(), parts.next(), parts.next()) else { continue; };
+        let (Some(mark), Ok(x), Ok(y)) = (Waymark::from_label(label), x.parse::<f32>(), y.parse::<f32>()) else {
+            warn!("Ignoring malformed waymark line {line:?}");
+            continue;
+        };
+        waymarks.positions.insert(mark, Vec2::new(x, y));
+    }
+}
+
+fn save_waymarks(encounter: &CurrentEncounter, waymarks: &Waymarks) {
+    let mut out = String::new();
+    for mark in Waymark::ALL {
+        if let Some(pos) = waymarks.positions.get(&mark) {
+            out.push_str(&format!("{} {:.1} {:.1}\n", mark.label(), pos.x, pos.y));
+        }
+    }
+    persist::save(&waymark_file(encounter), &out);
+}
+
+fn spawn_waymark_ui(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont { font_size: 16.0, ..default() },
+        TextColor(Color::linear_rgb(0.9, 0.9, 0.6)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        PlacementHint,
+        StateScoped(GameState::Playing),
+    ));
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                top: Val::Px(90.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            StateScoped(GameState::Playing),
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont { font_size: 30.0, ..default() },
+            TextColor(Color::linear_rgb(1.0, 0.95, 0.8).with_alpha(0.0)),
+            CalloutBanner { ttl: 0.0 },
+        ));
+}
+
+fn toggle_placement_mode(keys: Res<ButtonInput<KeyCode>>, mut placement: ResMut<WaymarkPlacement>) {
+    if keys.just_pressed(KeyCode::KeyM) {
+        placement.active = !placement.active;
+    }
+    if placement.active && keys.just_pressed(KeyCode::Tab) {
+        placement.selected = placement.selected.next();
+    }
+}
+
+fn place_waymarks(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    placement: Res<WaymarkPlacement>,
+    waymarks: Res<Waymarks>,
+    mut actions: ResMut<Actions>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform, Option<&CameraRest>), With<Camera2d>>,
+) {
+    if !placement.active {
+        return;
+    }
+    if mouse.just_pressed(MouseButton::Left) {
+        let cursor = windows.single().ok().and_then(|w| w.cursor_position());
+        if let (Some(cursor), Ok((camera, camera_transform, rest))) = (cursor, camera.single()) {
+            // Where the player clicked on the arena, not on wherever the shake has it this frame
+            let camera_transform = rest.map_or(*camera_transform, CameraRest::global);
+            if let Ok(world) = camera.viewport_to_world_2d(&camera_transform, cursor) {
+                actions.queue(SimAction::Waymark(placement.selected, Some(world)));
+            }
+        }
+    }
+    if mouse.just_pressed(MouseButton::Right) && waymarks.positions.contains_key(&placement.selected) {
+        actions.queue(SimAction::Waymark(placement.selected, None));
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        for mark in Waymark::ALL.into_iter().filter(|m| waymarks.positions.contains_key(m)) {
+            actions.queue(SimAction::Waymark(mark, None));
+        }
+    }
+}
+
+fn apply_waymarks(input: Res<SimInput>, mut waymarks: ResMut<Waymarks>) {
+    for action in &input.actions {
+        match *action {
+            SimAction::Waymark(mark, Some(pos)) => {
+                waymarks.positions.insert(mark, pos);
+            }
+            SimAction::Waymark(mark, None) => {
+                waymarks.positions.remove(&mark);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn save_placed_waymarks(input: Res<SimInput>, encounter: Res<CurrentEncounter>, waymarks: Res<Waymarks>) {
+    if input.actions.iter().any(|a| matches!(a, SimAction::Waymark(..))) {
+        save_waymarks(&encounter, &waymarks);
+    }
+}
+
+fn sync_waymark_sprites(
+    mut commands: Commands,
+    waymarks: Res<Waymarks>,
+    q_existing: Query<Entity, With<WaymarkSprite>>,
+) {
+    if !waymarks.is_changed() {
+        return;
+    }
+    for e in &q_existing {
+        commands.entity(e).despawn();
+    }
+    for (mark, pos) in waymarks.positions.iter() {
+        // Letters are circles in game, numbers are squares; diamonds read better than squares here
+        let rotation = if mark.is_letter() { 0.0 } else { std::f32::consts::FRAC_PI_4 };
+        commands.spawn((
+            Sprite::from_color(mark.color().with_alpha(0.35), Vec2::splat(WAYMARK_SIZE)),
+            Transform::from_translation(pos.extend(0.1)).with_rotation(Quat::from_rotation_z(rotation)),
+            WaymarkSprite(*mark),
+            StateScoped(GameState::Playing),
+        ));
+        commands.spawn((
+            Text2d::new(mark.label()),
+            TextFont { font_size: 28.0, ..default() },
+            TextColor(mark.color()),
+            Transform::from_translation(pos.extend(0.2)),
+            WaymarkSprite(*mark),
+            StateScoped(GameState::Playing),
+        ));
+    }
+}
+
+fn update_placement_hint(
+    placement: Res<WaymarkPlacement>,
+    mut q: Query<(&mut Text, &mut Visibility), With<PlacementHint>>,
+) {
+    if !placement.is_changed() {
+        return;
+    }
+    if let Ok((mut text, mut vis)) = q.single_mut() {
+        *vis = if placement.active { Visibility::Inherited } else { Visibility::Hidden };
+        text.0 = format!(
+            "Placing waymark {} — Tab: next, LMB: place, RMB: remove, Backspace: clear all, M: done",
+            placement.selected.label()
+        );
+    }
+}
+
+fn show_callouts(
+    mut evr: EventReader<CalloutEvent>,
+    waymarks: Res<Waymarks>,
+    mut highlight: ResMut<CalloutHighlight>,
+    mut q: Query<(&mut Text, &mut CalloutBanner)>,
+) {
+    let Ok((mut text, mut banner)) = q.single_mut() else { return; };
+    for CalloutEvent { text: callout, waymark } in evr.read() {
+        text.0 = match waymark {
+            Some(mark) if !waymarks.positions.contains_key(mark) => {
+                format!("{callout} (waymark {} not placed)", mark.label())
+            }
+            _ => callout.clone(),
+        };
+        banner.ttl = CALLOUT_TTL;
+        highlight.waymark = *waymark;
+        highlight.remaining = CALLOUT_TTL;
+    }
+}
+
+/// Puts a stack marker on the waymark a callout points at, so it is easy to find off screen
+fn mark_called_waymark(
+    mut evr: EventReader<CalloutEvent>,
+    q: Query<(Entity, &WaymarkSprite), With<Sprite>>,
+    mut writer: EventWriter<ShowMarkerEvent>,
+) {
+    for CalloutEvent { waymark, .. } in evr.read() {
+        let Some(mark) = waymark else { continue; };
+        if let Some((e, _)) = q.iter().find(|(_, sprite)| sprite.0 == *mark) {
+            writer.write(ShowMarkerEvent { target: MarkerTarget::Entity(e), kind: MarkerKind::Stack, duration: CALLOUT_TTL });
+        }
+    }
+}
+
+fn fade_callouts(time: SimTime, mut q: Query<(&mut TextColor, &mut CalloutBanner)>) {
+    let dt = time.scaled_delta();
+    for (mut color, mut banner) in &mut q {
+        banner.ttl = (banner.ttl - dt).max(0.0);
+        // Hold fully visible, then fade over the last second
+        color.0 = color.0.with_alpha(banner.ttl.min(1.0));
+    }
+}
+
+fn pulse_called_waymark(
+    time: SimTime,
+    mut highlight: ResMut<CalloutHighlight>,
+    mut q: Query<(&WaymarkSprite, &mut Transform)>,
+) {
+    highlight.remaining = (highlight.remaining - time.scaled_delta()).max(0.0);
+    let active = if highlight.remaining > 0.0 { highlight.waymark } else { None };
+    for (sprite, mut tf) in &mut q {
+        let scale = if Some(sprite.0) == active {
+            1.0 + 0.15 * (time.elapsed_secs() * 8.0).sin().abs()
+        } else {
+            1.0
+        };
+        tf.scale = Vec3::splat(scale);
+    }
+}
@@ -1,81 +1,278 @@
 use bevy::prelude::*;
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::combat::{ApplyDotEvent, DamageEvent};
+use crate::adds::Add;
+use crate::combat::{
+    AbilityBook, AbilityId, ApplyDotEvent, BossPhaseEvent, CombatState, CurrentEncounter, DamageEvent, EncounterLibrary,
+    PlayerDamageEvent, ProjectileSpec, ShredSpec,
+};
+use crate::console::{CommandResult, ConsoleAppExt};
 use crate::loading::TextureAssets;
+use crate::mechanics::{AutoAttack, BossCleave};
+use crate::player::Player;
+use crate::sim_time::SimTime;
 use crate::{vfx, GameState, GameSet};
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_enemy_and_ui)
+        app.add_event::<DamageDealtEvent>()
+            .add_event::<SpawnHazardEvent>()
+            .init_resource::<Arena>()
+            .init_resource::<CurrentTarget>()
+            .init_resource::<HealTarget>()
+            .add_console_cheat("sethp", "sethp <hp or percent> [player]: sets the boss's HP, or yours", set_hp)
             .add_systems(
-                Update,
-                (handle_damage_events, handle_apply_dot_events, tick_dots)
+                OnEnter(GameState::Playing),
+                (spawn_enemy_and_ui, spawn_player_healthbar, reset_target, (load_arena, spawn_arena_floor).chain()),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    tick_dots.before(handle_damage_events),
+                    // Before the combat sim settles what's in flight at the end of a pull
+                    handle_damage_events.before(settle_projectiles),
+                    fly_projectiles.before(settle_projectiles),
+                    handle_apply_dot_events,
+                    tick_armor_shred,
+                    handle_boss_phase,
+                )
+                    .in_set(GameSet::Sim)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (keep_in_arena, spawn_hazards, tick_hazards)
                     .in_set(GameSet::Sim)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
-                (update_enemy_healthbar, animate_damage_numbers)
+                (
+                    update_enemy_healthbar,
+                    update_swing_timer,
+                    update_player_healthbar,
+                    update_armor_icon,
+                    update_dot_row,
+                    show_dot_auras,
+                )
                     .in_set(GameSet::Ui)
                     .run_if(in_state(GameState::Playing)),
             );
     }
 }
 
+/// The boss. Adds are separate entities, see [`crate::adds`]
 #[derive(Component)]
 pub struct Enemy;
 
+/// Enemy the player's abilities and DoTs go to; `None` (or a target that died) means the boss
+#[derive(Resource, Debug, Default)]
+pub struct CurrentTarget(pub Option<Entity>);
+
+/// Party member the player's heals go to, picked on the party frames; `None` means the player
+#[derive(Resource, Debug, Default)]
+pub struct HealTarget(pub Option<Entity>);
+
+/// Damage that actually came off the enemy's HP, after armor
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageDealtEvent {
+    pub amount: i32,
+    pub crit: bool,
+    pub source: Option<AbilityId>,
+    /// The enemy that took it
+    pub target: Entity,
+    /// Whether the positional landed, for abilities that have one
+    pub positional: Option<bool>,
+    /// A DoT tick rather than a hit
+    pub tick: bool,
+}
+
+/// Direction the enemy or the player is facing; positionals are judged against the enemy's
+#[derive(Component)]
+pub struct Facing(pub Vec2);
+
+/// Threat built up on the enemy; tankbusters go to whoever has the most
+#[derive(Component, Debug, Default)]
+pub struct Enmity(pub f32);
+
 #[derive(Component)]
 pub struct Health {
     pub current: i32,
     pub max: i32,
 }
 
+/// Defense that scales down incoming hits; shred lowers it for a while
+#[derive(Component, Debug)]
+pub struct Armor {
+    pub base: f32,
+    pub shred: f32,
+    pub shred_remaining: f32,
+    /// What `shred_remaining` started from, for the shred icon to drain over
+    pub shred_duration: f32,
+}
+
+/// Armor at which incoming damage is halved
+const ARMOR_HALF_POINT: f32 = 500.0;
+
+impl Armor {
+    pub fn new(base: f32) -> Self {
+        Self { base, shred: 0.0, shred_remaining: 0.0, shred_duration: 0.0 }
+    }
+
+    pub fn effective(&self) -> f32 {
+        (self.base - self.shred).max(0.0)
+    }
+
+    /// Damage multiplier for a hit ignoring `penetration` (0..1) of the armor
+    pub fn damage_taken(&self, penetration: f32) -> f32 {
+        let armor = self.effective() * (1.0 - penetration.clamp(0.0, 1.0));
+        ARMOR_HALF_POINT / (ARMOR_HALF_POINT + armor)
+    }
+
+    /// Shred doesn't stack. One at least as strong as the current one takes over and refreshes
+    /// the timer; a weaker one, or the same with less time than is left, does nothing until the
+    /// current one wears off
+    pub fn apply_shred(&mut self, shred: ShredSpec) {
+        let weaker = shred.armor < self.shred || (shred.armor == self.shred && shred.duration <= self.shred_remaining);
+        if self.shred_remaining > 0.0 && weaker {
+            return;
+        }
+        self.shred = shred.armor;
+        self.shred_remaining = shred.duration;
+        self.shred_duration = shred.duration;
+    }
+
+    /// Color of the armor icon, orange while shredded
+    pub fn icon_color(&self) -> Color {
+        if self.shred > 0.0 {
+            Color::linear_rgb(1.0, 0.6, 0.2)
+        } else {
+            Color::linear_rgb(0.7, 0.75, 0.85)
+        }
+    }
+}
+
 #[derive(Component)]
 struct EnemyHpRoot;
 
+#[derive(Component)]
+struct ArmorIcon;
+
+/// The bar itself, which gives way to nameplates while adds are up
+#[derive(Component)]
+struct EnemyHpBar;
+
 #[derive(Component)]
 struct EnemyHpFill;
 
 #[derive(Component)]
-struct DamageNumber {
-    ttl: f32,
-    vel: Vec2,
-}
+struct SwingTimerFill;
+
+#[derive(Component)]
+struct SwingTimerText;
+
+#[derive(Component)]
+struct PlayerHpFill;
+
+/// Shield overlay drawn on top of the player's HP bar
+#[derive(Component)]
+struct PlayerShieldFill;
+
+#[derive(Component)]
+struct PlayerHpText;
+
+#[derive(Component)]
+struct DotRow;
 
+/// A chip in the DoT row: its icon, which drains as the effect runs out, and its text
 #[derive(Component)]
-struct DotEffect {
-    remaining: f32,
-    tick_every: f32,
-    tick_accum: f32,
-    dps: i32,
+struct DotChip {
+    icon: Entity,
+    text: Entity,
+}
+
+/// One damage-over-time instance. Tick damage is snapshotted on application,
+/// so buffs that expire afterwards don't change it.
+#[derive(Debug, Clone)]
+pub struct Dot {
+    pub source: AbilityId,
+    pub remaining: f32,
+    pub duration: f32,
+    pub tick_every: f32,
+    pub tick_accum: f32,
+    pub dps: i32,
+}
+
+/// All DoTs currently ticking on an entity, at most one per source ability.
+#[derive(Component, Default, Debug)]
+pub struct DotEffects {
+    pub dots: Vec<Dot>,
+}
+
+impl DotEffects {
+    /// Adds a DoT, replacing (and re-snapshotting) any existing one from the same ability
+    pub fn apply(&mut self, dot: Dot) {
+        if let Some(existing) = self.dots.iter_mut().find(|d| d.source == dot.source) {
+            *existing = dot;
+        } else {
+            self.dots.push(dot);
+        }
+    }
+}
+
+fn dot_color(source: AbilityId) -> Color {
+    match source {
+        AbilityId::Burn => Color::linear_rgb(1.0, 0.45, 0.1),
+        _ => Color::linear_rgb(0.7, 0.7, 0.7),
+    }
 }
 
-fn spawn_enemy_and_ui(mut commands: Commands, textures: Res<TextureAssets>) {
+/// Striking dummy HP: more than any pull gets through
+const DUMMY_HP: i32 = 1_000_000_000;
+
+fn spawn_enemy_and_ui(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    library: Res<EncounterLibrary>,
+    encounter: Res<CurrentEncounter>,
+) {
+    let dummy = library.get(&encounter.id).is_some_and(|def| def.dummy);
+    let hp = if dummy { DUMMY_HP } else { 2000 };
     // Enemy sprite
-    commands.spawn((
+    let mut enemy = commands.spawn((
         Sprite::from_image(textures.github.clone()),
         Transform::from_translation(Vec3::new(200.0, 0.0, 0.5)),
         Enemy,
-        Health { current: 2000, max: 2000 },
+        Facing(Vec2::NEG_X), // towards the player's starting spot
+        Health { current: hp, max: hp },
+        Armor::new(50.0),
+        DotEffects::default(),
+        vfx::VfxAuras::default(),
+        StateScoped(GameState::Playing),
     ));
+    // A dummy just stands there
+    if !dummy {
+        enemy.insert((BossCleave::default(), AutoAttack::default()));
+    }
 
     // Enemy HP bar at top center
     commands
         .spawn((
             Node {
                 width: Val::Percent(100.0),
-                height: Val::Px(40.0),
+                height: Val::Auto,
                 position_type: PositionType::Absolute,
                 top: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
+                row_gap: Val::Px(4.0),
                 ..default()
             },
             EnemyHpRoot,
+            StateScoped(GameState::Playing),
         ))
         .with_children(|root| {
             root
@@ -88,6 +285,7 @@ fn spawn_enemy_and_ui(mut commands: Commands, textures: Res<TextureAssets>) {
                         ..default()
                     },
                     BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05)),
+                    EnemyHpBar,
                 ))
                 .with_children(|bar| {
                     bar.spawn((
@@ -96,48 +294,300 @@ fn spawn_enemy_and_ui(mut commands: Commands, textures: Res<TextureAssets>) {
                         EnemyHpFill,
                     ));
                 });
+
+            // Swing timer: fills up towards the next auto-attack
+            root.spawn(Node { flex_direction: FlexDirection::Row, align_items: AlignItems::Center, column_gap: Val::Px(6.0), ..default() })
+                .with_children(|row| {
+                    row.spawn((
+                        Node { width: Val::Px(300.0), height: Val::Px(6.0), ..default() },
+                        BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05)),
+                    ))
+                    .with_child((
+                        Node { width: Val::Percent(0.0), height: Val::Percent(100.0), ..default() },
+                        BackgroundColor(Color::linear_rgb(0.95, 0.75, 0.3)),
+                        SwingTimerFill,
+                    ));
+                    row.spawn((
+                        Text::new(""),
+                        TextFont { font_size: 12.0, ..default() },
+                        TextColor(Color::linear_rgb(0.95, 0.85, 0.6)),
+                        SwingTimerText,
+                    ));
+                });
+
+            // Armor icon, a small shield; the DoT row says how much is shredded and for how long
+            root.spawn((
+                Node { width: Val::Px(14.0), height: Val::Px(16.0), ..default() },
+                BorderRadius::bottom(Val::Px(7.0)),
+                BackgroundColor(Color::linear_rgb(0.7, 0.75, 0.85)),
+                ArmorIcon,
+            ));
+
+            // DoTs and debuffs on the current target with timers, under the HP bar
+            root.spawn((
+                Node {
+                    width: Val::Px(420.0),
+                    height: Val::Px(22.0),
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                },
+                DotRow,
+            ));
+        });
+}
+
+fn spawn_player_healthbar(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.0),
+                bottom: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Text::new(""),
+                TextFont { font_size: 14.0, ..default() },
+                TextColor(Color::WHITE),
+                PlayerHpText,
+            ));
+            root.spawn((
+                Node { width: Val::Px(240.0), height: Val::Px(12.0), ..default() },
+                BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05)),
+            ))
+            .with_children(|bar| {
+                bar.spawn((
+                    Node { width: Val::Percent(100.0), height: Val::Percent(100.0), ..default() },
+                    BackgroundColor(Color::linear_rgb(0.2, 0.75, 0.3)),
+                    PlayerHpFill,
+                ));
+                bar.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::linear_rgb(1.0, 0.9, 0.4).with_alpha(0.85)),
+                    PlayerShieldFill,
+                ));
+            });
         });
 }
 
+fn reset_target(mut target: ResMut<CurrentTarget>, mut heal_target: ResMut<HealTarget>) {
+    target.0 = None;
+    heal_target.0 = None;
+}
+
+/// Everything a hit can land on
+type EnemyQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, &'static mut Health, &'static mut Armor), Or<(With<Enemy>, With<Add>)>>;
+
 fn handle_damage_events(
-    textures: Res<TextureAssets>,
+    book: Res<AbilityBook>,
+    current: Res<CurrentTarget>,
     mut evr: EventReader<DamageEvent>,
-    mut q_enemy: Query<(&Transform, &mut Health), With<Enemy>>,
+    q_boss: Query<Entity, With<Enemy>>,
+    q_player: Query<&Transform, With<Player>>,
+    mut q_enemies: EnemyQuery,
+    mut dealt_writer: EventWriter<DamageDealtEvent>,
     mut commands: Commands,
 ) {
-    if let Ok((transform, mut hp)) = q_enemy.get_single_mut() {
-        for DamageEvent { amount } in evr.read() {
-            
-            hp.current = (hp.current - *amount).max(0);
-
-            // Spawn floating damage number
-            let mut rng = rand::thread_rng();
-            let jitter_x: f32 = rng.gen_range(-10.0..10.0);
-            let start = transform.translation + Vec3::new(jitter_x, 40.0, 1.0);
-            let vel = Vec2::new(0.0, rng.gen_range(30.0..60.0));
-            commands.spawn((
-                Text2d::new(format!("{}", amount)),
-                TextFont { font_size: 22.0, ..default() },
-                TextColor(Color::linear_rgb(1.0, 0.9, 0.9)),
-                Transform::from_translation(start),
-                DamageNumber { ttl: 0.8, vel },
-            ));
-            vfx::vfx_y2k_stars(&mut commands, &textures, transform.translation);
-        //    vfx::vfx_retro_explosion(&mut commands, transform.translation, time.elapsed_secs());
+    let boss = q_boss.single().ok();
+    for hit in evr.read() {
+        // DoT ticks name their target; ability hits go to the player's target
+        let entity = hit.target.or(current.0).filter(|e| q_enemies.contains(*e)).or(boss);
+        let Some(entity) = entity else { continue; };
+        // Ranged hits fly there first. Without a player to fly from they land right away
+        let projectile = hit
+            .source
+            .filter(|_| !hit.tick)
+            .and_then(|id| book.by_id.get(&id))
+            .and_then(|ability| ability.projectile);
+        if let (Some(spec), Ok(player)) = (projectile, q_player.single()) {
+            launch_projectile(&mut commands, *hit, spec, player.translation, entity);
+            continue;
         }
+        land_hit(hit, entity, &book, &mut q_enemies, &mut dealt_writer, &mut commands);
+    }
+}
+
+/// Takes a hit off `entity`'s HP after armor, with its shred and impact effect
+fn land_hit(
+    hit: &DamageEvent,
+    entity: Entity,
+    book: &AbilityBook,
+    q_enemies: &mut EnemyQuery,
+    dealt_writer: &mut EventWriter<DamageDealtEvent>,
+    commands: &mut Commands,
+) {
+    let DamageEvent { amount, crit, positional, penetration, shred, source, tick, .. } = hit;
+    let Ok((transform, mut hp, mut armor)) = q_enemies.get_mut(entity) else { return; };
+    let amount = (*amount as f32 * armor.damage_taken(*penetration)) as i32;
+    hp.current = (hp.current - amount).max(0);
+    // The numbers floating off it are `battle_text`'s
+    dealt_writer.write(DamageDealtEvent {
+        amount,
+        crit: *crit,
+        source: *source,
+        target: entity,
+        positional: *positional,
+        tick: *tick,
+    });
+    // Shred lands after the hit that applies it
+    if let Some(shred) = shred {
+        armor.apply_shred(*shred);
+    }
+    // DoT ticks and status damage get the stars; ability hits their own impact if they have one
+    let impact = (*source)
+        .filter(|_| !tick)
+        .and_then(|id| book.by_id.get(&id))
+        .and_then(|ability| ability.impact_vfx);
+    vfx::vfx_play(commands, impact.unwrap_or("y2k_stars"), transform.translation, None);
+}
+
+// ==== Projectiles ====
+
+/// Seconds between puffs of a projectile's trail
+const TRAIL_EVERY: f32 = 0.03;
+const PROJECTILE_Z: f32 = 1.5;
+
+/// A ranged hit on its way; it lands when the projectile gets to its target, or at once
+/// if the pull ends first
+#[derive(Component)]
+struct Projectile {
+    hit: DamageEvent,
+    target: Entity,
+    from: Vec2,
+    spec: ProjectileSpec,
+    /// 0 at the player, 1 at the target
+    progress: f32,
+    since_trail: f32,
+}
+
+fn launch_projectile(commands: &mut Commands, hit: DamageEvent, spec: ProjectileSpec, from: Vec3, target: Entity) {
+    commands.spawn((
+        Sprite::from_color(Color::linear_rgb(1.0, 0.55, 0.15), Vec2::splat(12.0)),
+        Transform::from_translation(from.truncate().extend(PROJECTILE_Z)),
+        Projectile { hit, target, from: from.truncate(), spec, progress: 0.0, since_trail: 0.0 },
+        StateScoped(GameState::Playing),
+    ));
+}
+
+/// Flies projectiles at where their targets are now, rising and falling by their arc on the
+/// way. One whose target died on the way goes on to the boss
+fn fly_projectiles(
+    time: SimTime,
+    book: Res<AbilityBook>,
+    q_boss: Query<Entity, With<Enemy>>,
+    mut q_projectiles: Query<(Entity, &mut Projectile, &mut Transform), (Without<Enemy>, Without<Add>)>,
+    mut q_enemies: EnemyQuery,
+    mut dealt_writer: EventWriter<DamageDealtEvent>,
+    mut commands: Commands,
+) {
+    let dt = time.scaled_delta();
+    let boss = q_boss.single().ok();
+    for (e, mut projectile, mut tf) in &mut q_projectiles {
+        let target = Some(projectile.target).filter(|t| q_enemies.contains(*t)).or(boss);
+        let Some((target, to)) = target.and_then(|t| Some((t, q_enemies.get(t).ok()?.0.translation.truncate()))) else {
+            commands.entity(e).despawn();
+            continue;
+        };
+        projectile.target = target;
+        let distance = projectile.from.distance(to).max(1.0);
+        projectile.progress = (projectile.progress + projectile.spec.speed * dt / distance).min(1.0);
+        let p = projectile.progress;
+        let at = projectile.from.lerp(to, p) + Vec2::Y * projectile.spec.arc * 4.0 * p * (1.0 - p);
+        tf.translation = at.extend(tf.translation.z);
+
+        projectile.since_trail += dt;
+        if projectile.since_trail >= TRAIL_EVERY {
+            projectile.since_trail = 0.0;
+            vfx::vfx_play(&mut commands, projectile.spec.trail, tf.translation, None);
+        }
+        if p >= 1.0 {
+            let hit = projectile.hit;
+            land_hit(&hit, target, &book, &mut q_enemies, &mut dealt_writer, &mut commands);
+            commands.entity(e).despawn();
+        }
+    }
+}
+
+/// Lands every projectile still in flight right away, so damage already thrown when the pull
+/// ends counts towards it. Run by the combat sim on the tick the pull ends, before it tallies
+pub fn settle_projectiles(
+    book: Res<AbilityBook>,
+    q_boss: Query<Entity, With<Enemy>>,
+    q_projectiles: Query<(Entity, &Projectile)>,
+    mut q_enemies: EnemyQuery,
+    mut dealt_writer: EventWriter<DamageDealtEvent>,
+    mut commands: Commands,
+) {
+    let boss = q_boss.single().ok();
+    for (e, projectile) in &q_projectiles {
+        commands.entity(e).despawn();
+        let Some(target) = Some(projectile.target).filter(|t| q_enemies.contains(*t)).or(boss) else { continue; };
+        land_hit(&projectile.hit, target, &book, &mut q_enemies, &mut dealt_writer, &mut commands);
+    }
+}
+
+/// Console: `sethp 5000` or `sethp 30%` for the boss, with `player` after it for the player
+fn set_hp(
+    In(args): In<Vec<String>>,
+    mut q_boss: Query<&mut Health, (With<Enemy>, Without<Player>)>,
+    mut q_player: Query<&mut Health, With<Player>>,
+) -> CommandResult {
+    let Some(amount) = args.first() else { return Err("usage: sethp <hp or percent> [player]".to_string()); };
+    let (who, mut hp) = match args.get(1).map(String::as_str) {
+        Some("player") => ("Player", q_player.single_mut().map_err(|_| "no player right now".to_string())?),
+        None | Some("boss") => ("Boss", q_boss.single_mut().map_err(|_| "no boss right now".to_string())?),
+        Some(other) => return Err(format!("{other:?} isn't boss or player")),
+    };
+    let value = match amount.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().map(|p| (hp.max as f32 * p / 100.0).round() as i32),
+        None => amount.parse::<f32>().map(|v| v as i32),
+    }
+    .map_err(|_| format!("can't read {amount:?} as HP"))?;
+    hp.current = value.clamp(0, hp.max);
+    Ok(format!("{who} HP {}/{}", hp.current, hp.max))
+}
+
+/// New boss phase: recolor the enemy and mark the transition with a burst
+fn handle_boss_phase(
+    mut evr: EventReader<BossPhaseEvent>,
+    mut q_enemy: Query<(&Transform, &mut Sprite), With<Enemy>>,
+    mut commands: Commands,
+) {
+    let Ok((transform, mut sprite)) = q_enemy.single_mut() else { return; };
+    for BossPhaseEvent { name, tint, .. } in evr.read() {
+        info!("Boss phase: {name}");
+        sprite.color = *tint;
+        vfx::vfx_play(&mut commands, "retro_explosion", transform.translation, None);
+        vfx::vfx_play(&mut commands, "phase_flash", transform.translation, Some(*tint));
     }
 }
 
 fn handle_apply_dot_events(
+    current: Res<CurrentTarget>,
     mut evr: EventReader<ApplyDotEvent>,
-    mut q_enemy: Query<Entity, With<Enemy>>,
-    mut commands: Commands,
+    q_boss: Query<Entity, With<Enemy>>,
+    mut q_enemies: Query<&mut DotEffects>,
 ) {
-    if let Ok(enemy) = q_enemy.get_single_mut() {
-        for ApplyDotEvent { dps, duration, tick_every } in evr.read() {
-            // Add or refresh dot effect
-            commands.entity(enemy).insert(DotEffect {
+    let target = current.0.filter(|e| q_enemies.contains(*e)).or(q_boss.single().ok());
+    if let Some(mut dots) = target.and_then(|e| q_enemies.get_mut(e).ok()) {
+        for ApplyDotEvent { source, dps, duration, tick_every } in evr.read() {
+            dots.apply(Dot {
+                source: *source,
                 remaining: *duration,
+                duration: *duration,
                 tick_every: *tick_every,
                 tick_accum: 0.0,
                 dps: *dps,
@@ -147,50 +597,406 @@ fn handle_apply_dot_events(
 }
 
 fn tick_dots(
-    time: Res<Time>,
-    mut q: Query<(Entity, &mut DotEffect), With<Enemy>>,
-    mut commands: Commands,
+    time: SimTime,
+    mut q: Query<(Entity, &mut DotEffects)>,
     mut writer: EventWriter<DamageEvent>,
 ) {
-    let dt = time.delta_secs();
-    for (entity, mut dot) in &mut q {
-        dot.remaining -= dt;
-        dot.tick_accum += dt;
-        while dot.tick_accum >= dot.tick_every {
-            dot.tick_accum -= dot.tick_every;
-            writer.write(DamageEvent { amount: dot.dps });
+    let dt = time.scaled_delta();
+    for (entity, mut effects) in &mut q {
+        // Left untouched when there's nothing ticking, so it only shows as changed while there is
+        if effects.dots.is_empty() {
+            continue;
+        }
+        for dot in effects.dots.iter_mut() {
+            dot.remaining -= dt;
+            dot.tick_accum += dt;
+            while dot.tick_accum >= dot.tick_every {
+                dot.tick_accum -= dot.tick_every;
+                writer.write(DamageEvent {
+                    amount: dot.dps,
+                    crit: false,
+                    positional: None,
+                    penetration: 0.0,
+                    shred: None,
+                    target: Some(entity),
+                    source: Some(dot.source),
+                    tick: true,
+                });
+            }
+        }
+        effects.dots.retain(|d| d.remaining > 0.0);
+    }
+}
+
+fn tick_armor_shred(time: SimTime, mut q: Query<&mut Armor>) {
+    let dt = time.scaled_delta();
+    for mut armor in &mut q {
+        if armor.shred_remaining > 0.0 {
+            armor.shred_remaining = (armor.shred_remaining - dt).max(0.0);
+            if armor.shred_remaining == 0.0 {
+                armor.shred = 0.0;
+            }
+        }
+    }
+}
+
+fn update_armor_icon(q_enemy: Query<&Armor, With<Enemy>>, mut q_icon: Query<&mut BackgroundColor, With<ArmorIcon>>) {
+    let (Ok(armor), Ok(mut color)) = (q_enemy.single(), q_icon.single_mut()) else { return; };
+    color.set_if_neq(BackgroundColor(armor.icon_color()));
+}
+
+/// Aura on every enemy with a DoT ticking on it
+const DOT_VFX: &str = "dot_drip";
+
+fn show_dot_auras(mut q_enemies: Query<(&DotEffects, &mut vfx::VfxAuras)>) {
+    for (effects, mut auras) in &mut q_enemies {
+        let ids: &[&str] = if effects.dots.is_empty() { &[] } else { &[DOT_VFX] };
+        auras.set(ids);
+    }
+}
+
+/// DoTs and shred on whatever the player is targeting. When that's an add rather than the boss
+/// the row starts with its name, since the HP bar above is the boss's. Chips are only respawned
+/// when the target or which effects are up changes; otherwise their timers count down in place
+fn update_dot_row(
+    mut commands: Commands,
+    book: Res<AbilityBook>,
+    current: Res<CurrentTarget>,
+    // (row, target, what each chip stands for) as last built
+    mut built: Local<Option<(Entity, Entity, Vec<String>)>>,
+    q_boss: Query<Entity, With<Enemy>>,
+    q_targets: Query<(Ref<DotEffects>, Ref<Armor>, Option<&Add>)>,
+    row: Query<(Entity, Option<&Children>), With<DotRow>>,
+    q_chips: Query<&DotChip>,
+    mut q_icons: Query<&mut Node>,
+    mut q_text: Query<&mut Text>,
+) {
+    let Ok((row_entity, children)) = row.single() else { return; };
+    let despawn_children = |commands: &mut Commands| {
+        for child in children.into_iter().flat_map(|c| c.iter()) {
+            commands.entity(child).despawn();
         }
-        if dot.remaining <= 0.0 {
-            commands.entity(entity).remove::<DotEffect>();
+    };
+    let target = current.0.filter(|e| q_targets.contains(*e)).or(q_boss.single().ok());
+    let Some((target, (effects, armor, add))) = target.and_then(|e| Some((e, q_targets.get(e).ok()?))) else {
+        if built.take().is_some() {
+            despawn_children(&mut commands);
         }
+        return;
+    };
+    let same_target = built.as_ref().is_some_and(|(r, t, _)| *r == row_entity && *t == target);
+    if same_target && !effects.is_changed() && !armor.is_changed() {
+        return;
+    }
+    // (key, color, icon fill, text) per chip
+    let mut chips: Vec<(String, Color, f32, String)> = effects
+        .dots
+        .iter()
+        .map(|dot| {
+            let frac = if dot.duration > 0.0 { (dot.remaining / dot.duration).clamp(0.0, 1.0) } else { 0.0 };
+            let name = book.by_id.get(&dot.source).map(|a| a.name).unwrap_or("DoT");
+            (format!("{:?}", dot.source), dot_color(dot.source), frac, format!("{} {:.0}s", name, dot.remaining.ceil()))
+        })
+        .collect();
+    if armor.shred_remaining > 0.0 {
+        let frac =
+            if armor.shred_duration > 0.0 { (armor.shred_remaining / armor.shred_duration).clamp(0.0, 1.0) } else { 0.0 };
+        let label = format!("Shred -{:.0} {:.0}s", armor.shred, armor.shred_remaining.ceil());
+        chips.push(("shred".to_string(), Color::linear_rgb(0.6, 0.7, 1.0), frac, label));
+    }
+    let keys: Vec<String> = chips.iter().map(|(key, ..)| key.clone()).collect();
+
+    if same_target && built.as_ref().is_some_and(|(.., built_keys)| *built_keys == keys) {
+        let existing = children.into_iter().flat_map(|c| c.iter()).filter_map(|c| q_chips.get(c).ok());
+        for (chip, (_, _, frac, label)) in existing.zip(chips) {
+            if let Ok(mut icon) = q_icons.get_mut(chip.icon) {
+                icon.height = Val::Px(12.0 * frac);
+            }
+            if let Ok(mut text) = q_text.get_mut(chip.text) {
+                if text.0 != label {
+                    text.0 = label;
+                }
+            }
+        }
+        return;
+    }
+
+    despawn_children(&mut commands);
+    *built = Some((row_entity, target, keys));
+    if let Some(add) = add {
+        let name = commands
+            .spawn((
+                Text::new(format!("{}:", add.name)),
+                TextFont { font_size: 13.0, ..default() },
+                TextColor(Color::linear_rgb(1.0, 0.9, 0.3)),
+            ))
+            .id();
+        commands.entity(row_entity).add_child(name);
+    }
+    for (_, color, frac, label) in chips {
+        let icon_node = Node { width: Val::Px(12.0), height: Val::Px(12.0 * frac), ..default() };
+        let icon = commands.spawn((icon_node, BackgroundColor(color))).id();
+        let text = commands.spawn((Text::new(label), TextFont { font_size: 13.0, ..default() }, TextColor(Color::WHITE))).id();
+        let chip = commands
+            .spawn((
+                Node {
+                    height: Val::Px(20.0),
+                    padding: UiRect::horizontal(Val::Px(4.0)),
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                },
+                BackgroundColor(Color::linear_rgb(0.05, 0.05, 0.05).with_alpha(0.8)),
+                DotChip { icon, text },
+            ))
+            .add_children(&[icon, text])
+            .id();
+        commands.entity(row_entity).add_child(chip);
     }
 }
 
 fn update_enemy_healthbar(
     q_enemy: Query<&Health, With<Enemy>>,
+    q_adds: Query<(), With<Add>>,
+    mut q_bar: Query<&mut Node, (With<EnemyHpBar>, Without<EnemyHpFill>)>,
     mut q_fill: Query<&mut Node, With<EnemyHpFill>>,
 ) {
+    // Every enemy has a nameplate while adds are up, see `nameplates`
+    if let Ok(mut bar) = q_bar.single_mut() {
+        bar.display = if q_adds.is_empty() { Display::Flex } else { Display::None };
+    }
     if let (Ok(hp), Ok(mut node)) = (q_enemy.get_single(), q_fill.get_single_mut()) {
         let pct = if hp.max > 0 { (hp.current as f32 / hp.max as f32).clamp(0.0, 1.0) } else { 0.0 };
         node.width = Val::Percent(pct * 100.0);
     }
 }
 
-fn animate_damage_numbers(
-    time: Res<Time>,
-    mut q: Query<(Entity, &mut Transform, &mut TextColor, &mut DamageNumber)>,
-    mut commands: Commands,
+fn update_swing_timer(
+    q_enemy: Query<&AutoAttack, With<Enemy>>,
+    mut q_fill: Query<&mut Node, With<SwingTimerFill>>,
+    mut q_text: Query<&mut Text, With<SwingTimerText>>,
 ) {
-    let dt = time.delta_secs();
-    for (e, mut tf, mut color, mut num) in &mut q {
-        num.ttl -= dt;
-        tf.translation.x += num.vel.x * dt;
-        tf.translation.y += num.vel.y * dt;
-        let a = (num.ttl / 0.8).clamp(0.0, 1.0);
-        color.0 = color.0.with_alpha(a);
-        if num.ttl <= 0.0 {
-            commands.entity(e).despawn();
+    let Ok(auto) = q_enemy.single() else { return; };
+    if let Ok(mut node) = q_fill.single_mut() {
+        let frac = if auto.every > 0.0 { 1.0 - (auto.timer / auto.every).clamp(0.0, 1.0) } else { 0.0 };
+        node.width = Val::Percent(frac * 100.0);
+    }
+    if let Ok(mut text) = q_text.single_mut() {
+        text.0 = match &auto.target {
+            Some(target) => format!("Auto > {target} {:.1}s", auto.timer.max(0.0)),
+            None => String::new(),
+        };
+    }
+}
+
+/// The shield segment starts where HP ends; anything past full HP is drawn over the end of the bar
+fn update_player_healthbar(
+    combat: Res<CombatState>,
+    q_player: Query<&Health, With<Player>>,
+    mut q_fill: Query<&mut Node, (With<PlayerHpFill>, Without<PlayerShieldFill>)>,
+    mut q_shield: Query<&mut Node, (With<PlayerShieldFill>, Without<PlayerHpFill>)>,
+    mut q_text: Query<&mut Text, With<PlayerHpText>>,
+) {
+    let Ok(hp) = q_player.single() else { return; };
+    let max = hp.max.max(1) as f32;
+    let hp_pct = (hp.current as f32 / max).clamp(0.0, 1.0) * 100.0;
+    let shield = combat.shield.map(|s| s.amount).unwrap_or(0);
+    let shield_pct = (shield as f32 / max).clamp(0.0, 1.0) * 100.0;
+    if let Ok(mut node) = q_fill.single_mut() {
+        node.width = Val::Percent(hp_pct);
+    }
+    if let Ok(mut node) = q_shield.single_mut() {
+        let start = hp_pct.min(100.0 - shield_pct);
+        node.left = Val::Percent(start);
+        node.width = Val::Percent(shield_pct);
+    }
+    if let Ok(mut text) = q_text.single_mut() {
+        text.0 = if shield > 0 {
+            format!("HP {}/{} (+{})", hp.current, hp.max, shield)
+        } else {
+            format!("HP {}/{}", hp.current, hp.max)
+        };
+    }
+}
+
+
+// ==== Arena: floor, boundary and hazard puddles ====
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ArenaShape {
+    Circle { radius: f32 },
+    Square { half_size: f32 },
+}
+
+/// Where the fight takes place, from the encounter's `arena` field
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct Arena {
+    pub shape: ArenaShape,
+    /// Walking off the edge kills instead of stopping the player at the boundary
+    #[serde(default)]
+    pub deadly_edge: bool,
+    /// Line-of-sight blockers; the player can't walk through them either
+    #[serde(default)]
+    pub pillars: Vec<Pillar>,
+}
+
+/// A round obstacle standing in the arena
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Pillar {
+    pub at: (f32, f32),
+    pub radius: f32,
+}
+
+impl Pillar {
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.at.0, self.at.1)
+    }
+
+    /// Whether the segment from `a` to `b` passes through the pillar
+    pub fn blocks(&self, a: Vec2, b: Vec2) -> bool {
+        let ab = b - a;
+        let t = if ab.length_squared() > 0.0 { ((self.center() - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0) } else { 0.0 };
+        (a + ab * t).distance(self.center()) < self.radius
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self { shape: ArenaShape::Circle { radius: 320.0 }, deadly_edge: false, pillars: Vec::new() }
+    }
+}
+
+impl Arena {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        match self.shape {
+            ArenaShape::Circle { radius } => pos.length() <= radius,
+            ArenaShape::Square { half_size } => pos.abs().max_element() <= half_size,
+        }
+    }
+
+    /// Closest point to `pos` inside the arena
+    pub fn clamp(&self, pos: Vec2) -> Vec2 {
+        match self.shape {
+            ArenaShape::Circle { radius } => pos.clamp_length_max(radius),
+            ArenaShape::Square { half_size } => pos.clamp(Vec2::splat(-half_size), Vec2::splat(half_size)),
+        }
+    }
+
+    /// Whether a pillar stands between `from` and `to`
+    pub fn blocks_sight(&self, from: Vec2, to: Vec2) -> bool {
+        self.pillars.iter().any(|p| p.blocks(from, to))
+    }
+
+    /// Half the width of the square the arena fits in
+    pub fn extent(&self) -> f32 {
+        match self.shape {
+            ArenaShape::Circle { radius } => radius,
+            ArenaShape::Square { half_size } => half_size,
         }
     }
 }
 
+/// Drops a puddle under the player that hurts every tick they stand in it
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SpawnHazardEvent {
+    pub radius: f32,
+    pub damage: i32,
+    /// `None` keeps the puddle for the rest of the pull
+    pub duration: Option<f32>,
+}
+
+#[derive(Component, Debug)]
+pub struct Hazard {
+    pub radius: f32,
+    damage: i32,
+    remaining: Option<f32>,
+    until_tick: f32,
+}
+
+/// Seconds between hazard damage ticks
+const HAZARD_TICK: f32 = 1.0;
+
+#[derive(Component)]
+struct ArenaFloor;
+
+fn load_arena(library: Res<EncounterLibrary>, encounter: Res<CurrentEncounter>, mut arena: ResMut<Arena>) {
+    *arena = library.get(&encounter.id).map(|def| def.arena.clone()).unwrap_or_default();
+}
+
+fn spawn_arena_floor(mut commands: Commands, arena: Res<Arena>, q_existing: Query<Entity, With<ArenaFloor>>) {
+    for e in &q_existing {
+        commands.entity(e).despawn();
+    }
+    commands.spawn((
+        Sprite::from_color(Color::linear_rgb(0.08, 0.09, 0.12), Vec2::splat(arena.extent() * 2.0)),
+        Transform::from_translation(Vec3::new(0.0, 0.0, -1.0)),
+        ArenaFloor,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+/// Runs after player movement so the player is never drawn past the edge or inside a pillar
+pub(crate) fn keep_in_arena(arena: Res<Arena>, mut q_player: Query<(&mut Transform, &mut Health), With<Player>>) {
+    let Ok((mut transform, mut hp)) = q_player.single_mut() else { return; };
+    let mut pos = transform.translation.truncate();
+    for pillar in &arena.pillars {
+        let out = pos - pillar.center();
+        if out.length() < pillar.radius {
+            pos = pillar.center() + out.normalize_or(Vec2::NEG_X) * pillar.radius;
+            transform.translation = pos.extend(transform.translation.z);
+        }
+    }
+    if arena.contains(pos) {
+        return;
+    }
+    if arena.deadly_edge {
+        if hp.current > 0 {
+            info!("Fell off the arena");
+        }
+        hp.current = 0;
+    } else {
+        transform.translation = arena.clamp(pos).extend(transform.translation.z);
+    }
+}
+
+fn spawn_hazards(
+    mut commands: Commands,
+    mut evr: EventReader<SpawnHazardEvent>,
+    q_player: Query<&Transform, With<Player>>,
+) {
+    let Ok(player) = q_player.single() else { return; };
+    for ev in evr.read() {
+        commands.spawn((
+            Transform::from_translation(player.translation.truncate().extend(0.0)),
+            Hazard { radius: ev.radius, damage: ev.damage, remaining: ev.duration, until_tick: HAZARD_TICK },
+            StateScoped(GameState::Playing),
+        ));
+    }
+}
+
+fn tick_hazards(
+    time: SimTime,
+    mut commands: Commands,
+    mut q_hazards: Query<(Entity, &Transform, &mut Hazard)>,
+    q_player: Query<&Transform, (With<Player>, Without<Hazard>)>,
+    mut hit_writer: EventWriter<PlayerDamageEvent>,
+) {
+    let dt = time.scaled_delta();
+    let player = q_player.single().ok().map(|t| t.translation.truncate());
+    for (e, transform, mut hazard) in &mut q_hazards {
+        hazard.until_tick -= dt;
+        if hazard.until_tick <= 0.0 {
+            hazard.until_tick += HAZARD_TICK;
+            let inside = player.is_some_and(|p| p.distance(transform.translation.truncate()) <= hazard.radius);
+            if inside {
+                hit_writer.write(PlayerDamageEvent { amount: hazard.damage });
+            }
+        }
+        if let Some(remaining) = hazard.remaining.as_mut() {
+            *remaining -= dt;
+            if *remaining <= 0.0 {
+                commands.entity(e).despawn();
+            }
+        }
+    }
+}
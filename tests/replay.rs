@@ -0,0 +1,41 @@
+//! Replays re-simulate a pull exactly: a scripted pull played back from its recording
+//! deals the same hits and uses the same abilities at the same times
+
+use bevy_game::testing::{AbilityId, PullScript};
+
+#[test]
+fn played_back_pull_matches_the_recording() {
+    let live = PullScript::new()
+        .with_seed(11)
+        .press_at(0.0, AbilityId::Strike)
+        .press_at(0.6, AbilityId::WeaveDash)
+        .press_at(2.3, AbilityId::Strike)
+        .macro_at(5.0, "Strike, wait 0.8, Weave: Dash")
+        .run_for(8.0)
+        .run();
+    assert!(live.total_damage > 0, "the scripted pull should land hits");
+    let replay = live.replay.as_deref().expect("a live pull is recorded");
+
+    let played = PullScript::new().play_replay(replay).run_for(8.0).run();
+    assert!(played.replay.is_none(), "a playback isn't recorded over");
+    assert_eq!(played.total_damage, live.total_damage);
+    assert_eq!(played.hits, live.hits);
+    assert_eq!(played.crits, live.crits);
+    assert_eq!(played.used, live.used);
+}
+
+#[test]
+fn playback_ignores_its_own_script() {
+    let live = PullScript::new().with_seed(4).press_at(0.0, AbilityId::Strike).run_for(3.0).run();
+    let replay = live.replay.as_deref().expect("a live pull is recorded");
+
+    // The seed and presses here are the recording's to decide, not the script's
+    let played = PullScript::new()
+        .with_seed(99)
+        .press_at(0.5, AbilityId::WeaveDash)
+        .play_replay(replay)
+        .run_for(3.0)
+        .run();
+    assert_eq!(played.used, live.used);
+    assert_eq!(played.hits, live.hits);
+}
@@ -0,0 +1,72 @@
+//! GCD, queue and weave behavior, driven through the headless app with scripted presses
+
+use bevy_game::testing::{AbilityId, PullOutcome, PullScript};
+
+/// A tick or two of slack for when a press lands and the sim acts on it
+const SLACK_SECS: f32 = 0.1;
+
+fn used_at(outcome: &PullOutcome, ability: AbilityId) -> Vec<f32> {
+    outcome.used.iter().filter(|(_, id)| *id == ability).map(|(t, _)| *t).collect()
+}
+
+#[test]
+fn gcd_press_goes_off_right_away() {
+    let outcome = PullScript::new()
+        .with_seed(1)
+        .press_at(0.0, AbilityId::Strike)
+        .run_for(1.0)
+        .expect_uses(AbilityId::Strike, 1)
+        .run();
+    outcome.assert_passed();
+    assert!(outcome.total_damage > 0, "Strike should land");
+    let strikes = used_at(&outcome, AbilityId::Strike);
+    assert!(strikes[0] <= SLACK_SECS, "Strike went off at {:.2}s", strikes[0]);
+}
+
+#[test]
+fn gcd_press_long_before_recast_is_dropped() {
+    // 1.5s left on the GCD, past the queue window and the input buffer
+    let outcome = PullScript::new()
+        .with_seed(1)
+        .press_at(0.0, AbilityId::Strike)
+        .press_at(1.0, AbilityId::Strike)
+        .run_for(4.0)
+        .expect_uses(AbilityId::Strike, 1)
+        .run();
+    outcome.assert_passed();
+}
+
+#[test]
+fn gcd_press_in_queue_window_goes_off_when_gcd_is_back() {
+    let outcome = PullScript::new()
+        .with_seed(1)
+        .press_at(0.0, AbilityId::Strike)
+        .press_at(2.2, AbilityId::Strike)
+        .run_for(4.0)
+        .expect_uses(AbilityId::Strike, 2)
+        .expect_no_clip()
+        .run();
+    outcome.assert_passed();
+    let strikes = used_at(&outcome, AbilityId::Strike);
+    let gap = strikes[1] - strikes[0];
+    assert!((gap - 2.5).abs() <= SLACK_SECS, "queued Strike went off {gap:.2}s after the first");
+}
+
+#[test]
+fn ogcd_weaves_between_gcds_without_clipping() {
+    let outcome = PullScript::new()
+        .with_seed(1)
+        .press_at(0.0, AbilityId::Strike)
+        .press_at(0.5, AbilityId::WeaveDash)
+        .press_at(2.2, AbilityId::Strike)
+        .run_for(4.0)
+        .expect_uses(AbilityId::Strike, 2)
+        .expect_uses(AbilityId::WeaveDash, 1)
+        .expect_no_clip()
+        .run();
+    outcome.assert_passed();
+    let weave = used_at(&outcome, AbilityId::WeaveDash)[0];
+    let strikes = used_at(&outcome, AbilityId::Strike);
+    assert!(strikes[0] < weave && weave < strikes[1], "weave at {weave:.2}s should sit between {strikes:?}");
+    assert!((weave - 0.5).abs() <= SLACK_SECS, "weave went off at {weave:.2}s");
+}